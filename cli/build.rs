@@ -1,4 +1,39 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Backends and the crate whose version in Cargo.lock identifies each
+const LOCKED_CRATES: &[(&str, &str)] = &[
+	("biome", "biome_js_formatter"),
+	("dprint", "dprint-plugin-markdown"),
+	("dprint", "malva"),
+	("dprint", "pretty_yaml"),
+	("dprint-dockerfile", "dprint-plugin-dockerfile"),
+	("mago", "mago-formatter"),
+	("quick-xml", "quick-xml"),
+	("ruff", "ruff_python_formatter"),
+	("rubyfmt", "rubyfmt"),
+	("sqruff", "sqruff-lib"),
+	("stylua", "stylua"),
+	("taplo", "taplo"),
+	("tree-sitter-groovy", "tree-sitter-groovy"),
+];
+
+/// Go modules whose version in goffi's go.mod identifies each backend
+const GO_MODULES: &[(&str, &str)] = &[
+	("goffi", "mvdan.cc/sh/v3"),
+	("goffi", "github.com/hashicorp/hcl/v2"),
+	("goffi", "golang.org/x/mod"),
+];
+
+/// Vendored artifacts with no version of their own, identified by a hash
+/// of their contents
+const VENDORED: &[(&str, &str, &str)] = &[
+	(
+		"clang-format",
+		"clang-format.wasm",
+		"formatters/clang/wasm/clang-format.wasm",
+	),
+	("zigffi", "root.zig", "formatters/zigffi/zig/root.zig"),
+];
 
 fn main() {
 	// Set rpath for the goffi Go shared library
@@ -11,4 +46,93 @@ fn main() {
 		let go_dir_abs = std::fs::canonicalize(&go_dir).unwrap();
 		println!("cargo:rustc-link-arg=-Wl,-rpath,{}", go_dir_abs.display());
 	}
+
+	write_backend_versions();
+}
+
+/// Write `BACKEND_VERSIONS` for `fama --version` to OUT_DIR
+fn write_backend_versions() {
+	let root = Path::new("..");
+	let lock_path = root.join("Cargo.lock");
+	let go_mod_path = root.join("formatters/goffi/go/go.mod");
+	println!("cargo:rerun-if-changed={}", lock_path.display());
+	println!("cargo:rerun-if-changed={}", go_mod_path.display());
+
+	let lock = std::fs::read_to_string(&lock_path).unwrap_or_default();
+	let go_mod = std::fs::read_to_string(&go_mod_path).unwrap_or_default();
+
+	let mut versions: Vec<(&str, &str, String)> = Vec::new();
+	for &(backend, name) in LOCKED_CRATES {
+		versions.push((backend, name, locked_version(&lock, name)));
+	}
+	for &(backend, module) in GO_MODULES {
+		versions.push((backend, module, go_module_version(&go_mod, module)));
+	}
+	for &(backend, name, path) in VENDORED {
+		let path = root.join(path);
+		println!("cargo:rerun-if-changed={}", path.display());
+		let version = std::fs::read(&path)
+			.map(|bytes| format!("fnv:{:016x}", fnv1a64(&bytes)))
+			.unwrap_or_else(|_| "unknown".to_string());
+		versions.push((backend, name, version));
+	}
+	versions.sort();
+
+	let mut out = String::from(
+		"pub const BACKEND_VERSIONS: &[(&str, &str, &str)] = &[\n",
+	);
+	for (backend, component, version) in versions {
+		out += &format!("\t({:?}, {:?}, {:?}),\n", backend, component, version);
+	}
+	out += "];\n";
+
+	let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+	std::fs::write(out_dir.join("backend_versions.rs"), out).unwrap();
+}
+
+/// Version of `name` in Cargo.lock, with the short git revision for git
+/// dependencies. Path crates sharing a name with a registry crate (the
+/// `stylua` wrapper) are skipped in favor of the one with a source.
+fn locked_version(lock: &str, name: &str) -> String {
+	let mut fallback = None;
+	for package in lock.split("[[package]]") {
+		let field = |key: &str| {
+			package.lines().find_map(|line| {
+				line.strip_prefix(key)?
+					.strip_prefix(" = \"")?
+					.strip_suffix('"')
+			})
+		};
+		if field("name") != Some(name) {
+			continue;
+		}
+		let Some(version) = field("version") else {
+			continue;
+		};
+		match field("source") {
+			Some(source) if source.starts_with("git+") => {
+				let rev = source.rsplit('#').next().unwrap_or("");
+				return format!("{}+git.{}", version, &rev[..rev.len().min(7)]);
+			}
+			Some(_) => return version.to_string(),
+			None => fallback = Some(version.to_string()),
+		}
+	}
+	fallback.unwrap_or_else(|| "unknown".to_string())
 }
+
+/// Version of `module` in a go.mod require block
+fn go_module_version(go_mod: &str, module: &str) -> String {
+	go_mod
+		.lines()
+		.find_map(|line| {
+			let mut parts = line.split_whitespace();
+			(parts.next() == Some(module))
+				.then(|| parts.next())
+				.flatten()
+		})
+		.unwrap_or("unknown")
+		.to_string()
+}
+
+include!("src/fnv.rs");
@@ -0,0 +1,261 @@
+// adopt.rs - Churn estimate for `fama adopt-report`
+//
+// Formats every file in memory without writing anything, and totals how
+// many files and lines formatting would touch per directory and language,
+// so teams can pick where to turn formatting on first.
+
+use fama_common::{detect_file_type, FileType, LanguageMode, CONFIG};
+use rayon::prelude::*;
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::discovery;
+use crate::formatter::{self, FormatOptions};
+
+/// Churn totals for one group of files
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Churn {
+	/// Files checked
+	pub files: usize,
+	/// Files formatting would change
+	pub touched: usize,
+	/// Lines added plus lines removed
+	pub lines: usize,
+	/// Files that failed to read or format
+	pub errors: usize,
+}
+
+impl Churn {
+	fn add(&mut self, other: Churn) {
+		self.files += other.files;
+		self.touched += other.touched;
+		self.lines += other.lines;
+		self.errors += other.errors;
+	}
+
+	fn to_json(self) -> serde_json::Value {
+		serde_json::json!({
+			"files": self.files,
+			"touched": self.touched,
+			"lines": self.lines,
+			"errors": self.errors,
+		})
+	}
+}
+
+/// Churn grouped by directory and by language
+#[derive(Debug, Default)]
+pub struct Report {
+	pub directories: BTreeMap<String, Churn>,
+	pub languages: BTreeMap<String, Churn>,
+	pub total: Churn,
+}
+
+impl Report {
+	/// Add one file's churn to its directory and language
+	fn record(&mut self, path: &Path, file_type: FileType, churn: Churn) {
+		let dir = path
+			.parent()
+			.map(|dir| dir.strip_prefix(".").unwrap_or(dir))
+			.filter(|dir| !dir.as_os_str().is_empty())
+			.map_or(".".to_string(), |dir| dir.display().to_string());
+		self.directories.entry(dir).or_default().add(churn);
+		self.languages
+			.entry(format!("{file_type:?}"))
+			.or_default()
+			.add(churn);
+		self.total.add(churn);
+	}
+
+	/// Merge two reports (used in parallel reduce)
+	fn merge(mut self, other: Report) -> Report {
+		for (dir, churn) in other.directories {
+			self.directories.entry(dir).or_default().add(churn);
+		}
+		for (language, churn) in other.languages {
+			self.languages.entry(language).or_default().add(churn);
+		}
+		self.total.add(other.total);
+		self
+	}
+
+	pub fn to_json(&self) -> serde_json::Value {
+		let group = |map: &BTreeMap<String, Churn>| {
+			map.iter()
+				.map(|(name, churn)| (name.clone(), churn.to_json()))
+				.collect::<serde_json::Map<_, _>>()
+		};
+		serde_json::json!({
+			"directories": group(&self.directories),
+			"languages": group(&self.languages),
+			"total": self.total.to_json(),
+		})
+	}
+
+	/// Render as two tables, least churn first within each
+	pub fn to_text(&self) -> String {
+		let mut out = String::new();
+		for (title, map) in [
+			("Directory", &self.directories),
+			("Language", &self.languages),
+		] {
+			let mut rows: Vec<(&str, &Churn)> = map
+				.iter()
+				.map(|(name, churn)| (name.as_str(), churn))
+				.collect();
+			rows.sort_by_key(|(name, churn)| (churn.lines, *name));
+			rows.push(("Total", &self.total));
+			let width = rows
+				.iter()
+				.map(|(name, _)| name.len())
+				.chain([title.len()])
+				.max()
+				.unwrap_or(0);
+			out += &format!(
+				"{:width$}  {:>6}  {:>7}  {:>7}\n",
+				title, "Files", "Touched", "Lines"
+			);
+			for (name, churn) in rows {
+				out += &format!(
+					"{:width$}  {:>6}  {:>7}  {:>7}\n",
+					name, churn.files, churn.touched, churn.lines
+				);
+			}
+			out.push('\n');
+		}
+		if self.total.errors > 0 {
+			out += &format!(
+				"{} files could not be formatted and are not counted\n",
+				self.total.errors
+			);
+		}
+		out
+	}
+}
+
+/// Lines added plus lines removed between `original` and `formatted`
+fn changed_lines(original: &str, formatted: &str) -> usize {
+	TextDiff::from_lines(original, formatted)
+		.iter_all_changes()
+		.filter(|change| change.tag() != ChangeTag::Equal)
+		.count()
+}
+
+/// Churn for one file; languages whose mode is off are left out
+fn file_churn(path: &Path) -> Option<(FileType, Churn)> {
+	let file_type = detect_file_type(path.to_str().unwrap_or(""));
+	if CONFIG.mode_for(file_type) == LanguageMode::Off {
+		return None;
+	}
+	let mut churn = Churn {
+		files: 1,
+		..Churn::default()
+	};
+	let formatted = std::fs::read_to_string(path)
+		.map_err(anyhow::Error::from)
+		.and_then(|content| {
+			let formatted = formatter::format_source(
+				&content,
+				path,
+				FormatOptions::default(),
+			)?;
+			Ok((content, formatted))
+		});
+	match formatted {
+		Ok((content, formatted)) if content != formatted => {
+			churn.touched = 1;
+			churn.lines = changed_lines(&content, &formatted);
+		}
+		Ok(_) => {}
+		Err(_) => churn.errors = 1,
+	}
+	Some((file_type, churn))
+}
+
+/// Build the report for files matching `patterns`
+pub fn report(patterns: &[String]) -> anyhow::Result<Report> {
+	let mut files: Vec<PathBuf> = Vec::new();
+	for pattern in patterns {
+		files.extend(
+			discovery::discover_files(Some(pattern), true).map_err(|e| {
+				anyhow::anyhow!("Failed to discover files: {}", e)
+			})?,
+		);
+	}
+	files.sort();
+	files.dedup();
+
+	Ok(files
+		.par_iter()
+		.fold(Report::default, |mut report, path| {
+			if let Some((file_type, churn)) = file_churn(path) {
+				report.record(path, file_type, churn);
+			}
+			report
+		})
+		.reduce(Report::default, Report::merge))
+}
+
+/// Print the report for `patterns` as a table or JSON
+pub fn run(patterns: &[String], json: bool) -> anyhow::Result<()> {
+	let report = report(patterns)?;
+	if json {
+		println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+	} else {
+		print!("{}", report.to_text());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_changed_lines() {
+		assert_eq!(changed_lines("a\nb\n", "a\nb\n"), 0);
+		assert_eq!(changed_lines("a\nb\n", "a\nc\n"), 2);
+		assert_eq!(changed_lines("a\n", "a\nb\n"), 1);
+	}
+
+	#[test]
+	fn test_record_groups_by_directory_and_language() {
+		let mut report = Report::default();
+		let churn = Churn {
+			files: 1,
+			touched: 1,
+			lines: 4,
+			errors: 0,
+		};
+		report.record(Path::new("./src/a.ts"), FileType::TypeScript, churn);
+		report.record(Path::new("b.json"), FileType::Json, churn);
+
+		assert_eq!(report.directories["src"].lines, 4);
+		assert_eq!(report.directories["."].files, 1);
+		assert_eq!(report.languages["TypeScript"].touched, 1);
+		assert_eq!(report.total.lines, 8);
+	}
+
+	#[test]
+	fn test_report_counts_touched_files() {
+		let temp_dir = TempDir::new().unwrap();
+		let dir = temp_dir.path();
+		fs::write(dir.join("a.xml"), "<a><b/></a>").unwrap();
+		let formatted = formatter::format_source(
+			"<a><b/></a>",
+			Path::new("a.xml"),
+			FormatOptions::default(),
+		)
+		.unwrap();
+		fs::write(dir.join("b.xml"), &formatted).unwrap();
+
+		let report = report(&[dir.display().to_string()]).unwrap();
+		let xml = report.languages["Xml"];
+		assert_eq!((xml.files, xml.touched, xml.errors), (2, 1, 0));
+		assert!(xml.lines > 0);
+		assert_eq!(report.to_json()["total"]["touched"], 1);
+	}
+}
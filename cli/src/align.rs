@@ -0,0 +1,221 @@
+// align.rs - fama-align comments for hand-aligned literals
+//
+// A `fama-align` comment on its own line keeps the statement after it as
+// written, for matrices and tables of numbers. In JavaScript and
+// TypeScript it becomes a Biome suppression comment; in Python the
+// statement is wrapped in `# fmt: off`/`# fmt: on` for Ruff. Suppression
+// comments the user wrote themselves are left alone.
+
+use crate::rewrites::{Prepared, Undo};
+use fama_common::FileType;
+
+const ALIGN: &str = "fama-align";
+const JS_ALIGN: &str = "// fama-align";
+const PY_ALIGN: &str = "# fama-align";
+
+/// Biome's suppression comment standing in for `// fama-align`
+const BIOME_ALIGN: &str = "// biome-ignore format: fama-align";
+
+fn is_biome_align(line: &str) -> bool {
+	line == BIOME_ALIGN
+}
+
+fn is_fmt_toggle(line: &str) -> bool {
+	line == "# fmt: off" || line == "# fmt: on"
+}
+
+/// Rewrite `fama-align` comments into the backend's own suppression
+/// syntax, or `None` when there are none
+pub fn to_backend(content: &str, file_type: FileType) -> Option<Prepared> {
+	if !content.contains(ALIGN) {
+		return None;
+	}
+	let prepared = match file_type {
+		FileType::JavaScript
+		| FileType::TypeScript
+		| FileType::Jsx
+		| FileType::Tsx => biome_to_backend(content),
+		FileType::Python => python_to_backend(content),
+		_ => return None,
+	};
+	prepared.is_rewritten().then_some(prepared)
+}
+
+/// Turn each `// fama-align` line into a Biome suppression comment
+fn biome_to_backend(content: &str) -> Prepared {
+	let mut prepared = Prepared::new(content.len(), is_biome_align);
+	for line in content.split_inclusive('\n') {
+		if line.trim() == JS_ALIGN {
+			let rewritten = line.replacen(JS_ALIGN, BIOME_ALIGN, 1);
+			prepared.push_rewritten(&rewritten, Undo::Replace(JS_ALIGN.into()));
+		} else {
+			prepared.push_line(line);
+		}
+	}
+	prepared
+}
+
+/// Wrap the statement after each `# fama-align` line in `# fmt: off` and
+/// `# fmt: on`, which Ruff keeps as written
+fn python_to_backend(content: &str) -> Prepared {
+	let lines: Vec<&str> = content.split_inclusive('\n').collect();
+	let mut prepared = Prepared::new(content.len(), is_fmt_toggle);
+	let mut open: Option<(usize, &str)> = None;
+	for (i, line) in lines.iter().enumerate() {
+		if open.is_some_and(|(end, _)| end == i) && !line.ends_with('\n') {
+			prepared.push_line(&format!("{line}\n"));
+		} else {
+			prepared.push_line(line);
+		}
+		if open.is_none() && line.trim() == PY_ALIGN && line.ends_with('\n') {
+			let indent = &line[..line.len() - line.trim_start().len()];
+			if let Some(end) = statement_end(&lines, i + 1) {
+				let off = format!("{indent}# fmt: off\n");
+				prepared.push_rewritten(&off, Undo::Remove);
+				open = Some((end, indent));
+			}
+		}
+		if let Some((_, indent)) = open.filter(|(end, _)| *end == i) {
+			let on = format!("{indent}# fmt: on\n");
+			prepared.push_rewritten(&on, Undo::Remove);
+			open = None;
+		}
+	}
+	prepared
+}
+
+/// Index of the last line of the Python statement starting at the first
+/// non-blank line from `start`: the first line that closes every bracket
+/// and string and doesn't end in a `\` continuation
+fn statement_end(lines: &[&str], start: usize) -> Option<usize> {
+	let first = (start..lines.len()).find(|&i| !lines[i].trim().is_empty())?;
+	let mut depth = 0usize;
+	let mut quote: Option<&str> = None;
+	for (i, line) in lines.iter().enumerate().skip(first) {
+		let mut rest = *line;
+		while let Some(c) = rest.chars().next() {
+			if let Some(q) = quote {
+				if c == '\\' {
+					let mut chars = rest.chars();
+					chars.nth(1);
+					rest = chars.as_str();
+					continue;
+				}
+				if rest.starts_with(q) {
+					quote = None;
+					rest = &rest[q.len()..];
+					continue;
+				}
+			} else {
+				match c {
+					'#' => break,
+					'"' | '\'' => {
+						let q = ["\"\"\"", "'''", "\"", "'"]
+							.into_iter()
+							.find(|q| rest.starts_with(q))
+							.unwrap_or("'");
+						quote = Some(q);
+						rest = &rest[q.len()..];
+						continue;
+					}
+					'(' | '[' | '{' => depth += 1,
+					')' | ']' | '}' => depth = depth.saturating_sub(1),
+					_ => {}
+				}
+			}
+			rest = &rest[c.len_utf8()..];
+		}
+		// Only triple-quoted strings span lines
+		if matches!(quote, Some("\"" | "'")) {
+			quote = None;
+		}
+		if depth == 0 && quote.is_none() && !line.trim_end().ends_with('\\') {
+			return Some(i);
+		}
+	}
+	Some(lines.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_biome_round_trip() {
+		let source = concat!(
+			"// biome-ignore format: fama-align\n",
+			"const a = [1,  2];\n",
+			"  // fama-align\n",
+			"const m = [\n  1, 0,\n  0, 1,\n];\n",
+		);
+		let prepared = to_backend(source, FileType::Tsx).unwrap();
+		assert_eq!(
+			prepared.source,
+			source.replace(
+				"  // fama-align",
+				"  // biome-ignore format: fama-align"
+			)
+		);
+		// The user's own suppression comment stays, the mapped one is undone
+		let formatted = prepared.source.replace("  //", "//");
+		assert_eq!(
+			prepared.restore(&formatted).unwrap(),
+			source.replace("  //", "//")
+		);
+		assert!(to_backend("const a = 1;\n", FileType::Jsx).is_none());
+		assert!(
+			to_backend("x = 'fama-align'\n", FileType::JavaScript).is_none()
+		);
+	}
+
+	#[test]
+	fn test_python_round_trip() {
+		let source = concat!(
+			"def f():\n",
+			"    # fmt: off\n",
+			"    # fama-align\n",
+			"    m = [[1, 0],  # \"]\"\n",
+			"         [0, 1]]\n",
+			"    return m\n",
+			"# fmt: on\n",
+		);
+		let prepared = to_backend(source, FileType::Python).unwrap();
+		assert_eq!(
+			prepared.source,
+			concat!(
+				"def f():\n",
+				"    # fmt: off\n",
+				"    # fama-align\n",
+				"    # fmt: off\n",
+				"    m = [[1, 0],  # \"]\"\n",
+				"         [0, 1]]\n",
+				"    # fmt: on\n",
+				"    return m\n",
+				"# fmt: on\n",
+			)
+		);
+		assert_eq!(prepared.restore(&prepared.source).unwrap(), source);
+		assert!(to_backend("x = 1\n", FileType::Python).is_none());
+
+		// A statement on the last line without a trailing newline
+		let prepared =
+			to_backend("# fama-align\nx = (1,  2)", FileType::Python);
+		assert_eq!(
+			prepared.unwrap().source,
+			"# fama-align\n# fmt: off\nx = (1,  2)\n# fmt: on\n"
+		);
+	}
+
+	#[test]
+	fn test_statement_end() {
+		let end = |source: &str| {
+			let lines: Vec<&str> = source.split_inclusive('\n').collect();
+			statement_end(&lines, 0)
+		};
+		assert_eq!(end("\nx = (1,\n  2)\ny = 3\n"), Some(2));
+		assert_eq!(end("s = '''(\n'''\nt = 1\n"), Some(1));
+		assert_eq!(end("x = 1 + \\\n  2\n"), Some(1));
+		assert_eq!(end("x = '(' + \"\\\"[\"\n"), Some(0));
+		assert_eq!(end("\n\n"), None);
+	}
+}
@@ -0,0 +1,411 @@
+// backend.rs - FormatterBackend implementations for the formatter crates
+
+use fama_common::{ClangFallback, FileType, FormatterBackend, GoStyle, CONFIG};
+
+type FormatFn = fn(&str, &str, FileType) -> Result<String, String>;
+
+/// A backend that formats one file per call
+struct Single {
+	name: &'static str,
+	format: FormatFn,
+}
+
+impl FormatterBackend for Single {
+	fn name(&self) -> &'static str {
+		self.name
+	}
+
+	fn format(
+		&self,
+		source: &str,
+		path: &str,
+		file_type: FileType,
+	) -> Result<String, String> {
+		(self.format)(source, path, file_type)
+	}
+}
+
+/// Go FFI backend; batches cross the FFI boundary in one call
+#[cfg(feature = "native")]
+struct Goffi;
+
+#[cfg(feature = "native")]
+impl FormatterBackend for Goffi {
+	fn name(&self) -> &'static str {
+		"goffi"
+	}
+
+	fn format(
+		&self,
+		source: &str,
+		path: &str,
+		file_type: FileType,
+	) -> Result<String, String> {
+		goffi::format_file(source, path, file_type)
+	}
+
+	fn format_batch(
+		&self,
+		files: &[(&str, &str)],
+		file_type: FileType,
+	) -> Vec<Result<String, String>> {
+		let sources: Vec<&str> =
+			files.iter().map(|(_, source)| *source).collect();
+		match file_type {
+			FileType::Shell => goffi::format_shell_batch(&sources),
+			FileType::Go => goffi::format_go_batch(&sources),
+			FileType::Hcl => goffi::format_hcl_batch(&sources),
+			_ => files
+				.iter()
+				.map(|(path, source)| self.format(source, path, file_type))
+				.collect(),
+		}
+	}
+}
+
+/// Zig FFI backend; batches cross the FFI boundary in one call
+#[cfg(feature = "native")]
+struct Zigffi;
+
+#[cfg(feature = "native")]
+impl FormatterBackend for Zigffi {
+	fn name(&self) -> &'static str {
+		"zigffi"
+	}
+
+	fn format(
+		&self,
+		source: &str,
+		path: &str,
+		_file_type: FileType,
+	) -> Result<String, String> {
+		zigffi::format_zig(source, path)
+	}
+
+	fn format_batch(
+		&self,
+		files: &[(&str, &str)],
+		_file_type: FileType,
+	) -> Vec<Result<String, String>> {
+		let sources: Vec<&str> =
+			files.iter().map(|(_, source)| *source).collect();
+		zigffi::format_zig_batch(&sources)
+	}
+}
+
+/// clang-format WASM backend; batches share one instance
+struct Clang;
+
+impl FormatterBackend for Clang {
+	fn name(&self) -> &'static str {
+		"clang-format"
+	}
+
+	fn format(
+		&self,
+		source: &str,
+		path: &str,
+		file_type: FileType,
+	) -> Result<String, String> {
+		fama_clang::format_file(source, path, file_type)
+			.or_else(|error| clang_fallback(source, path, error))
+	}
+
+	fn format_batch(
+		&self,
+		files: &[(&str, &str)],
+		_file_type: FileType,
+	) -> Vec<Result<String, String>> {
+		fama_clang::format_batch(files)
+			.into_iter()
+			.zip(files)
+			.map(|(result, (path, source))| {
+				result.or_else(|error| clang_fallback(source, path, error))
+			})
+			.collect()
+	}
+}
+
+/// Retry a file the WASM build failed on, if `clang_fallback` allows it
+fn clang_fallback(
+	source: &str,
+	path: &str,
+	error: String,
+) -> Result<String, String> {
+	match CONFIG.clang_fallback {
+		ClangFallback::Never => Err(error),
+		ClangFallback::System => {
+			fama_process::format_clang(source, path, fama_clang::style_config())
+				.map_err(|fallback| {
+					format!(
+						"{}; system clang-format fallback: {}",
+						error, fallback
+					)
+				})
+		}
+	}
+}
+
+static BIOME: Single = Single {
+	name: "biome",
+	format: biome::format_file,
+};
+static DPRINT: Single = Single {
+	name: "dprint",
+	format: dprint::format_file,
+};
+static TAPLO: Single = Single {
+	name: "taplo",
+	format: |source, path, _| toml_fmt::format_toml(source, path),
+};
+static RUSTFMT: Single = Single {
+	name: "rustfmt",
+	format: |source, path, _| rustfmt::format_rust(source, path),
+};
+static RUFF: Single = Single {
+	name: "ruff",
+	format: |source, path, _| ruff::format_python(source, path),
+};
+static RUFF_STARLARK: Single = Single {
+	name: "ruff-starlark",
+	format: |source, path, _| ruff::format_starlark(source, path),
+};
+static STYLUA: Single = Single {
+	name: "stylua",
+	format: |source, path, _| stylua::format_lua(source, path),
+};
+#[cfg(feature = "native")]
+static RUBYFMT: Single = Single {
+	name: "rubyfmt",
+	format: |source, path, _| ruby_fmt::format_ruby(source, path),
+};
+#[cfg(feature = "native")]
+static GROOVY: Single = Single {
+	name: "tree-sitter-groovy",
+	format: |source, path, _| fama_groovy::format_groovy(source, path),
+};
+static DOCKERFILE: Single = Single {
+	name: "dprint-dockerfile",
+	format: |source, path, _| dockerfile::format_dockerfile(source, path),
+};
+static QUICK_XML: Single = Single {
+	name: "quick-xml",
+	format: |source, path, _| xml_fmt::format_xml(source, path),
+};
+static SQRUFF: Single = Single {
+	name: "sqruff",
+	format: |source, path, _| fama_sqruff::format_sql(source, path),
+};
+static MAGO: Single = Single {
+	name: "mago",
+	format: |source, path, _| php_fmt::format_php(source, path),
+};
+static KTFMT: Single = Single {
+	name: "ktfmt",
+	format: |source, path, _| fama_process::format_kotlin(source, path),
+};
+static GOFUMPT: Single = Single {
+	name: "gofumpt",
+	format: |source, path, _| fama_process::format_gofumpt(source, path),
+};
+/// Stands in for backends that link native code in builds without the
+/// native feature, such as wasm32-wasip1
+#[cfg(not(feature = "native"))]
+static NATIVE_ONLY: Single = Single {
+	name: "unavailable",
+	format: |_, _, file_type| {
+		Err(format!(
+			"{:?} needs a native backend, which this build of fama \
+			 does not include",
+			file_type
+		))
+	},
+};
+static NONE: Single = Single {
+	name: "none",
+	format: |_, _, _| Err("Unknown file type".to_string()),
+};
+
+// Every worker thread formats through the same backends; these stop
+// compiling if one of them picks up state that can't be shared
+const _: () = {
+	const fn assert_thread_safe<T: Send + Sync + ?Sized>() {}
+	assert_thread_safe::<dyn FormatterBackend>();
+	assert_thread_safe::<Single>();
+	assert_thread_safe::<Clang>();
+	#[cfg(feature = "native")]
+	assert_thread_safe::<Goffi>();
+	#[cfg(feature = "native")]
+	assert_thread_safe::<Zigffi>();
+};
+
+/// The backend that handles a file type
+pub fn backend_for(file_type: FileType) -> &'static dyn FormatterBackend {
+	match file_type {
+		// Web files -> biome
+		FileType::JavaScript
+		| FileType::TypeScript
+		| FileType::Jsx
+		| FileType::Tsx
+		| FileType::Json
+		| FileType::Jsonc
+		| FileType::Html
+		| FileType::Vue
+		| FileType::Svelte
+		| FileType::Astro
+		| FileType::GraphQL => &BIOME,
+
+		// Data + Style files -> dprint
+		FileType::Yaml
+		| FileType::Markdown
+		| FileType::Css
+		| FileType::Scss
+		| FileType::Less
+		| FileType::Sass => &DPRINT,
+
+		// C-family languages -> clang-format
+		FileType::C
+		| FileType::Cpp
+		| FileType::CSharp
+		| FileType::ObjectiveC
+		| FileType::ObjectiveCpp
+		| FileType::Cuda
+		| FileType::Shader
+		| FileType::Java
+		| FileType::Protobuf => &Clang,
+
+		// Individual formatters
+		FileType::Toml => &TAPLO,
+		FileType::Rust => &RUSTFMT,
+		FileType::Python => &RUFF,
+		FileType::Starlark => &RUFF_STARLARK,
+		FileType::Lua => &STYLUA,
+		FileType::Go if CONFIG.go_style == GoStyle::Gofumpt => &GOFUMPT,
+		#[cfg(feature = "native")]
+		FileType::Ruby => &RUBYFMT,
+		#[cfg(feature = "native")]
+		FileType::Shell | FileType::Go | FileType::GoMod | FileType::Hcl => &Goffi,
+		#[cfg(feature = "native")]
+		FileType::Zig => &Zigffi,
+		#[cfg(feature = "native")]
+		FileType::Groovy => &GROOVY,
+		#[cfg(not(feature = "native"))]
+		FileType::Ruby
+		| FileType::Shell
+		| FileType::Go
+		| FileType::GoMod
+		| FileType::Hcl
+		| FileType::Zig
+		| FileType::Groovy => &NATIVE_ONLY,
+		FileType::Dockerfile => &DOCKERFILE,
+		FileType::Xml => &QUICK_XML,
+		FileType::Sql => &SQRUFF,
+		FileType::Php => &MAGO,
+		FileType::Kotlin => &KTFMT,
+
+		FileType::Unknown => &NONE,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_backend_names() {
+		assert_eq!(backend_for(FileType::Tsx).name(), "biome");
+		assert_eq!(backend_for(FileType::Cpp).name(), "clang-format");
+		#[cfg(feature = "native")]
+		assert_eq!(backend_for(FileType::Hcl).name(), "goffi");
+		#[cfg(not(feature = "native"))]
+		assert_eq!(backend_for(FileType::Hcl).name(), "unavailable");
+		assert_eq!(backend_for(FileType::Unknown).name(), "none");
+	}
+
+	#[test]
+	fn test_format_batch_keeps_order() {
+		let files = [
+			("a.xml", "<a><b/></a>"),
+			("b.xml", "<a>"),
+			("c.xml", "<c/>"),
+		];
+		let backend = backend_for(FileType::Xml);
+		let results = backend.format_batch(&files, FileType::Xml);
+		assert_eq!(results.len(), files.len());
+		for ((path, source), result) in files.iter().zip(results) {
+			assert_eq!(result, backend.format(source, path, FileType::Xml));
+		}
+	}
+
+	/// Format with every backend from many threads at once, single files
+	/// and batches interleaved, and expect the single-threaded results;
+	/// hidden global state in a backend shows up as a mismatch or a crash
+	#[test]
+	fn test_backends_are_reentrant() {
+		// ktfmt is left out: it needs a JVM on PATH
+		let samples = [
+			("a.ts", FileType::TypeScript, "const a = {b:1}"),
+			("a.yaml", FileType::Yaml, "a:   1"),
+			("a.cpp", FileType::Cpp, "int main(){return 0;}"),
+			("a.toml", FileType::Toml, "a=1"),
+			("a.rs", FileType::Rust, "fn main(){}"),
+			("a.py", FileType::Python, "a=[1,2]"),
+			("BUILD", FileType::Starlark, "exports_files([ 'a' ])"),
+			("a.lua", FileType::Lua, "local a={1,2}"),
+			("a.rb", FileType::Ruby, "def a;1;end"),
+			("a.sh", FileType::Shell, "if true;then echo a;fi"),
+			("a.go", FileType::Go, "package a\nvar b=1"),
+			("go.mod", FileType::GoMod, "module a\ngo   1.22"),
+			("a.hcl", FileType::Hcl, "a=1"),
+			("a.zig", FileType::Zig, "const a=1;"),
+			("build.gradle", FileType::Groovy, "plugins{id 'java'}"),
+			("Dockerfile", FileType::Dockerfile, "from alpine"),
+			("a.xml", FileType::Xml, "<a><b/></a>"),
+			("a.sql", FileType::Sql, "select 1"),
+			("a.php", FileType::Php, "<?php echo  1;"),
+		];
+		let samples: Vec<_> = samples
+			.into_iter()
+			.filter(|(_, file_type, _)| {
+				backend_for(*file_type).name() != "unavailable"
+			})
+			.collect();
+		let expected: Vec<_> = samples
+			.iter()
+			.map(|(path, file_type, source)| {
+				backend_for(*file_type).format(source, path, *file_type)
+			})
+			.collect();
+
+		std::thread::scope(|scope| {
+			for thread in 0..8 {
+				let (samples, expected) = (&samples, &expected);
+				scope.spawn(move || {
+					for round in 0..4 {
+						// Start each thread on a different backend
+						for i in 0..samples.len() {
+							let i = (i + thread * 3) % samples.len();
+							let (path, file_type, source) = samples[i];
+							let backend = backend_for(file_type);
+							let result = if (thread + round) % 2 == 0 {
+								backend.format(source, path, file_type)
+							} else {
+								backend
+									.format_batch(&[(path, source)], file_type)
+									.remove(0)
+							};
+							assert_eq!(result, expected[i], "{}", path);
+						}
+					}
+				});
+			}
+		});
+	}
+
+	#[test]
+	fn test_unknown_backend_fails() {
+		let results = backend_for(FileType::Unknown)
+			.format_batch(&[("a.xyz", "x")], FileType::Unknown);
+		assert_eq!(results, [Err("Unknown file type".to_string())]);
+	}
+}
@@ -0,0 +1,204 @@
+// baseline.rs - Known unformatted files for `--check --baseline`
+//
+// A baseline lists files that were unformatted when fama was adopted.
+// Check runs then fail only on unformatted files outside it, so new
+// code is held to the formatter while old code is migrated over time.
+// `--update-baseline` replaces the entries of the files a run checked
+// with the ones that still need formatting, so fixed files drop out and
+// new ones only get in when someone asks for it.
+
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A baseline file and its entries. Entries are relative to the directory
+/// holding the file, so a baseline gives the same answers whichever
+/// directory fama runs from.
+pub struct Baseline {
+	path: PathBuf,
+	root: PathBuf,
+	pub entries: BTreeSet<String>,
+}
+
+impl Baseline {
+	/// Read the baseline at `path`; a missing file is an empty baseline
+	pub fn load(path: &Path) -> anyhow::Result<Self> {
+		let root = match path.parent() {
+			Some(parent) if !parent.as_os_str().is_empty() => parent,
+			_ => Path::new("."),
+		};
+		Ok(Baseline {
+			path: path.to_path_buf(),
+			root: absolute(root)?,
+			entries: read(path)?,
+		})
+	}
+
+	/// Entry for `path`: relative to the baseline's directory and
+	/// `/`-separated, or absolute for a file outside it
+	pub fn key(&self, path: &Path) -> String {
+		let path = absolute(path).unwrap_or_else(|_| path.to_path_buf());
+		let path = path.strip_prefix(&self.root).unwrap_or(&path);
+		path.to_string_lossy().replace('\\', "/")
+	}
+
+	/// Compare the `unformatted` files among `checked` to the entries
+	pub fn compare(
+		&self,
+		checked: &[PathBuf],
+		unformatted: &[PathBuf],
+	) -> Comparison {
+		let unformatted_keys: BTreeSet<String> =
+			unformatted.iter().map(|path| self.key(path)).collect();
+		let (known, new): (Vec<&PathBuf>, Vec<&PathBuf>) = unformatted
+			.iter()
+			.partition(|path| self.entries.contains(&self.key(path)));
+		let fixed = checked
+			.iter()
+			.map(|path| self.key(path))
+			.filter(|k| {
+				self.entries.contains(k) && !unformatted_keys.contains(k)
+			})
+			.count();
+		Comparison {
+			new: new.into_iter().cloned().collect(),
+			known: known.len(),
+			fixed,
+		}
+	}
+
+	/// Replace the entries of the `checked` files with the `unformatted`
+	/// ones; others are kept while their files exist
+	pub fn update(&mut self, checked: &[PathBuf], unformatted: &[PathBuf]) {
+		let checked: BTreeSet<String> =
+			checked.iter().map(|path| self.key(path)).collect();
+		let unformatted: Vec<String> =
+			unformatted.iter().map(|path| self.key(path)).collect();
+		let root = &self.root;
+		self.entries.retain(|entry| {
+			!checked.contains(entry) && root.join(entry).exists()
+		});
+		self.entries.extend(unformatted);
+	}
+
+	/// Write the entries back to the baseline file
+	pub fn save(&self) -> anyhow::Result<()> {
+		let value = json!({ "version": 1, "files": self.entries });
+		let mut text = serde_json::to_string_pretty(&value)?;
+		text.push('\n');
+		fs::write(&self.path, text).map_err(|e| {
+			anyhow::anyhow!(
+				"Failed to write baseline {}: {}",
+				self.path.display(),
+				e
+			)
+		})
+	}
+}
+
+/// Entries of the baseline file at `path`, or none if it doesn't exist
+fn read(path: &Path) -> anyhow::Result<BTreeSet<String>> {
+	let text = match fs::read_to_string(path) {
+		Ok(text) => text,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+			return Ok(BTreeSet::new());
+		}
+		Err(e) => {
+			anyhow::bail!("Failed to read baseline {}: {}", path.display(), e)
+		}
+	};
+	let value: serde_json::Value = serde_json::from_str(&text)
+		.map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+	let files = value["files"].as_array().ok_or_else(|| {
+		anyhow::anyhow!("{}: expected a \"files\" array", path.display())
+	})?;
+	Ok(files
+		.iter()
+		.filter_map(|file| file.as_str().map(str::to_string))
+		.collect())
+}
+
+/// `path` made absolute against the working directory, with `.` and `..`
+/// folded away without touching the filesystem
+fn absolute(path: &Path) -> std::io::Result<PathBuf> {
+	let mut resolved = PathBuf::new();
+	for component in std::path::absolute(path)?.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				resolved.pop();
+			}
+			component => resolved.push(component),
+		}
+	}
+	Ok(resolved)
+}
+
+/// How a check run's unformatted files compare to the baseline
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Comparison {
+	/// Unformatted files that are not in the baseline
+	pub new: Vec<PathBuf>,
+	/// Unformatted files that are in the baseline
+	pub known: usize,
+	/// Baseline entries that were checked and are formatted now
+	pub fixed: usize,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn paths(dir: &Path, names: &[&str]) -> Vec<PathBuf> {
+		names.iter().map(|name| dir.join(name)).collect()
+	}
+
+	#[test]
+	fn test_compare() {
+		let dir = tempfile::tempdir().unwrap();
+		let mut baseline = Baseline::load(&dir.path().join("b.json")).unwrap();
+		baseline.entries = BTreeSet::from(["a.ts".into(), "src/b.ts".into()]);
+		let checked = paths(dir.path(), &["./a.ts", "src/b.ts", "c.ts"]);
+		let unformatted = paths(dir.path(), &["./a.ts", "src/../c.ts"]);
+		assert_eq!(
+			baseline.compare(&checked, &unformatted),
+			Comparison {
+				new: paths(dir.path(), &["src/../c.ts"]),
+				known: 1,
+				fixed: 1,
+			}
+		);
+	}
+
+	#[test]
+	fn test_keys_follow_the_baseline_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let baseline =
+			Baseline::load(&dir.path().join("sub/../b.json")).unwrap();
+		assert_eq!(baseline.key(&dir.path().join("src/./a.ts")), "src/a.ts");
+		let outside = Path::new("/elsewhere/a.ts");
+		assert_eq!(baseline.key(outside), "/elsewhere/a.ts");
+	}
+
+	#[test]
+	fn test_save_and_update() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("baseline.json");
+		let mut baseline = Baseline::load(&path).unwrap();
+		assert!(baseline.entries.is_empty());
+
+		// Entries for files this run didn't check and that no longer exist
+		// are dropped; ones it did check follow the run
+		fs::write(dir.path().join("kept.ts"), "").unwrap();
+		baseline.entries =
+			BTreeSet::from(["kept.ts".into(), "gone.ts".into(), "a.ts".into()]);
+		let checked = paths(dir.path(), &["a.ts", "b.ts"]);
+		baseline.update(&checked, &paths(dir.path(), &["b.ts"]));
+		let expected = BTreeSet::from(["kept.ts".into(), "b.ts".into()]);
+		assert_eq!(baseline.entries, expected);
+
+		baseline.save().unwrap();
+		assert_eq!(Baseline::load(&path).unwrap().entries, expected);
+	}
+}
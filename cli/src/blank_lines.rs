@@ -0,0 +1,421 @@
+// blank_lines.rs - Language-agnostic cap on consecutive blank lines
+
+use fama_common::FileType;
+
+/// Open/close delimiter pairs
+type Pairs = &'static [(&'static str, &'static str)];
+
+/// The lexical syntax the post-pass needs to find verbatim spans
+#[derive(Debug)]
+pub struct Syntax {
+	/// Spans whose blank lines are content
+	spans: Pairs,
+	/// Single-line string quotes, skipped with backslash escapes
+	quotes: &'static [char],
+	/// Line comment markers
+	line_comments: &'static [&'static str],
+	/// Block comments, whose blank lines are not content
+	block_comments: Pairs,
+	/// Spans only open and close at the start of a line (Markdown fences),
+	/// so nothing else on a line needs scanning
+	fenced: bool,
+}
+
+const SCRIPT: Syntax = Syntax {
+	spans: &[("`", "`")],
+	quotes: &['"', '\''],
+	line_comments: &["//"],
+	block_comments: &[("/*", "*/")],
+	fenced: false,
+};
+
+/// Markup text is prose, where an apostrophe is not a quote and `//` is
+/// not a comment
+const MARKUP: Syntax = Syntax {
+	spans: &[("`", "`"), ("<pre", "</pre>"), ("<textarea", "</textarea>")],
+	quotes: &['"'],
+	line_comments: &[],
+	block_comments: &[("<!--", "-->"), ("/*", "*/")],
+	fenced: false,
+};
+
+const PYTHON: Syntax = Syntax {
+	spans: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+	quotes: &['"', '\''],
+	line_comments: &["#"],
+	block_comments: &[],
+	fenced: false,
+};
+
+const JVM: Syntax = Syntax {
+	spans: &[("\"\"\"", "\"\"\"")],
+	..SCRIPT
+};
+
+const GRAPHQL: Syntax = Syntax {
+	spans: &[("\"\"\"", "\"\"\"")],
+	quotes: &['"'],
+	line_comments: &["#"],
+	block_comments: &[],
+	fenced: false,
+};
+
+const GROOVY: Syntax = Syntax {
+	spans: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+	..JVM
+};
+
+const CSHARP: Syntax = Syntax {
+	spans: &[("\"\"\"", "\"\"\""), ("@\"", "\"")],
+	..JVM
+};
+
+const CPP: Syntax = Syntax {
+	spans: &[("R\"(", ")\"")],
+	..JVM
+};
+
+const MARKDOWN: Syntax = Syntax {
+	spans: &[("```", "```"), ("~~~", "~~~")],
+	quotes: &[],
+	line_comments: &[],
+	block_comments: &[],
+	fenced: true,
+};
+
+const LUA: Syntax = Syntax {
+	spans: &[("[[", "]]")],
+	quotes: &['"', '\''],
+	line_comments: &["--"],
+	block_comments: &[("--[[", "]]")],
+	fenced: false,
+};
+
+const XML: Syntax = Syntax {
+	spans: &[("<![CDATA[", "]]>")],
+	quotes: &['"'],
+	line_comments: &[],
+	block_comments: &[("<!--", "-->")],
+	fenced: false,
+};
+
+const NO_SPANS: Syntax = Syntax {
+	spans: &[],
+	quotes: &[],
+	line_comments: &[],
+	block_comments: &[],
+	fenced: false,
+};
+
+/// Multi-line string/template spans per language, used as hints so the
+/// post-pass never touches blank lines inside them.
+///
+/// Returns `None` for languages where spans can't be found by simple
+/// delimiter matching (heredocs, YAML block scalars, Rust raw strings);
+/// those rely on their backend alone, except HCL, whose heredocs
+/// `limit_blank_lines_hcl` tracks.
+pub fn verbatim_spans(file_type: FileType) -> Option<&'static Syntax> {
+	match file_type {
+		FileType::JavaScript
+		| FileType::TypeScript
+		| FileType::Jsx
+		| FileType::Tsx
+		| FileType::Go => Some(&SCRIPT),
+		FileType::Html | FileType::Vue | FileType::Svelte | FileType::Astro => {
+			Some(&MARKUP)
+		}
+		FileType::Python | FileType::Starlark | FileType::Toml => Some(&PYTHON),
+		FileType::Kotlin | FileType::Java => Some(&JVM),
+		FileType::GraphQL => Some(&GRAPHQL),
+		FileType::Groovy => Some(&GROOVY),
+		FileType::CSharp => Some(&CSHARP),
+		FileType::Cpp | FileType::ObjectiveCpp | FileType::Cuda => Some(&CPP),
+		FileType::Markdown => Some(&MARKDOWN),
+		FileType::Lua => Some(&LUA),
+		FileType::Xml => Some(&XML),
+		FileType::Json
+		| FileType::Jsonc
+		| FileType::Css
+		| FileType::Scss
+		| FileType::Less
+		| FileType::Sass
+		| FileType::C
+		| FileType::ObjectiveC
+		| FileType::Shader
+		| FileType::Protobuf
+		| FileType::GoMod
+		| FileType::Zig => Some(&NO_SPANS),
+		FileType::Yaml
+		| FileType::Rust
+		| FileType::Ruby
+		| FileType::Shell
+		| FileType::Hcl
+		| FileType::Dockerfile
+		| FileType::Sql
+		| FileType::Php
+		| FileType::Unknown => None,
+	}
+}
+
+/// Collapse runs of more than `max` blank lines outside of verbatim spans
+pub fn limit_blank_lines(
+	content: &str,
+	max: usize,
+	syntax: &'static Syntax,
+) -> String {
+	let mut scanner = SpanScanner::new(syntax);
+	limit(content, max, |line| scanner.scan(line))
+}
+
+/// Collapse runs of more than `max` blank lines outside of HCL heredocs
+/// (`<<EOT` or `<<-EOT` through the line holding only `EOT`)
+pub fn limit_blank_lines_hcl(content: &str, max: usize) -> String {
+	let mut heredoc: Option<&str> = None;
+	limit(content, max, |line| {
+		heredoc = match heredoc {
+			Some(marker) if line.trim() == marker => None,
+			Some(marker) => Some(marker),
+			None => heredoc_marker(line),
+		};
+		heredoc.is_some()
+	})
+}
+
+/// Collapse blank line runs; `in_span` sees each kept line and says
+/// whether the lines after it are inside a verbatim span
+fn limit<'a>(
+	content: &'a str,
+	max: usize,
+	mut in_span: impl FnMut(&'a str) -> bool,
+) -> String {
+	let mut result = String::with_capacity(content.len());
+	let mut blank_run = 0;
+	let mut open = false;
+
+	for line in content.split_inclusive('\n') {
+		if !open && line.trim().is_empty() {
+			blank_run += 1;
+			if blank_run > max {
+				continue;
+			}
+		} else {
+			blank_run = 0;
+		}
+		result.push_str(line);
+		open = in_span(line);
+	}
+
+	result
+}
+
+/// Closing marker of a heredoc opened at the end of `line`
+fn heredoc_marker(line: &str) -> Option<&str> {
+	let (_, rest) = line.rsplit_once("<<")?;
+	let marker = rest.strip_prefix('-').unwrap_or(rest).trim_end();
+	let identifier = marker.starts_with(|c: char| c.is_alphabetic())
+		&& marker.chars().all(|c| c.is_alphanumeric() || c == '_');
+	identifier.then_some(marker)
+}
+
+/// What a line left open
+#[derive(Debug, Clone, Copy)]
+enum Open {
+	/// A verbatim span, until its close delimiter
+	Span(&'static str),
+	/// A block comment, until its close delimiter
+	Comment(&'static str),
+}
+
+/// What the scanner found next on a line
+enum Token {
+	Open(Open, usize),
+	LineComment,
+	Quote(char),
+}
+
+/// Tracks, line by line, whether the text after a line is inside a
+/// verbatim span, skipping strings and comments so a delimiter inside one
+/// never opens a span
+#[derive(Debug)]
+pub struct SpanScanner {
+	syntax: &'static Syntax,
+	open: Option<Open>,
+}
+
+impl SpanScanner {
+	pub fn new(syntax: &'static Syntax) -> Self {
+		Self { syntax, open: None }
+	}
+
+	/// Scan one line; returns whether the lines after it are inside a
+	/// verbatim span
+	pub fn scan(&mut self, line: &str) -> bool {
+		if self.syntax.fenced {
+			self.scan_fence(line);
+		} else {
+			self.scan_tokens(line);
+		}
+		matches!(self.open, Some(Open::Span(_)))
+	}
+
+	fn scan_fence(&mut self, line: &str) {
+		let line = line.trim_start();
+		self.open = match self.open {
+			Some(Open::Span(close)) if line.starts_with(close) => None,
+			Some(open) => Some(open),
+			None => self
+				.syntax
+				.spans
+				.iter()
+				.find(|(open, _)| line.starts_with(open))
+				.map(|&(_, close)| Open::Span(close)),
+		};
+	}
+
+	fn scan_tokens(&mut self, line: &str) {
+		let mut rest = line;
+		loop {
+			if let Some(Open::Span(close) | Open::Comment(close)) = self.open {
+				let Some(i) = rest.find(close) else { return };
+				rest = &rest[i + close.len()..];
+				self.open = None;
+			}
+			let Some((i, token)) = self.next_token(rest) else {
+				return;
+			};
+			match token {
+				Token::Open(open, len) => {
+					rest = &rest[i + len..];
+					self.open = Some(open);
+				}
+				Token::LineComment => return,
+				// An unterminated string ends with its line
+				Token::Quote(quote) => {
+					let Some(end) = string_end(&rest[i + 1..], quote) else {
+						return;
+					};
+					rest = &rest[i + 1 + end..];
+				}
+			}
+		}
+	}
+
+	/// The first span, comment or string opening in `rest`. Spans are
+	/// tried first, since their openers can start with a quote (`"""`) or
+	/// a comment marker
+	fn next_token(&self, rest: &str) -> Option<(usize, Token)> {
+		let syntax = self.syntax;
+		rest.char_indices().find_map(|(i, c)| {
+			let here = &rest[i..];
+			let token = if let Some(&(open, close)) =
+				syntax.spans.iter().find(|(open, _)| here.starts_with(open))
+			{
+				Token::Open(Open::Span(close), open.len())
+			} else if let Some(&(open, close)) = syntax
+				.block_comments
+				.iter()
+				.find(|(open, _)| here.starts_with(open))
+			{
+				Token::Open(Open::Comment(close), open.len())
+			} else if syntax.line_comments.iter().any(|m| here.starts_with(m)) {
+				Token::LineComment
+			} else if syntax.quotes.contains(&c) {
+				Token::Quote(c)
+			} else {
+				return None;
+			};
+			Some((i, token))
+		})
+	}
+}
+
+/// Byte offset just past the quote closing a string whose body starts
+/// `body`
+fn string_end(body: &str, quote: char) -> Option<usize> {
+	let mut chars = body.char_indices();
+	while let Some((i, c)) = chars.next() {
+		if c == '\\' {
+			chars.next();
+		} else if c == quote {
+			return Some(i + c.len_utf8());
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_limit_blank_lines() {
+		let content = "a\n\n\n\nb\n\nc\n";
+		assert_eq!(limit_blank_lines(content, 1, &NO_SPANS), "a\n\nb\n\nc\n");
+		assert_eq!(limit_blank_lines(content, 0, &NO_SPANS), "a\nb\nc\n");
+	}
+
+	#[test]
+	fn test_whitespace_only_lines_count_as_blank() {
+		let content = "a\n  \n\t\r\n\nb";
+		assert_eq!(limit_blank_lines(content, 1, &NO_SPANS), "a\n  \nb");
+	}
+
+	#[test]
+	fn test_spans_are_kept_verbatim() {
+		let content = "const a = `x\n\n\n\ny`;\n\n\n\nb;\n";
+		let syntax = verbatim_spans(FileType::JavaScript).unwrap();
+		assert_eq!(
+			limit_blank_lines(content, 1, syntax),
+			"const a = `x\n\n\n\ny`;\n\nb;\n"
+		);
+	}
+
+	#[test]
+	fn test_span_opened_and_closed_on_one_line() {
+		let content = "a = \"\"\"doc\"\"\"\n\n\n\nb = 1\n";
+		let syntax = verbatim_spans(FileType::Python).unwrap();
+		assert_eq!(
+			limit_blank_lines(content, 1, syntax),
+			"a = \"\"\"doc\"\"\"\n\nb = 1\n"
+		);
+	}
+
+	#[test]
+	fn test_delimiters_in_strings_and_comments_open_no_span() {
+		let content =
+			"const q = \"`\"; // `\nconst a = `x\n\n\n\ny`;\n\n\n\nb;\n";
+		let syntax = verbatim_spans(FileType::JavaScript).unwrap();
+		assert_eq!(
+			limit_blank_lines(content, 1, syntax),
+			"const q = \"`\"; // `\nconst a = `x\n\n\n\ny`;\n\nb;\n"
+		);
+
+		let content = "# say \"\"\"\n\n\n\nb = 1\n";
+		let syntax = verbatim_spans(FileType::Python).unwrap();
+		assert_eq!(
+			limit_blank_lines(content, 1, syntax),
+			"# say \"\"\"\n\nb = 1\n"
+		);
+	}
+
+	#[test]
+	fn test_markdown_fences_only_open_at_line_start() {
+		let content = "Write `` ``` `` to fence.\n\n\n\n```\nx\n\n\n\ny\n```\n";
+		let syntax = verbatim_spans(FileType::Markdown).unwrap();
+		assert_eq!(
+			limit_blank_lines(content, 1, syntax),
+			"Write `` ``` `` to fence.\n\n```\nx\n\n\n\ny\n```\n"
+		);
+	}
+
+	#[test]
+	fn test_hcl_heredocs_are_kept_verbatim() {
+		let content = "a = <<-EOT\n  x\n\n\n\n  EOT\n\n\n\nb = 1\n";
+		assert_eq!(
+			limit_blank_lines_hcl(content, 1),
+			"a = <<-EOT\n  x\n\n\n\n  EOT\n\nb = 1\n"
+		);
+		assert_eq!(heredoc_marker("policy = <<EOT\n"), Some("EOT"));
+		assert_eq!(heredoc_marker("x = 1 << 2\n"), None);
+	}
+}
@@ -0,0 +1,32 @@
+// cancel.rs - Ctrl-C handling for long runs
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Install the Ctrl-C handler.
+///
+/// The first Ctrl-C only sets a flag: files already being formatted finish
+/// and are written, no new files start, and the run ends with its partial
+/// summary. Subprocess backends share the terminal's process group, so they
+/// receive the same interrupt and exit on their own.
+#[cfg(feature = "native")]
+pub fn install() {
+	let result = ctrlc::set_handler(|| {
+		if !CANCELLED.swap(true, Ordering::SeqCst) {
+			eprintln!("Cancelling, waiting for files in progress...");
+		}
+	});
+	if let Err(e) = result {
+		tracing::warn!("failed to install Ctrl-C handler: {}", e);
+	}
+}
+
+/// Without the native feature (WASI) there are no signals to handle
+#[cfg(not(feature = "native"))]
+pub fn install() {}
+
+/// Whether the run was cancelled; files not yet started should be skipped
+pub fn requested() -> bool {
+	CANCELLED.load(Ordering::SeqCst)
+}
@@ -0,0 +1,161 @@
+// comments.rs - Comment counts before and after formatting
+//
+// Re-serializing YAML, XML, TOML or JSON is where comments get lost, so
+// their comments are counted before and after formatting, along with
+// those of the CSS and Python families. The count is lexical, not a
+// parse: strings are skipped, and anything else that looks like a comment
+// counts as one, which is all a before/after comparison needs.
+
+use fama_common::{FileType, JsoncOutput, CONFIG};
+
+/// Number of comments in `text`, for the file types that are counted
+pub fn count(file_type: FileType, text: &str) -> Option<usize> {
+	match file_type {
+		FileType::Yaml => Some(count_hash(text, true)),
+		FileType::Toml | FileType::Python | FileType::Starlark => {
+			Some(count_hash(text, false))
+		}
+		FileType::Xml => Some(text.matches("<!--").count()),
+		FileType::Json
+		| FileType::Jsonc
+		| FileType::Css
+		| FileType::Scss
+		| FileType::Less => Some(count_c_style(text)),
+		_ => None,
+	}
+}
+
+/// Comment counts `(before, after)` when formatting `original` to
+/// `formatted` dropped comments it was not configured to drop
+pub fn lost(
+	file_type: FileType,
+	original: &str,
+	formatted: &str,
+) -> Option<(usize, usize)> {
+	if file_type == FileType::Jsonc
+		&& CONFIG.jsonc_output == JsoncOutput::Strict
+	{
+		return None;
+	}
+	let before = count(file_type, original)?;
+	let after = count(file_type, formatted)?;
+	(after < before).then_some((before, after))
+}
+
+/// `#` comments outside quoted strings; YAML only starts a comment at the
+/// start of a line or after whitespace, as `a#b` is a plain scalar
+fn count_hash(text: &str, after_space: bool) -> usize {
+	let mut count = 0;
+	for line in text.lines() {
+		let mut quote = None;
+		let mut previous = ' ';
+		let mut chars = line.chars();
+		while let Some(c) = chars.next() {
+			match quote {
+				Some('"') if c == '\\' => {
+					chars.next();
+				}
+				Some(q) if c == q => quote = None,
+				Some(_) => {}
+				// Quotes only open a value, so `don't` stays a plain scalar
+				None if matches!(c, '"' | '\'')
+					&& (previous.is_whitespace()
+						|| matches!(
+							previous,
+							'[' | '{' | '(' | ',' | ':' | '='
+						)) =>
+				{
+					quote = Some(c)
+				}
+				None if c == '#'
+					&& (!after_space || previous.is_whitespace()) =>
+				{
+					count += 1;
+					break;
+				}
+				None => {}
+			}
+			previous = c;
+		}
+	}
+	count
+}
+
+/// `//` and `/* */` comments outside strings
+fn count_c_style(text: &str) -> usize {
+	let mut count = 0;
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' | '\'' => {
+				while let Some(next) = chars.next() {
+					match next {
+						'\\' => {
+							chars.next();
+						}
+						_ if next == c => break,
+						_ => {}
+					}
+				}
+			}
+			'/' if chars.peek() == Some(&'/') => {
+				count += 1;
+				chars.find(|&c| c == '\n');
+			}
+			'/' if chars.peek() == Some(&'*') => {
+				count += 1;
+				chars.next();
+				let mut previous = ' ';
+				for c in chars.by_ref() {
+					if previous == '*' && c == '/' {
+						break;
+					}
+					previous = c;
+				}
+			}
+			_ => {}
+		}
+	}
+	count
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_count_hash_comments() {
+		let yaml =
+			"# top\na: 1 # trailing\nb: 'x # y'\nc: a#b\nd: \"\\\" #\"\n";
+		assert_eq!(count(FileType::Yaml, yaml), Some(2));
+		assert_eq!(count(FileType::Yaml, "a: don't # c\n"), Some(1));
+		assert_eq!(count(FileType::Toml, "a=1#c\nb=\"#\"\n"), Some(1));
+	}
+
+	#[test]
+	fn test_count_c_style_comments() {
+		let json =
+			"{\n// a\n\"u\": \"http://x\", /* b */\n\"v\": \"\\\"//\"\n}";
+		assert_eq!(count(FileType::Jsonc, json), Some(2));
+		assert_eq!(count(FileType::Xml, "<a><!-- x --><b/></a>"), Some(1));
+		assert_eq!(
+			count(FileType::Css, "a { content: '/*'; } /* b */"),
+			Some(1)
+		);
+		assert_eq!(count(FileType::Rust, "// a"), None);
+	}
+
+	#[test]
+	fn test_lost_comments() {
+		let original = "[a]\n# why\nb = 1 # unit\n";
+		assert_eq!(
+			lost(FileType::Toml, original, "[a]\nb = 1\n"),
+			Some((2, 0))
+		);
+		assert_eq!(lost(FileType::Toml, original, original), None);
+		assert_eq!(
+			lost(FileType::Python, "print('#')\n", "print(\"#\")\n"),
+			None
+		);
+	}
+}
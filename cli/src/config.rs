@@ -0,0 +1,410 @@
+// config.rs - JSON dumps of the config for `fama config`
+//
+// `--resolved` prints what applies to each given file, one JSON object per
+// line, from the same preset/CONFIG/override layering as `fama explain`.
+// `--default` prints the built-in defaults and `--schema` a JSON Schema of
+// `FormatConfig`. Enum values are written by variant name; the macro below
+// matches every variant, so a new one fails to compile until it is listed.
+
+use fama_common::{
+	BraceStyle, ClangFallback, FileType, FormatConfig, GoStyle, IndentStyle,
+	JsoncOutput, LanguageMode, LineEnding, MarkdownTextWrap, Override,
+	PointerAlignment, Preset, QuoteStyle, Semicolons, ShortFunctions,
+	SqlTemplater, TrailingComma, WidthMeasure, CONFIG,
+};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::explain;
+
+/// A config value that can be written as JSON and described by a schema
+pub trait ConfigValue {
+	fn to_json(&self) -> Value;
+	fn schema() -> Value;
+}
+
+impl ConfigValue for bool {
+	fn to_json(&self) -> Value {
+		json!(self)
+	}
+
+	fn schema() -> Value {
+		json!({ "type": "boolean" })
+	}
+}
+
+macro_rules! integer_values {
+	($($ty:ty),*) => {$(
+		impl ConfigValue for $ty {
+			fn to_json(&self) -> Value {
+				json!(self)
+			}
+
+			fn schema() -> Value {
+				let maximum = <$ty>::MAX;
+				json!({ "type": "integer", "minimum": 0, "maximum": maximum })
+			}
+		}
+	)*};
+}
+
+integer_values!(u8, u16);
+
+/// Name/value lists such as `sql_templater_vars`, as an object
+impl ConfigValue for &'static [(&'static str, &'static str)] {
+	fn to_json(&self) -> Value {
+		let map: serde_json::Map<String, Value> = self
+			.iter()
+			.map(|(name, value)| (name.to_string(), json!(value)))
+			.collect();
+		Value::Object(map)
+	}
+
+	fn schema() -> Value {
+		json!({
+			"type": "object",
+			"additionalProperties": { "type": "string" },
+		})
+	}
+}
+
+impl<T: ConfigValue> ConfigValue for Option<T> {
+	fn to_json(&self) -> Value {
+		self.as_ref().map_or(Value::Null, T::to_json)
+	}
+
+	fn schema() -> Value {
+		json!({ "anyOf": [T::schema(), { "type": "null" }] })
+	}
+}
+
+macro_rules! enum_values {
+	($($ty:ident { $($variant:ident),* $(,)? })*) => {$(
+		impl ConfigValue for $ty {
+			fn to_json(&self) -> Value {
+				match self {
+					$($ty::$variant => json!(stringify!($variant)),)*
+				}
+			}
+
+			fn schema() -> Value {
+				json!({ "enum": [$(stringify!($variant)),*] })
+			}
+		}
+	)*};
+}
+
+enum_values! {
+	IndentStyle { Spaces, Tabs }
+	LineEnding { Auto, Lf, Crlf }
+	QuoteStyle { Single, Double }
+	TrailingComma { All, None }
+	Semicolons { Always, AsNeeded }
+	BraceStyle { SameLine, NewLine }
+	PointerAlignment { Left, Right, Middle }
+	ShortFunctions { None, Empty, Inline, All }
+	ClangFallback { Never, System }
+	GoStyle { Gofmt, Gofumpt }
+	MarkdownTextWrap { Always, Never, Maintain }
+	WidthMeasure { Codepoints, UnicodeWidth }
+	LanguageMode { Format, CheckOnly, Off }
+	JsoncOutput { Preserve, Strict }
+	SqlTemplater { Raw, Jinja, Dbt }
+	Preset { Gofmt, Prettier, Google }
+	FileType {
+		JavaScript, TypeScript, Jsx, Tsx, Json, Jsonc, Css, Scss, Less, Sass,
+		Html, Vue, Svelte, Astro, Yaml, Toml, Markdown, Rust, Python,
+		Starlark, Lua, Ruby, Shell, Go, GoMod, Zig, Hcl, Dockerfile, GraphQL,
+		Sql, Xml, Php, Kotlin, Groovy, C, Cpp, CSharp, ObjectiveC,
+		ObjectiveCpp, Cuda, Shader, Java, Protobuf, Unknown,
+	}
+}
+
+/// Glob lists such as `jsonc_filenames`
+fn patterns_json(patterns: &[&str]) -> Value {
+	json!(patterns)
+}
+
+fn patterns_schema() -> Value {
+	json!({ "type": "array", "items": { "type": "string" } })
+}
+
+/// Per-language lists such as `language_modes`, as an object keyed by
+/// file type
+fn per_language_json<T: ConfigValue>(values: &[(FileType, T)]) -> Value {
+	let map: serde_json::Map<String, Value> = values
+		.iter()
+		.map(|(file_type, value)| (format!("{:?}", file_type), value.to_json()))
+		.collect();
+	Value::Object(map)
+}
+
+fn per_language_schema<T: ConfigValue>() -> Value {
+	json!({
+		"type": "object",
+		"propertyNames": FileType::schema(),
+		"additionalProperties": T::schema(),
+	})
+}
+
+fn overrides_json(overrides: &[Override]) -> Value {
+	overrides
+		.iter()
+		.map(|o| {
+			json!({
+				"files": patterns_json(o.files),
+				"ignore": o.ignore,
+				"line_width": o.line_width.to_json(),
+			})
+		})
+		.collect()
+}
+
+fn overrides_schema() -> Value {
+	json!({
+		"type": "array",
+		"items": {
+			"type": "object",
+			"properties": {
+				"files": patterns_schema(),
+				"ignore": bool::schema(),
+				"line_width": Option::<u16>::schema(),
+			},
+			"required": ["files", "ignore", "line_width"],
+			"additionalProperties": false,
+		},
+	})
+}
+
+/// Every field of `config` by name, each with the schema of its values
+fn fields(config: &FormatConfig) -> Vec<(&'static str, Value, Value)> {
+	// Listed without `..` so a new field fails to compile until it is here
+	let FormatConfig {
+		preset,
+		indent_style,
+		indent_width,
+		line_width,
+		width_measure,
+		line_ending,
+		insert_final_newline,
+		final_newline_overrides,
+		max_blank_lines,
+		language_modes,
+		overrides,
+		subprocess_backends,
+		review_threshold,
+		normalize_invisible_chars,
+		jsonc_filenames,
+		jsonc_output,
+		json_sort_keys,
+		json_sort_keys_paths,
+		locale_paths,
+		quote_style,
+		trailing_comma,
+		semicolons,
+		bracket_spacing,
+		sort_jsx_props,
+		remove_unused_imports,
+		preserve_banner_comments,
+		brace_style,
+		pointer_alignment,
+		short_functions,
+		sort_includes,
+		group_includes,
+		clang_fallback,
+		go_style,
+		starlark_sort_loads,
+		starlark_sort_arguments,
+		markdown_text_wrap,
+		prose_width,
+		markdown_format_tables,
+		markdown_toc,
+		markdown_check_links,
+		sql_lint,
+		sql_templater,
+		sql_templater_vars,
+	} = config;
+
+	macro_rules! values {
+		($($name:ident),* $(,)?) => {
+			vec![$((stringify!($name), $name.to_json(), value_schema($name)),)*]
+		};
+	}
+
+	let mut fields = values![
+		preset,
+		indent_style,
+		indent_width,
+		line_width,
+		width_measure,
+		line_ending,
+		insert_final_newline,
+		max_blank_lines,
+		subprocess_backends,
+		review_threshold,
+		normalize_invisible_chars,
+		jsonc_output,
+		json_sort_keys,
+		quote_style,
+		trailing_comma,
+		semicolons,
+		bracket_spacing,
+		sort_jsx_props,
+		remove_unused_imports,
+		preserve_banner_comments,
+		brace_style,
+		pointer_alignment,
+		short_functions,
+		sort_includes,
+		group_includes,
+		clang_fallback,
+		go_style,
+		starlark_sort_loads,
+		starlark_sort_arguments,
+		markdown_text_wrap,
+		prose_width,
+		markdown_format_tables,
+		markdown_toc,
+		markdown_check_links,
+		sql_lint,
+		sql_templater,
+		sql_templater_vars,
+	];
+	fields.extend([
+		(
+			"final_newline_overrides",
+			per_language_json(final_newline_overrides),
+			per_language_schema::<bool>(),
+		),
+		(
+			"language_modes",
+			per_language_json(language_modes),
+			per_language_schema::<LanguageMode>(),
+		),
+		("overrides", overrides_json(overrides), overrides_schema()),
+	]);
+	for (name, patterns) in [
+		("jsonc_filenames", jsonc_filenames),
+		("json_sort_keys_paths", json_sort_keys_paths),
+		("locale_paths", locale_paths),
+	] {
+		fields.push((name, patterns_json(patterns), patterns_schema()));
+	}
+	fields
+}
+
+/// The schema of `T`, for a value whose type is only inferred
+fn value_schema<T: ConfigValue>(_: &T) -> Value {
+	T::schema()
+}
+
+/// Every field of `config`, by name
+pub fn to_json(config: &FormatConfig) -> Value {
+	let map: serde_json::Map<String, Value> = fields(config)
+		.into_iter()
+		.map(|(name, value, _)| (name.to_string(), value))
+		.collect();
+	Value::Object(map)
+}
+
+/// A JSON Schema of `FormatConfig`, with fama's defaults
+pub fn schema() -> Value {
+	let properties: serde_json::Map<String, Value> = fields(&defaults())
+		.into_iter()
+		.map(|(name, default, mut schema)| {
+			schema["default"] = default;
+			(name.to_string(), schema)
+		})
+		.collect();
+	let required: Vec<&String> = properties.keys().collect();
+	json!({
+		"$schema": "https://json-schema.org/draft/2020-12/schema",
+		"title": "fama FormatConfig",
+		"type": "object",
+		"properties": properties,
+		"required": required,
+		"additionalProperties": false,
+	})
+}
+
+/// The config fama ships with, before `CONFIG` changes anything
+fn defaults() -> FormatConfig {
+	Preset::default().config()
+}
+
+/// What `fama config` prints
+pub enum Dump {
+	/// The config that applies to each file
+	Resolved(Vec<PathBuf>),
+	Default,
+	Schema,
+}
+
+/// Print `dump` to stdout
+pub fn run(dump: &Dump) -> anyhow::Result<()> {
+	match dump {
+		Dump::Resolved(paths) => {
+			for path in paths {
+				println!("{}", explain::to_json(path, &CONFIG));
+			}
+		}
+		Dump::Default => {
+			println!(
+				"{}",
+				serde_json::to_string_pretty(&to_json(&defaults()))?
+			);
+		}
+		Dump::Schema => {
+			println!("{}", serde_json::to_string_pretty(&schema())?);
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_to_json() {
+		let config = FormatConfig {
+			max_blank_lines: Some(2),
+			language_modes: &[(FileType::Sql, LanguageMode::CheckOnly)],
+			overrides: &[Override {
+				files: &["docs/**"],
+				line_width: Some(120),
+				..Override::NONE
+			}],
+			sql_templater_vars: &[("target", "dev")],
+			..fama_common::presets::PRETTIER
+		};
+		let value = to_json(&config);
+		assert_eq!(value["preset"], "Prettier");
+		assert_eq!(value["indent_width"], 2);
+		assert_eq!(value["max_blank_lines"], 2);
+		assert_eq!(value["prose_width"], Value::Null);
+		assert_eq!(value["language_modes"], json!({ "Sql": "CheckOnly" }));
+		assert_eq!(value["overrides"][0]["line_width"], 120);
+		assert_eq!(value["jsonc_filenames"][0], "tsconfig.json");
+		assert_eq!(value["sql_templater"], "Raw");
+		assert_eq!(value["sql_templater_vars"], json!({ "target": "dev" }));
+	}
+
+	#[test]
+	fn test_schema_describes_defaults() {
+		let schema = schema();
+		let properties = schema["properties"].as_object().unwrap();
+		let defaults = to_json(&defaults());
+		assert_eq!(properties.len(), defaults.as_object().unwrap().len());
+		assert_eq!(properties["line_ending"]["default"], "Auto");
+		assert_eq!(
+			properties["line_ending"]["enum"],
+			json!(["Auto", "Lf", "Crlf"])
+		);
+		assert_eq!(properties["line_width"]["maximum"], u16::MAX);
+		assert_eq!(
+			properties["prose_width"]["anyOf"][1],
+			json!({ "type": "null" })
+		);
+	}
+}
@@ -0,0 +1,240 @@
+// coverage.rs - What fama would handle, for `fama stats`
+//
+// Walks the repository like a formatting run, but keeps every file and
+// formats none. Files are counted per language when fama formats them and
+// per extension when it doesn't, so the extensions at the top of the
+// unknown list are the backends worth enabling next.
+
+use fama_common::{detect_file_type, FileType, LanguageMode, CONFIG};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::discovery;
+
+/// File count and size of one group
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+	pub files: usize,
+	pub bytes: u64,
+}
+
+impl Tally {
+	fn add(&mut self, bytes: u64) {
+		self.files += 1;
+		self.bytes += bytes;
+	}
+
+	fn to_json(self) -> serde_json::Value {
+		serde_json::json!({ "files": self.files, "bytes": self.bytes })
+	}
+}
+
+/// Files grouped by how fama treats them
+#[derive(Debug, Default)]
+pub struct Report {
+	/// Formatted or checked, by language
+	pub languages: BTreeMap<String, Tally>,
+	/// In a language whose mode is off, by language
+	pub off: BTreeMap<String, Tally>,
+	/// Not a supported type, by `.extension` or file name
+	pub unknown: BTreeMap<String, Tally>,
+	/// Generated, minified or ignored by an override
+	pub skipped: Tally,
+}
+
+/// Extensions of programming languages fama has no backend for, which
+/// `--strict-coverage` reports instead of skipping. Data, text and
+/// build files without a formatter are not listed.
+const SOURCE_EXTENSIONS: &[&str] = &[
+	"clj", "cljc", "cljs", "cr", "d", "dart", "el", "elm", "erl", "ex", "exs",
+	"f90", "fs", "fsi", "fsx", "gleam", "hrl", "hs", "jl", "lisp", "ml", "mli",
+	"nim", "nix", "pas", "pl", "pm", "ps1", "purs", "r", "rkt", "sc", "scala",
+	"scm", "sol", "swift", "vala", "vb",
+];
+
+/// Whether `path` is source code in a language fama cannot format; an
+/// `ignore` override for it silences this
+pub fn is_uncovered_source(path: &Path) -> bool {
+	let ext = path.extension().and_then(|ext| ext.to_str());
+	ext.is_some_and(|ext| {
+		SOURCE_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+	}) && detect_file_type(path.to_str().unwrap_or("")) == FileType::Unknown
+		&& CONFIG.ignore_override(path).is_none()
+}
+
+/// Source files in languages fama cannot format among those matching
+/// `pattern`
+pub fn uncovered_sources(
+	pattern: &str,
+	hidden: bool,
+) -> Result<Vec<PathBuf>, String> {
+	let files = discovery::discover_all_files(Some(pattern), hidden)?;
+	Ok(files
+		.into_iter()
+		.filter(|path| is_uncovered_source(path))
+		.collect())
+}
+
+/// `.ext` for a file with an extension, otherwise its name
+pub fn unknown_key(path: &Path) -> String {
+	match path.extension() {
+		Some(ext) => format!(".{}", ext.to_string_lossy()),
+		None => path
+			.file_name()
+			.map_or_else(String::new, |name| name.to_string_lossy().into()),
+	}
+}
+
+impl Report {
+	fn record(&mut self, path: &Path, bytes: u64) {
+		let file_type = detect_file_type(path.to_str().unwrap_or(""));
+		let language = format!("{file_type:?}");
+		if file_type == FileType::Unknown {
+			self.unknown
+				.entry(unknown_key(path))
+				.or_default()
+				.add(bytes);
+		} else if !discovery::is_supported_file(path) {
+			self.skipped.add(bytes);
+		} else if CONFIG.mode_for(file_type) == LanguageMode::Off {
+			self.off.entry(language).or_default().add(bytes);
+		} else {
+			self.languages.entry(language).or_default().add(bytes);
+		}
+	}
+
+	pub fn to_json(&self) -> serde_json::Value {
+		let group = |map: &BTreeMap<String, Tally>| {
+			map.iter()
+				.map(|(name, tally)| (name.clone(), tally.to_json()))
+				.collect::<serde_json::Map<_, _>>()
+		};
+		serde_json::json!({
+			"languages": group(&self.languages),
+			"off": group(&self.off),
+			"unknown": group(&self.unknown),
+			"skipped": self.skipped.to_json(),
+		})
+	}
+
+	/// Render as two tables, most files first, and a line for the rest
+	pub fn to_text(&self) -> String {
+		let mut out = String::new();
+		for (title, total, map) in [
+			("Language", "Handled", &self.languages),
+			("Not handled", "Unknown", &self.unknown),
+		] {
+			let mut rows: Vec<(&str, Tally)> = map
+				.iter()
+				.map(|(name, tally)| (name.as_str(), *tally))
+				.collect();
+			rows.sort_by_key(|(name, tally)| (Reverse(tally.files), *name));
+			let sum = Tally {
+				files: rows.iter().map(|(_, tally)| tally.files).sum(),
+				bytes: rows.iter().map(|(_, tally)| tally.bytes).sum(),
+			};
+			rows.push((total, sum));
+			let width = rows
+				.iter()
+				.map(|(name, _)| name.len())
+				.chain([title.len()])
+				.max()
+				.unwrap_or(0);
+			out +=
+				&format!("{:width$}  {:>6}  {:>10}\n", title, "Files", "Bytes");
+			for (name, tally) in rows {
+				out += &format!(
+					"{:width$}  {:>6}  {:>10}\n",
+					name, tally.files, tally.bytes
+				);
+			}
+			out.push('\n');
+		}
+		let off: usize = self.off.values().map(|tally| tally.files).sum();
+		if off > 0 {
+			out += &format!(
+				"{} files in languages that are off ({})\n",
+				off,
+				self.off.keys().cloned().collect::<Vec<_>>().join(", ")
+			);
+		}
+		if self.skipped.files > 0 {
+			out += &format!(
+				"{} files skipped as generated, minified or ignored\n",
+				self.skipped.files
+			);
+		}
+		out
+	}
+}
+
+/// Build the report for files matching `patterns`
+pub fn report(patterns: &[String]) -> anyhow::Result<Report> {
+	let mut files: Vec<PathBuf> = Vec::new();
+	for pattern in patterns {
+		files.extend(
+			discovery::discover_all_files(Some(pattern), true).map_err(
+				|e| anyhow::anyhow!("Failed to discover files: {}", e),
+			)?,
+		);
+	}
+	files.sort();
+	files.dedup();
+
+	let mut report = Report::default();
+	for path in &files {
+		report.record(path, path.metadata().map_or(0, |m| m.len()));
+	}
+	Ok(report)
+}
+
+/// Print the report for `patterns` as tables or JSON
+pub fn run(patterns: &[String], json: bool) -> anyhow::Result<()> {
+	let report = report(patterns)?;
+	if json {
+		println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+	} else {
+		print!("{}", report.to_text());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_report_groups_files() {
+		let temp_dir = TempDir::new().unwrap();
+		let dir = temp_dir.path();
+		fs::write(dir.join("a.ts"), "let a = 1;\n").unwrap();
+		fs::write(dir.join("b.scala"), "object B\n").unwrap();
+		fs::write(dir.join("c.scala"), "object C\n").unwrap();
+		fs::write(dir.join("Makefile"), "all:\n").unwrap();
+		fs::write(dir.join("app.min.js"), "a()").unwrap();
+
+		let report = report(&[dir.display().to_string()]).unwrap();
+		assert_eq!(report.languages["TypeScript"].bytes, 11);
+		assert_eq!(report.unknown[".scala"].files, 2);
+		assert_eq!(report.unknown["Makefile"].files, 1);
+		assert_eq!(report.skipped.files, 1);
+
+		let text = report.to_text();
+		assert!(text.contains("\n.scala            2          18\n"));
+		assert!(text.contains("1 files skipped as generated"));
+		assert_eq!(report.to_json()["unknown"]["Makefile"]["bytes"], 5);
+	}
+
+	#[test]
+	fn test_uncovered_sources() {
+		assert!(is_uncovered_source(Path::new("src/Main.scala")));
+		assert!(is_uncovered_source(Path::new("lib/app.EX")));
+		assert!(!is_uncovered_source(Path::new("src/main.rs")));
+		assert!(!is_uncovered_source(Path::new("data.csv")));
+		assert!(!is_uncovered_source(Path::new("Makefile")));
+		assert!(SOURCE_EXTENSIONS.windows(2).all(|w| w[0] < w[1]));
+	}
+}
@@ -0,0 +1,215 @@
+// directives.rs - Suppression comments meant for other formatters
+//
+// Files moving to fama often carry `// prettier-ignore`, `# fmt: off` or
+// `// clang-format off` from the tool they used before. Directives the
+// backend understands natively keep working; `prettier-ignore` is mapped
+// to Biome's own syntax; the rest are reported, since the backend will
+// format straight through them.
+
+use fama_common::FileType;
+
+use crate::backend::backend_for;
+use crate::ignore_regions::COMMENT_OPENERS;
+use crate::rewrites::{Prepared, Undo};
+
+/// Suppression directive text and the tool it belongs to
+const DIRECTIVES: &[(&str, &str)] = &[
+	("@formatter:off", "JetBrains"),
+	("biome-ignore format", "Biome"),
+	("clang-format off", "clang-format"),
+	("dprint-ignore", "dprint"),
+	("fmt: off", "Black/Ruff"),
+	("fmt: skip", "Black/Ruff"),
+	("prettier-ignore", "Prettier"),
+	("stylua: ignore", "StyLua"),
+	("yapf: disable", "YAPF"),
+];
+
+/// `prettier-ignore` comment lines and the Biome suppression comments,
+/// which require an explanation after `:`, that stand in for them
+const PRETTIER_IGNORE: &[(&str, &str)] = &[
+	(
+		"// prettier-ignore",
+		"// biome-ignore format: prettier-ignore",
+	),
+	(
+		"/* prettier-ignore */",
+		"/* biome-ignore format: prettier-ignore */",
+	),
+];
+
+fn is_biome_ignore(line: &str) -> bool {
+	PRETTIER_IGNORE.iter().any(|&(_, biome)| line == biome)
+}
+
+/// Whether the backend for `file_type` honors `directive`
+fn honored(directive: &str, file_type: FileType) -> bool {
+	use FileType::*;
+	match directive {
+		"biome-ignore format" => matches!(
+			file_type,
+			JavaScript | TypeScript | Jsx | Tsx | Json | Jsonc | GraphQL
+		),
+		"prettier-ignore" => maps_prettier_ignore(file_type),
+		"clang-format off" => backend_for(file_type).name() == "clang-format",
+		"dprint-ignore" => file_type == Markdown,
+		"fmt: off" | "fmt: skip" => matches!(file_type, Python | Starlark),
+		"yapf: disable" => file_type == Python,
+		"stylua: ignore" => file_type == Lua,
+		_ => false,
+	}
+}
+
+/// Whether `prettier-ignore` is rewritten to Biome's syntax for this type
+fn maps_prettier_ignore(file_type: FileType) -> bool {
+	matches!(
+		file_type,
+		FileType::JavaScript
+			| FileType::TypeScript
+			| FileType::Jsx
+			| FileType::Tsx
+	)
+}
+
+/// Rewrite `prettier-ignore` comment lines as Biome suppression comments
+/// so the Biome backend skips the next node, as Prettier would, or `None`
+/// when there are none
+pub fn to_backend(content: &str, file_type: FileType) -> Option<Prepared> {
+	if !maps_prettier_ignore(file_type) || !content.contains("prettier-ignore")
+	{
+		return None;
+	}
+	let mut prepared = Prepared::new(content.len(), is_biome_ignore);
+	for line in content.split_inclusive('\n') {
+		let trimmed = line.trim();
+		match PRETTIER_IGNORE.iter().find(|&&(ours, _)| trimmed == ours) {
+			Some(&(ours, biome)) => {
+				let rewritten = line.replacen(ours, biome, 1);
+				prepared.push_rewritten(&rewritten, Undo::Replace(ours.into()));
+			}
+			None => prepared.push_line(line),
+		}
+	}
+	prepared.is_rewritten().then_some(prepared)
+}
+
+/// Directives in `content` that the backend for `file_type` won't honor,
+/// as `(line number, directive, tool)`, first occurrence of each only
+pub fn unsupported(
+	content: &str,
+	file_type: FileType,
+) -> Vec<(usize, &'static str, &'static str)> {
+	let mut found: Vec<(usize, &str, &str)> = Vec::new();
+	for (number, line) in content.lines().enumerate() {
+		for &(directive, tool) in DIRECTIVES {
+			if honored(directive, file_type)
+				|| found.iter().any(|(_, d, _)| *d == directive)
+			{
+				continue;
+			}
+			let in_comment = line.match_indices(directive).any(|(i, _)| {
+				let before = line[..i].trim_end();
+				COMMENT_OPENERS.iter().any(|o| before.ends_with(o))
+			});
+			if in_comment {
+				found.push((number + 1, directive, tool));
+			}
+		}
+	}
+	found
+}
+
+/// Whether a Python or Starlark file turns formatting off before its first
+/// statement and never back on, so formatting and `--check` skip all of it
+pub fn fully_suppressed(content: &str, file_type: FileType) -> bool {
+	if !matches!(file_type, FileType::Python | FileType::Starlark) {
+		return false;
+	}
+	let mut off = false;
+	for line in content.lines().map(str::trim) {
+		match line {
+			"# fmt: off" | "# yapf: disable" => off = true,
+			"# fmt: on" | "# yapf: enable" => return false,
+			_ if !off && !line.is_empty() && !line.starts_with('#') => {
+				return false;
+			}
+			_ => {}
+		}
+	}
+	off
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_prettier_ignore_round_trip() {
+		let source = concat!(
+			"// biome-ignore format: prettier-ignore\n",
+			"const a = [1,0,\n  0,1];\n",
+			"  // prettier-ignore\n",
+			"const s = \"// prettier-ignore\";\n",
+			"/* prettier-ignore */\n",
+			"const m = [1,0,\n  0,1];\n",
+		);
+		let prepared = to_backend(source, FileType::TypeScript).unwrap();
+		assert_eq!(
+			prepared.source,
+			source
+				.replace(
+					"  // prettier-ignore\n",
+					"  // biome-ignore format: prettier-ignore\n"
+				)
+				.replace(
+					"/* prettier-ignore */",
+					"/* biome-ignore format: prettier-ignore */"
+				)
+		);
+		// The user's own Biome comment stays, the mapped ones are undone
+		let formatted = prepared.source.replace("  //", "//");
+		assert_eq!(
+			prepared.restore(&formatted).unwrap(),
+			source.replace("  //", "//")
+		);
+		assert!(to_backend(source, FileType::Css).is_none());
+		assert!(to_backend("const a = 1;\n", FileType::Jsx).is_none());
+		let block = "/* prettier-ignore-start */\nconst a = 1;\n";
+		assert!(to_backend(block, FileType::Jsx).is_none());
+	}
+
+	#[test]
+	fn test_unsupported_directives() {
+		let source = "x = 1  # fmt: skip\n# fmt: off\ny = 2\n# fmt: off\n";
+		assert!(unsupported(source, FileType::Python).is_empty());
+		assert_eq!(
+			unsupported(source, FileType::Ruby),
+			[
+				(1, "fmt: skip", "Black/Ruff"),
+				(2, "fmt: off", "Black/Ruff")
+			]
+		);
+		assert!(unsupported("// prettier-ignore\n", FileType::Tsx).is_empty());
+		assert_eq!(
+			unsupported("/* prettier-ignore */\na {}\n", FileType::Css),
+			[(1, "prettier-ignore", "Prettier")]
+		);
+		assert!(unsupported("s = \"fmt: off\"\n", FileType::Ruby).is_empty());
+	}
+
+	#[test]
+	fn test_fully_suppressed() {
+		let wrapped = "#!/usr/bin/env python\n# fmt: off\nx=1\ny=2\n";
+		assert!(fully_suppressed(wrapped, FileType::Python));
+		assert!(!fully_suppressed(wrapped, FileType::Ruby));
+		assert!(!fully_suppressed(
+			"x=1\n# fmt: off\ny=2\n",
+			FileType::Python
+		));
+		assert!(!fully_suppressed(
+			"# fmt: off\nx=1\n# fmt: on\n",
+			FileType::Python
+		));
+		assert!(!fully_suppressed("x = 1\n", FileType::Python));
+	}
+}
@@ -0,0 +1,115 @@
+// dirs.rs - Where fama keeps files between runs
+//
+// The one thing fama keeps between runs is the `--incremental` record.
+// It goes in `FAMA_CACHE_DIR` when that is set, otherwise in the XDG
+// cache dir on Linux and the BSDs (`$XDG_CACHE_HOME/fama`, falling back
+// to `~/.cache/fama`), and in fama's private directory in the temp dir
+// (see `temp.rs`) elsewhere. A cache dir that can't be created, such as
+// one under a read-only home on a CI runner, falls back to that private
+// directory too, never to the shared temp dir, where other users could
+// read the record or plant files under its name.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::temp;
+
+/// Overrides the cache dir
+pub const CACHE_DIR_VAR: &str = "FAMA_CACHE_DIR";
+
+/// The cache dir `env` asks for, or None to use fama's temp root
+fn configured_cache_dir(
+	env: impl Fn(&str) -> Option<OsString>,
+) -> Option<PathBuf> {
+	let var = |name: &str| env(name).filter(|value| !value.is_empty());
+	if let Some(dir) = var(CACHE_DIR_VAR) {
+		return Some(PathBuf::from(dir));
+	}
+	if !cfg!(all(unix, not(target_os = "macos"))) {
+		return None;
+	}
+	// Relative XDG paths are invalid and must be ignored
+	let xdg = var("XDG_CACHE_HOME")
+		.map(PathBuf::from)
+		.filter(|dir| dir.is_absolute());
+	let cache =
+		xdg.or_else(|| Some(PathBuf::from(var("HOME")?).join(".cache")));
+	cache.map(|dir| dir.join("fama"))
+}
+
+/// Directory for files kept between runs; created if needed. Fails only
+/// if the fallback, fama's temp root, isn't private to this user
+pub fn cache_dir() -> io::Result<PathBuf> {
+	usable(configured_cache_dir(|name| std::env::var_os(name)))
+}
+
+/// `dir` once created, or fama's temp root if there is none or it can't be
+fn usable(dir: Option<PathBuf>) -> io::Result<PathBuf> {
+	dir.filter(|dir| match fs::create_dir_all(dir) {
+		Ok(()) => true,
+		Err(e) => {
+			tracing::debug!(
+				"Using the temp root, {} is unusable: {}",
+				dir.display(),
+				e
+			);
+			false
+		}
+	})
+	.map_or_else(temp::root, Ok)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn env<'a>(
+		vars: &'a [(&str, &str)],
+	) -> impl Fn(&str) -> Option<OsString> + 'a {
+		move |name| {
+			vars.iter()
+				.find(|(var, _)| *var == name)
+				.map(|(_, value)| OsString::from(value))
+		}
+	}
+
+	#[test]
+	fn test_configured_cache_dir() {
+		let dir = |vars| configured_cache_dir(env(vars));
+		assert_eq!(
+			dir(&[(CACHE_DIR_VAR, "/ci/cache"), ("HOME", "/home/a")]),
+			Some(PathBuf::from("/ci/cache"))
+		);
+		if cfg!(all(unix, not(target_os = "macos"))) {
+			assert_eq!(
+				dir(&[("XDG_CACHE_HOME", "/xdg"), ("HOME", "/home/a")]),
+				Some(PathBuf::from("/xdg/fama"))
+			);
+			assert_eq!(
+				dir(&[("XDG_CACHE_HOME", "xdg"), ("HOME", "/home/a")]),
+				Some(PathBuf::from("/home/a/.cache/fama"))
+			);
+			assert_eq!(dir(&[(CACHE_DIR_VAR, "")]), None);
+		} else {
+			assert_eq!(dir(&[("HOME", "/home/a")]), None);
+		}
+	}
+
+	#[test]
+	fn test_cache_dir_falls_back_to_temp_root() {
+		let temp_dir = tempfile::TempDir::new().unwrap();
+		let dir = temp_dir.path().join("a/b");
+		assert_eq!(usable(Some(dir.clone())).unwrap(), dir);
+		assert!(dir.is_dir());
+
+		// A file in the way can't be made a directory
+		let root = temp::root().unwrap();
+		assert_ne!(root, std::env::temp_dir());
+		let file = temp_dir.path().join("file");
+		fs::write(&file, "").unwrap();
+		assert_eq!(usable(Some(file.join("cache"))).unwrap(), root);
+		assert_eq!(usable(None).unwrap(), root);
+	}
+}
@@ -1,6 +1,8 @@
 // discovery.rs - File discovery with gitignore support
 
-use fama_common::{detect_file_type, FileType};
+use fama_common::{
+	detect_file_type, file_type_for_extension, FileType, CONFIG,
+};
 use ignore::WalkBuilder;
 use std::path::{Path, PathBuf};
 
@@ -14,50 +16,89 @@ const IGNORED_PATTERNS: &[(&str, &str)] = &[
 	("*.min.js", "minified JavaScript"),
 ];
 
-const SUPPORTED_EXTENSIONS: &[&str] = &[
-	"js", "jsx", "ts", "tsx", "mjs", "mjsx", "mts", "json", "jsonc", "css",
-	"scss", "less", "html", "vue", "svelte", "astro", "yaml", "yml", "md",
-	"rs", "py", "lua", "rb", "rake", "gemspec", "ru", "sh", "bash", "zsh",
-	"go", "zig", "hcl", "tf", "tfvars", "toml", "graphql", "gql", "sql", "xml",
-	"php", "phtml", "kt", "kts", // C-family languages
-	"c", "h", "cpp", "cc", "cxx", "hpp", "hxx", "hh", "cs", "m", "mm", "java",
-	"proto",
-];
-
-/// Check if a filename matches any ignored pattern
-fn is_ignored_by_pattern(filename: &str) -> bool {
-	for (pattern, _) in IGNORED_PATTERNS {
-		if let Ok(glob) = glob::Pattern::new(pattern) {
-			if glob.matches(filename) {
-				return true;
-			}
-		}
-	}
-	false
+/// Find the ignored pattern a filename matches, with its description
+fn ignored_pattern(
+	filename: &str,
+) -> Option<&'static (&'static str, &'static str)> {
+	IGNORED_PATTERNS.iter().find(|(pattern, _)| {
+		glob::Pattern::new(pattern).is_ok_and(|glob| glob.matches(filename))
+	})
 }
 
-/// Check if a file is supported for formatting
-fn is_supported_path(path: &Path) -> bool {
+/// Explain why a path is not supported for formatting, or None if it is
+fn unsupported_reason(path: &Path) -> Option<String> {
+	if path.components().any(|c| c.as_os_str() == ".git") {
+		return Some("inside .git".to_string());
+	}
 	// Skip known generated/lock files
 	if let Some(filename) = path.file_name().and_then(|f| f.to_str()) {
 		if IGNORED_FILENAMES.contains(&filename) {
-			return false;
+			return Some(format!("'{}' is a generated/lock file", filename));
 		}
 		// Skip files matching ignored patterns (minified files, etc.)
-		if is_ignored_by_pattern(filename) {
-			return false;
+		if let Some((pattern, description)) = ignored_pattern(filename) {
+			return Some(format!("matches '{}' ({})", pattern, description));
 		}
 	}
+	if let Some(ignored) = CONFIG.ignore_override(path) {
+		return Some(format!(
+			"ignored by override for '{}'",
+			ignored.files.join("', '")
+		));
+	}
 	// First check by extension (fast path)
-	if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-		if SUPPORTED_EXTENSIONS.contains(&ext) {
-			return true;
-		}
+	let ext = path.extension().and_then(|e| e.to_str());
+	if ext.and_then(file_type_for_extension).is_some() {
+		return None;
 	}
 	// For files without supported extension, check if detect_file_type recognizes them
 	// This handles special filenames like Dockerfile, Rakefile, Gemfile, etc.
 	let path_str = path.to_str().unwrap_or("");
-	!matches!(detect_file_type(path_str), FileType::Unknown)
+	match detect_file_type(path_str) {
+		FileType::Unknown => Some("unsupported file type".to_string()),
+		_ => None,
+	}
+}
+
+/// Check if a file is supported for formatting
+fn is_supported_path(path: &Path) -> bool {
+	unsupported_reason(path).is_none()
+}
+
+/// Check whether a path, or any directory above it, is excluded by
+/// .gitignore/.ignore rules the way a directory walk would exclude it
+fn excluded_by_ignore_files(path: &Path) -> bool {
+	path.ancestors()
+		.take_while(|p| !p.as_os_str().is_empty())
+		.any(|entry| {
+			let Some(name) = entry.file_name() else {
+				return false;
+			};
+			let parent = entry
+				.parent()
+				.filter(|p| !p.as_os_str().is_empty())
+				.unwrap_or(Path::new("."));
+			!WalkBuilder::new(parent)
+				.hidden(false)
+				.max_depth(Some(1))
+				.build()
+				.filter_map(|entry| entry.ok())
+				.any(|child| child.depth() == 1 && child.file_name() == name)
+		})
+}
+
+/// Explain why discovery would skip a path, or None if it is formatted
+pub fn skip_reason(path: &Path) -> Option<String> {
+	if !path.is_file() {
+		return Some("not a file".to_string());
+	}
+	if let Some(reason) = unsupported_reason(path) {
+		return Some(reason);
+	}
+	if excluded_by_ignore_files(path) {
+		return Some("excluded by a .gitignore or .ignore file".to_string());
+	}
+	None
 }
 
 /// Check if a file is supported (has supported extension/filename and is a file)
@@ -65,17 +106,23 @@ pub fn is_supported_file(path: &Path) -> bool {
 	path.is_file() && is_supported_path(path)
 }
 
-/// Walk a directory respecting .gitignore rules, optionally filtering by glob pattern
+/// Walk a directory respecting .gitignore rules, optionally filtering by glob pattern.
+///
+/// Hidden files such as `.eslintrc.json` are walked unless `hidden` is
+/// false; `.git` is never descended into.
 fn walk_with_pattern(
 	base: &Path,
 	pattern: Option<&glob::Pattern>,
+	hidden: bool,
+	all: bool,
 ) -> Result<Vec<PathBuf>, String> {
 	let mut files: Vec<PathBuf> = WalkBuilder::new(base)
-		.hidden(false)
+		.hidden(!hidden)
+		.filter_entry(|entry| entry.file_name() != ".git")
 		.build()
 		.filter_map(|entry| entry.ok())
 		.filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
-		.filter(|entry| is_supported_path(entry.path()))
+		.filter(|entry| all || is_supported_path(entry.path()))
 		.filter(|entry| {
 			pattern
 				.map(|p| p.matches_path(entry.path()))
@@ -88,10 +135,21 @@ fn walk_with_pattern(
 	Ok(files)
 }
 
+/// Order `files` largest first, ties by path, so a parallel run starts on
+/// the files that take longest and isn't left waiting on one at the end.
+/// A file that can't be read counts as empty.
+pub fn largest_first(files: &mut [PathBuf]) {
+	files.sort_by_cached_key(|path| {
+		let len = path.metadata().map_or(0, |metadata| metadata.len());
+		(std::cmp::Reverse(len), path.clone())
+	});
+}
+
 /// Discover files matching the given pattern while respecting .gitignore rules.
 ///
 /// # Arguments
 /// * `pattern` - Optional glob pattern. If None, defaults to "**/*"
+/// * `hidden` - Whether to walk hidden files and directories
 ///
 /// Pattern types supported:
 /// - Single file: "src/main.rs" → returns that file if extension is supported
@@ -100,7 +158,27 @@ fn walk_with_pattern(
 ///
 /// # Returns
 /// A sorted list of file paths matching the pattern and supported extensions
-pub fn discover_files(pattern: Option<&str>) -> Result<Vec<PathBuf>, String> {
+pub fn discover_files(
+	pattern: Option<&str>,
+	hidden: bool,
+) -> Result<Vec<PathBuf>, String> {
+	discover(pattern, hidden, false)
+}
+
+/// Like [`discover_files`], but keeps files fama does not format, for
+/// reports on what a repository contains
+pub fn discover_all_files(
+	pattern: Option<&str>,
+	hidden: bool,
+) -> Result<Vec<PathBuf>, String> {
+	discover(pattern, hidden, true)
+}
+
+fn discover(
+	pattern: Option<&str>,
+	hidden: bool,
+	all: bool,
+) -> Result<Vec<PathBuf>, String> {
 	let pattern = pattern.unwrap_or("**/*");
 
 	// Check if pattern is a literal file path (no glob characters)
@@ -109,7 +187,7 @@ pub fn discover_files(pattern: Option<&str>) -> Result<Vec<PathBuf>, String> {
 
 		if path.is_file() {
 			// Single file - check if supported and return
-			if is_supported_path(&path) {
+			if all || is_supported_path(&path) {
 				return Ok(vec![path]);
 			} else {
 				let ext = path
@@ -124,15 +202,38 @@ pub fn discover_files(pattern: Option<&str>) -> Result<Vec<PathBuf>, String> {
 			}
 		} else if path.is_dir() {
 			// Directory path - walk from there
-			return walk_with_pattern(&path, None);
+			return walk_with_pattern(&path, None, hidden, all);
 		}
 		// Path doesn't exist, fall through to glob attempt
 	}
 
-	// It's a glob pattern - walk current directory and filter by pattern
+	// It's a glob pattern - walk from its literal prefix and filter by pattern
 	let glob_pattern = glob::Pattern::new(pattern)
 		.map_err(|e| format!("Invalid glob pattern '{}': {}", pattern, e))?;
-	walk_with_pattern(Path::new("."), Some(&glob_pattern))
+	let base = glob_base(pattern);
+	if !base.is_dir() {
+		return Ok(Vec::new());
+	}
+	walk_with_pattern(&base, Some(&glob_pattern), hidden, all)
+}
+
+/// Longest leading run of path components without glob characters.
+///
+/// Walking from here instead of "." lets absolute and `../` patterns
+/// work, and picks up the ignore files of the tree being formatted.
+/// Walked paths keep the prefix, so they still match the full pattern.
+fn glob_base(pattern: &str) -> PathBuf {
+	let base: PathBuf = Path::new(pattern)
+		.components()
+		.take_while(|c| {
+			!c.as_os_str().to_string_lossy().contains(['*', '?', '['])
+		})
+		.collect();
+	if base.as_os_str().is_empty() {
+		PathBuf::from(".")
+	} else {
+		base
+	}
 }
 
 #[cfg(test)]
@@ -143,21 +244,21 @@ mod tests {
 
 	#[test]
 	fn test_is_ignored_by_pattern_min_css() {
-		assert!(is_ignored_by_pattern("app.min.css"));
-		assert!(is_ignored_by_pattern("lib.min.css"));
+		assert!(ignored_pattern("app.min.css").is_some());
+		assert!(ignored_pattern("lib.min.css").is_some());
 	}
 
 	#[test]
 	fn test_is_ignored_by_pattern_min_js() {
-		assert!(is_ignored_by_pattern("app.min.js"));
-		assert!(is_ignored_by_pattern("bundle.min.js"));
+		assert!(ignored_pattern("app.min.js").is_some());
+		assert!(ignored_pattern("bundle.min.js").is_some());
 	}
 
 	#[test]
 	fn test_is_ignored_by_pattern_not_ignored() {
-		assert!(!is_ignored_by_pattern("app.css"));
-		assert!(!is_ignored_by_pattern("app.js"));
-		assert!(!is_ignored_by_pattern("test.min.rs"));
+		assert!(ignored_pattern("app.css").is_none());
+		assert!(ignored_pattern("app.js").is_none());
+		assert!(ignored_pattern("test.min.rs").is_none());
 	}
 
 	#[test]
@@ -170,6 +271,14 @@ mod tests {
 		assert!(is_supported_path(Path::new("test.kt")));
 	}
 
+	#[test]
+	fn test_every_registered_extension_is_supported() {
+		for (ext, _) in fama_common::EXTENSIONS {
+			let path = format!("file.{}", ext);
+			assert!(is_supported_path(Path::new(&path)), "{}", path);
+		}
+	}
+
 	#[test]
 	fn test_is_supported_path_with_ignored_filename() {
 		assert!(!is_supported_path(Path::new("pnpm-lock.yaml")));
@@ -203,6 +312,24 @@ mod tests {
 		assert!(!is_supported_path(Path::new("test.unknown")));
 	}
 
+	#[test]
+	fn test_largest_first() {
+		let temp_dir = TempDir::new().unwrap();
+		let dir = temp_dir.path();
+		fs::write(dir.join("a.js"), "a();").unwrap();
+		fs::write(dir.join("b.js"), "b();\nb();").unwrap();
+		fs::write(dir.join("c.js"), "c();").unwrap();
+
+		let mut files: Vec<PathBuf> = ["a.js", "c.js", "missing.js", "b.js"]
+			.iter()
+			.map(|name| dir.join(name))
+			.collect();
+		largest_first(&mut files);
+		let names: Vec<_> =
+			files.iter().map(|path| path.file_name().unwrap()).collect();
+		assert_eq!(names, ["b.js", "a.js", "c.js", "missing.js"]);
+	}
+
 	#[test]
 	fn test_discover_files_single_file() {
 		let temp_dir = TempDir::new().unwrap();
@@ -210,7 +337,7 @@ mod tests {
 		fs::write(&file_path, "console.log('hello');").unwrap();
 
 		// Test by directly passing the file path
-		let result = discover_files(Some(file_path.to_str().unwrap()));
+		let result = discover_files(Some(file_path.to_str().unwrap()), true);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
@@ -224,7 +351,7 @@ mod tests {
 		let file_path = temp_dir.path().join("test.xyz");
 		fs::write(&file_path, "content").unwrap();
 
-		let result = discover_files(Some(file_path.to_str().unwrap()));
+		let result = discover_files(Some(file_path.to_str().unwrap()), true);
 
 		assert!(result.is_err());
 		let err = result.unwrap_err();
@@ -237,7 +364,7 @@ mod tests {
 		let temp_dir = TempDir::new().unwrap();
 		let file_path = temp_dir.path().join("nonexistent.js");
 
-		let result = discover_files(Some(file_path.to_str().unwrap()));
+		let result = discover_files(Some(file_path.to_str().unwrap()), true);
 
 		// Non-existent files with glob characters aren't matched
 		// Non-existent files without glob characters fall through
@@ -253,7 +380,7 @@ mod tests {
 		fs::write(src_dir.join("main.rs"), "fn main() {}").unwrap();
 		fs::write(src_dir.join("lib.rs"), "pub fn lib() {}").unwrap();
 
-		let result = discover_files(Some(src_dir.to_str().unwrap()));
+		let result = discover_files(Some(src_dir.to_str().unwrap()), true);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
@@ -262,19 +389,57 @@ mod tests {
 
 	#[test]
 	fn test_discover_files_invalid_glob_pattern() {
-		let result = discover_files(Some("[invalid"));
+		let result = discover_files(Some("[invalid"), true);
 
 		assert!(result.is_err());
 		assert!(result.unwrap_err().contains("Invalid glob pattern"));
 	}
 
+	#[test]
+	fn test_glob_base() {
+		assert_eq!(glob_base("**/*.ts"), PathBuf::from("."));
+		assert_eq!(glob_base("src/**/*.ts"), PathBuf::from("src"));
+		assert_eq!(glob_base("/abs/path/*.ts"), PathBuf::from("/abs/path"));
+		assert_eq!(
+			glob_base("../web/src/[ab].ts"),
+			PathBuf::from("../web/src")
+		);
+	}
+
+	#[test]
+	fn test_discover_files_absolute_glob() {
+		let temp_dir = TempDir::new().unwrap();
+		let dir = temp_dir.path();
+		fs::create_dir(dir.join(".git")).unwrap();
+		fs::write(dir.join(".gitignore"), "dist/\n").unwrap();
+		fs::create_dir_all(dir.join("src/nested")).unwrap();
+		fs::create_dir(dir.join("dist")).unwrap();
+		fs::write(dir.join("src/a.ts"), "").unwrap();
+		fs::write(dir.join("src/nested/b.ts"), "").unwrap();
+		fs::write(dir.join("src/c.js"), "").unwrap();
+		fs::write(dir.join("dist/d.ts"), "").unwrap();
+
+		let pattern = format!("{}/**/*.ts", dir.display());
+		let files = discover_files(Some(&pattern), true).unwrap();
+
+		assert_eq!(files, [dir.join("src/a.ts"), dir.join("src/nested/b.ts")]);
+	}
+
+	#[test]
+	fn test_discover_files_glob_with_missing_base() {
+		let temp_dir = TempDir::new().unwrap();
+		let pattern = format!("{}/missing/*.ts", temp_dir.path().display());
+
+		assert!(discover_files(Some(&pattern), true).unwrap().is_empty());
+	}
+
 	#[test]
 	fn test_walk_with_pattern_no_pattern() {
 		let temp_dir = TempDir::new().unwrap();
 		fs::write(temp_dir.path().join("a.js"), "").unwrap();
 		fs::write(temp_dir.path().join("b.rs"), "").unwrap();
 
-		let result = walk_with_pattern(temp_dir.path(), None);
+		let result = walk_with_pattern(temp_dir.path(), None, true, false);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
@@ -288,7 +453,8 @@ mod tests {
 		fs::write(temp_dir.path().join("b.rs"), "").unwrap();
 
 		let pattern = glob::Pattern::new("*.js").unwrap();
-		let result = walk_with_pattern(temp_dir.path(), Some(&pattern));
+		let result =
+			walk_with_pattern(temp_dir.path(), Some(&pattern), true, false);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
@@ -328,7 +494,7 @@ mod tests {
 		fs::write(temp_dir.path().join("excluded.js"), "").unwrap();
 		fs::write(temp_dir.path().join(".gitignore"), "excluded.js").unwrap();
 
-		let result = walk_with_pattern(temp_dir.path(), None);
+		let result = walk_with_pattern(temp_dir.path(), None, true, false);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
@@ -344,7 +510,7 @@ mod tests {
 		fs::write(temp_dir.path().join("pnpm-lock.yaml"), "").unwrap();
 		fs::write(temp_dir.path().join("regular.js"), "").unwrap();
 
-		let result = walk_with_pattern(temp_dir.path(), None);
+		let result = walk_with_pattern(temp_dir.path(), None, true, false);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
@@ -352,6 +518,36 @@ mod tests {
 		assert!(files[0].to_string_lossy().ends_with("regular.js"));
 	}
 
+	#[test]
+	fn test_skip_reason() {
+		let temp_dir = TempDir::new().unwrap();
+		let dir = temp_dir.path();
+		fs::create_dir(dir.join(".git")).unwrap();
+		fs::write(dir.join(".gitignore"), "build/\n").unwrap();
+		fs::create_dir(dir.join("build")).unwrap();
+		for name in ["app.ts", "app.min.js", "package-lock.json", "a.xyz"] {
+			fs::write(dir.join(name), "").unwrap();
+		}
+		fs::write(dir.join("build/out.ts"), "").unwrap();
+
+		assert_eq!(skip_reason(&dir.join("app.ts")), None);
+		assert!(skip_reason(&dir.join("app.min.js"))
+			.unwrap()
+			.contains("minified JavaScript"));
+		assert!(skip_reason(&dir.join("package-lock.json"))
+			.unwrap()
+			.contains("generated/lock file"));
+		assert_eq!(
+			skip_reason(&dir.join("a.xyz")).as_deref(),
+			Some("unsupported file type")
+		);
+		assert_eq!(
+			skip_reason(&dir.join("build/out.ts")).as_deref(),
+			Some("excluded by a .gitignore or .ignore file")
+		);
+		assert_eq!(skip_reason(dir).as_deref(), Some("not a file"));
+	}
+
 	#[test]
 	fn test_walk_ignores_minified() {
 		let temp_dir = TempDir::new().unwrap();
@@ -359,11 +555,43 @@ mod tests {
 		fs::write(temp_dir.path().join("app.min.css"), "").unwrap();
 		fs::write(temp_dir.path().join("regular.js"), "").unwrap();
 
-		let result = walk_with_pattern(temp_dir.path(), None);
+		let result = walk_with_pattern(temp_dir.path(), None, true, false);
 
 		assert!(result.is_ok());
 		let files = result.unwrap();
 		assert_eq!(files.len(), 1);
 		assert!(files[0].to_string_lossy().ends_with("regular.js"));
 	}
+
+	#[test]
+	fn test_walk_includes_hidden_files_but_not_git() {
+		let temp_dir = TempDir::new().unwrap();
+		let dir = temp_dir.path();
+		fs::create_dir_all(dir.join(".git/info")).unwrap();
+		fs::create_dir(dir.join(".github")).unwrap();
+		fs::write(dir.join(".git/info/state.json"), "{}").unwrap();
+		fs::write(dir.join(".github/ci.yml"), "").unwrap();
+		fs::write(dir.join(".eslintrc.json"), "{}").unwrap();
+		fs::write(dir.join(".babelrc"), "{}").unwrap();
+		fs::write(dir.join("app.js"), "").unwrap();
+
+		let files = walk_with_pattern(dir, None, true, false).unwrap();
+		assert_eq!(
+			files,
+			[
+				dir.join(".babelrc"),
+				dir.join(".eslintrc.json"),
+				dir.join(".github/ci.yml"),
+				dir.join("app.js"),
+			]
+		);
+
+		let files = walk_with_pattern(dir, None, false, false).unwrap();
+		assert_eq!(files, [dir.join("app.js")]);
+
+		assert_eq!(
+			unsupported_reason(&dir.join(".git/info/state.json")).as_deref(),
+			Some("inside .git")
+		);
+	}
 }
@@ -0,0 +1,288 @@
+// editor_server.rs - Line-oriented stdio protocol for editor integrations
+//
+// Request:  FORMAT <len> <path>\n<len bytes of content>
+//           EDITS <len> <path>\n<len bytes of content>
+//           CURSOR <len> <offset> <path>\n<len bytes of content>
+// Response: OK <len>\n<len bytes of formatted content, or for EDITS a
+//           JSON array of LSP text edits>
+//           OK <len> <offset>\n<len bytes of formatted content> (CURSOR)
+//           ERROR <len>\n<len bytes of message>
+//
+// Lengths and cursor offsets are in bytes. The path only selects the language and config
+// overrides; nothing is read from or written to disk. The content of a
+// request that is refused is still read, so the next header is found.
+
+use fama_common::{detect_file_type, LanguageMode, CONFIG};
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+
+use crate::edits;
+use crate::formatter::{self, FormatOptions};
+
+/// Largest content accepted, to bound memory per request
+const MAX_CONTENT: usize = 16 * 1024 * 1024;
+
+/// Serve requests on stdin/stdout until stdin closes
+pub fn run(options: FormatOptions) -> io::Result<()> {
+	serve(io::stdin().lock(), io::stdout().lock(), options)
+}
+
+/// Answer every request read from `input` on `output`
+fn serve(
+	mut input: impl BufRead,
+	mut output: impl Write,
+	options: FormatOptions,
+) -> io::Result<()> {
+	let mut header = Vec::new();
+	loop {
+		header.clear();
+		if input.read_until(b'\n', &mut header)? == 0 {
+			return Ok(());
+		}
+		let header = String::from_utf8_lossy(&header);
+		let header = header.trim_end_matches(['\r', '\n']);
+		if header.is_empty() {
+			continue;
+		}
+
+		let response = match parse_header(header) {
+			Ok((command, len, path)) if len <= MAX_CONTENT => {
+				let mut content = vec![0; len];
+				input.read_exact(&mut content)?;
+				answer(command, &content, Path::new(path), options)
+			}
+			Ok((_, len, _)) => {
+				skip(&mut input, len)?;
+				Err(format!("content longer than {} bytes", MAX_CONTENT))
+			}
+			Err(e) => {
+				if let Some(len) = content_len(header) {
+					skip(&mut input, len)?;
+				}
+				Err(e)
+			}
+		};
+		match response {
+			Ok((body, Some(cursor))) => {
+				write!(output, "OK {} {}\n{}", body.len(), cursor, body)?
+			}
+			Ok((body, None)) => write!(output, "OK {}\n{}", body.len(), body)?,
+			Err(message) => {
+				write!(output, "ERROR {}\n{}", message.len(), message)?
+			}
+		}
+		output.flush()?;
+	}
+}
+
+/// What a request asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+	/// The formatted content
+	Format,
+	/// The edits from the content to its formatted version
+	Edits,
+	/// The formatted content and where the cursor at this byte offset
+	/// ends up in it
+	Cursor(usize),
+}
+
+/// Parse `FORMAT <len> <path>`, `EDITS <len> <path>` or
+/// `CURSOR <len> <offset> <path>` into the command, content length and path
+fn parse_header(header: &str) -> Result<(Command, usize, &str), String> {
+	let usage = || format!("expected 'FORMAT <len> <path>', got '{}'", header);
+	let mut parts = header.splitn(3, ' ');
+	let (Some(verb), Some(len), Some(rest)) =
+		(parts.next(), parts.next(), parts.next())
+	else {
+		return Err(usage());
+	};
+	let len = len
+		.parse()
+		.map_err(|_| format!("invalid content length '{}'", len))?;
+	let (command, path) = match verb {
+		"FORMAT" => (Command::Format, rest),
+		"EDITS" => (Command::Edits, rest),
+		"CURSOR" => {
+			let (offset, path) = rest.split_once(' ').ok_or_else(usage)?;
+			let offset = offset
+				.parse()
+				.map_err(|_| format!("invalid cursor offset '{}'", offset))?;
+			(Command::Cursor(offset), path)
+		}
+		_ => return Err(usage()),
+	};
+	if path.is_empty() {
+		return Err(usage());
+	}
+	Ok((command, len, path))
+}
+
+/// The `<len>` field of a header whose other fields may be invalid
+fn content_len(header: &str) -> Option<usize> {
+	header.split(' ').nth(1)?.parse().ok()
+}
+
+/// Read and drop the `len` bytes of content of a refused request
+fn skip(input: impl Read, len: usize) -> io::Result<()> {
+	let len = len as u64;
+	if io::copy(&mut input.take(len), &mut io::sink())? < len {
+		return Err(io::ErrorKind::UnexpectedEof.into());
+	}
+	Ok(())
+}
+
+/// Body of the answer to `command`, and the mapped cursor for `CURSOR`
+fn answer(
+	command: Command,
+	content: &[u8],
+	path: &Path,
+	options: FormatOptions,
+) -> Result<(String, Option<usize>), String> {
+	let formatted = format_request(content, path, options)?;
+	let original = String::from_utf8_lossy(content);
+	Ok(match command {
+		Command::Format => (formatted, None),
+		Command::Edits => {
+			let edits = edits::edits(&original, &formatted);
+			(edits::to_json(&edits).to_string(), None)
+		}
+		Command::Cursor(offset) => {
+			let cursor = edits::map_offset(&original, &formatted, offset);
+			(formatted, Some(cursor))
+		}
+	})
+}
+
+/// Format one request's content, leaving it unchanged for languages that
+/// are off or check-only
+pub fn format_request(
+	content: &[u8],
+	path: &Path,
+	options: FormatOptions,
+) -> Result<String, String> {
+	let content = std::str::from_utf8(content)
+		.map_err(|e| format!("invalid UTF-8 at byte {}", e.valid_up_to()))?;
+	let file_type = detect_file_type(path.to_str().unwrap_or(""));
+	if CONFIG.mode_for(file_type) != LanguageMode::Format {
+		return Ok(content.to_string());
+	}
+	formatter::format_source(content, path, options).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn run_session(input: &str) -> String {
+		let mut output = Vec::new();
+		serve(input.as_bytes(), &mut output, FormatOptions::default()).unwrap();
+		String::from_utf8(output).unwrap()
+	}
+
+	#[test]
+	fn test_parse_header() {
+		assert_eq!(
+			parse_header("FORMAT 12 src/my file.xml"),
+			Ok((Command::Format, 12, "src/my file.xml"))
+		);
+		assert_eq!(
+			parse_header("EDITS 3 a.xml"),
+			Ok((Command::Edits, 3, "a.xml"))
+		);
+		assert_eq!(
+			parse_header("CURSOR 3 1 my file.xml"),
+			Ok((Command::Cursor(1), 3, "my file.xml"))
+		);
+		assert!(parse_header("CURSOR 3 a.xml").is_err());
+		assert!(parse_header("FORMAT x a.xml").is_err());
+		assert!(parse_header("FORMAT 12").is_err());
+		assert!(parse_header("PING").is_err());
+	}
+
+	#[test]
+	fn test_format_requests() {
+		let expected = formatter::format_source(
+			"<a/>",
+			Path::new("a.xml"),
+			FormatOptions::default(),
+		)
+		.unwrap();
+		let output = run_session("FORMAT 4 a.xml\n<a/>FORMAT 4 b.xml\n<a/>");
+		let response = format!("OK {}\n{}", expected.len(), expected);
+		assert_eq!(output, response.repeat(2));
+	}
+
+	#[test]
+	fn test_edits_request() {
+		let content = "<a>\n<b/></a>";
+		let formatted = formatter::format_source(
+			content,
+			Path::new("a.xml"),
+			FormatOptions::default(),
+		)
+		.unwrap();
+		let expected =
+			edits::to_json(&edits::edits(content, &formatted)).to_string();
+		assert_ne!(expected, "[]");
+		let output = run_session(&format!("EDITS 12 a.xml\n{}", content));
+		assert_eq!(output, format!("OK {}\n{}", expected.len(), expected));
+	}
+
+	#[test]
+	fn test_cursor_request() {
+		let content = "<a>\n<b/></a>";
+		let formatted = formatter::format_source(
+			content,
+			Path::new("a.xml"),
+			FormatOptions::default(),
+		)
+		.unwrap();
+		let cursor = edits::map_offset(content, &formatted, 5);
+		let output = run_session(&format!("CURSOR 12 5 a.xml\n{}", content));
+		assert_eq!(
+			output,
+			format!("OK {} {}\n{}", formatted.len(), cursor, formatted)
+		);
+		assert_eq!(&formatted[cursor..cursor + 2], "b/");
+	}
+
+	#[test]
+	fn test_error_responses() {
+		let output = run_session("FORMAT 1 a.xyz\nx");
+		let message = "a.xyz: Unknown file type";
+		assert_eq!(output, format!("ERROR {}\n{}", message.len(), message));
+
+		let output = run_session("HELLO\n");
+		assert!(output.starts_with("ERROR "), "Got: {}", output);
+	}
+
+	#[test]
+	fn test_refused_content_is_skipped() {
+		// The content of a request with a bad header or too much content
+		// is read past, so the next request is answered
+		let expected = formatter::format_source(
+			"<a/>",
+			Path::new("a.xml"),
+			FormatOptions::default(),
+		)
+		.unwrap();
+		let ok = format!("OK {}\n{}", expected.len(), expected);
+		let output = run_session("CURSOR 4 x a.xml\n<a/>FORMAT 4 a.xml\n<a/>");
+		let message = "invalid cursor offset 'x'";
+		assert_eq!(
+			output,
+			format!("ERROR {}\n{}{}", message.len(), message, ok)
+		);
+
+		let huge = MAX_CONTENT + 1;
+		let input = format!(
+			"FORMAT {} a.xml\n{}FORMAT 4 a.xml\n<a/>",
+			huge,
+			"x".repeat(huge)
+		);
+		let output = run_session(&input);
+		assert!(output.starts_with("ERROR "), "Got: {}", &output[..40]);
+		assert!(output.ends_with(&ok));
+	}
+}
@@ -57,6 +57,8 @@ formatter:
 fn export_rustfmt(config: &FormatConfig, base_path: &Path) {
 	let hard_tabs = matches!(config.indent_style, IndentStyle::Tabs);
 	let newline_style = match config.line_ending {
+		// rustfmt's Auto keeps each file's first line ending
+		LineEnding::Auto => "Auto",
 		LineEnding::Lf => "Unix",
 		LineEnding::Crlf => "Windows",
 	};
@@ -102,7 +104,8 @@ mod tests {
 		assert!(content.contains("indent_style = tab"));
 		assert!(content.contains("indent_size = 4"));
 		assert!(content.contains("max_line_length = 80"));
-		assert!(content.contains("end_of_line = lf"));
+		// Auto line endings leave each file's own
+		assert!(!content.contains("end_of_line"));
 		assert!(content.contains("quote_type = double"));
 	}
 
@@ -121,7 +124,7 @@ mod tests {
 		assert!(content.contains("hard_tabs = true"));
 		assert!(content.contains("tab_spaces = 4"));
 		assert!(content.contains("max_width = 80"));
-		assert!(content.contains("newline_style = \"Unix\""));
+		assert!(content.contains("newline_style = \"Auto\""));
 	}
 
 	#[test]
@@ -167,7 +170,7 @@ mod tests {
 			trailing_comma: fama_common::TrailingComma::All,
 			semicolons: fama_common::Semicolons::Always,
 			bracket_spacing: true,
-			brace_style: fama_common::BraceStyle::SameLine,
+			..FormatConfig::default()
 		};
 
 		export_with_config(&config, temp_dir.path());
@@ -199,7 +202,7 @@ mod tests {
 			trailing_comma: fama_common::TrailingComma::All,
 			semicolons: fama_common::Semicolons::Always,
 			bracket_spacing: true,
-			brace_style: fama_common::BraceStyle::SameLine,
+			..FormatConfig::default()
 		};
 
 		export_with_config(&config, temp_dir.path());
@@ -0,0 +1,277 @@
+// edits.rs - Minimal text edits from a file to its formatted version
+//
+// Editors that replace the whole buffer lose the cursor, folds and marks.
+// Instead, the formatted text is diffed against the original and only the
+// changed spans are sent back, as LSP `TextEdit`s: 0-based lines and
+// UTF-16 character offsets, every range relative to the original text.
+// The same changes map a cursor offset into the formatted text.
+
+use serde_json::{json, Value};
+use similar::{DiffTag, TextDiff};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::formatter::{self, FormatOptions};
+
+/// Replace the text between `start` and `end` with `new_text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+	/// `(line, character)` where the replaced text starts
+	pub start: (usize, usize),
+	/// `(line, character)` just past the replaced text
+	pub end: (usize, usize),
+	pub new_text: String,
+}
+
+impl Edit {
+	/// The edit as an LSP `TextEdit`
+	pub fn to_json(&self) -> Value {
+		json!({
+			"range": {
+				"start": position_json(self.start),
+				"end": position_json(self.end),
+			},
+			"newText": self.new_text,
+		})
+	}
+}
+
+fn position_json((line, character): (usize, usize)) -> Value {
+	json!({ "line": line, "character": character })
+}
+
+/// Print the edits formatting would make to `path` as a JSON array
+pub fn run(path: &Path, options: FormatOptions) -> anyhow::Result<()> {
+	let content = std::fs::read_to_string(path)?;
+	let formatted = formatter::format_source(&content, path, options)?;
+	println!("{}", to_json(&edits(&content, &formatted)));
+	Ok(())
+}
+
+/// JSON array of LSP `TextEdit`s
+pub fn to_json(edits: &[Edit]) -> Value {
+	Value::Array(edits.iter().map(Edit::to_json).collect())
+}
+
+/// Non-overlapping edits, in document order, that turn `original` into
+/// `formatted`; empty when nothing changes
+pub fn edits(original: &str, formatted: &str) -> Vec<Edit> {
+	let line_starts = line_starts(original);
+	let position = |offset| position(original, &line_starts, offset);
+	changes(original, formatted)
+		.into_iter()
+		.map(|change| Edit {
+			start: position(change.old.start),
+			end: position(change.old.end),
+			new_text: formatted[change.new].to_string(),
+		})
+		.collect()
+}
+
+/// Byte offset in `formatted` of the spot at byte `offset` in `original`,
+/// so a cursor stays on the same code after formatting.
+///
+/// Inside a changed span, the cursor keeps its count of non-whitespace
+/// characters from the start of the span, which holds across the
+/// re-indenting and re-wrapping most formatting consists of.
+pub fn map_offset(original: &str, formatted: &str, offset: usize) -> usize {
+	let mut offset = offset.min(original.len());
+	while !original.is_char_boundary(offset) {
+		offset -= 1;
+	}
+
+	let (mut old_end, mut new_end) = (0, 0);
+	for change in changes(original, formatted) {
+		if offset <= change.old.start {
+			break;
+		}
+		if offset < change.old.end {
+			let before = &original[change.old.start..offset];
+			let span = &formatted[change.new.clone()];
+			return change.new.start + past_code(span, before);
+		}
+		(old_end, new_end) = (change.old.end, change.new.end);
+	}
+	new_end + (offset - old_end)
+}
+
+/// Byte length of the shortest prefix of `text` with as many
+/// non-whitespace characters as `before`
+fn past_code(text: &str, before: &str) -> usize {
+	let count = before.chars().filter(|c| !c.is_whitespace()).count();
+	if count == 0 {
+		return 0;
+	}
+	text.char_indices()
+		.filter(|(_, c)| !c.is_whitespace())
+		.nth(count - 1)
+		.map_or(text.len(), |(i, c)| i + c.len_utf8())
+}
+
+/// Bytes `old` of the original text that became bytes `new` of the
+/// formatted text
+struct Change {
+	old: Range<usize>,
+	new: Range<usize>,
+}
+
+/// Changed spans from `original` to `formatted`, in order, with any
+/// prefix and suffix the two sides share trimmed off
+fn changes(original: &str, formatted: &str) -> Vec<Change> {
+	let diff = TextDiff::from_lines(original, formatted);
+	let old_offsets = line_offsets(diff.old_slices());
+	let new_offsets = line_offsets(diff.new_slices());
+
+	// Adjacent changed line ranges, as byte ranges in both texts
+	let mut changes: Vec<(usize, usize, usize, usize)> = Vec::new();
+	for op in diff.ops() {
+		let (tag, old, new) = op.as_tag_tuple();
+		if tag == DiffTag::Equal {
+			continue;
+		}
+		let (old_start, old_end) =
+			(old_offsets[old.start], old_offsets[old.end]);
+		let (new_start, new_end) =
+			(new_offsets[new.start], new_offsets[new.end]);
+		match changes.last_mut() {
+			Some(last) if last.1 == old_start => {
+				last.1 = old_end;
+				last.3 = new_end;
+			}
+			_ => changes.push((old_start, old_end, new_start, new_end)),
+		}
+	}
+
+	changes
+		.into_iter()
+		.map(|(old_start, old_end, new_start, new_end)| {
+			let old = &original[old_start..old_end];
+			let new = &formatted[new_start..new_end];
+			let prefix = common_prefix(old, new);
+			let suffix = common_suffix(&old[prefix..], &new[prefix..]);
+			Change {
+				old: old_start + prefix..old_end - suffix,
+				new: new_start + prefix..new_end - suffix,
+			}
+		})
+		.collect()
+}
+
+/// Byte offset where each line starts, plus the total length
+fn line_offsets(lines: &[&str]) -> Vec<usize> {
+	let mut offsets = Vec::with_capacity(lines.len() + 1);
+	let mut offset = 0;
+	offsets.push(offset);
+	for line in lines {
+		offset += line.len();
+		offsets.push(offset);
+	}
+	offsets
+}
+
+/// Length in bytes of the longest shared prefix, on a char boundary
+fn common_prefix(a: &str, b: &str) -> usize {
+	a.char_indices()
+		.zip(b.chars())
+		.find(|((_, x), y)| x != y)
+		.map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+/// Length in bytes of the longest shared suffix, on a char boundary
+fn common_suffix(a: &str, b: &str) -> usize {
+	a.chars()
+		.rev()
+		.zip(b.chars().rev())
+		.take_while(|(x, y)| x == y)
+		.map(|(x, _)| x.len_utf8())
+		.sum()
+}
+
+/// Byte offset where each line of `text` starts
+fn line_starts(text: &str) -> Vec<usize> {
+	std::iter::once(0)
+		.chain(text.match_indices('\n').map(|(i, _)| i + 1))
+		.collect()
+}
+
+/// LSP position of byte `offset` in `text`
+fn position(
+	text: &str,
+	line_starts: &[usize],
+	offset: usize,
+) -> (usize, usize) {
+	let line = line_starts.partition_point(|&start| start <= offset) - 1;
+	let character = text[line_starts[line]..offset].encode_utf16().count();
+	(line, character)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn edit(start: (usize, usize), end: (usize, usize), text: &str) -> Edit {
+		Edit {
+			start,
+			end,
+			new_text: text.to_string(),
+		}
+	}
+
+	#[test]
+	fn test_edits_within_lines() {
+		let original = "let a=1\nkeep\nlet b='é'\n";
+		let formatted = "let a = 1;\nkeep\nlet b = \"é\";\n";
+		assert_eq!(
+			edits(original, formatted),
+			[
+				edit((0, 5), (0, 7), " = 1;"),
+				edit((2, 5), (2, 9), " = \"é\";"),
+			]
+		);
+		assert!(edits(original, original).is_empty());
+	}
+
+	#[test]
+	fn test_edits_insert_and_delete_lines() {
+		let original = "a\nx\ny\nb\nc";
+		let formatted = "a\nb\nc\n";
+		assert_eq!(
+			edits(original, formatted),
+			[edit((1, 0), (3, 0), ""), edit((4, 1), (4, 1), "\n")]
+		);
+	}
+
+	#[test]
+	fn test_edits_utf16_positions() {
+		let text = "😀x\ny";
+		let position = |offset| position(text, &line_starts(text), offset);
+		assert_eq!(position(4), (0, 2));
+		assert_eq!(position(5), (0, 3));
+		assert_eq!(position(6), (1, 0));
+		assert_eq!(position(7), (1, 1));
+	}
+
+	#[test]
+	fn test_map_offset() {
+		let original = "let a=1\nif (x) {\nfoo( bar )\n}\n";
+		let formatted = "let a = 1;\nif (x) {\n\tfoo(bar);\n}\n";
+		let map = |offset| map_offset(original, formatted, offset);
+		// Before any change, and in an unchanged line after one
+		assert_eq!(map(0), 0);
+		assert_eq!(map(original.find("(x)").unwrap()), 14);
+		// Inside a changed span, on the same code
+		let bar = original.find("bar").unwrap();
+		assert_eq!(&formatted[map(bar)..], "bar);\n}\n");
+		assert_eq!(&formatted[map(bar + 2)..], "r);\n}\n");
+		assert_eq!(map(original.len()), formatted.len());
+		assert_eq!(map(original.len() + 10), formatted.len());
+	}
+
+	#[test]
+	fn test_edits_json() {
+		let json = to_json(&[edit((0, 1), (0, 2), "x")]);
+		assert_eq!(json[0]["range"]["start"]["character"], 1);
+		assert_eq!(json[0]["range"]["end"]["line"], 0);
+		assert_eq!(json[0]["newText"], "x");
+	}
+}
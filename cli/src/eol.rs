@@ -0,0 +1,247 @@
+// eol.rs - Per-file line endings for `line_ending = Auto`
+//
+// With Auto, backends write LF and each file is then given the ending git
+// checks it out with, so fama never fights git's own normalization. The
+// `eol` attribute from `.gitattributes` wins, then `core.autocrlf`. A file
+// git doesn't convert (`-text`/`binary`, or nothing configured) keeps its
+// own first line ending.
+
+use fama_common::{LineEnding, CONFIG};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The attributes of one `.gitattributes` line that decide line endings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Attributes {
+	/// `eol=lf` or `eol=crlf`
+	eol: Option<LineEnding>,
+	/// `text`/`text=auto` (true) or `-text`/`binary` (false)
+	text: Option<bool>,
+}
+
+impl Attributes {
+	/// Apply a later, more specific match on top of these
+	fn merge(&mut self, other: &Attributes) {
+		self.eol = other.eol.or(self.eol);
+		self.text = other.text.or(self.text);
+	}
+}
+
+/// A pattern line of a `.gitattributes` file
+#[derive(Debug, Clone)]
+struct Rule {
+	pattern: glob::Pattern,
+	/// Matched against the path relative to the file's directory rather
+	/// than the file name, since the pattern has a `/`
+	anchored: bool,
+	attributes: Attributes,
+}
+
+/// The rules of a `.gitattributes` file that touch line endings
+fn parse(text: &str) -> Vec<Rule> {
+	text.lines()
+		.filter_map(|line| {
+			let mut fields = line.split_whitespace();
+			let pattern = fields.next().filter(|p| !p.starts_with('#'))?;
+			let mut attributes = Attributes::default();
+			for field in fields {
+				match field {
+					"eol=lf" => attributes.eol = Some(LineEnding::Lf),
+					"eol=crlf" => attributes.eol = Some(LineEnding::Crlf),
+					"text" | "text=auto" => attributes.text = Some(true),
+					"-text" | "binary" => attributes.text = Some(false),
+					_ => {}
+				}
+			}
+			// Directory patterns never match files
+			if attributes == Attributes::default() || pattern.ends_with('/') {
+				return None;
+			}
+			Some(Rule {
+				pattern: glob::Pattern::new(pattern.trim_start_matches('/'))
+					.ok()?,
+				anchored: pattern.contains('/'),
+				attributes,
+			})
+		})
+		.collect()
+}
+
+/// Parsed `.gitattributes` files, by path; run-wide, since every file in a
+/// directory reads the same ones
+fn rules(path: &Path) -> Arc<Vec<Rule>> {
+	static CACHE: OnceLock<Mutex<HashMap<PathBuf, Arc<Vec<Rule>>>>> =
+		OnceLock::new();
+	let cache = CACHE.get_or_init(Default::default);
+	if let Some(rules) = cache.lock().unwrap().get(path) {
+		return rules.clone();
+	}
+	let rules = Arc::new(
+		fs::read_to_string(path)
+			.map_or_else(|_| Vec::new(), |text| parse(&text)),
+	);
+	cache
+		.lock()
+		.unwrap()
+		.insert(path.to_path_buf(), rules.clone());
+	rules
+}
+
+/// The line-ending attributes git gives `path`, or None outside a git
+/// work tree
+fn attributes(path: &Path) -> Option<Attributes> {
+	let path = std::path::absolute(path).ok()?;
+	let mut dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+	let root = dirs.iter().position(|dir| dir.join(".git").exists())?;
+	dirs.truncate(root + 1);
+
+	let matches = |file: &Path, dir: &Path, attributes: &mut Attributes| {
+		let (Ok(relative), Some(name)) =
+			(path.strip_prefix(dir), path.file_name())
+		else {
+			return;
+		};
+		let options = glob::MatchOptions {
+			require_literal_separator: true,
+			..Default::default()
+		};
+		for rule in rules(file).iter() {
+			let matched = if rule.anchored {
+				rule.pattern.matches_path_with(relative, options)
+			} else {
+				rule.pattern.matches_with(&name.to_string_lossy(), options)
+			};
+			if matched {
+				attributes.merge(&rule.attributes);
+			}
+		}
+	};
+
+	// Outermost first, so deeper files and later lines take precedence;
+	// `.git/info/attributes` overrides them all
+	let mut attributes = Attributes::default();
+	for dir in dirs.iter().rev() {
+		matches(&dir.join(".gitattributes"), dir, &mut attributes);
+	}
+	let root = dirs[root];
+	matches(&root.join(".git/info/attributes"), root, &mut attributes);
+	Some(attributes)
+}
+
+/// `core.autocrlf` of the repository fama runs in, read once
+fn autocrlf() -> Option<&'static str> {
+	static AUTOCRLF: OnceLock<Option<String>> = OnceLock::new();
+	AUTOCRLF
+		.get_or_init(|| {
+			let output = Command::new("git")
+				.args(["config", "--get", "core.autocrlf"])
+				.output()
+				.ok()?;
+			let value = String::from_utf8(output.stdout).ok()?;
+			Some(value.trim().to_lowercase())
+		})
+		.as_deref()
+}
+
+/// The first line ending of `content`, LF for a single line
+fn own(content: &str) -> LineEnding {
+	match content.find('\n') {
+		Some(i) if content[..i].ends_with('\r') => LineEnding::Crlf,
+		_ => LineEnding::Lf,
+	}
+}
+
+/// The ending to write `path` with; never `Auto`
+pub fn resolve(path: &Path, content: &str) -> LineEnding {
+	match CONFIG.line_ending {
+		LineEnding::Auto => resolve_auto(path, content, autocrlf()),
+		line_ending => line_ending,
+	}
+}
+
+fn resolve_auto(
+	path: &Path,
+	content: &str,
+	autocrlf: Option<&str>,
+) -> LineEnding {
+	let Some(attributes) = attributes(path) else {
+		return own(content);
+	};
+	match attributes {
+		Attributes {
+			text: Some(false), ..
+		} => own(content),
+		Attributes { eol: Some(eol), .. } => eol,
+		_ => match autocrlf {
+			Some("true") => LineEnding::Crlf,
+			Some("input") => LineEnding::Lf,
+			_ => own(content),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_parse() {
+		let rules = parse(
+			"# comment\n* text=auto\n*.bat eol=crlf\n/docs/*.md -text\n\
+			 build/ eol=lf\n*.png diff\n",
+		);
+		let patterns: Vec<(&str, bool)> = rules
+			.iter()
+			.map(|rule| (rule.pattern.as_str(), rule.anchored))
+			.collect();
+		assert_eq!(
+			patterns,
+			[("*", false), ("*.bat", false), ("docs/*.md", true)]
+		);
+		assert_eq!(rules[1].attributes.eol, Some(LineEnding::Crlf));
+	}
+
+	#[test]
+	fn test_resolve_follows_git() {
+		let temp_dir = TempDir::new().unwrap();
+		let root = temp_dir.path();
+		fs::create_dir_all(root.join(".git/info")).unwrap();
+		fs::create_dir(root.join("sub")).unwrap();
+		fs::write(
+			root.join(".gitattributes"),
+			"*.sh eol=lf\n*.bat eol=crlf\n*.bin binary\n",
+		)
+		.unwrap();
+		fs::write(root.join("sub/.gitattributes"), "*.sh eol=crlf\n").unwrap();
+		fs::write(root.join(".git/info/attributes"), "/sub/x.sh eol=lf\n")
+			.unwrap();
+
+		let resolve = |name: &str, content: &str, autocrlf| {
+			resolve_auto(&root.join(name), content, autocrlf)
+		};
+		assert_eq!(resolve("a.sh", "a\r\n", None), LineEnding::Lf);
+		assert_eq!(resolve("a.bat", "a\n", None), LineEnding::Crlf);
+		assert_eq!(resolve("sub/a.sh", "a\n", None), LineEnding::Crlf);
+		assert_eq!(resolve("sub/x.sh", "a\n", None), LineEnding::Lf);
+		// Files git doesn't convert keep their own endings
+		assert_eq!(resolve("a.bin", "a\r\n", Some("true")), LineEnding::Crlf);
+		assert_eq!(resolve("a.txt", "a\r\nb\n", None), LineEnding::Crlf);
+		assert_eq!(resolve("a.txt", "a\r\n", Some("input")), LineEnding::Lf);
+		assert_eq!(resolve("a.txt", "a\n", Some("true")), LineEnding::Crlf);
+	}
+
+	#[test]
+	fn test_resolve_outside_git() {
+		let temp_dir = TempDir::new().unwrap();
+		let path = temp_dir.path().join("a.txt");
+		assert_eq!(
+			resolve_auto(&path, "a\r\n", Some("true")),
+			LineEnding::Crlf
+		);
+		assert_eq!(resolve_auto(&path, "a", Some("true")), LineEnding::Lf);
+	}
+}
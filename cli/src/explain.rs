@@ -0,0 +1,372 @@
+// explain.rs - Formatter decision trace for `fama explain`
+
+use fama_common::{
+	detect_file_type, matches_path_pattern, FileType, FormatConfig, CONFIG,
+};
+use serde_json::json;
+use std::fmt::Debug;
+use std::path::Path;
+
+use crate::backend::backend_for;
+use crate::config::ConfigValue;
+use crate::discovery;
+
+/// A config value that applies to a file, and where it came from
+struct Setting {
+	name: &'static str,
+	value: String,
+	json: serde_json::Value,
+	source: String,
+}
+
+impl Setting {
+	/// A config value, credited to the preset when it matches the preset's
+	/// own value and to `CONFIG` when it was changed on top of it
+	fn new<T: Debug + PartialEq + ConfigValue>(
+		name: &'static str,
+		config: &FormatConfig,
+		value: impl Fn(&FormatConfig) -> T,
+	) -> Self {
+		let current = value(config);
+		let source = if current == value(&config.preset.config()) {
+			format!("preset {:?}", config.preset)
+		} else {
+			"CONFIG".to_string()
+		};
+		Self {
+			name,
+			value: format!("{current:?}"),
+			json: current.to_json(),
+			source,
+		}
+	}
+
+	fn source(mut self, source: impl Into<String>) -> Self {
+		self.source = source.into();
+		self
+	}
+}
+
+/// Print the decision trace for `path` to stdout
+pub fn run(path: &Path) {
+	print!("{}", explain(path, &CONFIG));
+}
+
+/// Config values that apply to `path` and where each came from, one
+/// indented `name = value (source)` line per value
+pub fn provenance(path: &Path, config: &FormatConfig) -> String {
+	let file_type = detect_file_type(path.to_str().unwrap_or(""));
+	settings(path, file_type, config)
+		.iter()
+		.map(|setting| {
+			format!(
+				"  {} = {} ({})\n",
+				setting.name, setting.value, setting.source
+			)
+		})
+		.collect()
+}
+
+/// Whether discovery includes `path`, or why it skips it
+fn discovery(path: &Path) -> String {
+	match discovery::skip_reason(path) {
+		Some(reason) => format!("skipped: {}", reason),
+		None => "included".to_string(),
+	}
+}
+
+/// Describe how fama would handle `path`: detected type, backend,
+/// discovery outcome, and the config values that apply
+fn explain(path: &Path, config: &FormatConfig) -> String {
+	let file_type = detect_file_type(path.to_str().unwrap_or(""));
+
+	format!(
+		"path:      {}\n\
+		 file type: {:?}\n\
+		 backend:   {}\n\
+		 discovery: {}\n\
+		 config:\n{}",
+		path.display(),
+		file_type,
+		backend_for(file_type).name(),
+		discovery(path),
+		provenance(path, config),
+	)
+}
+
+/// What [`explain`] describes, as one JSON object with a `value` and
+/// `source` per config value, for `fama config --resolved`
+pub fn to_json(path: &Path, config: &FormatConfig) -> serde_json::Value {
+	let file_type = detect_file_type(path.to_str().unwrap_or(""));
+	let settings: serde_json::Map<String, serde_json::Value> =
+		settings(path, file_type, config)
+			.into_iter()
+			.map(|setting| {
+				let value =
+					json!({ "value": setting.json, "source": setting.source });
+				(setting.name.to_string(), value)
+			})
+			.collect();
+	json!({
+		"path": path.display().to_string(),
+		"file_type": file_type.to_json(),
+		"backend": backend_for(file_type).name(),
+		"discovery": discovery(path),
+		"config": settings,
+	})
+}
+
+/// Config values that apply to a file, with per-language and per-path
+/// overrides resolved
+fn settings(
+	path: &Path,
+	file_type: FileType,
+	config: &FormatConfig,
+) -> Vec<Setting> {
+	let language_override = format!("override for {:?}", file_type);
+
+	let mut mode = Setting::new("mode", config, |c| c.mode_for(file_type));
+	if has_override(config.language_modes, file_type) {
+		mode = mode.source(&language_override);
+	}
+	let mut final_newline = Setting::new("insert_final_newline", config, |c| {
+		c.insert_final_newline_for(file_type)
+	});
+	if has_override(config.final_newline_overrides, file_type) {
+		final_newline = final_newline.source(&language_override);
+	}
+
+	let path_str = path.to_str().unwrap_or("");
+	let mut line_width =
+		Setting::new("line_width", config, |c| c.line_width_for(path_str));
+	if let Some((o, _)) = config.override_for(path_str, |o| o.line_width) {
+		line_width = line_width
+			.source(format!("override for '{}'", o.files.join("', '")));
+	}
+
+	let mut settings = vec![
+		mode,
+		Setting::new("indent_style", config, |c| c.indent_style),
+		Setting::new("indent_width", config, |c| c.indent_width),
+		line_width,
+		Setting::new("width_measure", config, |c| c.width_measure),
+		Setting::new("line_ending", config, |c| c.line_ending),
+		final_newline,
+		Setting::new("max_blank_lines", config, |c| c.max_blank_lines),
+		Setting::new("review_threshold", config, |c| c.review_threshold),
+		Setting::new("normalize_invisible_chars", config, |c| {
+			c.normalize_invisible_chars
+		}),
+	];
+
+	match file_type {
+		FileType::JavaScript
+		| FileType::TypeScript
+		| FileType::Jsx
+		| FileType::Tsx => {
+			settings.extend([
+				Setting::new("quote_style", config, |c| c.quote_style),
+				Setting::new("trailing_comma", config, |c| c.trailing_comma),
+				Setting::new("semicolons", config, |c| c.semicolons),
+				Setting::new("bracket_spacing", config, |c| c.bracket_spacing),
+				Setting::new("remove_unused_imports", config, |c| {
+					c.remove_unused_imports
+				}),
+				Setting::new("preserve_banner_comments", config, |c| {
+					c.preserve_banner_comments
+				}),
+			]);
+			if matches!(file_type, FileType::Jsx | FileType::Tsx) {
+				settings.push(Setting::new("sort_jsx_props", config, |c| {
+					c.sort_jsx_props
+				}));
+			}
+		}
+		FileType::Json | FileType::Jsonc => {
+			let sort_keys = Setting::new("json_sort_keys", config, |c| {
+				c.json_sort_keys_for(path_str)
+			});
+			let pattern = config
+				.json_sort_keys_paths
+				.iter()
+				.find(|pattern| matches_path_pattern(path, &[*pattern]));
+			settings.push(match pattern {
+				Some(pattern) if !config.json_sort_keys => {
+					sort_keys.source(format!("matches '{}'", pattern))
+				}
+				_ => sort_keys,
+			});
+			if file_type == FileType::Jsonc {
+				settings.push(Setting::new("jsonc_output", config, |c| {
+					c.jsonc_output
+				}));
+			}
+		}
+		FileType::Css | FileType::Scss | FileType::Less | FileType::Sass => {
+			settings.extend([
+				Setting::new("quote_style", config, |c| c.quote_style),
+				Setting::new("trailing_comma", config, |c| c.trailing_comma),
+				Setting::new("brace_style", config, |c| c.brace_style),
+			]);
+		}
+		FileType::Python | FileType::Lua => {
+			settings
+				.push(Setting::new("quote_style", config, |c| c.quote_style));
+		}
+		FileType::Php => {
+			settings.extend([
+				Setting::new("quote_style", config, |c| c.quote_style),
+				Setting::new("trailing_comma", config, |c| c.trailing_comma),
+			]);
+		}
+		FileType::C
+		| FileType::Cpp
+		| FileType::CSharp
+		| FileType::ObjectiveC
+		| FileType::ObjectiveCpp
+		| FileType::Cuda
+		| FileType::Shader
+		| FileType::Java
+		| FileType::Protobuf => {
+			settings.extend([
+				Setting::new("brace_style", config, |c| c.brace_style),
+				Setting::new("pointer_alignment", config, |c| {
+					c.pointer_alignment
+				}),
+				Setting::new("short_functions", config, |c| c.short_functions),
+				Setting::new("sort_includes", config, |c| c.sort_includes),
+				Setting::new("group_includes", config, |c| c.group_includes),
+				Setting::new("clang_fallback", config, |c| c.clang_fallback),
+			]);
+		}
+		FileType::Go => {
+			settings.push(Setting::new("go_style", config, |c| c.go_style));
+		}
+		FileType::Starlark => {
+			settings.extend([
+				Setting::new("starlark_sort_loads", config, |c| {
+					c.starlark_sort_loads
+				}),
+				Setting::new("starlark_sort_arguments", config, |c| {
+					c.starlark_sort_arguments
+				}),
+			]);
+		}
+		FileType::Markdown => {
+			settings.extend([
+				Setting::new("markdown_text_wrap", config, |c| {
+					c.markdown_text_wrap
+				}),
+				Setting::new("prose_width", config, |c| {
+					c.prose_width_for(path_str)
+				}),
+				Setting::new("markdown_format_tables", config, |c| {
+					c.markdown_format_tables
+				}),
+				Setting::new("markdown_toc", config, |c| c.markdown_toc),
+				Setting::new("markdown_check_links", config, |c| {
+					c.markdown_check_links
+				}),
+			]);
+		}
+		FileType::Sql => {
+			settings.extend([
+				Setting::new("sql_lint", config, |c| c.sql_lint),
+				Setting::new("sql_templater", config, |c| c.sql_templater),
+				Setting::new("sql_templater_vars", config, |c| {
+					c.sql_templater_vars
+				}),
+			]);
+		}
+		FileType::Kotlin => {
+			settings.push(Setting::new("subprocess_backends", config, |c| {
+				c.subprocess_backends
+			}));
+		}
+		_ => {}
+	}
+
+	settings
+}
+
+fn has_override<T>(overrides: &[(FileType, T)], file_type: FileType) -> bool {
+	overrides.iter().any(|(ty, _)| *ty == file_type)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use fama_common::{LanguageMode, Override};
+	use std::fs;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_explain_typescript() {
+		let temp_dir = TempDir::new().unwrap();
+		let path = temp_dir.path().join("app.ts");
+		fs::write(&path, "").unwrap();
+
+		let out = explain(&path, &CONFIG);
+		assert!(out.contains("file type: TypeScript\n"));
+		assert!(out.contains("backend:   biome\n"));
+		assert!(out.contains("discovery: included\n"));
+		assert!(out.contains("  line_width = 80 (preset Gofmt)\n"));
+		assert!(out.contains("  semicolons = Always (preset Gofmt)\n"));
+		assert!(!out.contains("sort_jsx_props"));
+	}
+
+	#[test]
+	fn test_provenance_sources() {
+		let config = FormatConfig {
+			line_width: 100,
+			..fama_common::presets::PRETTIER
+		};
+		let out = provenance(Path::new("app.ts"), &config);
+		assert!(out.contains("  line_width = 100 (CONFIG)\n"));
+		assert!(out.contains("  indent_width = 2 (preset Prettier)\n"));
+	}
+
+	#[test]
+	fn test_to_json() {
+		let value = to_json(Path::new("app.tsx"), &CONFIG);
+		assert_eq!(value["file_type"], "Tsx");
+		assert_eq!(value["backend"], "biome");
+		assert_eq!(
+			value["config"]["line_width"],
+			json!({ "value": 80, "source": "preset Gofmt" })
+		);
+		assert_eq!(value["config"]["sort_jsx_props"]["value"], false);
+		assert_eq!(value["config"]["max_blank_lines"]["value"], json!(null));
+	}
+
+	#[test]
+	fn test_explain_skipped_file() {
+		let temp_dir = TempDir::new().unwrap();
+		let path = temp_dir.path().join("app.min.js");
+		fs::write(&path, "").unwrap();
+
+		let out = explain(&path, &CONFIG);
+		assert!(out.contains("discovery: skipped: matches '*.min.js'"));
+	}
+
+	#[test]
+	fn test_explain_overrides() {
+		let config = FormatConfig {
+			language_modes: &[(FileType::Json, LanguageMode::CheckOnly)],
+			json_sort_keys_paths: &["locales/*.json"],
+			overrides: &[Override {
+				files: &["locales/**"],
+				line_width: Some(120),
+				..Override::NONE
+			}],
+			..CONFIG
+		};
+		let out = explain(Path::new("locales/en.json"), &config);
+		assert!(out.contains("  mode = CheckOnly (override for Json)\n"));
+		assert!(
+			out.contains("  line_width = 120 (override for 'locales/**')\n")
+		);
+		assert!(out
+			.contains("  json_sort_keys = true (matches 'locales/*.json')\n"));
+	}
+}
@@ -0,0 +1,206 @@
+// export.rs - Config files for other tools, from fama's own config
+//
+// `fama export --format prettier|biome|editorconfig` writes what those
+// tools would need to format like fama does, so editors that run them
+// instead of fama still show consistent settings. Options the other tool
+// has no equivalent for are left out.
+
+use clap::ValueEnum;
+use fama_common::{
+	glob_at_any_depth, FormatConfig, IndentStyle, LineEnding, MarkdownTextWrap,
+	QuoteStyle, Semicolons, TrailingComma,
+};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+use crate::editorconfig;
+
+/// Which tool to write a config file for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ExportFormat {
+	/// `.editorconfig`, plus `rustfmt.toml` and `analysis_options.yaml`
+	/// next to a `Cargo.toml` or `pubspec.yaml`
+	#[default]
+	Editorconfig,
+	/// `.prettierrc.json`
+	Prettier,
+	/// `biome.json`
+	Biome,
+}
+
+/// Write the config file for `format` into the current directory
+pub fn run(format: ExportFormat) -> anyhow::Result<()> {
+	let config = FormatConfig::default();
+	let (name, value) = match format {
+		ExportFormat::Editorconfig => {
+			editorconfig::export();
+			return Ok(());
+		}
+		ExportFormat::Prettier => (".prettierrc.json", prettier(&config)),
+		ExportFormat::Biome => ("biome.json", biome(&config)),
+	};
+	let mut contents = serde_json::to_string_pretty(&value)?;
+	contents.push('\n');
+	fs::write(Path::new(name), contents)?;
+	println!("Wrote {}", name);
+	Ok(())
+}
+
+/// Prettier options; `overrides` carries the per-glob line widths and
+/// the Markdown prose width
+fn prettier(config: &FormatConfig) -> Value {
+	let mut overrides: Vec<Value> = config
+		.overrides
+		.iter()
+		.filter_map(|o| {
+			let width = o.line_width?;
+			let files: Vec<String> =
+				o.files.iter().map(|p| glob_at_any_depth(p)).collect();
+			Some(json!({ "files": files, "options": { "printWidth": width } }))
+		})
+		.collect();
+	if let Some(width) = config.prose_width {
+		overrides.push(json!({
+			"files": ["*.md", "*.markdown"],
+			"options": { "printWidth": width },
+		}));
+	}
+
+	let mut options = json!({
+		"useTabs": config.indent_style == IndentStyle::Tabs,
+		"tabWidth": config.indent_width,
+		"printWidth": config.line_width,
+		"endOfLine": match config.line_ending {
+			LineEnding::Auto => "auto",
+			LineEnding::Lf => "lf",
+			LineEnding::Crlf => "crlf",
+		},
+		"singleQuote": config.quote_style == QuoteStyle::Single,
+		"semi": config.semicolons == Semicolons::Always,
+		"trailingComma": match config.trailing_comma {
+			TrailingComma::All => "all",
+			TrailingComma::None => "none",
+		},
+		"bracketSpacing": config.bracket_spacing,
+		"proseWrap": match config.markdown_text_wrap {
+			MarkdownTextWrap::Always => "always",
+			MarkdownTextWrap::Never => "never",
+			MarkdownTextWrap::Maintain => "preserve",
+		},
+	});
+	if !overrides.is_empty() {
+		options["overrides"] = Value::Array(overrides);
+	}
+	options
+}
+
+/// Biome 1.x `biome.json` for the languages fama formats with Biome
+fn biome(config: &FormatConfig) -> Value {
+	let trailing_commas = match config.trailing_comma {
+		TrailingComma::All => "all",
+		TrailingComma::None => "none",
+	};
+	let overrides: Vec<Value> = config
+		.overrides
+		.iter()
+		.filter_map(|o| {
+			let width = o.line_width?;
+			// Biome anchors every pattern at the project root
+			let include: Vec<String> = o
+				.files
+				.iter()
+				.map(|p| {
+					if p.starts_with("**/") {
+						p.to_string()
+					} else {
+						format!("**/{}", p)
+					}
+				})
+				.collect();
+			Some(json!({
+				"include": include,
+				"formatter": { "lineWidth": width },
+			}))
+		})
+		.collect();
+
+	let mut options = json!({
+		"formatter": {
+			"enabled": true,
+			"indentStyle": match config.indent_style {
+				IndentStyle::Tabs => "tab",
+				IndentStyle::Spaces => "space",
+			},
+			"indentWidth": config.indent_width,
+			"lineWidth": config.line_width,
+			// Biome 1.x can't keep each file's own line ending
+			"lineEnding": match config.line_ending {
+				LineEnding::Auto | LineEnding::Lf => "lf",
+				LineEnding::Crlf => "crlf",
+			},
+		},
+		"javascript": {
+			"formatter": {
+				"quoteStyle": match config.quote_style {
+					QuoteStyle::Single => "single",
+					QuoteStyle::Double => "double",
+				},
+				"semicolons": match config.semicolons {
+					Semicolons::Always => "always",
+					Semicolons::AsNeeded => "asNeeded",
+				},
+				"trailingCommas": trailing_commas,
+				"bracketSpacing": config.bracket_spacing,
+			},
+		},
+		"json": {
+			"formatter": { "trailingCommas": trailing_commas },
+		},
+	});
+	if !overrides.is_empty() {
+		options["overrides"] = Value::Array(overrides);
+	}
+	options
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use fama_common::{presets, Override};
+
+	#[test]
+	fn test_prettier() {
+		let config = FormatConfig {
+			overrides: &[Override {
+				files: &["docs/**"],
+				line_width: Some(120),
+				..Override::NONE
+			}],
+			..presets::GOOGLE
+		};
+		let options = prettier(&config);
+		assert_eq!(options["useTabs"], false);
+		assert_eq!(options["tabWidth"], 2);
+		assert_eq!(options["singleQuote"], true);
+		assert_eq!(options["bracketSpacing"], false);
+		assert_eq!(options["proseWrap"], "preserve");
+		assert_eq!(
+			options["overrides"],
+			json!([{
+				"files": ["**/docs/**"],
+				"options": { "printWidth": 120 },
+			}])
+		);
+	}
+
+	#[test]
+	fn test_biome() {
+		let options = biome(&FormatConfig::default());
+		assert_eq!(options["formatter"]["indentStyle"], "tab");
+		assert_eq!(options["formatter"]["lineWidth"], 80);
+		assert_eq!(options["javascript"]["formatter"]["semicolons"], "always");
+		assert_eq!(options["json"]["formatter"]["trailingCommas"], "all");
+		assert!(options.get("overrides").is_none());
+	}
+}
@@ -0,0 +1,23 @@
+// fnv.rs - 64-bit FNV-1a hashing
+//
+// Unlike std's `DefaultHasher`, FNV-1a gives the same hash in every build
+// and Rust version, so hashes can be stored and compared across runs.
+// build.rs `include!`s this file, so it holds nothing but the function.
+
+/// 64-bit FNV-1a hash of `bytes`
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+		(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_fnv1a64() {
+		assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+		assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+	}
+}
@@ -1,83 +1,443 @@
 // formatter.rs - Format routing logic
 
-use fama_common::{detect_file_type, FileType};
+use crate::backend::backend_for;
+use crate::{
+	align, blank_lines, comments, directives, eol, ignore_regions, indent,
+	interactive, invisible, jsonc, report, risk, sfc,
+};
+use fama_common::{detect_file_type, FileType, LineEnding, CONFIG};
+use std::borrow::Cow;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Options controlling how a single file is formatted and written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FormatOptions {
+	/// Report changes without writing them
+	pub check: bool,
+	/// Convert leading indentation for backends lacking a full reformat
+	pub fix_indentation: bool,
+	/// Write even if the file changed on disk since it was read
+	pub force_write: bool,
+	/// Refuse to write output whose data model differs from the input
+	pub validate: bool,
+	/// Show the diff and ask before writing each changed file
+	pub interactive: bool,
+	/// Replace invalid UTF-8 sequences instead of failing on them
+	pub lossy_utf8: bool,
+	/// Make read-only files writable for the write, then restore them
+	pub chmod_writable: bool,
+}
+
+/// Metadata snapshot taken when a file is read, to detect concurrent edits
+#[derive(Debug, PartialEq, Eq)]
+struct FileStamp {
+	modified: Option<SystemTime>,
+	len: u64,
+}
+
+impl FileStamp {
+	fn of(path: &Path) -> std::io::Result<Self> {
+		let metadata = fs::metadata(path)?;
+		Ok(Self {
+			modified: metadata.modified().ok(),
+			len: metadata.len(),
+		})
+	}
+}
+
+/// Check whether a file no longer holds the content read at `stamp` time.
+///
+/// Metadata is compared first; the content is only re-read when it differs.
+fn changed_since_read(
+	path: &Path,
+	stamp: &FileStamp,
+	original: &[u8],
+) -> std::io::Result<bool> {
+	if FileStamp::of(path)? == *stamp {
+		return Ok(false);
+	}
+	Ok(fs::read(path)? != original)
+}
+
+/// Whether a file is marked read-only. Files writable by mode but not by
+/// this user (owned by someone else, ACLs) only show up when the write
+/// fails, see `is_permission_denied`.
+fn is_read_only(path: &Path) -> std::io::Result<bool> {
+	Ok(fs::metadata(path)?.permissions().readonly())
+}
+
+/// Whether `write` failed because the file can't be written by this user
+fn is_permission_denied(error: &anyhow::Error) -> bool {
+	error
+		.downcast_ref::<std::io::Error>()
+		.is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+/// Write `contents` to `path`; with `chmod_writable`, a read-only file is
+/// made writable by its owner first and gets its permissions back after
+fn write(
+	path: &Path,
+	contents: &str,
+	chmod_writable: bool,
+) -> anyhow::Result<()> {
+	let permissions = fs::metadata(path)?.permissions();
+	if !chmod_writable || !permissions.readonly() {
+		fs::write(path, contents)?;
+		return Ok(());
+	}
+
+	let mut writable = permissions.clone();
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		writable.set_mode(permissions.mode() | 0o200);
+	}
+	#[cfg(not(unix))]
+	#[allow(clippy::permissions_set_readonly_false)]
+	writable.set_readonly(false);
+	fs::set_permissions(path, writable).map_err(|e| {
+		anyhow::anyhow!("{}: failed to make writable: {}", path.display(), e)
+	})?;
+	let written = fs::write(path, contents);
+	fs::set_permissions(path, permissions).map_err(|e| {
+		anyhow::anyhow!(
+			"{}: failed to restore read-only permissions: {}",
+			path.display(),
+			e
+		)
+	})?;
+	Ok(written?)
+}
+
+/// Decode file bytes as UTF-8, or with `lossy` replace invalid sequences
+/// with U+FFFD and warn
+fn decode(path: &Path, bytes: &[u8], lossy: bool) -> anyhow::Result<String> {
+	match std::str::from_utf8(bytes) {
+		Ok(content) => Ok(content.to_string()),
+		Err(_) if lossy => {
+			tracing::warn!(
+				"{}: replaced invalid UTF-8 sequences",
+				path.display()
+			);
+			Ok(String::from_utf8_lossy(bytes).into_owned())
+		}
+		Err(e) => anyhow::bail!(
+			"{}: invalid UTF-8 at byte {} (use --lossy-utf8 to replace \
+			 invalid sequences)",
+			path.display(),
+			e.valid_up_to()
+		),
+	}
+}
+
+/// What formatting a file did, or would do in check mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+	Unchanged,
+	Changed,
+	/// Changed, and the input mixed CRLF and LF line endings
+	MixedLineEndings,
+	/// Not written: write mode, and the file is read-only or this user
+	/// may not write it
+	ReadOnly,
+	/// Changed, but not written because the user declined it in
+	/// `--interactive` mode
+	Declined,
+}
 
 /// Format a single file based on its detected type
-/// Returns true if the file was changed (or would be changed in check mode)
-pub fn format_file(file_path: &PathBuf, check: bool) -> anyhow::Result<bool> {
-	let content = fs::read_to_string(file_path)?;
-	let path_str = file_path.to_str().unwrap_or("");
-	let file_type = detect_file_type(path_str);
+pub fn format_file(
+	file_path: &PathBuf,
+	options: FormatOptions,
+) -> anyhow::Result<Outcome> {
+	format_file_counted(file_path, options, &mut 0)
+}
 
-	let formatted = format_content(&content, path_str, file_type)
-		.map_err(|e| anyhow::anyhow!("{}: {}", file_path.display(), e))?;
+/// `format_file`, setting `read` to the size of the content it read, so
+/// statistics needn't stat a file that may since have been rewritten
+pub fn format_file_counted(
+	file_path: &PathBuf,
+	options: FormatOptions,
+	read: &mut u64,
+) -> anyhow::Result<Outcome> {
+	let _current = report::CurrentFile::enter(file_path);
+	// Skip before any formatting work, since the result couldn't be written
+	if !options.check && !options.chmod_writable && is_read_only(file_path)? {
+		return Ok(Outcome::ReadOnly);
+	}
+	let stamp = FileStamp::of(file_path)?;
+	let bytes = fs::read(file_path)?;
+	*read = bytes.len() as u64;
+	let content = decode(file_path, &bytes, options.lossy_utf8)?;
+	let file_type = detect_file_type(file_path.to_str().unwrap_or(""));
+	let mixed = has_mixed_line_endings(&content);
+	for (line, directive, tool) in directives::unsupported(&content, file_type)
+	{
+		tracing::warn!(
+			"{}:{}: {} directive `{}` is not honored by {}; use \
+			 fama-ignore-start/end instead",
+			file_path.display(),
+			line,
+			tool,
+			directive,
+			backend_for(file_type).name()
+		);
+	}
+	if file_type == FileType::Markdown && CONFIG.markdown_check_links {
+		for link in dprint::links::check(&content, &file_path.to_string_lossy())
+		{
+			tracing::warn!(
+				"{}:{}: broken link `{}`: {}",
+				file_path.display(),
+				link.line,
+				link.target,
+				link.reason
+			);
+		}
+	}
+	if options.check && directives::fully_suppressed(&content, file_type) {
+		tracing::warn!(
+			"{}: `# fmt: off` before the first statement with no \
+			 `# fmt: on` leaves the whole file unformatted and unchecked",
+			file_path.display()
+		);
+	}
+	let formatted = format_source(&content, file_path, options)?;
+	if file_type == FileType::Sql && CONFIG.sql_lint {
+		// Report lines of the file as it will be left
+		let linted = if options.check { &content } else { &formatted };
+		for finding in
+			fama_sqruff::lint_sql(linted, &file_path.to_string_lossy())
+		{
+			tracing::warn!(
+				"{}:{}: {}: {}",
+				file_path.display(),
+				finding.line,
+				finding.code,
+				finding.description
+			);
+		}
+	}
 
 	if formatted != content {
-		if !check {
-			fs::write(file_path, formatted)?;
+		// A cheap guard against backends that re-serialize data and drop
+		// comments with it, as serde_yaml and XML attributes once did
+		if let Some((before, after)) =
+			comments::lost(file_type, &content, &formatted)
+		{
+			anyhow::bail!(
+				"{}: internal error: {} dropped {} of {} comments; file left \
+				 unchanged",
+				file_path.display(),
+				backend_for(file_type).name(),
+				before - after,
+				before
+			);
 		}
-		Ok(true)
+		if options.validate {
+			validate_output(file_type, &content, &formatted).map_err(|e| {
+				anyhow::anyhow!(
+					"{}: internal error: {} output differs semantically \
+					 from input ({}); file left unchanged",
+					file_path.display(),
+					backend_for(file_type).name(),
+					e
+				)
+			})?;
+		}
+		if options.check {
+			let reasons = risk::assess(file_type, &content, &formatted);
+			if !reasons.is_empty() {
+				tracing::warn!(
+					"{}: review before writing: {}",
+					file_path.display(),
+					reasons.join("; ")
+				);
+			}
+		} else {
+			if options.interactive
+				&& !interactive::confirm(file_path, &content, &formatted)?
+			{
+				return Ok(Outcome::Declined);
+			}
+			if !options.force_write
+				&& changed_since_read(file_path, &stamp, &bytes)?
+			{
+				anyhow::bail!(
+					"{}: changed on disk while formatting, skipped \
+					 (use --force-write to overwrite)",
+					file_path.display()
+				);
+			}
+			match write(file_path, &formatted, options.chmod_writable) {
+				Err(e) if is_permission_denied(&e) => {
+					return Ok(Outcome::ReadOnly);
+				}
+				result => result?,
+			}
+		}
+		Ok(if mixed {
+			Outcome::MixedLineEndings
+		} else {
+			Outcome::Changed
+		})
+	} else {
+		Ok(Outcome::Unchanged)
+	}
+}
+
+/// Format in-memory content as `format_file` would format the file at
+/// `path`, without reading or writing it
+pub fn format_source(
+	content: &str,
+	path: &Path,
+	options: FormatOptions,
+) -> anyhow::Result<String> {
+	let path_str = path.to_str().unwrap_or("");
+	let file_type = detect_file_type(path_str);
+	let line_ending = eol::resolve(path, content);
+
+	// Backends differ in which ending they keep for each line, so a mixed
+	// file is normalized first; otherwise it can flip between runs
+	let source = if has_mixed_line_endings(content) {
+		Cow::Owned(normalize_line_endings(content, line_ending))
+	} else {
+		Cow::Borrowed(content)
+	};
+	let source = if CONFIG.normalize_invisible_chars {
+		let (normalized, fixes) = invisible::normalize(&source, file_type);
+		for fix in fixes {
+			tracing::warn!("{}:{}: {}", path.display(), fix.line, fix.change);
+		}
+		Cow::Owned(normalized)
+	} else {
+		source
+	};
+
+	let protected = ignore_regions::protect(&source)
+		.map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+	let formatted = format_content(
+		&protected.source,
+		path_str,
+		file_type,
+		options.fix_indentation,
+	)
+	.map_err(|e| {
+		report::backend_error(path, &e);
+		anyhow::anyhow!("{}: {}", path.display(), e)
+	})?;
+
+	let formatted = protected
+		.restore(formatted)
+		.map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+	// Backends write LF for Auto; the file gets the ending git expects
+	Ok(if CONFIG.line_ending == LineEnding::Auto {
+		normalize_line_endings(&formatted, line_ending)
 	} else {
-		Ok(false)
+		formatted
+	})
+}
+
+/// Whether a file has both CRLF and bare LF line endings
+fn has_mixed_line_endings(content: &str) -> bool {
+	let crlf = content.matches("\r\n").count();
+	crlf > 0 && crlf < content.matches('\n').count()
+}
+
+/// Rewrite every line ending as `line_ending`
+fn normalize_line_endings(content: &str, line_ending: LineEnding) -> String {
+	let lf = content.replace("\r\n", "\n");
+	match line_ending {
+		LineEnding::Lf | LineEnding::Auto => lf,
+		LineEnding::Crlf => lf.replace('\n', "\r\n"),
 	}
 }
 
-/// Format content string based on file type
+/// Whether the backend may hand back its input verbatim instead of
+/// reformatting it (the SFC backends fall back when the HTML parser fails)
+fn lacks_full_reformat(file_type: FileType) -> bool {
+	matches!(
+		file_type,
+		FileType::Vue | FileType::Svelte | FileType::Astro
+	)
+}
+
+/// Compare input and output data models for backends with known
+/// data-loss risks; other file types always pass
+fn validate_output(
+	file_type: FileType,
+	source: &str,
+	formatted: &str,
+) -> Result<(), String> {
+	match file_type {
+		FileType::Xml => xml_fmt::validate_xml(source, formatted),
+		FileType::Yaml => dprint::validate_yaml(source, formatted),
+		FileType::Json | FileType::Jsonc => {
+			let parse = |text: &str| {
+				let value = if file_type == FileType::Jsonc {
+					jsonc::parse(text)
+				} else {
+					serde_json::from_str(text)
+				};
+				value.map_err(|e| format!("JSON parse error: {}", e))
+			};
+			if parse(source)? == parse(formatted)? {
+				Ok(())
+			} else {
+				Err("JSON values differ".to_string())
+			}
+		}
+		_ => Ok(()),
+	}
+}
+
+/// Format content string based on file type, then enforce the blank line
+/// and final newline policies so every backend ends up consistent
 fn format_content(
 	content: &str,
 	path: &str,
 	file_type: FileType,
+	fix_indentation: bool,
 ) -> Result<String, String> {
-	match file_type {
-		// Web files -> biome
-		FileType::JavaScript
-		| FileType::TypeScript
-		| FileType::Jsx
-		| FileType::Tsx
-		| FileType::Json
-		| FileType::Jsonc
-		| FileType::Html
-		| FileType::Vue
-		| FileType::Svelte
-		| FileType::Astro
-		| FileType::GraphQL => biome::format_file(content, path, file_type),
-
-		// Data + Style files -> dprint
-		FileType::Yaml
-		| FileType::Markdown
-		| FileType::Css
-		| FileType::Scss
-		| FileType::Less
-		| FileType::Sass => dprint::format_file(content, path, file_type),
-
-		// C-family languages -> clang-format
-		FileType::C
-		| FileType::Cpp
-		| FileType::CSharp
-		| FileType::ObjectiveC
-		| FileType::Java
-		| FileType::Protobuf => fama_clang::format_file(content, path, file_type),
-
-		// Individual formatters
-		FileType::Toml => toml_fmt::format_toml(content, path),
-		FileType::Rust => rustfmt::format_rust(content, path),
-		FileType::Python => ruff::format_python(content, path),
-		FileType::Lua => stylua::format_lua(content, path),
-		FileType::Ruby => ruby_fmt::format_ruby(content, path),
-		FileType::Shell => goffi::format_shell(content, path),
-		FileType::Go => goffi::format_go(content, path),
-		FileType::Zig => zigffi::format_zig(content, path),
-		FileType::Hcl => goffi::format_hcl(content, path),
-		FileType::Dockerfile => dockerfile::format_dockerfile(content, path),
-		FileType::Xml => xml_fmt::format_xml(content, path),
-		FileType::Sql => fama_sqruff::format_sql(content, path),
-		FileType::Php => php_fmt::format_php(content, path),
-		FileType::Kotlin => fama_process::format_kotlin(content, path),
-
-		FileType::Unknown => Err("Unknown file type".to_string()),
+	let backend = backend_for(file_type);
+	let aligned = align::to_backend(content, file_type);
+	let source = aligned.as_ref().map_or(content, |p| p.source.as_str());
+	let mapped = directives::to_backend(source, file_type);
+	let source = mapped.as_ref().map_or(source, |p| p.source.as_str());
+	// Closing the span logs the backend's timing at debug level
+	let span = tracing::debug_span!("format", backend = backend.name(), path);
+	let mut formatted =
+		span.in_scope(|| backend.format(source, path, file_type))?;
+	let fell_back = formatted == source;
+	if let Some(mapped) = mapped {
+		formatted = mapped.restore(&formatted)?;
+	}
+	if let Some(aligned) = aligned {
+		formatted = aligned.restore(&formatted)?;
+	}
+	if fix_indentation && fell_back && lacks_full_reformat(file_type) {
+		formatted = indent::fix_indentation(&formatted, file_type);
+	}
+	if matches!(file_type, FileType::Vue | FileType::Svelte) {
+		formatted = sfc::format_blocks(&formatted, path);
 	}
+	if let Some(max) = CONFIG.max_blank_lines {
+		if let Some(syntax) = blank_lines::verbatim_spans(file_type) {
+			formatted = blank_lines::limit_blank_lines(
+				&formatted,
+				usize::from(max),
+				syntax,
+			);
+		} else if file_type == FileType::Hcl {
+			formatted = blank_lines::limit_blank_lines_hcl(
+				&formatted,
+				usize::from(max),
+			);
+		}
+	}
+	Ok(CONFIG.apply_final_newline(formatted, file_type))
 }
 
 #[cfg(test)]
@@ -93,7 +453,7 @@ mod tests {
 		// Write already formatted JSON
 		fs::write(&file_path, "{}").unwrap();
 
-		let result = format_file(&file_path, false);
+		let result = format_file(&file_path, FormatOptions::default());
 
 		// Just check that the function runs without error
 		// The formatter may or may not modify "{}"
@@ -107,11 +467,11 @@ mod tests {
 		// Malformed JSON that needs formatting
 		fs::write(&file_path, r#"{"key":   "value"}"#).unwrap();
 
-		let result = format_file(&file_path, false);
+		let result = format_file(&file_path, FormatOptions::default());
 
 		assert!(result.is_ok());
 		// JSON should be formatted
-		assert!(result.unwrap());
+		assert_eq!(result.unwrap(), Outcome::Changed);
 	}
 
 	#[test]
@@ -121,10 +481,16 @@ mod tests {
 		fs::write(&file_path, r#"{"key":   "value"}"#).unwrap();
 		let original_content = fs::read_to_string(&file_path).unwrap();
 
-		let result = format_file(&file_path, true);
+		let result = format_file(
+			&file_path,
+			FormatOptions {
+				check: true,
+				..FormatOptions::default()
+			},
+		);
 
 		assert!(result.is_ok());
-		assert!(result.unwrap());
+		assert_eq!(result.unwrap(), Outcome::Changed);
 		// File should NOT be modified in check mode
 		let after_content = fs::read_to_string(&file_path).unwrap();
 		assert_eq!(original_content, after_content);
@@ -134,7 +500,7 @@ mod tests {
 	fn test_format_file_nonexistent() {
 		let file_path = PathBuf::from("/nonexistent/path/file.json");
 
-		let result = format_file(&file_path, false);
+		let result = format_file(&file_path, FormatOptions::default());
 
 		assert!(result.is_err());
 	}
@@ -145,7 +511,7 @@ mod tests {
 		let file_path = temp_dir.path().join("test.xyz");
 		fs::write(&file_path, "content").unwrap();
 
-		let result = format_file(&file_path, false);
+		let result = format_file(&file_path, FormatOptions::default());
 
 		assert!(result.is_err());
 		assert!(result
@@ -157,7 +523,8 @@ mod tests {
 	#[test]
 	fn test_format_content_json() {
 		let content = r#"{"key":   "value"}"#;
-		let result = format_content(content, "test.json", FileType::Json);
+		let result =
+			format_content(content, "test.json", FileType::Json, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -169,7 +536,8 @@ mod tests {
 	#[test]
 	fn test_format_content_toml() {
 		let content = "key=\"value\"";
-		let result = format_content(content, "test.toml", FileType::Toml);
+		let result =
+			format_content(content, "test.toml", FileType::Toml, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -180,7 +548,7 @@ mod tests {
 	#[test]
 	fn test_format_content_rust() {
 		let content = "fn main() {}";
-		let result = format_content(content, "test.rs", FileType::Rust);
+		let result = format_content(content, "test.rs", FileType::Rust, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -190,7 +558,8 @@ mod tests {
 	#[test]
 	fn test_format_content_python() {
 		let content = "x=1";
-		let result = format_content(content, "test.py", FileType::Python);
+		let result =
+			format_content(content, "test.py", FileType::Python, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -200,17 +569,18 @@ mod tests {
 	#[test]
 	fn test_format_content_lua() {
 		let content = "x=1";
-		let result = format_content(content, "test.lua", FileType::Lua);
+		let result = format_content(content, "test.lua", FileType::Lua, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
 		assert!(formatted.contains("x = 1"));
 	}
 
+	#[cfg(feature = "native")]
 	#[test]
 	fn test_format_content_shell() {
 		let content = "echo hello";
-		let result = format_content(content, "test.sh", FileType::Shell);
+		let result = format_content(content, "test.sh", FileType::Shell, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -218,20 +588,22 @@ mod tests {
 		assert!(formatted.contains("hello"));
 	}
 
+	#[cfg(feature = "native")]
 	#[test]
 	fn test_format_content_go() {
 		let content = "package main";
-		let result = format_content(content, "test.go", FileType::Go);
+		let result = format_content(content, "test.go", FileType::Go, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
 		assert!(formatted.contains("package main"));
 	}
 
+	#[cfg(feature = "native")]
 	#[test]
 	fn test_format_content_zig() {
 		let content = "const x = 1;";
-		let result = format_content(content, "test.zig", FileType::Zig);
+		let result = format_content(content, "test.zig", FileType::Zig, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -241,7 +613,7 @@ mod tests {
 	#[test]
 	fn test_format_content_xml() {
 		let content = "<root><item/></root>";
-		let result = format_content(content, "test.xml", FileType::Xml);
+		let result = format_content(content, "test.xml", FileType::Xml, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -249,10 +621,29 @@ mod tests {
 		assert!(formatted.contains("</root>"));
 	}
 
+	#[test]
+	fn test_format_source_keeps_ignored_region() {
+		let content = concat!(
+			"<root><a/>\n",
+			"<!-- fama-ignore-start -->\n",
+			"<b>   <c/>   </b>\n",
+			"<!-- fama-ignore-end -->\n",
+			"</root>\n",
+		);
+		let formatted = format_source(
+			content,
+			Path::new("test.xml"),
+			FormatOptions::default(),
+		)
+		.unwrap();
+		assert!(formatted.contains("\n<b>   <c/>   </b>\n"), "{formatted}");
+		assert!(!formatted.contains("fama-ignore-region"));
+	}
+
 	#[test]
 	fn test_format_content_sql() {
 		let content = "SELECT 1;";
-		let result = format_content(content, "test.sql", FileType::Sql);
+		let result = format_content(content, "test.sql", FileType::Sql, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -263,17 +654,18 @@ mod tests {
 	fn test_format_content_dockerfile() {
 		let content = "FROM alpine\nRUN echo hi";
 		let result =
-			format_content(content, "Dockerfile", FileType::Dockerfile);
+			format_content(content, "Dockerfile", FileType::Dockerfile, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
 		assert!(formatted.contains("FROM alpine"));
 	}
 
+	#[cfg(feature = "native")]
 	#[test]
 	fn test_format_content_hcl() {
 		let content = "resource \"test\" \"name\" {}";
-		let result = format_content(content, "test.hcl", FileType::Hcl);
+		let result = format_content(content, "test.hcl", FileType::Hcl, false);
 
 		assert!(result.is_ok());
 	}
@@ -281,17 +673,18 @@ mod tests {
 	#[test]
 	fn test_format_content_php() {
 		let content = "<?php echo 'hello'; ?>";
-		let result = format_content(content, "test.php", FileType::Php);
+		let result = format_content(content, "test.php", FileType::Php, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
 		assert!(formatted.contains("<?php"));
 	}
 
+	#[cfg(feature = "native")]
 	#[test]
 	fn test_format_content_ruby() {
 		let content = "x = 1";
-		let result = format_content(content, "test.rb", FileType::Ruby);
+		let result = format_content(content, "test.rb", FileType::Ruby, false);
 
 		assert!(result.is_ok());
 		let formatted = result.unwrap();
@@ -301,7 +694,8 @@ mod tests {
 	#[test]
 	fn test_format_content_yaml() {
 		let content = "key: value";
-		let result = format_content(content, "test.yaml", FileType::Yaml);
+		let result =
+			format_content(content, "test.yaml", FileType::Yaml, false);
 
 		assert!(result.is_ok());
 	}
@@ -309,7 +703,8 @@ mod tests {
 	#[test]
 	fn test_format_content_markdown() {
 		let content = "# Hello";
-		let result = format_content(content, "test.md", FileType::Markdown);
+		let result =
+			format_content(content, "test.md", FileType::Markdown, false);
 
 		assert!(result.is_ok());
 	}
@@ -317,7 +712,7 @@ mod tests {
 	#[test]
 	fn test_format_content_css() {
 		let content = "a{color:red}";
-		let result = format_content(content, "test.css", FileType::Css);
+		let result = format_content(content, "test.css", FileType::Css, false);
 
 		assert!(result.is_ok());
 	}
@@ -325,7 +720,8 @@ mod tests {
 	#[test]
 	fn test_format_content_javascript() {
 		let content = "const x=1;";
-		let result = format_content(content, "test.js", FileType::JavaScript);
+		let result =
+			format_content(content, "test.js", FileType::JavaScript, false);
 
 		assert!(result.is_ok());
 	}
@@ -333,7 +729,8 @@ mod tests {
 	#[test]
 	fn test_format_content_typescript() {
 		let content = "const x: number = 1;";
-		let result = format_content(content, "test.ts", FileType::TypeScript);
+		let result =
+			format_content(content, "test.ts", FileType::TypeScript, false);
 
 		assert!(result.is_ok());
 	}
@@ -341,7 +738,7 @@ mod tests {
 	#[test]
 	fn test_format_content_jsx() {
 		let content = "const el = <div />;";
-		let result = format_content(content, "test.jsx", FileType::Jsx);
+		let result = format_content(content, "test.jsx", FileType::Jsx, false);
 
 		assert!(result.is_ok());
 	}
@@ -349,7 +746,7 @@ mod tests {
 	#[test]
 	fn test_format_content_tsx() {
 		let content = "const el = <div />;";
-		let result = format_content(content, "test.tsx", FileType::Tsx);
+		let result = format_content(content, "test.tsx", FileType::Tsx, false);
 
 		assert!(result.is_ok());
 	}
@@ -357,7 +754,8 @@ mod tests {
 	#[test]
 	fn test_format_content_html() {
 		let content = "<html><body>Hi</body></html>";
-		let result = format_content(content, "test.html", FileType::Html);
+		let result =
+			format_content(content, "test.html", FileType::Html, false);
 
 		assert!(result.is_ok());
 	}
@@ -365,7 +763,7 @@ mod tests {
 	#[test]
 	fn test_format_content_vue() {
 		let content = "<template><div>Hi</div></template>";
-		let result = format_content(content, "test.vue", FileType::Vue);
+		let result = format_content(content, "test.vue", FileType::Vue, false);
 
 		assert!(result.is_ok());
 	}
@@ -373,7 +771,8 @@ mod tests {
 	#[test]
 	fn test_format_content_svelte() {
 		let content = "<div>Hello</div>";
-		let result = format_content(content, "test.svelte", FileType::Svelte);
+		let result =
+			format_content(content, "test.svelte", FileType::Svelte, false);
 
 		assert!(result.is_ok());
 	}
@@ -381,7 +780,8 @@ mod tests {
 	#[test]
 	fn test_format_content_astro() {
 		let content = "---\nconst x = 1;\n---\n<div></div>";
-		let result = format_content(content, "test.astro", FileType::Astro);
+		let result =
+			format_content(content, "test.astro", FileType::Astro, false);
 
 		assert!(result.is_ok());
 	}
@@ -389,7 +789,8 @@ mod tests {
 	#[test]
 	fn test_format_content_graphql() {
 		let content = "query { field }";
-		let result = format_content(content, "test.graphql", FileType::GraphQL);
+		let result =
+			format_content(content, "test.graphql", FileType::GraphQL, false);
 
 		assert!(result.is_ok());
 	}
@@ -397,7 +798,7 @@ mod tests {
 	#[test]
 	fn test_format_content_c() {
 		let content = "int main() { return 0; }";
-		let result = format_content(content, "test.c", FileType::C);
+		let result = format_content(content, "test.c", FileType::C, false);
 
 		assert!(result.is_ok());
 	}
@@ -405,7 +806,7 @@ mod tests {
 	#[test]
 	fn test_format_content_cpp() {
 		let content = "int main() { return 0; }";
-		let result = format_content(content, "test.cpp", FileType::Cpp);
+		let result = format_content(content, "test.cpp", FileType::Cpp, false);
 
 		assert!(result.is_ok());
 	}
@@ -413,7 +814,8 @@ mod tests {
 	#[test]
 	fn test_format_content_csharp() {
 		let content = "class Test {}";
-		let result = format_content(content, "test.cs", FileType::CSharp);
+		let result =
+			format_content(content, "test.cs", FileType::CSharp, false);
 
 		assert!(result.is_ok());
 	}
@@ -421,7 +823,8 @@ mod tests {
 	#[test]
 	fn test_format_content_java() {
 		let content = "public class Test {}";
-		let result = format_content(content, "test.java", FileType::Java);
+		let result =
+			format_content(content, "test.java", FileType::Java, false);
 
 		assert!(result.is_ok());
 	}
@@ -429,7 +832,8 @@ mod tests {
 	#[test]
 	fn test_format_content_objective_c() {
 		let content = "int main() { return 0; }";
-		let result = format_content(content, "test.m", FileType::ObjectiveC);
+		let result =
+			format_content(content, "test.m", FileType::ObjectiveC, false);
 
 		assert!(result.is_ok());
 	}
@@ -437,7 +841,8 @@ mod tests {
 	#[test]
 	fn test_format_content_protobuf() {
 		let content = "syntax = \"proto3\";";
-		let result = format_content(content, "test.proto", FileType::Protobuf);
+		let result =
+			format_content(content, "test.proto", FileType::Protobuf, false);
 
 		assert!(result.is_ok());
 	}
@@ -445,7 +850,8 @@ mod tests {
 	#[test]
 	fn test_format_content_unknown() {
 		let content = "anything";
-		let result = format_content(content, "test.xyz", FileType::Unknown);
+		let result =
+			format_content(content, "test.xyz", FileType::Unknown, false);
 
 		assert!(result.is_err());
 		assert_eq!(result.unwrap_err(), "Unknown file type");
@@ -454,7 +860,8 @@ mod tests {
 	#[test]
 	fn test_format_content_jsonc() {
 		let content = "{\"key\": \"value\" // comment\n}";
-		let result = format_content(content, "test.jsonc", FileType::Jsonc);
+		let result =
+			format_content(content, "test.jsonc", FileType::Jsonc, false);
 
 		assert!(result.is_ok());
 	}
@@ -462,7 +869,8 @@ mod tests {
 	#[test]
 	fn test_format_content_scss() {
 		let content = "a { color: red; }";
-		let result = format_content(content, "test.scss", FileType::Scss);
+		let result =
+			format_content(content, "test.scss", FileType::Scss, false);
 
 		assert!(result.is_ok());
 	}
@@ -470,7 +878,8 @@ mod tests {
 	#[test]
 	fn test_format_content_less() {
 		let content = "a { color: red; }";
-		let result = format_content(content, "test.less", FileType::Less);
+		let result =
+			format_content(content, "test.less", FileType::Less, false);
 
 		assert!(result.is_ok());
 	}
@@ -478,8 +887,244 @@ mod tests {
 	#[test]
 	fn test_format_content_sass() {
 		let content = "a\n  color: red";
-		let result = format_content(content, "test.sass", FileType::Sass);
+		let result =
+			format_content(content, "test.sass", FileType::Sass, false);
 
 		assert!(result.is_ok());
 	}
+
+	#[test]
+	fn test_format_file_fix_indentation() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("test.astro");
+		fs::write(
+			&file_path,
+			"---\n---\n<div>\n    {items}\n    <pre>\n    x\n</pre>\n</div>\n",
+		)
+		.unwrap();
+
+		let options = FormatOptions {
+			fix_indentation: true,
+			..FormatOptions::default()
+		};
+		assert!(format_file(&file_path, options).is_ok());
+
+		let after = fs::read_to_string(&file_path).unwrap();
+		assert!(!after.contains("    {items}"), "Got: {}", after);
+		assert!(after.contains("\n    x\n"), "Got: {}", after);
+	}
+
+	#[test]
+	fn test_decode_invalid_utf8() {
+		let path = Path::new("a.txt");
+		let bytes = b"ab\xffc";
+		let error = decode(path, bytes, false).unwrap_err().to_string();
+		assert!(error.contains("invalid UTF-8 at byte 2"), "Got: {}", error);
+		assert_eq!(decode(path, bytes, true).unwrap(), "ab\u{FFFD}c");
+		assert_eq!(decode(path, b"abc", false).unwrap(), "abc");
+	}
+
+	#[test]
+	fn test_mixed_line_endings() {
+		assert!(has_mixed_line_endings("a\r\nb\n"));
+		assert!(!has_mixed_line_endings("a\r\nb\r\n"));
+		assert!(!has_mixed_line_endings("a\nb\n"));
+		assert_eq!(
+			normalize_line_endings("a\r\nb\nc", LineEnding::Lf),
+			"a\nb\nc"
+		);
+		assert_eq!(
+			normalize_line_endings("a\r\nb\nc", LineEnding::Crlf),
+			"a\r\nb\r\nc"
+		);
+	}
+
+	#[test]
+	fn test_format_file_normalizes_mixed_line_endings() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("test.xml");
+		fs::write(&file_path, "<a>\r\n<b/>\n</a>\n").unwrap();
+
+		let check = FormatOptions {
+			check: true,
+			..FormatOptions::default()
+		};
+		let result = format_file(&file_path, check).unwrap();
+		assert_eq!(result, Outcome::MixedLineEndings);
+
+		let result = format_file(&file_path, FormatOptions::default()).unwrap();
+		assert_eq!(result, Outcome::MixedLineEndings);
+		let after = fs::read_to_string(&file_path).unwrap();
+		assert!(!has_mixed_line_endings(&after), "Got: {:?}", after);
+	}
+
+	#[test]
+	fn test_markup_backends_agree_on_final_newline() {
+		// Each backend ends its output itself, the way `format_content`
+		// would, whatever the input ends with
+		let samples = [
+			("test.md", FileType::Markdown, "# Title"),
+			("test.yaml", FileType::Yaml, "a: 1"),
+			("test.toml", FileType::Toml, "a = 1"),
+			("test.xml", FileType::Xml, "<root/>"),
+		];
+		for (path, file_type, content) in samples {
+			for ending in ["", "\n", "\n\n\n", "\r\n"] {
+				let source = format!("{content}{ending}");
+				let formatted = backend_for(file_type)
+					.format(&source, path, file_type)
+					.unwrap_or_else(|e| panic!("{path}: {e}"));
+				assert_eq!(
+					CONFIG.apply_final_newline(formatted.clone(), file_type),
+					formatted,
+					"{path} with {ending:?}"
+				);
+				assert!(
+					formatted.ends_with('\n') && !formatted.ends_with("\n\n"),
+					"{path} with {ending:?}: {formatted:?}"
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn test_every_backend_ends_with_final_newline() {
+		// Kotlin is skipped: it needs `ktfmt` on PATH
+		let samples = [
+			("test.js", FileType::JavaScript, "const a = 1;"),
+			("test.ts", FileType::TypeScript, "const a: number = 1;"),
+			("test.jsx", FileType::Jsx, "const a = <div />;"),
+			("test.tsx", FileType::Tsx, "const a = <div />;"),
+			("test.json", FileType::Json, "{}"),
+			("test.jsonc", FileType::Jsonc, "{}"),
+			("test.html", FileType::Html, "<div></div>"),
+			("test.vue", FileType::Vue, "<template></template>"),
+			("test.svelte", FileType::Svelte, "<div></div>"),
+			("test.astro", FileType::Astro, "<div></div>"),
+			("test.graphql", FileType::GraphQL, "type A { b: Int }"),
+			("test.yaml", FileType::Yaml, "a: 1"),
+			("test.md", FileType::Markdown, "# Title"),
+			("test.css", FileType::Css, "a { color: red; }"),
+			("test.scss", FileType::Scss, "a { color: red; }"),
+			("test.less", FileType::Less, "a { color: red; }"),
+			("test.sass", FileType::Sass, "a\n  color: red"),
+			("test.c", FileType::C, "int a;"),
+			("test.cpp", FileType::Cpp, "int a;"),
+			("test.cs", FileType::CSharp, "class A {}"),
+			("test.m", FileType::ObjectiveC, "int a;"),
+			("test.java", FileType::Java, "class A {}"),
+			("test.proto", FileType::Protobuf, "syntax = \"proto3\";"),
+			("test.toml", FileType::Toml, "a = 1"),
+			("test.rs", FileType::Rust, "fn main() {}"),
+			("test.py", FileType::Python, "a = 1"),
+			("BUILD", FileType::Starlark, "exports_files([\"a\"])"),
+			("test.lua", FileType::Lua, "local a = 1"),
+			("test.rb", FileType::Ruby, "a = 1"),
+			("test.sh", FileType::Shell, "echo hello"),
+			("test.go", FileType::Go, "package main"),
+			("go.mod", FileType::GoMod, "module example.com/m"),
+			("test.zig", FileType::Zig, "const a = 1;"),
+			("test.hcl", FileType::Hcl, "a = 1"),
+			("Dockerfile", FileType::Dockerfile, "FROM alpine"),
+			("test.xml", FileType::Xml, "<root/>"),
+			("test.sql", FileType::Sql, "SELECT 1"),
+			("test.php", FileType::Php, "<?php echo 1;"),
+			("build.gradle", FileType::Groovy, "plugins {}"),
+		];
+
+		for (path, file_type, content) in samples {
+			if !cfg!(feature = "native")
+				&& backend_for(file_type).name() == "unavailable"
+			{
+				continue;
+			}
+			let formatted = format_content(content, path, file_type, false)
+				.unwrap_or_else(|e| panic!("{path}: {e}"));
+			assert!(
+				formatted.ends_with('\n'),
+				"{path} should end with a newline. Got: {formatted:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn test_format_file_read_only() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("test.xml");
+		fs::write(&file_path, "<a><b/></a>").unwrap();
+		let mut permissions = fs::metadata(&file_path).unwrap().permissions();
+		permissions.set_readonly(true);
+		fs::set_permissions(&file_path, permissions).unwrap();
+
+		let outcome = format_file(&file_path, FormatOptions::default());
+		assert_eq!(outcome.unwrap(), Outcome::ReadOnly);
+		let check = FormatOptions {
+			check: true,
+			..Default::default()
+		};
+		assert_eq!(format_file(&file_path, check).unwrap(), Outcome::Changed);
+		assert_eq!(fs::read_to_string(&file_path).unwrap(), "<a><b/></a>");
+
+		let chmod = FormatOptions {
+			chmod_writable: true,
+			..Default::default()
+		};
+		assert_eq!(format_file(&file_path, chmod).unwrap(), Outcome::Changed);
+		assert_ne!(fs::read_to_string(&file_path).unwrap(), "<a><b/></a>");
+		assert!(fs::metadata(&file_path).unwrap().permissions().readonly());
+	}
+
+	#[test]
+	fn test_format_file_counts_bytes_read() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("test.xml");
+		fs::write(&file_path, "<a><b/></a>").unwrap();
+		let mut read = 0;
+		let outcome = format_file_counted(
+			&file_path,
+			FormatOptions::default(),
+			&mut read,
+		);
+		assert_eq!(outcome.unwrap(), Outcome::Changed);
+		assert_eq!(read, 11);
+		assert_ne!(fs::metadata(&file_path).unwrap().len(), 11);
+	}
+
+	#[test]
+	fn test_is_permission_denied() {
+		let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+		assert!(is_permission_denied(&denied.into()));
+		let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+		assert!(!is_permission_denied(&missing.into()));
+		assert!(!is_permission_denied(&anyhow::anyhow!("denied")));
+	}
+
+	#[test]
+	fn test_changed_since_read() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("test.json");
+		fs::write(&file_path, "{}").unwrap();
+
+		let stamp = FileStamp::of(&file_path).unwrap();
+		assert!(!changed_since_read(&file_path, &stamp, b"{}").unwrap());
+
+		fs::write(&file_path, "{\"edited\": true}").unwrap();
+		assert!(changed_since_read(&file_path, &stamp, b"{}").unwrap());
+	}
+
+	#[test]
+	fn test_validate_output() {
+		assert!(
+			validate_output(FileType::Json, "{\"a\":1}", "{ \"a\": 1 }\n")
+				.is_ok()
+		);
+		assert!(validate_output(FileType::Json, "{\"a\":1}", "{}").is_err());
+		let jsonc = "{\"a\": 1, // one\n}";
+		assert!(validate_output(FileType::Jsonc, jsonc, "{ \"a\": 1 }").is_ok());
+		assert!(validate_output(FileType::Jsonc, jsonc, "{}").is_err());
+		assert!(
+			validate_output(FileType::Xml, r#"<a x="1"/>"#, "<a/>").is_err()
+		);
+		assert!(validate_output(FileType::Rust, "fn a(){}", "").is_ok());
+	}
 }
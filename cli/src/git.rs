@@ -1,12 +1,13 @@
 // git.rs - Git integration for filtering files by git status
 
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Component, PathBuf};
 use std::process::Command;
 
 use crate::discovery;
 
 /// Get the git repository root directory
-fn get_git_root() -> anyhow::Result<PathBuf> {
+pub fn get_git_root() -> anyhow::Result<PathBuf> {
 	let output = Command::new("git")
 		.args(["rev-parse", "--show-toplevel"])
 		.output()
@@ -107,6 +108,62 @@ pub fn commit_files(message: &str) -> anyhow::Result<()> {
 	Ok(())
 }
 
+/// Hash of the current HEAD commit
+pub fn head_commit() -> anyhow::Result<String> {
+	let output = Command::new("git")
+		.args(["rev-parse", "HEAD"])
+		.output()
+		.map_err(|e| anyhow::anyhow!("Failed to run git rev-parse: {}", e))?;
+
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(anyhow::anyhow!("git rev-parse failed: {}", stderr));
+	}
+
+	Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Files under the current directory that git tracks, relative to it
+pub fn tracked_files() -> anyhow::Result<HashSet<PathBuf>> {
+	let output = Command::new("git")
+		.args(["ls-files", "-z"])
+		.output()
+		.map_err(|e| anyhow::anyhow!("Failed to run git ls-files: {}", e))?;
+
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(anyhow::anyhow!("git ls-files failed: {}", stderr));
+	}
+
+	Ok(String::from_utf8_lossy(&output.stdout)
+		.split('\0')
+		.filter(|path| !path.is_empty())
+		.map(PathBuf::from)
+		.collect())
+}
+
+/// `path` without `.` components, as `tracked_files` lists it
+pub fn normalize(path: &std::path::Path) -> PathBuf {
+	path.components()
+		.filter(|c| !matches!(c, Component::CurDir))
+		.collect()
+}
+
+/// Whether the working tree and index have no changes to tracked files
+pub fn is_clean() -> anyhow::Result<bool> {
+	let output = Command::new("git")
+		.args(["status", "--porcelain", "--untracked-files=no"])
+		.output()
+		.map_err(|e| anyhow::anyhow!("Failed to run git status: {}", e))?;
+
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(anyhow::anyhow!("git status failed: {}", stderr));
+	}
+
+	Ok(output.stdout.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
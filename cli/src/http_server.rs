@@ -0,0 +1,514 @@
+// http_server.rs - Minimal HTTP endpoint for formatting over the network
+//
+// POST /format  {"path": "...", "content": "...", "options": {...}}
+//   200         {"formatted": "..."}, plus "edits" when options.edits is set
+//               and "cursor" when options.cursor (a byte offset) is, and
+//               "lints" for SQL when `sql_lint` is on
+//   422         {"diagnostics": [{"message": "...", "line": 1, "column": 1}]}
+// GET /metrics  Prometheus counters
+//
+// Only what this one endpoint needs is implemented: one request per
+// connection, Content-Length bodies, and JSON in and out.
+
+use fama_common::{detect_file_type, FileType, CONFIG};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::editor_server;
+use crate::edits;
+use crate::formatter::FormatOptions;
+use crate::metrics::Metrics;
+
+/// Largest request body accepted, to bound memory per connection
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Longest request or header line accepted, and most headers per request
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// How long a connection may stall on a read or write before it's dropped,
+/// so slow clients can't hold a slot forever
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connections kept open beyond `max_concurrent`, so `/metrics` still
+/// answers and extra format requests get a 503 while the server is busy
+const SPARE_CONNECTIONS: usize = 16;
+
+/// Serve `POST /format` on `addr`, answering 503 once `max_concurrent`
+/// requests are being formatted
+pub fn run(addr: &str, max_concurrent: usize) -> anyhow::Result<()> {
+	let listener = TcpListener::bind(addr)
+		.map_err(|e| anyhow::anyhow!("Failed to bind {}: {}", addr, e))?;
+	eprintln!("Listening on http://{}", listener.local_addr()?);
+
+	let connections = Arc::new(AtomicUsize::new(0));
+	let server = Arc::new(Server {
+		active: Arc::new(AtomicUsize::new(0)),
+		max_concurrent,
+		metrics: Metrics::default(),
+	});
+	let max_connections = max_concurrent + SPARE_CONNECTIONS;
+	for stream in listener.incoming() {
+		let Ok(stream) = stream else { continue };
+		let Some(connection) = Slot::acquire(&connections, max_connections)
+		else {
+			server.metrics.record_rejected();
+			let _ = respond(&stream, &busy());
+			continue;
+		};
+		let server = Arc::clone(&server);
+		std::thread::spawn(move || {
+			let _connection = connection;
+			let _ = server.handle(&stream);
+		});
+	}
+	Ok(())
+}
+
+/// State shared by every connection
+struct Server {
+	/// Requests being formatted
+	active: Arc<AtomicUsize>,
+	max_concurrent: usize,
+	metrics: Metrics,
+}
+
+impl Server {
+	/// Read one request from `stream` and write its response
+	fn handle(&self, stream: &TcpStream) -> io::Result<()> {
+		stream.set_read_timeout(Some(TIMEOUT))?;
+		stream.set_write_timeout(Some(TIMEOUT))?;
+		let response = match read_request(BufReader::new(stream)) {
+			Ok((method, target, body)) => self.respond(&method, &target, &body),
+			Err(RequestError::Io(e)) => return Err(e),
+			Err(RequestError::Status(status, message)) => {
+				Response::json(status, diagnostics(&message))
+			}
+		};
+		respond(stream, &response)
+	}
+
+	/// Route a parsed request. Only formatting takes one of the
+	/// `max_concurrent` slots, so `/metrics` answers even when the server
+	/// is busy; a panicking backend becomes a 500 and is counted.
+	fn respond(&self, method: &str, target: &str, body: &[u8]) -> Response {
+		let metrics = &self.metrics;
+		if target == "/metrics" {
+			return route(method, target, body, metrics);
+		}
+		let Some(_slot) = Slot::acquire(&self.active, self.max_concurrent)
+		else {
+			metrics.record_rejected();
+			return busy();
+		};
+		panic::catch_unwind(AssertUnwindSafe(|| {
+			route(method, target, body, metrics)
+		}))
+		.unwrap_or_else(|_| {
+			metrics.record_panic();
+			Response::json(500, diagnostics("formatter panicked"))
+		})
+	}
+}
+
+/// One of a limited number of slots counted in `in_use`, given back when
+/// dropped so a handler that panics still releases it
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+	fn acquire(in_use: &Arc<AtomicUsize>, limit: usize) -> Option<Self> {
+		if in_use.fetch_add(1, Ordering::SeqCst) >= limit {
+			in_use.fetch_sub(1, Ordering::SeqCst);
+			return None;
+		}
+		Some(Slot(Arc::clone(in_use)))
+	}
+}
+
+impl Drop for Slot {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+struct Response {
+	status: u16,
+	content_type: &'static str,
+	body: String,
+}
+
+impl Response {
+	fn json(status: u16, body: Value) -> Self {
+		Response {
+			status,
+			content_type: "application/json",
+			body: body.to_string(),
+		}
+	}
+}
+
+enum RequestError {
+	Io(io::Error),
+	Status(u16, String),
+}
+
+impl From<io::Error> for RequestError {
+	fn from(e: io::Error) -> Self {
+		RequestError::Io(e)
+	}
+}
+
+/// Parse the request line, headers and body
+fn read_request(
+	mut reader: impl BufRead,
+) -> Result<(String, String, Vec<u8>), RequestError> {
+	let bad_request =
+		|message: &str| RequestError::Status(400, message.to_string());
+
+	let mut line = String::new();
+	read_line(&mut reader, &mut line)?;
+	let mut parts = line.split_whitespace();
+	let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+		return Err(bad_request("malformed request line"));
+	};
+	let (method, target) = (method.to_string(), target.to_string());
+
+	let mut content_length = 0;
+	for headers in 0.. {
+		if read_line(&mut reader, &mut line)? == 0 {
+			return Err(bad_request("unexpected end of headers"));
+		}
+		let header = line.trim_end();
+		if header.is_empty() {
+			break;
+		}
+		if headers == MAX_HEADERS {
+			let message = format!("more than {} headers", MAX_HEADERS);
+			return Err(RequestError::Status(431, message));
+		}
+		if let Some((name, value)) = header.split_once(':') {
+			if name.trim().eq_ignore_ascii_case("content-length") {
+				content_length = value
+					.trim()
+					.parse()
+					.map_err(|_| bad_request("invalid Content-Length"))?;
+			}
+		}
+	}
+	if content_length > MAX_BODY {
+		return Err(RequestError::Status(
+			413,
+			format!("body larger than {} bytes", MAX_BODY),
+		));
+	}
+
+	let mut body = vec![0; content_length];
+	reader.read_exact(&mut body)?;
+	Ok((method, target, body))
+}
+
+/// Replace `line` with the next line of `reader`, refusing lines longer
+/// than `MAX_LINE` instead of buffering them whole
+fn read_line(
+	reader: impl BufRead,
+	line: &mut String,
+) -> Result<usize, RequestError> {
+	line.clear();
+	let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+	if read > MAX_LINE {
+		let message = format!("line longer than {} bytes", MAX_LINE);
+		return Err(RequestError::Status(431, message));
+	}
+	Ok(read)
+}
+
+/// Dispatch a parsed request to its handler
+fn route(
+	method: &str,
+	target: &str,
+	body: &[u8],
+	metrics: &Metrics,
+) -> Response {
+	match (method, target) {
+		("POST", "/format") => format(body, metrics),
+		("GET", "/metrics") => Response {
+			status: 200,
+			content_type: "text/plain; version=0.0.4",
+			body: metrics.render(),
+		},
+		(_, "/format") => Response::json(405, diagnostics("use POST")),
+		(_, "/metrics") => Response::json(405, diagnostics("use GET")),
+		_ => Response::json(404, diagnostics("not found")),
+	}
+}
+
+/// Handle `POST /format`
+fn format(body: &[u8], metrics: &Metrics) -> Response {
+	let request: Value = match serde_json::from_slice(body) {
+		Ok(value) => value,
+		Err(e) => {
+			let message = format!("invalid JSON: {}", e);
+			return Response::json(400, diagnostics(&message));
+		}
+	};
+	let (Some(path), Some(content)) =
+		(request["path"].as_str(), request["content"].as_str())
+	else {
+		let message = "'path' and 'content' must be strings";
+		return Response::json(400, diagnostics(message));
+	};
+	let options = FormatOptions {
+		fix_indentation: request["options"]["fix_indentation"]
+			.as_bool()
+			.unwrap_or(false),
+		..Default::default()
+	};
+
+	let started = Instant::now();
+	let result = editor_server::format_request(
+		content.as_bytes(),
+		Path::new(path),
+		options,
+	);
+	let file_type = detect_file_type(path);
+	metrics.record(file_type, result.is_ok(), started.elapsed());
+	let formatted = match result {
+		Ok(formatted) => formatted,
+		Err(message) => return Response::json(422, diagnostics(&message)),
+	};
+	let mut response = json!({ "formatted": formatted });
+	if request["options"]["edits"] == true {
+		response["edits"] = edits::to_json(&edits::edits(content, &formatted));
+	}
+	if let Some(cursor) = request["options"]["cursor"].as_u64() {
+		let cursor = usize::try_from(cursor).unwrap_or(usize::MAX);
+		response["cursor"] =
+			json!(edits::map_offset(content, &formatted, cursor));
+	}
+	if file_type == FileType::Sql && CONFIG.sql_lint {
+		response["lints"] = lints(&formatted, path);
+	}
+	Response::json(200, response)
+}
+
+/// Lint findings formatting left in SQL `formatted`, each with its rule
+/// code and line
+fn lints(formatted: &str, path: &str) -> Value {
+	fama_sqruff::lint_sql(formatted, path)
+		.into_iter()
+		.map(|finding| {
+			json!({
+				"rule": finding.code,
+				"line": finding.line,
+				"message": finding.description,
+			})
+		})
+		.collect()
+}
+
+fn busy() -> Response {
+	Response::json(503, diagnostics("server busy"))
+}
+
+/// Diagnostics body for `message`, with `line` and `column` when the
+/// backend reported a position as `line:column: ` (as zigffi does)
+fn diagnostics(message: &str) -> Value {
+	let mut diagnostic = json!({ "message": message });
+	if let Some((line, column)) = location(message) {
+		diagnostic["line"] = json!(line);
+		diagnostic["column"] = json!(column);
+	}
+	json!({ "diagnostics": [diagnostic] })
+}
+
+/// The first `line:column` segment of a `path: line:column: message` error
+fn location(message: &str) -> Option<(usize, usize)> {
+	message.split(": ").find_map(|part| {
+		let (line, column) = part.split_once(':')?;
+		Some((line.parse().ok()?, column.parse().ok()?))
+	})
+}
+
+fn respond(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+	write!(
+		stream,
+		"HTTP/1.1 {} {}\r\nContent-Type: {}\r\n\
+		 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+		response.status,
+		reason(response.status),
+		response.content_type,
+		response.body.len(),
+		response.body
+	)?;
+	stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+	match status {
+		200 => "OK",
+		400 => "Bad Request",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		413 => "Payload Too Large",
+		431 => "Request Header Fields Too Large",
+		500 => "Internal Server Error",
+		422 => "Unprocessable Entity",
+		503 => "Service Unavailable",
+		_ => "",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_read_request() {
+		let raw = "POST /format HTTP/1.1\r\nHost: x\r\n\
+		           content-length: 2\r\n\r\n{}";
+		let Ok((method, target, body)) = read_request(raw.as_bytes()) else {
+			panic!("request should parse");
+		};
+		assert_eq!((method.as_str(), target.as_str()), ("POST", "/format"));
+		assert_eq!(body, b"{}");
+
+		let raw = "POST /format HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n";
+		assert!(matches!(
+			read_request(raw.as_bytes()),
+			Err(RequestError::Status(413, _))
+		));
+
+		let raw =
+			format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE));
+		assert!(matches!(
+			read_request(raw.as_bytes()),
+			Err(RequestError::Status(431, _))
+		));
+		let raw = format!(
+			"GET / HTTP/1.1\r\n{}\r\n",
+			"X: a\r\n".repeat(MAX_HEADERS + 1)
+		);
+		assert!(matches!(
+			read_request(raw.as_bytes()),
+			Err(RequestError::Status(431, _))
+		));
+		let raw =
+			format!("GET / HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(MAX_HEADERS));
+		assert!(read_request(raw.as_bytes()).is_ok());
+	}
+
+	#[test]
+	fn test_slot_released_on_panic() {
+		let active = Arc::new(AtomicUsize::new(0));
+		let slot = Slot::acquire(&active, 1).unwrap();
+		assert!(Slot::acquire(&active, 1).is_none());
+		let panicked = std::thread::spawn(move || {
+			let _slot = slot;
+			panic!("handler failed");
+		})
+		.join();
+		assert!(panicked.is_err());
+		assert_eq!(active.load(Ordering::SeqCst), 0);
+		assert!(Slot::acquire(&active, 1).is_some());
+	}
+
+	fn post_format(
+		metrics: &Metrics,
+		path: &str,
+		content: &str,
+	) -> (u16, Value) {
+		let body = json!({ "path": path, "content": content }).to_string();
+		let response = route("POST", "/format", body.as_bytes(), metrics);
+		(
+			response.status,
+			serde_json::from_str(&response.body).unwrap(),
+		)
+	}
+
+	#[test]
+	fn test_format_endpoint() {
+		let metrics = Metrics::default();
+		let (status, response) = post_format(&metrics, "a.xml", "<a/>");
+		assert_eq!(status, 200);
+		assert!(response["formatted"].is_string());
+
+		let (status, response) = post_format(&metrics, "a.xyz", "x");
+		assert_eq!(status, 422);
+		assert_eq!(
+			response["diagnostics"][0]["message"],
+			"a.xyz: Unknown file type"
+		);
+	}
+
+	#[test]
+	fn test_format_endpoint_edits_and_cursor() {
+		let body = json!({
+			"path": "a.xml",
+			"content": "<a>\n<b/></a>",
+			"options": { "edits": true, "cursor": 5 },
+		});
+		let response = route(
+			"POST",
+			"/format",
+			body.to_string().as_bytes(),
+			&Metrics::default(),
+		);
+		let response: Value = serde_json::from_str(&response.body).unwrap();
+		let formatted = response["formatted"].as_str().unwrap();
+		let cursor = response["cursor"].as_u64().unwrap() as usize;
+		assert_eq!(&formatted[cursor..cursor + 2], "b/");
+		assert!(response["edits"].is_array());
+	}
+
+	#[test]
+	fn test_diagnostics_location() {
+		let body = diagnostics("a.zig: 2:11: expected expression, found ';'");
+		assert_eq!(body["diagnostics"][0]["line"], 2);
+		assert_eq!(body["diagnostics"][0]["column"], 11);
+		let body = diagnostics("a.xyz: Unknown file type");
+		assert!(body["diagnostics"][0].get("line").is_none());
+	}
+
+	#[test]
+	fn test_metrics_endpoint() {
+		let metrics = Metrics::default();
+		post_format(&metrics, "a.xml", "<a/>");
+		let response = route("GET", "/metrics", b"", &metrics);
+		assert_eq!(response.status, 200);
+		assert!(response
+			.body
+			.contains("fama_requests_total{language=\"Xml\"} 1"));
+	}
+
+	#[test]
+	fn test_metrics_answer_when_busy() {
+		let server = Server {
+			active: Arc::new(AtomicUsize::new(0)),
+			max_concurrent: 0,
+			metrics: Metrics::default(),
+		};
+		assert_eq!(server.respond("POST", "/format", b"{}").status, 503);
+		let response = server.respond("GET", "/metrics", b"");
+		assert_eq!(response.status, 200);
+		assert!(response.body.contains("fama_rejected_total 1"));
+	}
+
+	#[test]
+	fn test_bad_requests() {
+		let metrics = Metrics::default();
+		let status = |method, target, body: &[u8]| {
+			route(method, target, body, &metrics).status
+		};
+		assert_eq!(status("POST", "/format", b"not json"), 400);
+		assert_eq!(status("POST", "/format", b"{\"path\": 1}"), 400);
+		assert_eq!(status("GET", "/format", b""), 405);
+		assert_eq!(status("POST", "/metrics", b""), 405);
+		assert_eq!(status("POST", "/other", b""), 404);
+	}
+}
@@ -0,0 +1,274 @@
+// ignore_regions.rs - Verbatim fama-ignore-start/end regions
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+const START_MARKER: &str = "fama-ignore-start";
+const END_MARKER: &str = "fama-ignore-end";
+
+/// Line comment and block comment openers across supported languages
+pub const COMMENT_OPENERS: &[&str] = &["//", "/*", "#", "--", "<!--", ";", "%"];
+
+/// Comment text standing in for the `i`th region while the backend runs
+const PLACEHOLDER: &str = "fama-ignore-region-";
+
+/// The text after `marker` if a line is a comment whose text starts with it
+fn after_marker<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+	let line = line.trim_start();
+	COMMENT_OPENERS.iter().find_map(|opener| {
+		line.strip_prefix(opener)?.trim_start().strip_prefix(marker)
+	})
+}
+
+/// Whether a line is a comment whose text starts with `marker`
+fn is_marker_line(line: &str, marker: &str) -> bool {
+	after_marker(line, marker).is_some()
+}
+
+/// A region's byte range, between its marker lines, and the start marker
+/// line itself
+struct Region<'a> {
+	range: Range<usize>,
+	start_line: &'a str,
+}
+
+/// Every region between a start and end marker line.
+///
+/// A marker must open its line's comment, in any comment syntax
+/// (`// fama-ignore-start`, `# fama-ignore-start`, `<!-- ... -->`), so
+/// marker text inside strings is not mistaken for a marker.
+fn regions(content: &str) -> Result<Vec<Region<'_>>, String> {
+	let mut regions = Vec::new();
+	let mut open = None;
+	let mut offset = 0;
+
+	for line in content.split_inclusive('\n') {
+		let line_start = offset;
+		offset += line.len();
+		if is_marker_line(line, START_MARKER) {
+			if open.is_some() {
+				return Err(format!("nested {START_MARKER}"));
+			}
+			open = Some((offset, line));
+		} else if is_marker_line(line, END_MARKER) {
+			let (start, start_line) = open.take().ok_or_else(|| {
+				format!("{END_MARKER} without {START_MARKER}")
+			})?;
+			regions.push(Region {
+				range: start..line_start,
+				start_line,
+			});
+		}
+	}
+
+	match open {
+		Some(_) => Err(format!("{START_MARKER} without {END_MARKER}")),
+		None => Ok(regions),
+	}
+}
+
+/// Source with each ignored region cut out, and the regions to put back
+pub struct Protected<'a> {
+	pub source: Cow<'a, str>,
+	regions: Vec<&'a str>,
+}
+
+/// Cut the text of every ignored region out of `content` before it's
+/// formatted, leaving one placeholder comment per region in the start
+/// marker's comment syntax, so the backend never sees the protected lines
+pub fn protect(content: &str) -> Result<Protected<'_>, String> {
+	let found = regions(content)?;
+	if found.is_empty() {
+		return Ok(Protected {
+			source: Cow::Borrowed(content),
+			regions: Vec::new(),
+		});
+	}
+
+	let mut source = String::with_capacity(content.len());
+	let mut last = 0;
+	for (i, region) in found.iter().enumerate() {
+		source.push_str(&content[last..region.range.start]);
+		let placeholder = format!("{PLACEHOLDER}{i}");
+		source.push_str(&region.start_line.replacen(
+			START_MARKER,
+			&placeholder,
+			1,
+		));
+		last = region.range.end;
+	}
+	source.push_str(&content[last..]);
+	Ok(Protected {
+		source: Cow::Owned(source),
+		regions: found.iter().map(|r| &content[r.range.clone()]).collect(),
+	})
+}
+
+impl Protected<'_> {
+	/// Replace each placeholder line in `formatted` with its region's
+	/// original text, wherever the backend moved it. Fails if the backend
+	/// dropped, duplicated or merged a placeholder, so the file is left
+	/// unchanged.
+	pub fn restore(&self, formatted: String) -> Result<String, String> {
+		if self.regions.is_empty() {
+			return Ok(formatted);
+		}
+		let mismatch = || {
+			format!("formatter did not keep the {START_MARKER} placeholders")
+		};
+
+		let mut restored = vec![false; self.regions.len()];
+		let mut result = String::with_capacity(formatted.len());
+		for line in formatted.split_inclusive('\n') {
+			let Some(rest) = after_marker(line, PLACEHOLDER) else {
+				result.push_str(line);
+				continue;
+			};
+			let digits = rest.len()
+				- rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+			let i = rest[..digits]
+				.parse::<usize>()
+				.ok()
+				.filter(|&i| i < self.regions.len() && !restored[i])
+				.ok_or_else(mismatch)?;
+			restored[i] = true;
+			result.push_str(self.regions[i]);
+		}
+		if restored.contains(&false) {
+			return Err(mismatch());
+		}
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn protect_and_format(
+		original: &str,
+		format: impl Fn(&str) -> String,
+	) -> Result<String, String> {
+		let protected = protect(original)?;
+		protected.restore(format(&protected.source))
+	}
+
+	#[test]
+	fn test_restore_regions() {
+		let original = concat!(
+			"a  =  1\n",
+			"// fama-ignore-start\n",
+			"x   =   [ 1,  2,\n",
+			"          3 ]\n",
+			"// fama-ignore-end\n",
+			"b  =  2\n",
+		);
+		let protected = protect(original).unwrap();
+		assert_eq!(
+			protected.source,
+			concat!(
+				"a  =  1\n",
+				"// fama-ignore-start\n",
+				"// fama-ignore-region-0\n",
+				"// fama-ignore-end\n",
+				"b  =  2\n",
+			)
+		);
+		let formatted = protected.source.replace("  =  ", " = ");
+		assert_eq!(
+			protected.restore(formatted).unwrap(),
+			concat!(
+				"a = 1\n",
+				"// fama-ignore-start\n",
+				"x   =   [ 1,  2,\n",
+				"          3 ]\n",
+				"// fama-ignore-end\n",
+				"b = 2\n",
+			)
+		);
+	}
+
+	#[test]
+	fn test_restore_by_placeholder_not_position() {
+		let original = concat!(
+			"<!-- fama-ignore-start -->\n",
+			"one\n",
+			"<!-- fama-ignore-end -->\n",
+			"<!-- fama-ignore-start -->\n",
+			"two\n",
+			"<!-- fama-ignore-end -->\n",
+		);
+		// A backend that reorders blocks, and one that adds lines between a
+		// marker and its placeholder
+		let swapped = protect_and_format(original, |source| {
+			let (first, second) = source.split_at(source.len() / 2);
+			format!("{second}{first}")
+		});
+		assert_eq!(
+			swapped.unwrap(),
+			concat!(
+				"<!-- fama-ignore-start -->\n",
+				"two\n",
+				"<!-- fama-ignore-end -->\n",
+				"<!-- fama-ignore-start -->\n",
+				"one\n",
+				"<!-- fama-ignore-end -->\n",
+			)
+		);
+		let padded = protect_and_format(original, |source| {
+			source.replace("start -->\n", "start -->\n\n")
+		});
+		assert_eq!(
+			padded.unwrap(),
+			original.replace("start -->\n", "start -->\n\n")
+		);
+	}
+
+	#[test]
+	fn test_restore_reindented_placeholder() {
+		let original =
+			"{\n# fama-ignore-start\n  a:    1\n# fama-ignore-end\n}\n";
+		let formatted = protect_and_format(original, |source| {
+			source.replace("\n#", "\n\t#")
+		});
+		assert_eq!(
+			formatted.unwrap(),
+			"{\n\t# fama-ignore-start\n  a:    1\n\t# fama-ignore-end\n}\n"
+		);
+	}
+
+	#[test]
+	fn test_no_regions_returns_formatted() {
+		let protected = protect("a  =  1\n").unwrap();
+		assert!(matches!(protected.source, Cow::Borrowed(_)));
+		assert_eq!(protected.restore("a = 1\n".into()).unwrap(), "a = 1\n");
+	}
+
+	#[test]
+	fn test_unbalanced_markers() {
+		assert!(protect("// fama-ignore-start\nx\n").is_err());
+		assert!(protect("x\n// fama-ignore-end\n").is_err());
+	}
+
+	#[test]
+	fn test_marker_text_outside_comments_is_ignored() {
+		let original = "const s = \"fama-ignore-start\";\n";
+		assert_eq!(protect(original).unwrap().source, original);
+		assert!(is_marker_line("  <!-- fama-ignore-end -->", END_MARKER));
+		assert!(is_marker_line("#fama-ignore-start", START_MARKER));
+		assert!(!is_marker_line("x = 1 # fama-ignore-start", START_MARKER));
+	}
+
+	#[test]
+	fn test_dropped_or_duplicated_placeholders() {
+		let original = "// fama-ignore-start\nx\n// fama-ignore-end\n";
+		assert!(protect_and_format(original, |_| "x\n".into()).is_err());
+		assert!(protect_and_format(original, |source| {
+			source.replace("// fama-ignore-region-0\n", "")
+		})
+		.is_err());
+		assert!(
+			protect_and_format(original, |source| source.repeat(2)).is_err()
+		);
+	}
+}
@@ -0,0 +1,236 @@
+// incremental.rs - `--incremental`: skip files untouched since the last run
+//
+// Outside git there is no cheap way to tell which files changed, so an
+// incremental run keeps its own record: the size, modification time and
+// content hash of every file the previous runs left formatted. A file
+// whose size and time still match is skipped; one whose time changed is
+// hashed, so a touch alone doesn't bring it back. The record lives in the
+// cache dir (see `dirs.rs`), and is discarded whenever fama, its backends
+// or its config change.
+
+use fama_common::CONFIG;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::fnv::fnv1a64;
+use crate::{dirs, lock, version};
+
+/// What a file looked like when a run last left it formatted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+	len: u64,
+	/// Modification time, in nanoseconds since the Unix epoch
+	modified: u64,
+	hash: u64,
+}
+
+impl Entry {
+	fn of(path: &Path) -> Option<Entry> {
+		let metadata = fs::metadata(path).ok()?;
+		Some(Entry {
+			len: metadata.len(),
+			modified: modified(&metadata)?,
+			hash: fnv1a64(&fs::read(path).ok()?),
+		})
+	}
+}
+
+fn modified(metadata: &fs::Metadata) -> Option<u64> {
+	let since_epoch = metadata.modified().ok()?.duration_since(UNIX_EPOCH);
+	u64::try_from(since_epoch.ok()?.as_nanos()).ok()
+}
+
+/// The build and config a record is valid for
+fn key() -> String {
+	let config = format!("{:?}", CONFIG);
+	format!(
+		"{} {:016x}",
+		version::full_version(),
+		fnv1a64(config.as_bytes())
+	)
+}
+
+/// Files known to be formatted, by canonical path
+#[derive(Debug, Default)]
+pub struct State {
+	files: BTreeMap<String, Entry>,
+}
+
+fn state_key(path: &Path) -> Option<String> {
+	Some(path.canonicalize().ok()?.to_string_lossy().into_owned())
+}
+
+impl State {
+	/// Whether `path` may have changed since a run left it formatted
+	pub fn is_stale(&self, path: &Path) -> bool {
+		let Some(entry) = state_key(path).and_then(|key| self.files.get(&key))
+		else {
+			return true;
+		};
+		let Ok(metadata) = fs::metadata(path) else {
+			return true;
+		};
+		if metadata.len() != entry.len {
+			return true;
+		}
+		modified(&metadata) != Some(entry.modified)
+			&& fs::read(path)
+				.map_or(true, |bytes| fnv1a64(&bytes) != entry.hash)
+	}
+
+	/// Forget every file this run `processed`, then record the ones it
+	/// left formatted
+	pub fn update(&mut self, processed: &[PathBuf], formatted: &[PathBuf]) {
+		for path in processed {
+			if let Some(key) = state_key(path) {
+				self.files.remove(&key);
+			}
+		}
+		for path in formatted {
+			if let (Some(key), Some(entry)) = (state_key(path), Entry::of(path))
+			{
+				self.files.insert(key, entry);
+			}
+		}
+		self.files.retain(|path, _| Path::new(path).exists());
+	}
+
+	fn to_json(&self) -> serde_json::Value {
+		let files: serde_json::Map<String, serde_json::Value> = self
+			.files
+			.iter()
+			.map(|(path, entry)| {
+				let entry = json!({
+					"len": entry.len,
+					"modified": entry.modified,
+					"hash": format!("{:016x}", entry.hash),
+				});
+				(path.clone(), entry)
+			})
+			.collect();
+		json!({ "version": 1, "key": key(), "files": files })
+	}
+
+	/// The record in `value`, or an empty one if it was made by another
+	/// build or config
+	fn from_json(value: &serde_json::Value) -> State {
+		if value["key"].as_str() != Some(key().as_str()) {
+			return State::default();
+		}
+		let Some(files) = value["files"].as_object() else {
+			return State::default();
+		};
+		let files = files
+			.iter()
+			.filter_map(|(path, entry)| {
+				let entry = Entry {
+					len: entry["len"].as_u64()?,
+					modified: entry["modified"].as_u64()?,
+					hash: u64::from_str_radix(entry["hash"].as_str()?, 16)
+						.ok()?,
+				};
+				Some((path.clone(), entry))
+			})
+			.collect();
+		State { files }
+	}
+}
+
+fn state_path(root: &Path) -> std::io::Result<PathBuf> {
+	let name = lock::project_file_name(root, "state.json");
+	Ok(dirs::cache_dir()?.join(name))
+}
+
+/// The record for the project at `root`; a missing or unreadable one is
+/// empty, so the run formats everything
+pub fn load(root: &Path) -> State {
+	state_path(root)
+		.and_then(fs::read_to_string)
+		.ok()
+		.and_then(|text| serde_json::from_str(&text).ok())
+		.map_or_else(State::default, |value| State::from_json(&value))
+}
+
+/// Remove the records of every project, returning how many there were
+pub fn clean() -> std::io::Result<usize> {
+	let mut removed = 0;
+	for entry in fs::read_dir(dirs::cache_dir()?)? {
+		let path = entry?.path();
+		// The cache dir may be the temp root, which also holds run
+		// directories and crash reports, so only touch records
+		let name = path.file_name().unwrap_or_default().to_string_lossy();
+		if name.starts_with("fama-") && name.ends_with(".state.json") {
+			fs::remove_file(&path)?;
+			removed += 1;
+		}
+	}
+	Ok(removed)
+}
+
+/// Write the record for the project at `root`. It goes to a temp file
+/// that replaces the record in one rename, so a run that is killed or
+/// runs out of disk never leaves half a record for the next one to load
+pub fn save(root: &Path, state: &State) -> anyhow::Result<()> {
+	let path = state_path(root)
+		.map_err(|e| anyhow::anyhow!("Failed to find the cache dir: {}", e))?;
+	let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+	let written = fs::write(&temp, state.to_json().to_string())
+		.and_then(|()| fs::rename(&temp, &path));
+	written.map_err(|e| {
+		let _ = fs::remove_file(&temp);
+		anyhow::anyhow!("Failed to write {}: {}", path.display(), e)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_state_tracks_changes() {
+		let temp_dir = TempDir::new().unwrap();
+		let a = temp_dir.path().join("a.ts");
+		let b = temp_dir.path().join("b.ts");
+		fs::write(&a, "let a = 1;\n").unwrap();
+		fs::write(&b, "let b = 1\n").unwrap();
+
+		// b still needs formatting, so only a is recorded
+		let mut state = State::default();
+		state.update(&[a.clone(), b.clone()], std::slice::from_ref(&a));
+		assert!(!state.is_stale(&a));
+		assert!(state.is_stale(&b));
+
+		// Rewriting the same content only changes the time
+		fs::write(&a, "let a = 1;\n").unwrap();
+		assert!(!state.is_stale(&a));
+		fs::write(&a, "let a = 22;\n").unwrap();
+		assert!(state.is_stale(&a));
+
+		fs::write(&a, "let a = 1;\n").unwrap();
+		let state = State::from_json(&state.to_json());
+		assert!(!state.is_stale(&a));
+		let mut other_build = state.to_json();
+		other_build["key"] = json!("0.0.0");
+		assert!(State::from_json(&other_build).is_stale(&a));
+	}
+
+	#[test]
+	fn test_update_forgets_failed_and_deleted_files() {
+		let temp_dir = TempDir::new().unwrap();
+		let a = temp_dir.path().join("a.ts");
+		let b = temp_dir.path().join("b.ts");
+		fs::write(&a, "").unwrap();
+		fs::write(&b, "").unwrap();
+
+		let mut state = State::default();
+		state.update(&[], &[a.clone(), b.clone()]);
+		fs::remove_file(&b).unwrap();
+		// a failed this run
+		state.update(std::slice::from_ref(&a), &[]);
+		assert!(state.files.is_empty());
+	}
+}
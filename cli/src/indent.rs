@@ -0,0 +1,118 @@
+// indent.rs - Leading indentation conversion for --fix-indentation
+
+use crate::blank_lines::{self, SpanScanner, Syntax};
+use fama_common::{FileType, IndentStyle, CONFIG};
+
+/// Rewrite the leading whitespace of every line to the configured indent style.
+///
+/// Tabs advance to the next tab stop (`CONFIG.indent_width` columns), so
+/// mixed indentation keeps its visual width after conversion. Lines inside
+/// verbatim spans (`<pre>`, template literals) are content and kept as is.
+pub fn fix_indentation(content: &str, file_type: FileType) -> String {
+	convert_indentation(
+		content,
+		CONFIG.indent_style,
+		CONFIG.indent_width,
+		blank_lines::verbatim_spans(file_type),
+	)
+}
+
+/// Convert leading indentation to `style` using `width`-column tab stops,
+/// outside the verbatim spans of `syntax`
+fn convert_indentation(
+	content: &str,
+	style: IndentStyle,
+	width: u8,
+	syntax: Option<&'static Syntax>,
+) -> String {
+	let width = usize::from(width.max(1));
+	let mut result = String::with_capacity(content.len());
+	let mut scanner = syntax.map(SpanScanner::new);
+	let mut in_span = false;
+
+	for line in content.split_inclusive('\n') {
+		let verbatim = in_span;
+		if let Some(scanner) = &mut scanner {
+			in_span = scanner.scan(line);
+		}
+		if verbatim {
+			result.push_str(line);
+			continue;
+		}
+		let body = line.trim_start_matches([' ', '\t']);
+		let leading = &line[..line.len() - body.len()];
+		let columns = leading.chars().fold(0, |column, c| match c {
+			'\t' => column + width - column % width,
+			_ => column + 1,
+		});
+
+		match style {
+			IndentStyle::Tabs => {
+				result.push_str(&"\t".repeat(columns / width));
+				result.push_str(&" ".repeat(columns % width));
+			}
+			IndentStyle::Spaces => {
+				result.push_str(&" ".repeat(columns));
+			}
+		}
+		result.push_str(body);
+	}
+
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_spaces_to_tabs() {
+		let content = "a\n    b\n        c\n      d\n";
+		assert_eq!(
+			convert_indentation(content, IndentStyle::Tabs, 4, None),
+			"a\n\tb\n\t\tc\n\t  d\n"
+		);
+	}
+
+	#[test]
+	fn test_tabs_to_spaces() {
+		let content = "a\n\tb\n\t\tc\r\n";
+		assert_eq!(
+			convert_indentation(content, IndentStyle::Spaces, 2, None),
+			"a\n  b\n    c\r\n"
+		);
+	}
+
+	#[test]
+	fn test_mixed_indentation_uses_tab_stops() {
+		// Two spaces then a tab reaches the first tab stop, not column 6
+		let content = "  \tx\n";
+		assert_eq!(
+			convert_indentation(content, IndentStyle::Spaces, 4, None),
+			"    x\n"
+		);
+		assert_eq!(
+			convert_indentation(content, IndentStyle::Tabs, 4, None),
+			"\tx\n"
+		);
+	}
+
+	#[test]
+	fn test_verbatim_spans_untouched() {
+		let content = "<div>\n    <pre>\n    a\n    </pre>\n    b\n</div>\n";
+		let syntax = blank_lines::verbatim_spans(FileType::Vue);
+		assert_eq!(
+			convert_indentation(content, IndentStyle::Tabs, 4, syntax),
+			"<div>\n\t<pre>\n    a\n    </pre>\n\tb\n</div>\n"
+		);
+	}
+
+	#[test]
+	fn test_inner_whitespace_untouched() {
+		let content = "    a\tb    c";
+		assert_eq!(
+			convert_indentation(content, IndentStyle::Tabs, 4, None),
+			"\ta\tb    c"
+		);
+	}
+}
@@ -0,0 +1,162 @@
+// interactive.rs - Per-file diff review for --interactive
+
+use crate::color::Color;
+use similar::TextDiff;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+const HELP: &str = "y - write this file\n\
+	n - skip this file\n\
+	a - write this file and all remaining files\n\
+	q - quit; skip this file and all remaining files";
+
+/// Answer to a per-file prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+	Yes,
+	No,
+	All,
+	Quit,
+}
+
+/// Answers that apply beyond the current file
+#[derive(Debug, Default)]
+struct Session {
+	all: bool,
+	quit: bool,
+}
+
+static SESSION: Mutex<Session> = Mutex::new(Session {
+	all: false,
+	quit: false,
+});
+
+/// Whether the user quit; remaining files should be left untouched
+pub fn quit_requested() -> bool {
+	SESSION.lock().unwrap_or_else(|e| e.into_inner()).quit
+}
+
+/// Show the diff for a file and ask whether to write it.
+///
+/// End of input counts as quitting, so a closed stdin never writes.
+pub fn confirm(
+	path: &Path,
+	original: &str,
+	formatted: &str,
+) -> io::Result<bool> {
+	let mut session = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+	if session.all {
+		return Ok(true);
+	}
+	if session.quit {
+		return Ok(false);
+	}
+
+	eprint!("{}", render_diff(path, original, formatted));
+	let answer = loop {
+		eprint!("Write {}? [y,n,a,q,?] ", path.display());
+		io::stderr().flush()?;
+		let mut line = String::new();
+		if io::stdin().lock().read_line(&mut line)? == 0 {
+			break Answer::Quit;
+		}
+		match parse_answer(&line) {
+			Some(answer) => break answer,
+			None => eprintln!("{HELP}"),
+		}
+	};
+
+	Ok(session.apply(answer))
+}
+
+impl Session {
+	/// Record an answer; returns whether the current file is written
+	fn apply(&mut self, answer: Answer) -> bool {
+		match answer {
+			Answer::Yes => true,
+			Answer::All => {
+				self.all = true;
+				true
+			}
+			Answer::No => false,
+			Answer::Quit => {
+				self.quit = true;
+				false
+			}
+		}
+	}
+}
+
+fn parse_answer(line: &str) -> Option<Answer> {
+	match line.trim().to_ascii_lowercase().as_str() {
+		"y" | "yes" => Some(Answer::Yes),
+		"n" | "no" => Some(Answer::No),
+		"a" | "all" => Some(Answer::All),
+		"q" | "quit" => Some(Answer::Quit),
+		_ => None,
+	}
+}
+
+/// Unified diff with added lines in green and removed lines in red
+fn render_diff(path: &Path, original: &str, formatted: &str) -> String {
+	let name = path.display().to_string();
+	let diff = TextDiff::from_lines(original, formatted)
+		.unified_diff()
+		.header(&name, &name)
+		.to_string();
+
+	let mut result = String::with_capacity(diff.len());
+	for line in diff.lines() {
+		let painted = if line.starts_with("+++") || line.starts_with("---") {
+			line.to_string()
+		} else if line.starts_with('+') {
+			Color::Green.paint(line)
+		} else if line.starts_with('-') {
+			Color::Red.paint(line)
+		} else {
+			line.to_string()
+		};
+		result.push_str(&painted);
+		result.push('\n');
+	}
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_answer() {
+		assert_eq!(parse_answer("y\n"), Some(Answer::Yes));
+		assert_eq!(parse_answer(" N "), Some(Answer::No));
+		assert_eq!(parse_answer("all"), Some(Answer::All));
+		assert_eq!(parse_answer("q"), Some(Answer::Quit));
+		assert_eq!(parse_answer("?"), None);
+		assert_eq!(parse_answer(""), None);
+	}
+
+	#[test]
+	fn test_session_apply() {
+		let mut session = Session::default();
+		assert!(session.apply(Answer::Yes));
+		assert!(!session.apply(Answer::No));
+		assert!(!session.all && !session.quit);
+		assert!(session.apply(Answer::All));
+		assert!(session.all);
+
+		let mut session = Session::default();
+		assert!(!session.apply(Answer::Quit));
+		assert!(session.quit);
+	}
+
+	#[test]
+	fn test_render_diff() {
+		let diff =
+			render_diff(Path::new("a.json"), "{\"a\":1}\n", "{ \"a\": 1 }\n");
+		assert!(diff.starts_with("--- a.json\n+++ a.json\n"));
+		assert!(diff.contains(&Color::Red.paint("-{\"a\":1}")));
+		assert!(diff.contains(&Color::Green.paint("+{ \"a\": 1 }")));
+	}
+}
@@ -0,0 +1,345 @@
+// invisible.rs - Opt-in cleanup of invisible characters
+//
+// U+2028/U+2029 separators, byte order marks past the start of a file and
+// zero-width spaces render as nothing but still show up in diffs. With
+// `normalize_invisible_chars` on, the separators become line breaks and
+// the others are removed before a file is formatted. Strings are found
+// lexically with each language's quotes, including ones spanning lines,
+// and left alone, since a character there is usually deliberate.
+
+use fama_common::FileType;
+
+/// A character that was replaced or removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+	/// 1-based line of the character in the original text
+	pub line: usize,
+	pub change: &'static str,
+}
+
+/// How a language writes strings and comments, as far as finding strings
+/// needs
+struct Syntax {
+	/// String delimiters that may span lines, longest first
+	multi_line: &'static [&'static str],
+	/// String delimiters that end with their line. One without a closing
+	/// quote on the same line is an apostrophe or lifetime, not a string
+	single_line: &'static [&'static str],
+	/// Delimiters of strings where a backslash is an ordinary character
+	raw: &'static [&'static str],
+	/// Starts a comment running to the end of the line
+	line_comment: Option<&'static str>,
+	/// The comment marker only counts at the start of the line or after
+	/// whitespace, as in YAML and shell
+	comment_after_space: bool,
+	/// YAML `|` and `>` block scalars
+	block_scalars: bool,
+}
+
+const DEFAULT: Syntax = Syntax {
+	multi_line: &[],
+	single_line: &["\"", "'"],
+	raw: &[],
+	line_comment: None,
+	comment_after_space: false,
+	block_scalars: false,
+};
+
+fn syntax(file_type: FileType) -> Syntax {
+	use FileType::*;
+	let slashes = Some("//");
+	let hash = Some("#");
+	match file_type {
+		// Apostrophes in prose would open strings that never close
+		Markdown | Unknown => Syntax {
+			single_line: &[],
+			..DEFAULT
+		},
+		JavaScript | TypeScript | Jsx | Tsx | Vue | Svelte | Astro => Syntax {
+			multi_line: &["`"],
+			line_comment: slashes,
+			..DEFAULT
+		},
+		Python | Starlark => Syntax {
+			multi_line: &["\"\"\"", "\'\'\'"],
+			line_comment: hash,
+			..DEFAULT
+		},
+		Yaml => Syntax {
+			line_comment: hash,
+			comment_after_space: true,
+			block_scalars: true,
+			..DEFAULT
+		},
+		Toml => Syntax {
+			multi_line: &["\"\"\"", "\'\'\'"],
+			raw: &["\'\'\'", "'"],
+			line_comment: hash,
+			..DEFAULT
+		},
+		Rust => Syntax {
+			multi_line: &["\""],
+			single_line: &["'"],
+			line_comment: slashes,
+			..DEFAULT
+		},
+		Go => Syntax {
+			multi_line: &["`"],
+			raw: &["`"],
+			line_comment: slashes,
+			..DEFAULT
+		},
+		Java | Kotlin | Groovy => Syntax {
+			multi_line: &["\"\"\"", "\'\'\'"],
+			line_comment: slashes,
+			..DEFAULT
+		},
+		C | Cpp | CSharp | ObjectiveC | ObjectiveCpp | Cuda | Shader
+		| Protobuf | Zig | Php => Syntax {
+			line_comment: slashes,
+			..DEFAULT
+		},
+		Shell => Syntax {
+			multi_line: &["\"", "'"],
+			single_line: &[],
+			raw: &["'"],
+			line_comment: hash,
+			comment_after_space: true,
+			..DEFAULT
+		},
+		Ruby => Syntax {
+			multi_line: &["\"", "'"],
+			single_line: &[],
+			line_comment: hash,
+			..DEFAULT
+		},
+		Hcl | Dockerfile => Syntax {
+			line_comment: hash,
+			..DEFAULT
+		},
+		GraphQL => Syntax {
+			multi_line: &["\"\"\""],
+			single_line: &["\""],
+			line_comment: hash,
+			..DEFAULT
+		},
+		Lua => Syntax {
+			line_comment: Some("--"),
+			..DEFAULT
+		},
+		Sql => Syntax {
+			multi_line: &["'"],
+			single_line: &["\""],
+			line_comment: Some("--"),
+			..DEFAULT
+		},
+		_ => DEFAULT,
+	}
+}
+
+/// `text` with invisible characters outside strings normalized, and what
+/// was changed
+pub fn normalize(text: &str, file_type: FileType) -> (String, Vec<Fix>) {
+	let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+	let syntax = syntax(file_type);
+
+	let mut out = String::with_capacity(text.len());
+	let mut fixes = Vec::new();
+	// Delimiter of a string an earlier line left open
+	let mut open: Option<&str> = None;
+	// Indentation of the line that started a YAML block scalar
+	let mut block: Option<usize> = None;
+	for (number, line) in text.split_inclusive('\n').enumerate() {
+		let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+		if let Some(parent) = block {
+			if line.trim().is_empty() || indent > parent {
+				out.push_str(line);
+				continue;
+			}
+			block = None;
+		}
+
+		let mut code = |out: &mut String, c: char| {
+			let change = match c {
+				'\u{2028}' => {
+					"U+2028 LINE SEPARATOR replaced with a line break"
+				}
+				'\u{2029}' => {
+					"U+2029 PARAGRAPH SEPARATOR replaced with a line break"
+				}
+				'\u{200B}' => "U+200B ZERO WIDTH SPACE removed",
+				'\u{FEFF}' if !out.is_empty() => {
+					"U+FEFF byte order mark removed"
+				}
+				_ => {
+					out.push(c);
+					return;
+				}
+			};
+			if matches!(c, '\u{2028}' | '\u{2029}') {
+				out.push_str(newline);
+			}
+			fixes.push(Fix {
+				line: number + 1,
+				change,
+			});
+		};
+
+		let mut rest = line;
+		while let Some(c) = rest.chars().next() {
+			if let Some(quote) = open {
+				let len = if rest.starts_with(quote) {
+					open = None;
+					quote.len()
+				} else if c == '\\' && !syntax.raw.contains(&quote) {
+					escaped_len(rest)
+				} else {
+					c.len_utf8()
+				};
+				out.push_str(&rest[..len]);
+				rest = &rest[len..];
+				continue;
+			}
+			let comment = syntax.line_comment.is_some_and(|marker| {
+				rest.starts_with(marker)
+					&& (!syntax.comment_after_space
+						|| out.is_empty() || out.ends_with(char::is_whitespace))
+			});
+			if comment {
+				rest.chars().for_each(|c| code(&mut out, c));
+				break;
+			}
+			if let Some(&quote) =
+				syntax.multi_line.iter().find(|q| rest.starts_with(**q))
+			{
+				open = Some(quote);
+				out.push_str(quote);
+				rest = &rest[quote.len()..];
+				continue;
+			}
+			let string = syntax
+				.single_line
+				.iter()
+				.filter(|q| rest.starts_with(**q))
+				.find_map(|q| string_len(rest, q, syntax.raw.contains(q)));
+			if let Some(len) = string {
+				out.push_str(&rest[..len]);
+				rest = &rest[len..];
+				continue;
+			}
+			code(&mut out, c);
+			rest = &rest[c.len_utf8()..];
+		}
+
+		if syntax.block_scalars && open.is_none() && starts_block_scalar(line) {
+			block = Some(indent);
+		}
+	}
+	(out, fixes)
+}
+
+/// Length of a backslash and the character it escapes at the start of
+/// `text`
+fn escaped_len(text: &str) -> usize {
+	1 + text[1..].chars().next().map_or(0, char::len_utf8)
+}
+
+/// Length of the string opened by `quote` at the start of `line`, through
+/// its closing quote, or `None` if it isn't closed on this line
+fn string_len(line: &str, quote: &str, raw: bool) -> Option<usize> {
+	let mut i = quote.len();
+	while let Some(c) = line[i..].chars().next() {
+		if line[i..].starts_with(quote) {
+			return Some(i + quote.len());
+		}
+		if c == '\n' {
+			return None;
+		}
+		i += if c == '\\' && !raw {
+			escaped_len(&line[i..])
+		} else {
+			c.len_utf8()
+		};
+	}
+	None
+}
+
+/// Whether a YAML line ends in a `|` or `>` block scalar header, so the
+/// more indented lines after it are the scalar's text
+fn starts_block_scalar(line: &str) -> bool {
+	let line = match line.find(" #") {
+		Some(comment) => &line[..comment],
+		None => line,
+	};
+	let line = line.trim_end();
+	let header = line
+		.trim_end_matches(|c: char| c.is_ascii_digit() || c == '-' || c == '+');
+	let Some(before) = header.strip_suffix(['|', '>']) else {
+		return false;
+	};
+	before.is_empty() || before.ends_with([' ', ':', '-'])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_normalize() {
+		let text = "\u{FEFF}let a\u{200B} = 1;\u{2028}let b = \"x\u{200B}\";\n\
+		            \u{FEFF}let c = '\\'\u{2029}';\n";
+		let (normalized, fixes) = normalize(text, FileType::JavaScript);
+		assert_eq!(
+			normalized,
+			"\u{FEFF}let a = 1;\nlet b = \"x\u{200B}\";\nlet c = '\\'\u{2029}';\n"
+		);
+		let lines: Vec<usize> = fixes.iter().map(|fix| fix.line).collect();
+		assert_eq!(lines, [1, 1, 2]);
+		assert_eq!(fixes[2].change, "U+FEFF byte order mark removed");
+	}
+
+	#[test]
+	fn test_strings_spanning_lines() {
+		let z = '\u{200B}';
+		let text = format!("const s = `a\n{z}b`;{z}\nlet t = '{z}';\n");
+		let (normalized, fixes) = normalize(&text, FileType::TypeScript);
+		assert_eq!(
+			normalized,
+			format!("const s = `a\n{z}b`;\nlet t = '{z}';\n")
+		);
+		assert_eq!(fixes.len(), 1);
+
+		let text = format!("s = \"\"\"\n{z}\\\"\"\"\n\"\"\"\nx = 1{z}\n");
+		let (normalized, _) = normalize(&text, FileType::Python);
+		assert_eq!(normalized, text.replacen(&format!("1{z}"), "1", 1));
+
+		let text = format!("a: |\n  {z}x\n\n  y\nb: c{z}\n");
+		let (normalized, _) = normalize(&text, FileType::Yaml);
+		assert_eq!(normalized, format!("a: |\n  {z}x\n\n  y\nb: c\n"));
+	}
+
+	#[test]
+	fn test_unpaired_quotes_are_not_strings() {
+		let z = '\u{200B}';
+		let text = format!("// don't{z}\nlet a{z} = 1;\n");
+		let (normalized, _) = normalize(&text, FileType::JavaScript);
+		assert_eq!(normalized, "// don't\nlet a = 1;\n");
+
+		let text = format!("fn f<'a>(x: &'a str) {{}}{z} // it's{z}\n");
+		let (normalized, fixes) = normalize(&text, FileType::Rust);
+		assert_eq!(normalized, "fn f<'a>(x: &'a str) {} // it's\n");
+		assert_eq!(fixes.len(), 2);
+
+		let text = format!("echo hi # it's{z}\nx='a{z}\nb'\n");
+		let (normalized, _) = normalize(&text, FileType::Shell);
+		assert_eq!(normalized, format!("echo hi # it's\nx='a{z}\nb'\n"));
+	}
+
+	#[test]
+	fn test_prose_has_no_strings() {
+		let text = "Don't\u{200B} split\r\nhere\u{2028}now\r\n";
+		let (normalized, fixes) = normalize(text, FileType::Markdown);
+		assert_eq!(normalized, "Don't split\r\nhere\r\nnow\r\n");
+		assert_eq!(fixes.len(), 2);
+	}
+}
@@ -0,0 +1,105 @@
+// jsonc.rs - Parse JSON with comments and trailing commas
+//
+// Comments and trailing commas are blanked out with spaces, keeping every
+// newline, so serde_json's error positions still point into the original.
+
+/// Parse JSONC text into a JSON value
+pub fn parse(text: &str) -> serde_json::Result<serde_json::Value> {
+	serde_json::from_str(&strip(text))
+}
+
+/// `text` with comments and trailing commas replaced by whitespace
+fn strip(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+	let mut in_string = false;
+	let mut escaped = false;
+	// Offset in `out` of a comma followed so far only by whitespace
+	let mut comma = None;
+	while let Some(c) = chars.next() {
+		if in_string {
+			out.push(c);
+			if escaped {
+				escaped = false;
+			} else if c == '\\' {
+				escaped = true;
+			} else if c == '"' {
+				in_string = false;
+			}
+			continue;
+		}
+		match (c, chars.peek()) {
+			('/', Some('/')) => {
+				out.push(' ');
+				while let Some(&c) = chars.peek() {
+					if c == '\n' {
+						break;
+					}
+					out.push(' ');
+					chars.next();
+				}
+			}
+			('/', Some('*')) => {
+				chars.next();
+				out.push_str("  ");
+				let mut star = false;
+				for c in chars.by_ref() {
+					out.push(if c == '\n' { '\n' } else { ' ' });
+					if star && c == '/' {
+						break;
+					}
+					star = c == '*';
+				}
+			}
+			(',', _) => {
+				comma = Some(out.len());
+				out.push(c);
+			}
+			('}' | ']', _) => {
+				if let Some(at) = comma.take() {
+					out.replace_range(at..at + 1, " ");
+				}
+				out.push(c);
+			}
+			_ if c.is_whitespace() => out.push(c),
+			_ => {
+				comma = None;
+				in_string = c == '"';
+				out.push(c);
+			}
+		}
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_jsonc() {
+		let text = concat!(
+			"{\n",
+			"\t// line comment\n",
+			"\t\"a\": \"// not a comment\", /* block\n comment */\n",
+			"\t\"b\": [1, 2,],\n",
+			"\t\"c\": \"\\\",\",\n",
+			"}\n",
+		);
+		assert_eq!(
+			parse(text).unwrap(),
+			serde_json::json!({
+				"a": "// not a comment",
+				"b": [1, 2],
+				"c": "\",",
+			})
+		);
+		assert!(parse("{\"a\": 1,, }").is_err());
+	}
+
+	#[test]
+	fn test_error_positions_are_kept() {
+		let error = parse("{\n/* a\nb */ \"a\": }").unwrap_err();
+		assert_eq!((error.line(), error.column()), (3, 11));
+	}
+}
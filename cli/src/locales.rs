@@ -0,0 +1,173 @@
+// locales.rs - Key consistency check across i18n locale files
+
+use crate::jsonc;
+use fama_common::{matches_path_pattern, CONFIG};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Flattened key sets of locale files, grouped by directory
+#[derive(Debug, Default)]
+pub struct LocaleKeys {
+	dirs: BTreeMap<PathBuf, BTreeMap<PathBuf, BTreeSet<String>>>,
+}
+
+/// Whether a file is a locale file according to `CONFIG.locale_paths`
+pub fn is_locale_file(path: &Path) -> bool {
+	matches_path_pattern(path, CONFIG.locale_paths)
+}
+
+impl LocaleKeys {
+	/// Record the keys of one locale file, JSON or JSONC. A file that
+	/// doesn't parse is left out of the comparison and the error returned.
+	pub fn record(&mut self, path: &Path, content: &str) -> Result<(), String> {
+		let value = jsonc::parse(content).map_err(|e| e.to_string())?;
+		let mut keys = BTreeSet::new();
+		flatten_keys(&value, String::new(), &mut keys);
+
+		let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+		self.dirs
+			.entry(dir)
+			.or_default()
+			.insert(path.to_path_buf(), keys);
+		Ok(())
+	}
+
+	/// Merge two LocaleKeys instances (used in parallel reduce)
+	pub fn merge(mut self, other: LocaleKeys) -> LocaleKeys {
+		for (dir, files) in other.dirs {
+			self.dirs.entry(dir).or_default().extend(files);
+		}
+		self
+	}
+
+	/// Describe keys missing from a file but present in a sibling, and keys
+	/// no sibling has
+	pub fn warnings(&self) -> Vec<String> {
+		let mut warnings = Vec::new();
+
+		for files in self.dirs.values().filter(|files| files.len() > 1) {
+			for (path, keys) in files {
+				let siblings: BTreeSet<&String> = files
+					.iter()
+					.filter(|(other, _)| *other != path)
+					.flat_map(|(_, keys)| keys)
+					.collect();
+				let missing: Vec<&str> = siblings
+					.iter()
+					.filter(|key| !keys.contains(**key))
+					.map(|key| key.as_str())
+					.collect();
+				let extra: Vec<&str> = keys
+					.iter()
+					.filter(|key| !siblings.contains(key))
+					.map(String::as_str)
+					.collect();
+
+				if !missing.is_empty() {
+					warnings.push(format!(
+						"{}: missing locale keys: {}",
+						path.display(),
+						missing.join(", ")
+					));
+				}
+				if !extra.is_empty() {
+					warnings.push(format!(
+						"{}: extra locale keys: {}",
+						path.display(),
+						extra.join(", ")
+					));
+				}
+			}
+		}
+
+		warnings
+	}
+}
+
+/// Collect dotted key paths of all leaf values; arrays count as leaves
+fn flatten_keys(
+	value: &serde_json::Value,
+	prefix: String,
+	keys: &mut BTreeSet<String>,
+) {
+	match value {
+		serde_json::Value::Object(map) => {
+			for (key, child) in map {
+				let path = if prefix.is_empty() {
+					key.clone()
+				} else {
+					format!("{prefix}.{key}")
+				};
+				flatten_keys(child, path, keys);
+			}
+		}
+		_ if !prefix.is_empty() => {
+			keys.insert(prefix);
+		}
+		_ => {}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_is_locale_file() {
+		assert!(is_locale_file(Path::new("web/locales/en.json")));
+		assert!(!is_locale_file(Path::new("web/config/en.json")));
+	}
+
+	#[test]
+	fn test_flatten_keys() {
+		let value = serde_json::json!({
+			"home": { "title": "Home", "items": [1, 2] },
+			"empty": {},
+			"ok": "OK",
+		});
+		let mut keys = BTreeSet::new();
+		flatten_keys(&value, String::new(), &mut keys);
+		let keys: Vec<_> = keys.iter().map(String::as_str).collect();
+		assert_eq!(keys, ["home.items", "home.title", "ok"]);
+	}
+
+	#[test]
+	fn test_warnings_report_missing_and_extra_keys() {
+		let mut a = LocaleKeys::default();
+		a.record(
+			Path::new("locales/en.json"),
+			r#"{"a": "A", "b": {"c": "C"}}"#,
+		)
+		.unwrap();
+		let mut b = LocaleKeys::default();
+		b.record(
+			Path::new("locales/fr.json"),
+			"{\"a\": \"A\", // greeting\n\"d\": \"D\",}",
+		)
+		.unwrap();
+		b.record(Path::new("other/de.json"), r#"{"z": "Z"}"#)
+			.unwrap();
+
+		let warnings = a.merge(b).warnings();
+		assert_eq!(
+			warnings,
+			[
+				"locales/en.json: missing locale keys: d",
+				"locales/en.json: extra locale keys: b.c",
+				"locales/fr.json: missing locale keys: b.c",
+				"locales/fr.json: extra locale keys: d",
+			]
+		);
+	}
+
+	#[test]
+	fn test_consistent_locales_have_no_warnings() {
+		let mut keys = LocaleKeys::default();
+		keys.record(Path::new("locales/en.json"), r#"{"a": "A"}"#)
+			.unwrap();
+		keys.record(Path::new("locales/fr.json"), r#"{"a": "Á"}"#)
+			.unwrap();
+		assert!(keys.record(Path::new("locales/broken.json"), "{").is_err());
+		assert!(keys.warnings().is_empty());
+	}
+}
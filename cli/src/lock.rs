@@ -0,0 +1,106 @@
+// lock.rs - Advisory lock so concurrent write-mode runs don't interleave
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// Exclusive lock on a project root, released when dropped
+#[derive(Debug)]
+pub struct RunLock {
+	_file: File,
+}
+
+/// Lock `root` for a write-mode run, waiting while another run holds it.
+///
+/// The lock file lives in fama's private temp root rather than the project,
+/// so it never shows up as an untracked file or gets picked up by discovery,
+/// and other users can't create or hold it in our place.
+pub fn acquire(root: &Path) -> anyhow::Result<RunLock> {
+	let path = lock_path(root).map_err(|e| {
+		anyhow::anyhow!("Failed to create the lock directory: {}", e)
+	})?;
+	let file = OpenOptions::new()
+		.create(true)
+		.truncate(false)
+		.write(true)
+		.open(&path)
+		.map_err(|e| {
+			anyhow::anyhow!(
+				"Failed to open lock file {}: {}",
+				path.display(),
+				e
+			)
+		})?;
+
+	match file.try_lock() {
+		Ok(()) => {}
+		Err(TryLockError::WouldBlock) => {
+			tracing::warn!(
+				"Waiting for another fama run in {} to finish \
+				 (use --no-lock to skip)",
+				root.display()
+			);
+			file.lock()?;
+		}
+		// WASI and some network filesystems have no file locks; run
+		// unlocked rather than refuse to run at all
+		Err(TryLockError::Error(e))
+			if e.kind() == std::io::ErrorKind::Unsupported => {}
+		Err(TryLockError::Error(e)) => {
+			return Err(anyhow::anyhow!(
+				"Failed to lock {}: {}",
+				path.display(),
+				e
+			));
+		}
+	}
+	Ok(RunLock { _file: file })
+}
+
+/// Lock file for a project root. `temp::clean` only removes directories,
+/// so it is safe next to the run directories
+fn lock_path(root: &Path) -> std::io::Result<PathBuf> {
+	Ok(crate::temp::root()?.join(project_file_name(root, "lock")))
+}
+
+/// A file name for the project at `root`, from its canonical path, for
+/// per-project files kept outside the project
+pub fn project_file_name(root: &Path, extension: &str) -> String {
+	let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+	let name: String = root
+		.to_string_lossy()
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect();
+	format!("fama-{}.{}", name, extension)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_lock_path_is_per_root() {
+		let a = TempDir::new().unwrap();
+		let b = TempDir::new().unwrap();
+		let path = |dir: &TempDir| lock_path(dir.path()).unwrap();
+		assert_eq!(path(&a), path(&a));
+		assert_ne!(path(&a), path(&b));
+		assert_eq!(
+			path(&a).parent(),
+			Some(crate::temp::root().unwrap().as_path())
+		);
+	}
+
+	#[test]
+	fn test_lock_excludes_other_runs_until_dropped() {
+		let root = TempDir::new().unwrap();
+		let lock = acquire(root.path()).unwrap();
+
+		let other = File::open(lock_path(root.path()).unwrap()).unwrap();
+		assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+
+		drop(lock);
+		assert!(other.try_lock().is_ok());
+	}
+}
@@ -0,0 +1,194 @@
+// logging.rs - Diagnostics through `tracing`
+//
+// Warnings, debug events and per-backend spans go through `tracing`, so
+// `--log-level`, `--log-file` and `--log-format` control all of them at
+// once. Text output keeps fama's `Warning: ...` lines; with `--log-level
+// debug` each backend call is a `format` span whose close event carries
+// its timing. User-facing output (summaries, errors, diffs) stays on
+// stdout and stderr as before.
+
+use clap::ValueEnum;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::{FmtSpan, Writer};
+use tracing_subscriber::fmt::{
+	FmtContext, FormatEvent, FormatFields, FormattedFields,
+};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Most verbose events to log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+	fn from(level: LogLevel) -> Self {
+		match level {
+			LogLevel::Error => LevelFilter::ERROR,
+			LogLevel::Warn => LevelFilter::WARN,
+			LogLevel::Info => LevelFilter::INFO,
+			LogLevel::Debug => LevelFilter::DEBUG,
+			LogLevel::Trace => LevelFilter::TRACE,
+		}
+	}
+}
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+	/// `Warning: message`, one line per event
+	#[default]
+	Text,
+	/// One JSON object per event, with the enclosing spans
+	Json,
+}
+
+/// Install the global subscriber, writing to `file` (appended to) or to
+/// stderr
+pub fn init(
+	level: LogLevel,
+	format: LogFormat,
+	file: Option<&Path>,
+) -> anyhow::Result<()> {
+	let builder = tracing_subscriber::fmt()
+		.with_max_level(level)
+		.with_span_events(FmtSpan::CLOSE)
+		.with_ansi(false);
+	let file = match file {
+		Some(path) => Some(
+			File::options()
+				.create(true)
+				.append(true)
+				.open(path)
+				.map_err(|e| {
+					anyhow::anyhow!(
+						"Failed to open log file {}: {}",
+						path.display(),
+						e
+					)
+				})?,
+		),
+		None => None,
+	};
+
+	let result = match (format, file) {
+		(LogFormat::Text, None) => builder
+			.event_format(Text)
+			.with_writer(std::io::stderr)
+			.try_init(),
+		(LogFormat::Text, Some(file)) => builder
+			.event_format(Text)
+			.with_writer(Mutex::new(file))
+			.try_init(),
+		(LogFormat::Json, None) => builder
+			.json()
+			.with_span_list(true)
+			.with_writer(std::io::stderr)
+			.try_init(),
+		(LogFormat::Json, Some(file)) => builder
+			.json()
+			.with_span_list(true)
+			.with_writer(Mutex::new(file))
+			.try_init(),
+	};
+	result.map_err(|e| anyhow::anyhow!("Failed to set up logging: {}", e))
+}
+
+/// Label an event line starts with
+fn label(level: Level) -> &'static str {
+	match level {
+		Level::ERROR => "Error",
+		Level::WARN => "Warning",
+		Level::INFO => "info",
+		Level::DEBUG => "debug",
+		Level::TRACE => "trace",
+	}
+}
+
+/// `Warning: message`, with the enclosing spans and their fields between
+/// the label and the message, as in `debug: format{backend="biome"}: close`
+struct Text;
+
+impl<S, N> FormatEvent<S, N> for Text
+where
+	S: Subscriber + for<'a> LookupSpan<'a>,
+	N: for<'a> FormatFields<'a> + 'static,
+{
+	fn format_event(
+		&self,
+		ctx: &FmtContext<'_, S, N>,
+		mut writer: Writer<'_>,
+		event: &Event<'_>,
+	) -> fmt::Result {
+		write!(writer, "{}: ", label(*event.metadata().level()))?;
+		for span in ctx.event_scope().into_iter().flat_map(|s| s.from_root()) {
+			write!(writer, "{}", span.name())?;
+			let extensions = span.extensions();
+			if let Some(fields) = extensions.get::<FormattedFields<N>>() {
+				if !fields.is_empty() {
+					write!(writer, "{{{}}}", fields)?;
+				}
+			}
+			write!(writer, ": ")?;
+		}
+		ctx.field_format().format_fields(writer.by_ref(), event)?;
+		writeln!(writer)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+	use std::sync::Arc;
+
+	/// Writer collecting everything logged through it
+	#[derive(Clone, Default)]
+	struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+	impl Write for Buffer {
+		fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+
+		fn flush(&mut self) -> std::io::Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_text_format() {
+		let buffer = Buffer::default();
+		let writer = buffer.clone();
+		let subscriber = tracing_subscriber::fmt()
+			.with_max_level(LogLevel::Debug)
+			.with_ansi(false)
+			.event_format(Text)
+			.with_writer(move || writer.clone())
+			.finish();
+		tracing::subscriber::with_default(subscriber, || {
+			tracing::warn!("pattern 'x' matched 0 files");
+			let _span =
+				tracing::debug_span!("format", backend = "biome").entered();
+			tracing::debug!(bytes = 3, "formatted");
+			tracing::trace!("hidden");
+		});
+		let output = String::from_utf8(buffer.0.lock().unwrap().clone());
+		assert_eq!(
+			output.unwrap(),
+			concat!(
+				"Warning: pattern 'x' matched 0 files\n",
+				"debug: format{backend=\"biome\"}: formatted bytes=3\n",
+			)
+		);
+	}
+}
@@ -1,25 +1,70 @@
+mod adopt;
+mod align;
+mod backend;
+mod baseline;
+mod blank_lines;
+mod cancel;
 mod color;
+mod comments;
+mod config;
+mod coverage;
+mod directives;
+mod dirs;
 mod discovery;
+mod editor_server;
 mod editorconfig;
+mod edits;
+mod eol;
+mod explain;
+mod export;
+mod fnv;
 mod formatter;
 mod git;
+mod http_server;
+mod ignore_regions;
+mod incremental;
+mod indent;
+mod interactive;
+mod invisible;
+mod jsonc;
+mod locales;
+mod lock;
+mod logging;
+mod metrics;
+mod migrate;
+mod report;
+mod rewrites;
+mod risk;
+mod sfc;
+mod stats;
+mod temp;
+mod version;
+mod why;
 
 extern crate biome;
 extern crate dockerfile;
 extern crate dprint;
+#[cfg(feature = "native")]
 extern crate goffi;
 extern crate ruff;
 extern crate rustfmt;
 extern crate stylua;
 
-use clap::Parser;
-use color::Color;
+use clap::{Parser, Subcommand};
+use fama_common::{detect_file_type, LanguageMode, CONFIG};
+use formatter::Outcome;
 use rayon::prelude::*;
+use std::io::Write;
+use std::time::Instant;
 
 #[derive(Parser)]
 #[command(name = "fama")]
 #[command(about = "A code formatter for many languages", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+	#[command(subcommand)]
+	command: Option<Command>,
+
 	/// Glob patterns to match files
 	#[arg(default_values_t = ["**/*".to_string()])]
 	pattern: Vec<String>,
@@ -28,18 +73,77 @@ struct Cli {
 	#[arg(long, short)]
 	export: bool,
 
-	/// Print each file being formatted to stderr
+	/// Show the diff formatting would make to FILE, with each hunk labeled
+	/// by the rule or backend that likely caused it; nothing is written
+	#[arg(long, value_name = "FILE")]
+	why: Option<std::path::PathBuf>,
+
+	/// Print the edits formatting would make to FILE as a JSON array of
+	/// LSP text edits, for editors that apply changes incrementally;
+	/// nothing is written
+	#[arg(long, value_name = "FILE")]
+	edits: Option<std::path::PathBuf>,
+
+	/// Format buffers sent over stdin for format-on-save editor plugins
+	/// (see README for the protocol)
+	#[arg(long)]
+	editor_server: bool,
+
+	/// Log each file being formatted at info level, with the config values
+	/// applied to it and where each came from
 	#[arg(long, short)]
 	debug: bool,
 
+	/// Most verbose diagnostics to log; `debug` adds a timed span per
+	/// backend call [default: warn, info with --debug, or error with
+	/// --quiet]
+	#[arg(long, value_enum, value_name = "LEVEL")]
+	log_level: Option<logging::LogLevel>,
+
+	/// Append diagnostics to this file instead of printing them to stderr
+	#[arg(long, value_name = "PATH")]
+	log_file: Option<std::path::PathBuf>,
+
+	/// Write diagnostics as text or as one JSON object per line
+	#[arg(long, value_enum, value_name = "FORMAT", default_value_t)]
+	log_format: logging::LogFormat,
+
 	/// Check if files are formatted, exit with non-zero if not
 	#[arg(long, short)]
 	check: bool,
 
+	/// With --check, only fail on unformatted files not listed in this
+	/// JSON file, for adopting fama one file at a time
+	#[arg(long, value_name = "PATH", requires = "check")]
+	baseline: Option<std::path::PathBuf>,
+
+	/// Rewrite the --baseline file with the files that need formatting now
+	#[arg(long, requires = "baseline")]
+	update_baseline: bool,
+
 	/// Quiet mode, only output errors
 	#[arg(long, short)]
 	quiet: bool,
 
+	/// Skip hidden files and directories such as `.eslintrc.json` or
+	/// `.github/` (`.git` is always skipped)
+	#[arg(long)]
+	no_hidden: bool,
+
+	/// Report source files in languages fama has no formatter for (such as
+	/// `.scala` or `.ex`) as errors and exit with non-zero
+	#[arg(long, conflicts_with_all = ["staged", "changed", "commit"])]
+	strict_coverage: bool,
+
+	/// Only format files changed since the last --incremental run, as
+	/// recorded in the cache dir; for directories without git
+	#[arg(
+		long,
+		alias = "since-last-run",
+		conflicts_with_all = ["staged", "changed", "commit", "interactive"]
+	)]
+	incremental: bool,
+
 	/// Only format git staged files
 	#[arg(long, group = "git_filter")]
 	staged: bool,
@@ -51,17 +155,335 @@ struct Cli {
 	/// Format changed files and commit with message "style: fmt"
 	#[arg(long)]
 	commit: bool,
+
+	/// Print a line per file as soon as it is done: its path, status and
+	/// time in milliseconds, so long runs show progress
+	#[arg(long, conflicts_with = "interactive")]
+	stream: bool,
+
+	/// Show each file's diff and ask before writing it (y/n/a/q)
+	#[arg(long, short, conflicts_with = "check")]
+	interactive: bool,
+
+	/// Convert leading spaces/tabs to the configured indent style for
+	/// Vue/Svelte/Astro files the formatter couldn't parse and left as is
+	#[arg(long)]
+	fix_indentation: bool,
+
+	/// Overwrite files even if they changed on disk during the run
+	#[arg(long)]
+	force_write: bool,
+
+	/// Format read-only files by making them writable for the write and
+	/// restoring their permissions after; by default they are skipped
+	#[arg(long, conflicts_with = "check")]
+	chmod_writable: bool,
+
+	/// Replace invalid UTF-8 sequences with U+FFFD and format anyway,
+	/// instead of reporting the file as an error
+	#[arg(long)]
+	lossy_utf8: bool,
+
+	/// Fail instead of downloading anything; only pre-seeded caches and
+	/// installed tools are used
+	#[arg(long)]
+	offline: bool,
+
+	/// Don't lock the project root; by default a write-mode run waits for
+	/// any other one in the same project to finish
+	#[arg(long)]
+	no_lock: bool,
+
+	/// Refuse to write files whose formatted data model (XML, YAML, JSON,
+	/// JSONC) differs from the input
+	#[arg(long)]
+	validate: bool,
+
+	/// Warn when locale files in one directory don't share the same keys
+	#[arg(long)]
+	check_locales: bool,
+
+	/// Write a bug-report bundle to the temp dir on panics or backend errors
+	#[arg(long)]
+	debug_report: bool,
+
+	/// Include the offending file's content in debug reports
+	#[arg(long, requires = "debug_report")]
+	report_content: bool,
+
+	/// Write run statistics (per-language counts, bytes, backend timings)
+	/// as JSON to this path
+	#[arg(long, value_name = "PATH")]
+	stats_file: Option<std::path::PathBuf>,
+
+	/// Print the fama version and the version of every backend
+	#[arg(long, short = 'V')]
+	version: bool,
+
+	/// Print `--version` as JSON
+	#[arg(long, requires = "version")]
+	json: bool,
+
+	/// Fail unless this is fama VERSION, or VERSION+FINGERPRINT to also
+	/// pin backends (see `fama --version`)
+	#[arg(long, value_name = "VERSION")]
+	required_version: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Show the detected file type, backend, applied config values, and
+	/// whether discovery would skip a file
+	Explain {
+		/// File to explain
+		path: std::path::PathBuf,
+	},
+	/// Print the config as JSON: what applies to given files, the defaults,
+	/// or a JSON Schema
+	#[command(group(
+		clap::ArgGroup::new("dump")
+			.required(true)
+			.args(["resolved", "default", "schema"])
+	))]
+	Config {
+		/// Print the config that applies to each file and where each value
+		/// came from, one JSON object per line
+		#[arg(long, value_name = "PATH", num_args = 1..)]
+		resolved: Vec<std::path::PathBuf>,
+		/// Print fama's built-in defaults
+		#[arg(long)]
+		default: bool,
+		/// Print a JSON Schema of the config
+		#[arg(long)]
+		schema: bool,
+	},
+	/// Estimate per-directory and per-language churn before adopting fama;
+	/// nothing is written
+	AdoptReport {
+		/// Glob patterns to match files
+		#[arg(default_values_t = ["**/*".to_string()])]
+		pattern: Vec<String>,
+		/// Print the report as JSON
+		#[arg(long)]
+		json: bool,
+	},
+	/// Count files and bytes per language, and the files fama would not
+	/// handle per extension; nothing is formatted
+	Stats {
+		/// Glob patterns to match files
+		#[arg(default_values_t = ["**/*".to_string()])]
+		pattern: Vec<String>,
+		/// Print the report as JSON
+		#[arg(long)]
+		json: bool,
+	},
+	/// Format the repository in dedicated commits so `git blame` can skip
+	/// them; the working tree must be clean
+	Migrate {
+		/// Glob patterns to match files
+		#[arg(default_values_t = ["**/*".to_string()])]
+		pattern: Vec<String>,
+		/// Make one commit per language instead of a single commit
+		#[arg(long)]
+		commit_per_language: bool,
+		/// Append the formatting commits to .git-blame-ignore-revs and
+		/// commit that file
+		#[arg(long)]
+		write_ignore_revs: bool,
+	},
+	/// Write a config file for Prettier, Biome or EditorConfig with fama's
+	/// settings, for editors that run those tools instead of fama
+	Export {
+		/// Tool to write the config file for
+		#[arg(long, value_enum, default_value_t)]
+		format: export::ExportFormat,
+	},
+	/// Manage the files fama keeps outside the project
+	Cache {
+		#[command(subcommand)]
+		action: CacheAction,
+	},
+	/// Serve `POST /format` over HTTP for playgrounds and other services
+	Serve {
+		/// Port to listen on
+		#[arg(long, default_value_t = 8890)]
+		port: u16,
+		/// Address to bind; the default only accepts local connections
+		#[arg(long, default_value = "127.0.0.1")]
+		host: String,
+		/// Requests formatted at once before answering 503
+		/// [default: number of CPUs]
+		#[arg(long)]
+		max_concurrent: Option<usize>,
+	},
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+	/// Remove the --incremental records of every project
+	Clean {
+		/// Remove the temp files that killed runs left behind and the
+		/// crash-report bundles instead
+		#[arg(long)]
+		temp: bool,
+	},
 }
 
 fn main() -> anyhow::Result<()> {
 	let cli = Cli::parse();
+	let log_level = cli.log_level.unwrap_or(if cli.debug {
+		logging::LogLevel::Info
+	} else if cli.quiet {
+		logging::LogLevel::Error
+	} else {
+		logging::LogLevel::Warn
+	});
+	logging::init(log_level, cli.log_format, cli.log_file.as_deref())?;
+
+	if cli.version {
+		if cli.json {
+			println!("{}", serde_json::to_string_pretty(&version::to_json())?);
+		} else {
+			print!("{}", version::to_text());
+		}
+		return Ok(());
+	}
+
+	if let Some(required) = &cli.required_version {
+		version::check_required(required)?;
+	}
+
+	if cli.offline {
+		fama_common::set_offline();
+	}
+
+	// Removed when main returns or unwinds; exit() skips it, so it is
+	// dropped by hand before a non-zero exit
+	let temp_dir = match temp::root().and_then(|r| temp::RunTempDir::create(&r))
+	{
+		Ok(dir) => {
+			fama_common::set_temp_dir(dir.path().to_path_buf());
+			Some(dir)
+		}
+		Err(e) => {
+			tracing::debug!("Using the system temp dir: {}", e);
+			None
+		}
+	};
+
+	match &cli.command {
+		Some(Command::Explain { path }) => {
+			explain::run(path);
+			return Ok(());
+		}
+		Some(Command::Config {
+			resolved,
+			default,
+			schema,
+		}) => {
+			let dump = if *default {
+				config::Dump::Default
+			} else if *schema {
+				config::Dump::Schema
+			} else {
+				config::Dump::Resolved(resolved.clone())
+			};
+			return config::run(&dump);
+		}
+		Some(Command::AdoptReport { pattern, json }) => {
+			return adopt::run(pattern, *json);
+		}
+		Some(Command::Stats { pattern, json }) => {
+			return coverage::run(pattern, *json);
+		}
+		Some(Command::Migrate {
+			pattern,
+			commit_per_language,
+			write_ignore_revs,
+		}) => {
+			let options = migrate::MigrateOptions {
+				commit_per_language: *commit_per_language,
+				write_ignore_revs: *write_ignore_revs,
+			};
+			return migrate::run(pattern, options);
+		}
+		Some(Command::Export { format }) => {
+			return export::run(*format);
+		}
+		Some(Command::Cache {
+			action: CacheAction::Clean { temp: clean_temp },
+		}) => {
+			let (removed, dir) = if *clean_temp {
+				let root = temp::root()?;
+				(temp::clean(&root)?, root)
+			} else {
+				let dir = dirs::cache_dir()?;
+				(incremental::clean()?, dir)
+			};
+			if !cli.quiet {
+				println!("Removed {} entries from {}", removed, dir.display());
+			}
+			return Ok(());
+		}
+		Some(Command::Serve {
+			port,
+			host,
+			max_concurrent,
+		}) => {
+			let max_concurrent = max_concurrent.unwrap_or_else(|| {
+				std::thread::available_parallelism().map_or(1, |n| n.get())
+			});
+			return http_server::run(
+				&format!("{}:{}", host, port),
+				max_concurrent,
+			);
+		}
+		None => {}
+	}
 
 	if cli.export {
 		editorconfig::export();
 		return Ok(());
 	}
 
-	run(cli)
+	if let Some(path) = &cli.why {
+		return why::run(
+			path,
+			formatter::FormatOptions {
+				fix_indentation: cli.fix_indentation,
+				..Default::default()
+			},
+		);
+	}
+
+	if let Some(path) = &cli.edits {
+		return edits::run(
+			path,
+			formatter::FormatOptions {
+				fix_indentation: cli.fix_indentation,
+				..Default::default()
+			},
+		);
+	}
+
+	if cli.editor_server {
+		return Ok(editor_server::run(formatter::FormatOptions {
+			fix_indentation: cli.fix_indentation,
+			..Default::default()
+		})?);
+	}
+
+	if cli.debug_report {
+		report::install(cli.report_content);
+	}
+
+	cancel::install();
+	let code = run(cli)?;
+	if code != 0 {
+		drop(temp_dir);
+		std::process::exit(code);
+	}
+	Ok(())
 }
 
 /// Statistics collected during formatting
@@ -69,8 +491,26 @@ fn main() -> anyhow::Result<()> {
 struct FormatStats {
 	formatted: usize,
 	unchanged: usize,
+	/// Files in check-only languages that need formatting
+	check_only: usize,
+	/// Changed files the user chose not to write in --interactive mode
+	declined: usize,
+	/// Files in languages whose mode is off
+	skipped: usize,
+	/// Files not formatted because the run was cancelled
+	cancelled: usize,
+	/// Files whose input mixed CRLF and LF line endings
+	mixed_line_endings: Vec<std::path::PathBuf>,
+	/// Files not formatted because they are read-only
+	read_only: Vec<std::path::PathBuf>,
+	/// Files this run left formatted, for --incremental
+	settled: Vec<std::path::PathBuf>,
+	/// Per-file errors, keyed by path so they can be reported in order
+	file_errors: Vec<(std::path::PathBuf, String)>,
 	errors: Vec<String>,
 	formatted_files: Vec<std::path::PathBuf>,
+	run: stats::RunStats,
+	locales: locales::LocaleKeys,
 }
 
 impl FormatStats {
@@ -78,18 +518,52 @@ impl FormatStats {
 	fn merge(mut self, other: FormatStats) -> FormatStats {
 		self.formatted += other.formatted;
 		self.unchanged += other.unchanged;
+		self.check_only += other.check_only;
+		self.declined += other.declined;
+		self.skipped += other.skipped;
+		self.cancelled += other.cancelled;
+		self.mixed_line_endings.extend(other.mixed_line_endings);
+		self.read_only.extend(other.read_only);
+		self.settled.extend(other.settled);
+		self.file_errors.extend(other.file_errors);
 		self.errors.extend(other.errors);
 		self.formatted_files.extend(other.formatted_files);
+		self.run = self.run.merge(other.run);
+		self.locales = self.locales.merge(other.locales);
 		self
 	}
 }
 
-fn run(options: Cli) -> anyhow::Result<()> {
+/// Format or check the files `options` selects, returning the exit code
+fn run(options: Cli) -> anyhow::Result<i32> {
+	let started = Instant::now();
 	let patterns = options.pattern;
 	let debug = options.debug;
 	let check = options.check;
 	let quiet = options.quiet;
+	let format_options = formatter::FormatOptions {
+		check,
+		fix_indentation: options.fix_indentation,
+		force_write: options.force_write,
+		validate: options.validate,
+		interactive: options.interactive,
+		lossy_utf8: options.lossy_utf8,
+		chmod_writable: options.chmod_writable,
+	};
+
+	// Keep two write-mode runs in one project (editor and terminal) from
+	// interleaving their writes; check runs never write, so skip the lock
+	let project_root =
+		|| git::get_git_root().or_else(|_| std::env::current_dir());
+	let _lock = if check || options.no_lock {
+		None
+	} else {
+		Some(lock::acquire(&project_root()?)?)
+	};
+
 	let mut all_files: Vec<std::path::PathBuf> = Vec::new();
+	// Source files nothing formats, with --strict-coverage
+	let mut uncovered: Vec<std::path::PathBuf> = Vec::new();
 
 	// Get files from git if --staged, --changed, or --commit is specified
 	if options.staged || options.changed || options.commit {
@@ -98,64 +572,185 @@ fn run(options: Cli) -> anyhow::Result<()> {
 			if !quiet {
 				println!("No files to format");
 			}
-			return Ok(());
+			return Ok(0);
 		}
 		all_files.extend(git_files);
 	} else {
 		for pattern in &patterns {
 			let files =
-				discovery::discover_files(Some(pattern)).map_err(|e| {
-					anyhow::anyhow!("Failed to discover files: {}", e)
-				})?;
-			if files.is_empty() && !quiet {
-				eprintln!("Warning: pattern '{}' matched 0 files", pattern);
+				discovery::discover_files(Some(pattern), !options.no_hidden)
+					.map_err(|e| {
+						anyhow::anyhow!("Failed to discover files: {}", e)
+					})?;
+			if files.is_empty() {
+				tracing::warn!("pattern '{}' matched 0 files", pattern);
 			}
 			all_files.extend(files);
+			if options.strict_coverage {
+				uncovered.extend(
+					coverage::uncovered_sources(pattern, !options.no_hidden)
+						.map_err(|e| {
+							anyhow::anyhow!("Failed to discover files: {}", e)
+						})?,
+				);
+			}
 		}
 	}
 
 	// Remove duplicates while preserving order
 	let mut seen = std::collections::HashSet::new();
-	let files: Vec<_> = all_files
+	let mut files: Vec<_> = all_files
 		.into_iter()
 		.filter(|p| seen.insert(p.clone()))
 		.collect();
 
-	// Parallel formatting with fold/reduce pattern
-	let mut stats = files
-		.par_iter()
-		.fold(FormatStats::default, |mut stats, file| {
-			match formatter::format_file(file, check) {
-				Ok(true) => {
-					if debug {
-						// Green for formatted files
-						eprintln!(
-							"{}",
-							Color::Green.paint(&file.display().to_string())
-						);
-					}
-					stats.formatted += 1;
-					stats.formatted_files.push(file.clone());
-				}
-				Ok(false) => {
-					if debug {
-						eprintln!("{}", file.display());
-					}
-					stats.unchanged += 1;
+	// Leave out files an earlier --incremental run left formatted
+	let incremental = if options.incremental {
+		let root = project_root()?;
+		Some((incremental::load(&root), root))
+	} else {
+		None
+	};
+	let mut untouched = 0;
+	if let Some((state, _)) = &incremental {
+		let before = files.len();
+		files.retain(|file| state.is_stale(file));
+		untouched = before - files.len();
+	}
+
+	let process = |mut stats: FormatStats, file: &std::path::PathBuf| {
+		if cancel::requested() {
+			stats.cancelled += 1;
+			return stats;
+		}
+		if options.interactive && interactive::quit_requested() {
+			stats.skipped += 1;
+			return stats;
+		}
+		let file_type = detect_file_type(file.to_str().unwrap_or(""));
+		let mode = CONFIG.mode_for(file_type);
+		if mode == LanguageMode::Off {
+			if debug {
+				tracing::info!("{} (off)", file.display());
+			}
+			stats.skipped += 1;
+			return stats;
+		}
+		let file_options = formatter::FormatOptions {
+			check: check || mode == LanguageMode::CheckOnly,
+			..format_options
+		};
+
+		let file_started = Instant::now();
+		let mut bytes = 0;
+		let result =
+			formatter::format_file_counted(file, file_options, &mut bytes);
+		let elapsed = file_started.elapsed();
+		stats.run.record(file_type, bytes, elapsed);
+		if options.check_locales && locales::is_locale_file(file) {
+			if let Ok(content) = std::fs::read_to_string(file) {
+				if let Err(e) = stats.locales.record(file, &content) {
+					tracing::warn!(
+						"{}: not checked for locale keys: {}",
+						file.display(),
+						e
+					);
 				}
-				Err(e) => {
-					if debug {
-						eprintln!(
-							"{}",
-							Color::Red.paint(&file.display().to_string())
-						);
-					}
-					stats.errors.push(e.to_string());
+			}
+		}
+		if let Ok(Outcome::MixedLineEndings) = result {
+			stats.mixed_line_endings.push(file.clone());
+		}
+		let status = match result {
+			Ok(Outcome::Unchanged) => {
+				stats.unchanged += 1;
+				stats.settled.push(file.clone());
+				"unchanged"
+			}
+			Ok(Outcome::ReadOnly) => {
+				stats.read_only.push(file.clone());
+				"read-only"
+			}
+			Ok(Outcome::Declined) => {
+				stats.declined += 1;
+				"declined"
+			}
+			Ok(_) if mode == LanguageMode::CheckOnly => {
+				stats.check_only += 1;
+				"check-only"
+			}
+			Ok(_) => {
+				stats.formatted += 1;
+				stats.formatted_files.push(file.clone());
+				if check {
+					"unformatted"
+				} else {
+					stats.settled.push(file.clone());
+					"formatted"
 				}
 			}
-			stats
-		})
-		.reduce(FormatStats::default, FormatStats::merge);
+			// Subprocess backends exit on the same Ctrl-C, so their
+			// failures after a cancel are interruptions, not errors
+			Err(_) if cancel::requested() => {
+				stats.cancelled += 1;
+				"cancelled"
+			}
+			Err(e) => {
+				stats.file_errors.push((file.clone(), e.to_string()));
+				"error"
+			}
+		};
+		if options.stream {
+			// One write per line, so parallel workers never interleave
+			let line = format!(
+				"{} {} {}ms\n",
+				file.display(),
+				status,
+				elapsed.as_millis()
+			);
+			let mut stdout = std::io::stdout().lock();
+			let _ = stdout.write_all(line.as_bytes());
+			let _ = stdout.flush();
+		}
+		// The file, then each option that applied and where its value came
+		// from, as one event so parallel workers never interleave
+		if debug {
+			let provenance = explain::provenance(file, &CONFIG);
+			tracing::info!(
+				"{} ({})\n{}",
+				file.display(),
+				status,
+				provenance.trim_end()
+			);
+		}
+		stats
+	};
+
+	// Prompts must come in file order, so interactive runs are sequential;
+	// otherwise format in parallel with the fold/reduce pattern. Parallel
+	// runs take files largest first from a shared queue, so every thread
+	// picks up the next biggest one as it frees up; splitting the list as
+	// `par_iter` does would leave the largest files to one thread
+	let mut stats = if options.interactive {
+		files.iter().fold(FormatStats::default(), process)
+	} else {
+		discovery::largest_first(&mut files);
+		files
+			.iter()
+			.par_bridge()
+			.fold(FormatStats::default, process)
+			.reduce(FormatStats::default, FormatStats::merge)
+	};
+
+	// Merge order depends on rayon scheduling; sort by path so error logs
+	// and staged file lists are identical from run to run
+	stats.file_errors.sort();
+	stats.formatted_files.sort();
+	stats.mixed_line_endings.sort();
+	let file_errors = std::mem::take(&mut stats.file_errors);
+	stats
+		.errors
+		.extend(file_errors.into_iter().map(|(_, error)| error));
 
 	// If --staged was used, automatically re-stage formatted files
 	let restaged_count = if options.staged && !stats.formatted_files.is_empty() {
@@ -170,8 +765,13 @@ fn run(options: Cli) -> anyhow::Result<()> {
 		0
 	};
 
-	// If --commit was used, stage formatted files and commit
-	if options.commit && !stats.formatted_files.is_empty() {
+	// If --commit was used, stage formatted files and commit; a cancelled
+	// run is never committed, since only part of the files were formatted
+	if options.commit && cancel::requested() {
+		stats
+			.errors
+			.push("Run was cancelled, nothing committed".to_string());
+	} else if options.commit && !stats.formatted_files.is_empty() {
 		match git::stage_files(&stats.formatted_files) {
 			Ok(count) if count > 0 => {
 				match git::commit_files("style: fmt") {
@@ -192,6 +792,87 @@ fn run(options: Cli) -> anyhow::Result<()> {
 		}
 	}
 
+	if let Some(path) = &options.stats_file {
+		if let Err(e) = stats.run.write(path, started.elapsed()) {
+			stats
+				.errors
+				.push(format!("Failed to write stats file: {}", e));
+		}
+	}
+
+	if let Some((mut state, root)) = incremental {
+		if !cancel::requested() {
+			state.update(&files, &stats.settled);
+			if let Err(e) = incremental::save(&root, &state) {
+				stats.errors.push(e.to_string());
+			}
+		}
+	}
+
+	// Unformatted files outside the baseline; without one, every file
+	let mut unbaselined = stats.formatted;
+	if let Some(path) = &options.baseline {
+		let mut known = baseline::Baseline::load(path)?;
+		if options.update_baseline && !cancel::requested() {
+			known.update(&files, &stats.formatted_files);
+			known.save()?;
+			if !quiet {
+				let count = known.entries.len();
+				println!("Wrote {} files to {}", count, path.display());
+			}
+			unbaselined = 0;
+		} else {
+			let comparison = known.compare(&files, &stats.formatted_files);
+			for file in &comparison.new {
+				eprintln!(
+					"{}: needs formatting (not in baseline)",
+					file.display()
+				);
+			}
+			if comparison.fixed > 0 {
+				tracing::warn!(
+					"{} files in {} are formatted now; run with \
+					 --update-baseline to drop them",
+					comparison.fixed,
+					path.display()
+				);
+			}
+			unbaselined = comparison.new.len();
+		}
+	}
+
+	uncovered.sort();
+	uncovered.dedup();
+	for path in &uncovered {
+		stats.errors.push(format!(
+			"{}: no formatter for {} files (--strict-coverage)",
+			path.display(),
+			coverage::unknown_key(path)
+		));
+	}
+
+	for warning in stats.locales.warnings() {
+		tracing::warn!("{}", warning);
+	}
+
+	// Check mode reports mixed line endings as their own finding; write
+	// mode has already normalized them
+	if check && !quiet {
+		for path in &stats.mixed_line_endings {
+			eprintln!("{}: mixed CRLF and LF line endings", path.display());
+		}
+	}
+
+	if !quiet {
+		stats.read_only.sort();
+		for path in &stats.read_only {
+			eprintln!(
+				"{}: skipped (read-only), use --chmod-writable to format it",
+				path.display()
+			);
+		}
+	}
+
 	// Print collected errors (always print errors)
 	for error in &stats.errors {
 		eprintln!("Error: {}", error);
@@ -199,31 +880,157 @@ fn run(options: Cli) -> anyhow::Result<()> {
 
 	// Print stats (unless quiet mode)
 	if !quiet {
-		if check {
-			println!(
+		let mut message = if check {
+			format!(
 				"{} files need formatting, {} unchanged, {} errors",
 				stats.formatted,
 				stats.unchanged,
 				stats.errors.len()
-			);
+			)
 		} else {
-			let mut message = format!(
+			format!(
 				"Formatted {} files, {} unchanged, {} errors",
 				stats.formatted,
 				stats.unchanged,
 				stats.errors.len()
-			);
-			if restaged_count > 0 {
-				message.push_str(&format!(", restaged {}", restaged_count));
-			}
-			println!("{}", message);
+			)
+		};
+		if stats.formatted > unbaselined {
+			message.push_str(&format!(
+				", {} of them in the baseline",
+				stats.formatted - unbaselined
+			));
+		}
+		if stats.check_only > 0 {
+			message.push_str(&format!(
+				", {} need formatting (check-only)",
+				stats.check_only
+			));
+		}
+		if stats.declined > 0 {
+			message.push_str(&format!(", {} declined", stats.declined));
+		}
+		if stats.skipped > 0 {
+			message.push_str(&format!(", {} skipped", stats.skipped));
+		}
+		if untouched > 0 {
+			message
+				.push_str(&format!(", {} untouched since last run", untouched));
+		}
+		if !stats.read_only.is_empty() {
+			message.push_str(&format!(
+				", {} skipped (read-only)",
+				stats.read_only.len()
+			));
 		}
+		if !stats.mixed_line_endings.is_empty() {
+			message.push_str(&format!(
+				", {} with mixed line endings",
+				stats.mixed_line_endings.len()
+			));
+		}
+		if stats.cancelled > 0 {
+			message.push_str(&format!(", {} cancelled", stats.cancelled));
+		}
+		if !check && restaged_count > 0 {
+			message.push_str(&format!(", restaged {}", restaged_count));
+		}
+		println!("{}", message);
 	}
 
-	// Exit with non-zero if check mode and files need formatting
-	if check && stats.formatted > 0 {
-		std::process::exit(1);
+	// Exit like an interrupted process once the partial summary is out;
+	// otherwise with non-zero if check mode and files outside the baseline
+	// need formatting, or with --strict-coverage and uncovered files
+	Ok(if cancel::requested() {
+		130
+	} else if (check && unbaselined > 0) || !uncovered.is_empty() {
+		1
+	} else {
+		0
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use clap::CommandFactory;
+
+	#[test]
+	fn test_cli_definition() {
+		Cli::command().debug_assert();
 	}
 
-	Ok(())
+	#[test]
+	fn test_git_filters_are_exclusive() {
+		assert!(Cli::try_parse_from(["fama", "--staged"]).is_ok());
+		assert!(Cli::try_parse_from(["fama", "--staged", "--changed"]).is_err());
+	}
+
+	#[test]
+	fn test_stream_conflicts_with_interactive() {
+		assert!(Cli::try_parse_from(["fama", "--stream", "--check"]).is_ok());
+		assert!(Cli::try_parse_from(["fama", "--stream", "-i"]).is_err());
+	}
+
+	#[test]
+	fn test_explain_subcommand() {
+		let cli = Cli::try_parse_from(["fama", "explain", "a.ts"]).unwrap();
+		assert!(matches!(cli.command, Some(Command::Explain { .. })));
+	}
+
+	#[test]
+	fn test_config_subcommand() {
+		let cli = Cli::try_parse_from([
+			"fama",
+			"config",
+			"--resolved",
+			"a.ts",
+			"b.md",
+		])
+		.unwrap();
+		let Some(Command::Config { resolved, .. }) = cli.command else {
+			panic!("expected config");
+		};
+		assert_eq!(resolved.len(), 2);
+		assert!(Cli::try_parse_from(["fama", "config"]).is_err());
+		assert!(Cli::try_parse_from([
+			"fama",
+			"config",
+			"--default",
+			"--schema"
+		])
+		.is_err());
+	}
+
+	#[test]
+	fn test_adopt_report_subcommand() {
+		let cli =
+			Cli::try_parse_from(["fama", "adopt-report", "--json", "src/**"])
+				.unwrap();
+		let Some(Command::AdoptReport { pattern, json }) = cli.command else {
+			panic!("expected adopt-report");
+		};
+		assert_eq!((pattern, json), (vec!["src/**".to_string()], true));
+	}
+
+	#[test]
+	fn test_cache_subcommand() {
+		let cli =
+			Cli::try_parse_from(["fama", "cache", "clean", "--temp"]).unwrap();
+		assert!(matches!(
+			cli.command,
+			Some(Command::Cache {
+				action: CacheAction::Clean { temp: true }
+			})
+		));
+	}
+
+	#[test]
+	fn test_serve_subcommand() {
+		let cli = Cli::try_parse_from(["fama", "serve"]).unwrap();
+		assert!(matches!(
+			cli.command,
+			Some(Command::Serve { port: 8890, .. })
+		));
+	}
 }
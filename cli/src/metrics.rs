@@ -0,0 +1,164 @@
+// metrics.rs - Prometheus counters for `fama serve`
+
+use fama_common::FileType;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the request latency histogram buckets
+const BUCKETS: [f64; 10] =
+	[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0];
+
+/// Counters for one language
+#[derive(Debug, Default)]
+struct LanguageMetrics {
+	requests: u64,
+	errors: u64,
+	/// Requests per bucket in `BUCKETS`, not cumulative
+	buckets: [u64; BUCKETS.len()],
+	seconds: f64,
+}
+
+/// Request counters shared by all connections of a server
+#[derive(Debug, Default)]
+pub struct Metrics {
+	languages: Mutex<BTreeMap<String, LanguageMetrics>>,
+	rejected: Mutex<u64>,
+	panics: Mutex<u64>,
+}
+
+impl Metrics {
+	/// Record one formatting request
+	pub fn record(&self, file_type: FileType, ok: bool, elapsed: Duration) {
+		let mut languages = self.languages.lock().unwrap();
+		let language = languages.entry(format!("{file_type:?}")).or_default();
+		language.requests += 1;
+		if !ok {
+			language.errors += 1;
+		}
+		let seconds = elapsed.as_secs_f64();
+		if let Some(i) = BUCKETS.iter().position(|&bound| seconds <= bound) {
+			language.buckets[i] += 1;
+		}
+		language.seconds += seconds;
+	}
+
+	/// Record a request turned away because the server was busy
+	pub fn record_rejected(&self) {
+		*self.rejected.lock().unwrap() += 1;
+	}
+
+	/// Record a request whose handler panicked
+	pub fn record_panic(&self) {
+		*self.panics.lock().unwrap() += 1;
+	}
+
+	/// Render in the Prometheus text exposition format.
+	///
+	/// There is no format cache yet, so no cache hit counter is exported.
+	pub fn render(&self) -> String {
+		let languages = self.languages.lock().unwrap();
+		let mut out = String::new();
+
+		out.push_str(
+			"# HELP fama_requests_total Formatting requests by language.\n\
+			 # TYPE fama_requests_total counter\n",
+		);
+		for (name, language) in languages.iter() {
+			let _ = writeln!(
+				out,
+				"fama_requests_total{{language=\"{}\"}} {}",
+				name, language.requests
+			);
+		}
+
+		out.push_str(
+			"# HELP fama_errors_total Formatting requests that failed.\n\
+			 # TYPE fama_errors_total counter\n",
+		);
+		for (name, language) in languages.iter() {
+			let _ = writeln!(
+				out,
+				"fama_errors_total{{language=\"{}\"}} {}",
+				name, language.errors
+			);
+		}
+
+		out.push_str(
+			"# HELP fama_request_duration_seconds Time spent formatting.\n\
+			 # TYPE fama_request_duration_seconds histogram\n",
+		);
+		for (name, language) in languages.iter() {
+			let mut cumulative = 0;
+			for (bound, count) in BUCKETS.iter().zip(language.buckets) {
+				cumulative += count;
+				let _ = writeln!(
+					out,
+					"fama_request_duration_seconds_bucket\
+					 {{language=\"{}\",le=\"{}\"}} {}",
+					name, bound, cumulative
+				);
+			}
+			let _ = writeln!(
+				out,
+				"fama_request_duration_seconds_bucket\
+				 {{language=\"{}\",le=\"+Inf\"}} {}\n\
+				 fama_request_duration_seconds_sum{{language=\"{}\"}} {}\n\
+				 fama_request_duration_seconds_count{{language=\"{}\"}} {}",
+				name,
+				language.requests,
+				name,
+				language.seconds,
+				name,
+				language.requests
+			);
+		}
+
+		let _ = write!(
+			out,
+			"# HELP fama_rejected_total Requests refused because the server \
+			 was busy.\n\
+			 # TYPE fama_rejected_total counter\n\
+			 fama_rejected_total {}\n",
+			self.rejected.lock().unwrap()
+		);
+		let _ = write!(
+			out,
+			"# HELP fama_panics_total Requests whose formatter panicked.\n\
+			 # TYPE fama_panics_total counter\n\
+			 fama_panics_total {}\n",
+			self.panics.lock().unwrap()
+		);
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_render_counts_and_histogram() {
+		let metrics = Metrics::default();
+		metrics.record(FileType::Xml, true, Duration::from_millis(3));
+		metrics.record(FileType::Xml, false, Duration::from_millis(30));
+		metrics.record_rejected();
+		metrics.record_panic();
+
+		let text = metrics.render();
+		for line in [
+			"fama_requests_total{language=\"Xml\"} 2",
+			"fama_errors_total{language=\"Xml\"} 1",
+			"fama_request_duration_seconds_bucket{language=\"Xml\",le=\"0.005\"} 1",
+			"fama_request_duration_seconds_bucket{language=\"Xml\",le=\"0.01\"} 1",
+			"fama_request_duration_seconds_bucket{language=\"Xml\",le=\"0.05\"} 2",
+			"fama_request_duration_seconds_bucket{language=\"Xml\",le=\"+Inf\"} 2",
+			"fama_request_duration_seconds_count{language=\"Xml\"} 2",
+			"fama_rejected_total 1",
+			"fama_panics_total 1",
+		] {
+			assert!(text.lines().any(|l| l == line), "missing {line}:\n{text}");
+		}
+	}
+}
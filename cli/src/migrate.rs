@@ -0,0 +1,268 @@
+// migrate.rs - Blame-friendly bulk formatting for `fama migrate`
+//
+// Formats a whole repository in commits of their own, one per language
+// if asked, and records them in .git-blame-ignore-revs so `git blame`
+// skips straight past the reformat.
+
+use fama_common::{detect_file_type, LanguageMode, CONFIG};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::formatter::{self, FormatOptions, Outcome};
+use crate::{discovery, git};
+
+/// File read by `git blame` when `blame.ignoreRevsFile` points at it
+const IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+
+/// Options for a migration run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MigrateOptions {
+	/// One commit per language instead of a single commit
+	pub commit_per_language: bool,
+	/// Append the formatting commits to .git-blame-ignore-revs and commit it
+	pub write_ignore_revs: bool,
+}
+
+/// Group formattable files by language name, skipping languages that are
+/// off or check-only
+fn group_by_language(files: Vec<PathBuf>) -> BTreeMap<String, Vec<PathBuf>> {
+	let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+	for file in files {
+		let file_type = detect_file_type(file.to_str().unwrap_or(""));
+		if CONFIG.mode_for(file_type) == LanguageMode::Format {
+			groups
+				.entry(format!("{file_type:?}"))
+				.or_default()
+				.push(file);
+		}
+	}
+	groups
+}
+
+/// Format every file in check mode, failing with all errors at once so a
+/// file that can't be formatted stops the migration before anything is
+/// written
+fn check_files(files: &[PathBuf]) -> anyhow::Result<()> {
+	let options = FormatOptions {
+		check: true,
+		..FormatOptions::default()
+	};
+	let errors: Vec<String> = files
+		.par_iter()
+		.filter_map(|file| formatter::format_file(file, options).err())
+		.map(|e| e.to_string())
+		.collect();
+	fail_on(errors, "nothing was written")
+}
+
+/// Format `files` in place and return the ones that changed
+fn format_files(files: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+	let results: Vec<(&PathBuf, anyhow::Result<Outcome>)> = files
+		.par_iter()
+		.map(|file| {
+			(file, formatter::format_file(file, FormatOptions::default()))
+		})
+		.collect();
+
+	let mut changed = Vec::new();
+	let mut errors = Vec::new();
+	for (file, result) in results {
+		match result {
+			Ok(Outcome::Changed | Outcome::MixedLineEndings) => {
+				changed.push(file.clone());
+			}
+			Ok(Outcome::ReadOnly) => {
+				tracing::warn!("{}: skipped (read-only)", file.display());
+			}
+			Ok(Outcome::Unchanged | Outcome::Declined) => {}
+			Err(e) => errors.push(e.to_string()),
+		}
+	}
+	fail_on(
+		errors,
+		"formatted files were left in the working tree, uncommitted",
+	)?;
+	Ok(changed)
+}
+
+/// Fail with every error in `errors`, if any
+fn fail_on(errors: Vec<String>, consequence: &str) -> anyhow::Result<()> {
+	if errors.is_empty() {
+		return Ok(());
+	}
+	anyhow::bail!(
+		"{} files failed to format; {}:\n{}",
+		errors.len(),
+		consequence,
+		errors.join("\n")
+	)
+}
+
+/// Lines to append to .git-blame-ignore-revs for `commits`
+fn ignore_revs_entry(commits: &[(String, String)]) -> String {
+	commits
+		.iter()
+		.map(|(message, hash)| format!("# {}\n{}\n", message, hash))
+		.collect()
+}
+
+/// Append `commits` to the ignore-revs file in `root`, starting on a new
+/// line if the file doesn't end with one
+fn append_ignore_revs(
+	root: &Path,
+	commits: &[(String, String)],
+) -> anyhow::Result<PathBuf> {
+	let path = root.join(IGNORE_REVS_FILE);
+	let existing = fs::read_to_string(&path).unwrap_or_default();
+	let mut file = fs::OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(&path)?;
+	if !existing.is_empty() && !existing.ends_with('\n') {
+		file.write_all(b"\n")?;
+	}
+	file.write_all(ignore_revs_entry(commits).as_bytes())?;
+	Ok(path)
+}
+
+/// Format files matching `patterns` and commit the result
+pub fn run(patterns: &[String], options: MigrateOptions) -> anyhow::Result<()> {
+	let root = git::get_git_root()?;
+	if !git::is_clean()? {
+		anyhow::bail!(
+			"Working tree has uncommitted changes; commit or stash them \
+			 before migrating so formatting commits contain only formatting"
+		);
+	}
+
+	let mut files = Vec::new();
+	for pattern in patterns {
+		files.extend(
+			discovery::discover_files(Some(pattern), true).map_err(|e| {
+				anyhow::anyhow!("Failed to discover files: {}", e)
+			})?,
+		);
+	}
+	// Only tracked files, so the commits don't add files git ignored or
+	// the user hasn't added yet
+	let tracked = git::tracked_files()?;
+	files.retain(|file| tracked.contains(&git::normalize(file)));
+	files.sort();
+	files.dedup();
+
+	let groups: Vec<(String, Vec<PathBuf>)> = if options.commit_per_language {
+		group_by_language(files)
+			.into_iter()
+			.map(|(language, files)| {
+				(format!("style: fmt {}", language), files)
+			})
+			.collect()
+	} else {
+		let files = group_by_language(files).into_values().flatten().collect();
+		vec![("style: fmt".to_string(), files)]
+	};
+
+	let all: Vec<PathBuf> = groups
+		.iter()
+		.flat_map(|(_, files)| files)
+		.cloned()
+		.collect();
+	check_files(&all)?;
+
+	let mut commits = Vec::new();
+	for (message, files) in groups {
+		let changed = format_files(&files)?;
+		if changed.is_empty() {
+			continue;
+		}
+		git::stage_files(&changed)?;
+		git::commit_files(&message)?;
+		let hash = git::head_commit()?;
+		println!("{} {} ({} files)", &hash[..12], message, changed.len());
+		commits.push((message, hash));
+	}
+
+	if commits.is_empty() {
+		println!("Already formatted, nothing to commit");
+		return Ok(());
+	}
+
+	if options.write_ignore_revs {
+		let path = append_ignore_revs(&root, &commits)?;
+		git::stage_files(&[path])?;
+		git::commit_files(&format!(
+			"chore: add fama formatting commits to {}",
+			IGNORE_REVS_FILE
+		))?;
+		println!(
+			"Recorded {} commits in {}; enable it with:\n  \
+			 git config blame.ignoreRevsFile {}",
+			commits.len(),
+			IGNORE_REVS_FILE,
+			IGNORE_REVS_FILE
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_group_by_language() {
+		let groups = group_by_language(vec![
+			PathBuf::from("a.ts"),
+			PathBuf::from("b.json"),
+			PathBuf::from("c.ts"),
+		]);
+		assert_eq!(groups.keys().collect::<Vec<_>>(), ["Json", "TypeScript"]);
+		assert_eq!(groups["TypeScript"].len(), 2);
+	}
+
+	#[test]
+	fn test_failed_check_writes_nothing() {
+		let temp_dir = TempDir::new().unwrap();
+		let good = temp_dir.path().join("good.xml");
+		let bad = temp_dir.path().join("bad.xml");
+		fs::write(&good, "<a><b/></a>").unwrap();
+		fs::write(&bad, "<a><b></a>").unwrap();
+
+		let error = check_files(&[good.clone(), bad.clone()]).unwrap_err();
+		assert!(error.to_string().starts_with("1 files failed to format"));
+		assert!(error.to_string().contains("bad.xml"));
+		assert_eq!(fs::read_to_string(&good).unwrap(), "<a><b/></a>");
+	}
+
+	#[test]
+	fn test_normalize() {
+		assert_eq!(
+			git::normalize(Path::new("./src/./a.rs")),
+			PathBuf::from("src/a.rs")
+		);
+	}
+
+	#[test]
+	fn test_append_ignore_revs() {
+		let temp_dir = TempDir::new().unwrap();
+		let path = temp_dir.path().join(IGNORE_REVS_FILE);
+		fs::write(&path, "# older reformat\nabc123").unwrap();
+
+		let commits = [
+			("style: fmt Json".to_string(), "1111".to_string()),
+			("style: fmt Xml".to_string(), "2222".to_string()),
+		];
+		append_ignore_revs(temp_dir.path(), &commits).unwrap();
+
+		assert_eq!(
+			fs::read_to_string(&path).unwrap(),
+			"# older reformat\nabc123\n\
+			 # style: fmt Json\n1111\n\
+			 # style: fmt Xml\n2222\n"
+		);
+	}
+}
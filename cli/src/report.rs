@@ -0,0 +1,170 @@
+// report.rs - Crash-report bundles for --debug-report
+
+use fama_common::{detect_file_type, CONFIG};
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::backend::backend_for;
+use crate::fnv::fnv1a64;
+use crate::temp;
+
+/// Settings for bundle generation, fixed once per run
+#[derive(Debug, Clone, Copy)]
+struct ReportSettings {
+	/// Copy the offending file into the bundle instead of only its hash
+	include_content: bool,
+}
+
+static SETTINGS: OnceLock<ReportSettings> = OnceLock::new();
+
+thread_local! {
+	/// File currently being formatted on this thread
+	static CURRENT_FILE: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Enable bundle generation and hook panics so they produce one too
+pub fn install(include_content: bool) {
+	let settings = ReportSettings { include_content };
+	if SETTINGS.set(settings).is_err() {
+		return;
+	}
+
+	let default_hook = std::panic::take_hook();
+	std::panic::set_hook(Box::new(move |info| {
+		default_hook(info);
+		let Some(file) = CURRENT_FILE.with(|f| f.borrow().clone()) else {
+			return;
+		};
+		let backtrace = Backtrace::force_capture();
+		let message = format!("panic: {info}\n\nbacktrace:\n{backtrace}");
+		announce(write_bundle(&file, &message, settings));
+	}));
+}
+
+/// Marks a file as in-progress on this thread until dropped
+pub struct CurrentFile;
+
+impl CurrentFile {
+	pub fn enter(path: &Path) -> Self {
+		CURRENT_FILE.with(|f| *f.borrow_mut() = Some(path.to_path_buf()));
+		CurrentFile
+	}
+}
+
+impl Drop for CurrentFile {
+	fn drop(&mut self) {
+		CURRENT_FILE.with(|f| *f.borrow_mut() = None);
+	}
+}
+
+/// Write a bundle for a backend error, if --debug-report is enabled
+pub fn backend_error(path: &Path, error: &str) {
+	if let Some(&settings) = SETTINGS.get() {
+		announce(write_bundle(path, &format!("error: {error}"), settings));
+	}
+}
+
+fn announce(result: std::io::Result<PathBuf>) {
+	match result {
+		Ok(dir) => eprintln!("Debug report written to {}", dir.display()),
+		Err(e) => eprintln!("Failed to write debug report: {}", e),
+	}
+}
+
+/// Write a report bundle into a fresh directory under fama's temp dir
+fn write_bundle(
+	path: &Path,
+	message: &str,
+	settings: ReportSettings,
+) -> std::io::Result<PathBuf> {
+	let timestamp = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs());
+	let dir = tempfile::Builder::new()
+		.prefix(&format!("report-{timestamp}-"))
+		.tempdir_in(temp::root()?)?
+		.keep();
+
+	let file_type = detect_file_type(path.to_str().unwrap_or(""));
+	let content = fs::read(path).ok();
+	let input = match &content {
+		Some(bytes) => {
+			format!("{} bytes, fnv1a64 {:016x}", bytes.len(), fnv1a64(bytes))
+		}
+		None => "unreadable".to_string(),
+	};
+
+	let report = format!(
+		"fama {version}\n\
+		 os: {os}/{arch}\n\
+		 file: {file}\n\
+		 file type: {file_type:?}\n\
+		 backend: {backend}\n\
+		 input: {input}\n\n\
+		 {message}\n\n\
+		 config:\n{config:#?}\n",
+		version = env!("CARGO_PKG_VERSION"),
+		os = std::env::consts::OS,
+		arch = std::env::consts::ARCH,
+		file = path.display(),
+		backend = backend_for(file_type).name(),
+		config = CONFIG,
+	);
+	fs::write(dir.join("report.txt"), report)?;
+
+	if settings.include_content {
+		if let Some(bytes) = &content {
+			let name = path.file_name().unwrap_or("input".as_ref());
+			fs::write(dir.join(name), bytes)?;
+		}
+	}
+
+	Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_write_bundle_hash_only() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("broken.ts");
+		fs::write(&file_path, "const = ;").unwrap();
+
+		let settings = ReportSettings {
+			include_content: false,
+		};
+		let dir = write_bundle(&file_path, "error: boom", settings).unwrap();
+
+		let report = fs::read_to_string(dir.join("report.txt")).unwrap();
+		assert!(report.contains("backend: biome"));
+		assert!(report.contains("file type: TypeScript"));
+		assert!(report.contains("input: 9 bytes, fnv1a64 "));
+		assert!(report.contains("error: boom"));
+		assert!(report.contains("config:"));
+		assert!(!dir.join("broken.ts").exists());
+		fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn test_write_bundle_with_content() {
+		let temp_dir = TempDir::new().unwrap();
+		let file_path = temp_dir.path().join("broken.ts");
+		fs::write(&file_path, "const = ;").unwrap();
+
+		let settings = ReportSettings {
+			include_content: true,
+		};
+		let dir = write_bundle(&file_path, "error: boom", settings).unwrap();
+
+		let copied = fs::read_to_string(dir.join("broken.ts")).unwrap();
+		assert_eq!(copied, "const = ;");
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
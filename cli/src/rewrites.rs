@@ -0,0 +1,114 @@
+// rewrites.rs - Reversible whole-line rewrites around a backend
+//
+// Some fama features work by writing comment lines a backend understands
+// before it runs, and undoing them afterwards. Only the lines fama wrote
+// are undone: identical lines the user wrote are recorded too, so they can
+// be told apart by position once the backend has re-indented everything.
+
+const MISMATCH: &str =
+	"backend moved a comment fama added, so it can't be undone";
+
+/// What to do with a line fama wrote once the backend has run
+#[derive(Debug, Clone, PartialEq)]
+pub enum Undo {
+	/// Put the user's text back, at the line's new indentation
+	Replace(String),
+	/// Drop the line, which fama added
+	Remove,
+}
+
+/// Source for a backend, plus what's needed to undo fama's lines in its
+/// output
+pub struct Prepared {
+	pub source: String,
+	/// One entry per tracked line of `source`, in order; `None` for the
+	/// ones the user wrote
+	undo: Vec<Option<Undo>>,
+	/// Whether a trimmed line is one fama may have written
+	tracked: fn(&str) -> bool,
+}
+
+impl Prepared {
+	pub fn new(capacity: usize, tracked: fn(&str) -> bool) -> Self {
+		Prepared {
+			source: String::with_capacity(capacity),
+			undo: Vec::new(),
+			tracked,
+		}
+	}
+
+	/// Append a line as the user wrote it
+	pub fn push_line(&mut self, line: &str) {
+		if (self.tracked)(line.trim()) {
+			self.undo.push(None);
+		}
+		self.source.push_str(line);
+	}
+
+	/// Append a line fama wrote, undone by `undo` after formatting
+	pub fn push_rewritten(&mut self, line: &str, undo: Undo) {
+		debug_assert!((self.tracked)(line.trim()));
+		self.undo.push(Some(undo));
+		self.source.push_str(line);
+	}
+
+	/// Whether any line was rewritten or added
+	pub fn is_rewritten(&self) -> bool {
+		self.undo.iter().any(Option::is_some)
+	}
+
+	/// Undo fama's lines in the backend's output. Fails if the backend
+	/// added, removed or merged tracked lines, since they can no longer be
+	/// matched up
+	pub fn restore(&self, formatted: &str) -> Result<String, String> {
+		let mut out = String::with_capacity(formatted.len());
+		let mut undo = self.undo.iter();
+		for line in formatted.split_inclusive('\n') {
+			if !(self.tracked)(line.trim()) {
+				out.push_str(line);
+				continue;
+			}
+			match undo.next() {
+				Some(None) => out.push_str(line),
+				Some(Some(Undo::Remove)) => {}
+				Some(Some(Undo::Replace(text))) => {
+					let body = line.trim_end_matches(['\r', '\n']);
+					let indent = &body[..body.len() - body.trim_start().len()];
+					out.push_str(indent);
+					out.push_str(text);
+					out.push_str(&line[body.len()..]);
+				}
+				None => return Err(MISMATCH.to_string()),
+			}
+		}
+		if undo.next().is_some() {
+			return Err(MISMATCH.to_string());
+		}
+		Ok(out)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn is_marker(line: &str) -> bool {
+		line == "// marker"
+	}
+
+	#[test]
+	fn test_restore_only_undoes_own_lines() {
+		let mut prepared = Prepared::new(0, is_marker);
+		prepared.push_line("// marker\n");
+		prepared.push_rewritten("// marker\n", Undo::Replace("// m".into()));
+		prepared.push_line("x\n");
+		prepared.push_rewritten("// marker\n", Undo::Remove);
+		assert!(prepared.is_rewritten());
+		assert_eq!(
+			prepared.restore("// marker\n\t// marker\r\nx\n// marker\n"),
+			Ok("// marker\n\t// m\r\nx\n".to_string())
+		);
+		assert!(prepared.restore("// marker\nx\n").is_err());
+		assert!(prepared.restore(&"// marker\n".repeat(4)).is_err());
+	}
+}
@@ -0,0 +1,104 @@
+// risk.rs - Review warnings for risky changes in check mode
+//
+// Most formatting only moves whitespace. Re-serializing YAML, rewriting
+// XML attributes or sorting JSON keys changes the text itself, and can
+// change what it means. `--check` flags files where formatting would
+// change more than `review_threshold` percent of the non-whitespace text,
+// or would change how many comments there are, so someone looks at them
+// before a write-mode run.
+
+use fama_common::{FileType, CONFIG};
+use similar::{DiffTag, TextDiff};
+use std::time::Duration;
+
+use crate::comments;
+
+/// Longer diffs are cut short and count as fully changed
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Reasons a human should review formatting `original` to `formatted`
+/// before it is written; empty when nothing stands out
+pub fn assess(
+	file_type: FileType,
+	original: &str,
+	formatted: &str,
+) -> Vec<String> {
+	let mut reasons = Vec::new();
+
+	let changed = changed_percent(original, formatted);
+	if changed > usize::from(CONFIG.review_threshold) {
+		reasons
+			.push(format!("{}% of the non-whitespace text changes", changed));
+	}
+
+	let before = comments::count(file_type, original);
+	let after = comments::count(file_type, formatted);
+	if let (Some(before), Some(after)) = (before, after) {
+		if before != after {
+			reasons.push(format!("comments go from {} to {}", before, after));
+		}
+	}
+
+	reasons
+}
+
+/// Percent of the non-whitespace characters of `original` that are
+/// removed or replaced in `formatted`, rounded up
+fn changed_percent(original: &str, formatted: &str) -> usize {
+	let old: String = original.chars().filter(|c| !c.is_whitespace()).collect();
+	let new: String =
+		formatted.chars().filter(|c| !c.is_whitespace()).collect();
+	if old == new {
+		return 0;
+	}
+	if old.is_empty() {
+		return 100;
+	}
+
+	let diff = TextDiff::configure()
+		.timeout(DIFF_TIMEOUT)
+		.diff_chars(&old, &new);
+	let changed: usize = diff
+		.ops()
+		.iter()
+		.filter(|op| op.tag() != DiffTag::Equal)
+		.map(|op| op.old_range().len())
+		.sum();
+	let total = old.chars().count();
+	// Pure insertions change nothing of the original but still count
+	(changed.max(1) * 100).div_ceil(total).min(100)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_whitespace_changes_are_not_risky() {
+		let original = "a:   1\nb:\n    - x\n";
+		let formatted = "a: 1\nb:\n  - x\n";
+		assert!(assess(FileType::Yaml, original, formatted).is_empty());
+	}
+
+	#[test]
+	fn test_rewritten_text_is_risky() {
+		let original = "{\"b\": 1, \"a\": 2}";
+		let formatted = "{\"a\": 2, \"b\": 1}";
+		assert_eq!(changed_percent(original, formatted), 31);
+		assert_eq!(
+			assess(FileType::Json, original, formatted),
+			["31% of the non-whitespace text changes"]
+		);
+		assert_eq!(changed_percent("abcdefghij", "abcdefghijk"), 10);
+	}
+
+	#[test]
+	fn test_lost_comments_are_risky() {
+		let original = "# keep\nname: fama\nversion: 1\nlicense: MIT # why\n";
+		let formatted = "# keep\nname: fama\nversion: 1\nlicense: MIT\n";
+		assert_eq!(
+			assess(FileType::Yaml, original, formatted),
+			["comments go from 2 to 1"]
+		);
+	}
+}
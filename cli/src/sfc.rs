@@ -0,0 +1,122 @@
+// sfc.rs - Per-block formatting for Vue and Svelte components
+//
+// The SFC backends lay out the markup only. Each `<script>` and `<style>`
+// block is then formatted by the backend for its `lang` attribute, so
+// `<script setup lang="tsx">` goes through Biome's TSX formatter and
+// `<style lang="scss">` through Malva. Blocks in other languages, or that
+// fail to format, are left as written.
+
+use biome::sfc;
+use fama_common::{LineEnding, CONFIG};
+
+use crate::backend::backend_for;
+
+const NEWLINE: &str = match CONFIG.line_ending {
+	LineEnding::Lf | LineEnding::Auto => "\n",
+	LineEnding::Crlf => "\r\n",
+};
+
+/// Format every script and style block of a component with the backend
+/// for its language, keeping the block's own indentation
+pub fn format_blocks(content: &str, path: &str) -> String {
+	let mut result = String::with_capacity(content.len());
+	let mut last = 0;
+
+	for block in sfc::blocks(content) {
+		let Some(file_type) = block.file_type() else {
+			continue;
+		};
+		let text = &content[block.content.clone()];
+		if text.trim().is_empty() {
+			continue;
+		}
+		// Formatting the dedented text keeps multi-line strings stable
+		let indent = common_indent(text);
+		let dedented = reindent(text, indent, "");
+		let Ok(formatted) =
+			backend_for(file_type).format(&dedented, path, file_type)
+		else {
+			continue;
+		};
+
+		result.push_str(&content[last..block.content.start]);
+		result.push_str(NEWLINE);
+		result.push_str(&reindent(formatted.trim_end(), "", indent));
+		result.push_str(NEWLINE);
+		last = block.content.end;
+	}
+
+	result.push_str(&content[last..]);
+	result
+}
+
+/// Leading whitespace shared by every non-blank line of `text`
+fn common_indent(text: &str) -> &str {
+	text.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| &line[..line.len() - line.trim_start().len()])
+		.reduce(|common, indent| {
+			let shared = common
+				.bytes()
+				.zip(indent.bytes())
+				.take_while(|(a, b)| a == b)
+				.count();
+			&common[..shared]
+		})
+		.unwrap_or("")
+}
+
+/// `text` with `from` stripped from and `to` added to each non-blank line,
+/// dropping leading and trailing blank lines
+fn reindent(text: &str, from: &str, to: &str) -> String {
+	let lines: Vec<String> = text
+		.trim_matches(['\r', '\n'])
+		.lines()
+		.map(|line| {
+			if line.trim().is_empty() {
+				String::new()
+			} else {
+				format!("{}{}", to, line.strip_prefix(from).unwrap_or(line))
+			}
+		})
+		.collect();
+	lines.join(NEWLINE)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_common_indent() {
+		assert_eq!(common_indent("\n\tconst a = 1;\n\t\tb();\n"), "\t");
+		assert_eq!(common_indent("  a\n\n    b\n"), "  ");
+		assert_eq!(common_indent("a\n  b\n"), "");
+	}
+
+	#[test]
+	fn test_reindent() {
+		assert_eq!(reindent("\n\ta\n\n\t\tb\n", "\t", ""), "a\n\n\tb");
+		assert_eq!(reindent("a\n\tb", "", "  "), "  a\n  \tb");
+	}
+
+	#[test]
+	fn test_format_blocks_by_lang() {
+		let content = concat!(
+			"<script setup lang=\"tsx\">\n",
+			"const a:number=1\n",
+			"</script>\n",
+			"<style lang=\"stylus\">\n",
+			"a\n  color red\n",
+			"</style>\n",
+		);
+		let result = format_blocks(content, "App.vue");
+		assert!(result.starts_with(concat!(
+			"<script setup lang=\"tsx\">\n",
+			"const a: number = 1;\n",
+			"</script>\n",
+		)));
+		assert!(result
+			.ends_with("<style lang=\"stylus\">\na\n  color red\n</style>\n"));
+	}
+}
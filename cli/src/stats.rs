@@ -0,0 +1,133 @@
+// stats.rs - Run statistics for --stats-file
+
+use fama_common::FileType;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::backend::backend_for;
+
+/// Time and file count spent in one backend
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BackendStats {
+	pub files: usize,
+	pub elapsed: Duration,
+}
+
+/// Per-language and per-backend totals collected during a run
+#[derive(Debug, Default)]
+pub struct RunStats {
+	pub files: usize,
+	pub bytes: u64,
+	pub languages: BTreeMap<String, usize>,
+	pub backends: BTreeMap<&'static str, BackendStats>,
+}
+
+impl RunStats {
+	/// Record one processed file
+	pub fn record(
+		&mut self,
+		file_type: FileType,
+		bytes: u64,
+		elapsed: Duration,
+	) {
+		self.files += 1;
+		self.bytes += bytes;
+		*self.languages.entry(format!("{file_type:?}")).or_default() += 1;
+		let backend = self
+			.backends
+			.entry(backend_for(file_type).name())
+			.or_default();
+		backend.files += 1;
+		backend.elapsed += elapsed;
+	}
+
+	/// Merge two RunStats instances (used in parallel reduce)
+	pub fn merge(mut self, other: RunStats) -> RunStats {
+		self.files += other.files;
+		self.bytes += other.bytes;
+		for (language, count) in other.languages {
+			*self.languages.entry(language).or_default() += count;
+		}
+		for (name, stats) in other.backends {
+			let backend = self.backends.entry(name).or_default();
+			backend.files += stats.files;
+			backend.elapsed += stats.elapsed;
+		}
+		self
+	}
+
+	/// Render as JSON; `wall_time` covers the whole run
+	pub fn to_json(&self, wall_time: Duration) -> serde_json::Value {
+		let backends: serde_json::Map<_, _> = self
+			.backends
+			.iter()
+			.map(|(name, stats)| {
+				let value = serde_json::json!({
+					"files": stats.files,
+					"seconds": stats.elapsed.as_secs_f64(),
+				});
+				(name.to_string(), value)
+			})
+			.collect();
+
+		serde_json::json!({
+			"version": env!("CARGO_PKG_VERSION"),
+			"files": self.files,
+			"bytes": self.bytes,
+			"wall_time_seconds": wall_time.as_secs_f64(),
+			"languages": self.languages,
+			"backends": backends,
+			// fama has no result cache, so there is no hit rate to report
+			"cache_hit_rate": serde_json::Value::Null,
+		})
+	}
+
+	/// Write the JSON report to `path`
+	pub fn write(
+		&self,
+		path: &Path,
+		wall_time: Duration,
+	) -> anyhow::Result<()> {
+		let json = serde_json::to_string_pretty(&self.to_json(wall_time))?;
+		std::fs::write(path, json + "\n")?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_record_and_merge() {
+		let mut a = RunStats::default();
+		a.record(FileType::TypeScript, 10, Duration::from_millis(5));
+		a.record(FileType::Json, 2, Duration::from_millis(1));
+		let mut b = RunStats::default();
+		b.record(FileType::TypeScript, 30, Duration::from_millis(10));
+
+		let merged = a.merge(b);
+		assert_eq!(merged.files, 3);
+		assert_eq!(merged.bytes, 42);
+		assert_eq!(merged.languages["TypeScript"], 2);
+		assert_eq!(merged.languages["Json"], 1);
+		assert_eq!(merged.backends["biome"].files, 3);
+		assert_eq!(merged.backends["biome"].elapsed, Duration::from_millis(16));
+	}
+
+	#[test]
+	fn test_to_json() {
+		let mut stats = RunStats::default();
+		stats.record(FileType::Rust, 100, Duration::from_millis(250));
+
+		let json = stats.to_json(Duration::from_secs(1));
+		assert_eq!(json["files"], 1);
+		assert_eq!(json["bytes"], 100);
+		assert_eq!(json["wall_time_seconds"], 1.0);
+		assert_eq!(json["languages"]["Rust"], 1);
+		assert_eq!(json["backends"]["rustfmt"]["files"], 1);
+		assert_eq!(json["backends"]["rustfmt"]["seconds"], 0.25);
+		assert!(json["cache_hit_rate"].is_null());
+	}
+}
@@ -0,0 +1,195 @@
+// temp.rs - One temp dir per run, removed when the run ends
+//
+// Temp files that backends need during a run, such as the copies that
+// process backends format in place, go in a randomly named directory of
+// the run's own under `<temp dir>/fama-<uid>`, which only its owner can
+// read or write. Dropping the `RunTempDir` removes it, which covers normal
+// exits, errors and panics. A run that is killed leaves its
+// directory behind; `fama cache clean --temp` removes every directory
+// whose run no longer holds its lock, and the crash-report bundles kept
+// next to them.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Held by a live run inside its directory
+const LOCK_FILE: &str = ".lock";
+
+/// Where every run directory and crash-report bundle goes, created if
+/// needed. Fails if the directory exists but isn't the current user's
+/// private one
+pub fn root() -> io::Result<PathBuf> {
+	#[cfg(unix)]
+	{
+		// SAFETY: geteuid has no preconditions and can't fail
+		let uid = unsafe { libc::geteuid() };
+		let root = std::env::temp_dir().join(format!("fama-{uid}"));
+		ensure_private(&root, uid)?;
+		Ok(root)
+	}
+	#[cfg(not(unix))]
+	{
+		// The temp dir is already per user
+		let root = std::env::temp_dir().join("fama");
+		fs::create_dir_all(&root)?;
+		Ok(root)
+	}
+}
+
+/// Create `dir` with mode 0700, or check that an existing one is a real
+/// directory owned by `uid` that no one else can access. Anyone can
+/// create names in the system temp dir, and a directory planted there by
+/// another user could redirect what runs write, or what `clean` deletes
+#[cfg(unix)]
+fn ensure_private(dir: &Path, uid: u32) -> io::Result<()> {
+	use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+	match fs::DirBuilder::new().mode(0o700).create(dir) {
+		Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+		_ => {}
+	}
+	let meta = fs::symlink_metadata(dir)?;
+	if !meta.is_dir() || meta.uid() != uid || meta.mode() & 0o077 != 0 {
+		return Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			format!(
+				"{} is not a directory private to user {}",
+				dir.display(),
+				uid
+			),
+		));
+	}
+	Ok(())
+}
+
+/// The temp dir of this run, removed with everything in it when dropped
+#[derive(Debug)]
+pub struct RunTempDir {
+	path: PathBuf,
+	lock: Option<File>,
+}
+
+impl RunTempDir {
+	/// Create a fresh, randomly named directory under `root` and lock it
+	/// as in use
+	pub fn create(root: &Path) -> io::Result<Self> {
+		let path = tempfile::Builder::new()
+			.prefix(&format!("run-{}-", std::process::id()))
+			.tempdir_in(root)?
+			.keep();
+		let lock = OpenOptions::new()
+			.create(true)
+			.truncate(false)
+			.write(true)
+			.open(path.join(LOCK_FILE))?;
+		// Without file locks a live run can't be told from a dead one, so
+		// `clean` may remove this directory while it is in use
+		let _ = lock.try_lock();
+		Ok(RunTempDir {
+			path,
+			lock: Some(lock),
+		})
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for RunTempDir {
+	fn drop(&mut self) {
+		// Close the lock first; Windows can't remove an open file
+		drop(self.lock.take());
+		if let Err(e) = fs::remove_dir_all(&self.path) {
+			tracing::debug!("Failed to remove {}: {}", self.path.display(), e);
+		}
+	}
+}
+
+/// Whether a run still holds the directory at `path`
+fn in_use(path: &Path) -> bool {
+	let Ok(lock) = File::open(path.join(LOCK_FILE)) else {
+		return false;
+	};
+	matches!(lock.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+/// Remove the directories under `root` except those of live runs,
+/// returning how many were removed. Files are left alone: they are
+/// `--incremental` records kept here when there is no cache dir
+pub fn clean(root: &Path) -> io::Result<usize> {
+	let entries = match fs::read_dir(root) {
+		Ok(entries) => entries,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+		Err(e) => return Err(e),
+	};
+	let mut removed = 0;
+	for entry in entries {
+		let path = entry?.path();
+		if !path.is_dir() || in_use(&path) {
+			continue;
+		}
+		fs::remove_dir_all(&path)?;
+		removed += 1;
+	}
+	Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	#[test]
+	fn test_run_temp_dir_is_removed_on_drop() {
+		let root = TempDir::new().unwrap();
+		let run = RunTempDir::create(root.path()).unwrap();
+		let path = run.path().to_path_buf();
+		fs::write(path.join("a.kt"), "").unwrap();
+		assert!(path.starts_with(root.path()));
+
+		drop(run);
+		assert!(!path.exists());
+	}
+
+	#[test]
+	fn test_clean_keeps_live_runs() {
+		let root = TempDir::new().unwrap();
+		let live = RunTempDir::create(root.path()).unwrap();
+		let orphan = root.path().join("run-1-1");
+		fs::create_dir(&orphan).unwrap();
+		fs::write(orphan.join(LOCK_FILE), "").unwrap();
+		fs::create_dir(root.path().join("report-1")).unwrap();
+		let record = root.path().join("fama-_p.state.json");
+		fs::write(&record, "{}").unwrap();
+
+		assert_eq!(clean(root.path()).unwrap(), 2);
+		assert!(live.path().exists());
+		assert!(!orphan.exists());
+		assert!(record.exists());
+		assert_eq!(clean(&root.path().join("missing")).unwrap(), 0);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_root_must_be_private() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let tmp = TempDir::new().unwrap();
+		let uid = unsafe { libc::geteuid() };
+		let dir = tmp.path().join("fama");
+		ensure_private(&dir, uid).unwrap();
+		let mode = fs::metadata(&dir).unwrap().permissions().mode();
+		assert_eq!(mode & 0o777, 0o700);
+		ensure_private(&dir, uid).unwrap();
+		assert!(ensure_private(&dir, uid + 1).is_err());
+
+		fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+		assert!(ensure_private(&dir, uid).is_err());
+
+		let link = tmp.path().join("link");
+		std::os::unix::fs::symlink(&dir, &link).unwrap();
+		assert!(ensure_private(&link, uid).is_err());
+	}
+}
@@ -0,0 +1,109 @@
+// version.rs - Backend versions for `fama --version` and --required-version
+//
+// Output only stays stable while every backend stays the same, so a
+// version here covers the fama release plus a fingerprint of the backend
+// versions it was built with: `0.1.0+<fingerprint>`.
+
+use crate::fnv::fnv1a64;
+
+include!(concat!(env!("OUT_DIR"), "/backend_versions.rs"));
+
+/// Short hash of every backend component and version
+pub fn fingerprint() -> String {
+	let versions: String = BACKEND_VERSIONS
+		.iter()
+		.map(|(backend, component, version)| {
+			format!("{backend} {component} {version}\n")
+		})
+		.collect();
+	format!("{:08x}", fnv1a64(versions.as_bytes()) >> 32)
+}
+
+/// Full version, pinning both fama and its backends
+pub fn full_version() -> String {
+	format!("{}+{}", env!("CARGO_PKG_VERSION"), fingerprint())
+}
+
+pub fn to_json() -> serde_json::Value {
+	let backends: Vec<_> = BACKEND_VERSIONS
+		.iter()
+		.map(|(backend, component, version)| {
+			serde_json::json!({
+				"backend": backend,
+				"component": component,
+				"version": version,
+			})
+		})
+		.collect();
+	serde_json::json!({
+		"version": env!("CARGO_PKG_VERSION"),
+		"fingerprint": fingerprint(),
+		"full_version": full_version(),
+		"backends": backends,
+	})
+}
+
+pub fn to_text() -> String {
+	let width = BACKEND_VERSIONS
+		.iter()
+		.map(|(backend, component, _)| backend.len() + component.len() + 1)
+		.max()
+		.unwrap_or(0);
+	let mut out = format!("fama {}\n", full_version());
+	for (backend, component, version) in BACKEND_VERSIONS {
+		let name = format!("{backend}/{component}");
+		out += &format!("  {:width$}  {}\n", name, version);
+	}
+	out
+}
+
+/// Fail unless this build matches `required`: either a bare fama version
+/// (`0.1.0`) or a full version that also pins the backends
+/// (`0.1.0+1a2b3c4d`)
+pub fn check_required(required: &str) -> anyhow::Result<()> {
+	let actual = match required.contains('+') {
+		true => full_version(),
+		false => env!("CARGO_PKG_VERSION").to_string(),
+	};
+	if actual != required {
+		anyhow::bail!(
+			"This run requires fama {} but this is fama {}; formatting \
+			 with different backend versions can flip files back and forth. \
+			 Run `fama --version` to compare backends.",
+			required,
+			full_version()
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_backend_versions_are_sorted_and_known() {
+		assert!(BACKEND_VERSIONS.windows(2).all(|w| w[0] <= w[1]));
+		assert!(BACKEND_VERSIONS.iter().any(|(b, _, _)| *b == "biome"));
+	}
+
+	#[test]
+	fn test_check_required() {
+		let version = env!("CARGO_PKG_VERSION");
+		assert!(check_required(version).is_ok());
+		assert!(check_required(&full_version()).is_ok());
+		assert!(check_required("0.0.0-other").is_err());
+		assert!(check_required(&format!("{}+00000000", version)).is_err());
+	}
+
+	#[test]
+	fn test_json_matches_text() {
+		let json = to_json();
+		assert_eq!(json["full_version"], full_version());
+		assert_eq!(
+			json["backends"].as_array().unwrap().len(),
+			BACKEND_VERSIONS.len()
+		);
+		assert!(to_text().starts_with(&format!("fama {}\n", full_version())));
+	}
+}
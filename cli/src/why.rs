@@ -0,0 +1,228 @@
+// why.rs - Annotated diff for `fama --why <file>`
+//
+// Formats a file in memory and labels each diff hunk with the likeliest
+// cause, so a surprising rewrite can be traced to a config value or a
+// backend instead of argued about. Causes are guessed from the shape of
+// each change; anything unrecognized is credited to the backend as a
+// whole.
+
+use fama_common::{detect_file_type, IndentStyle, LineEnding, CONFIG};
+use similar::{ChangeTag, DiffTag, TextDiff};
+use std::path::Path;
+
+use crate::backend::backend_for;
+use crate::color::Color;
+use crate::formatter::{self, FormatOptions};
+
+/// Print the annotated diff for `path`, or say that it is formatted
+pub fn run(path: &Path, options: FormatOptions) -> anyhow::Result<()> {
+	let content = std::fs::read_to_string(path)?;
+	let formatted = formatter::format_source(&content, path, options)?;
+	if content == formatted {
+		println!("{} is already formatted", path.display());
+	} else {
+		print!("{}", annotate(path, &content, &formatted));
+	}
+	Ok(())
+}
+
+/// Unified diff of `original` and `formatted` with each hunk header
+/// followed by the reasons for its changes
+fn annotate(path: &Path, original: &str, formatted: &str) -> String {
+	let path_str = path.to_str().unwrap_or("");
+	let backend = backend_for(detect_file_type(path_str)).name();
+	let width = CONFIG.line_width_for(path_str);
+
+	let diff = TextDiff::from_lines(original, formatted);
+	let (old, new) = (diff.old_slices(), diff.new_slices());
+	let mut out = format!("--- {0}\n+++ {0}\n", path.display());
+	for hunk in diff.unified_diff().iter_hunks() {
+		let mut reasons: Vec<String> = Vec::new();
+		for op in hunk.ops() {
+			let (tag, old_range, new_range) = op.as_tag_tuple();
+			if tag == DiffTag::Equal {
+				continue;
+			}
+			let reason =
+				reason(&old[old_range], &new[new_range], backend, width);
+			if !reasons.contains(&reason) {
+				reasons.push(reason);
+			}
+		}
+		out += &format!("{} {}\n", hunk.header(), reasons.join("; "));
+		for change in hunk.iter_changes() {
+			let line = format!("{}{}", change.tag(), change.value());
+			let line = line.trim_end_matches(['\r', '\n']);
+			out += &match change.tag() {
+				ChangeTag::Delete => Color::Red.paint(line),
+				ChangeTag::Insert => Color::Green.paint(line),
+				ChangeTag::Equal => line.to_string(),
+			};
+			out.push('\n');
+		}
+	}
+	out
+}
+
+/// Best guess at why `removed` lines became `added` lines
+fn reason(
+	removed: &[&str],
+	added: &[&str],
+	backend: &str,
+	width: u16,
+) -> String {
+	let blank = |lines: &[&str]| lines.iter().all(|l| l.trim().is_empty());
+	if removed.is_empty() && blank(added) {
+		return "blank line inserted".to_string();
+	}
+	if added.is_empty() && blank(removed) {
+		return match CONFIG.max_blank_lines {
+			Some(max) => format!("blank lines capped at {}", max),
+			None => "blank lines removed".to_string(),
+		};
+	}
+
+	if removed.len() == added.len() {
+		let pairs = || removed.iter().zip(added);
+		if pairs().all(|(r, a)| r.trim_end() == a.trim_end()) {
+			let eol = |l: &str| l.trim_end_matches(['\r', '\n']).len();
+			if pairs().any(|(r, a)| eol(r) != eol(a)) {
+				return "trailing whitespace removed".to_string();
+			}
+			if removed.iter().any(|l| !l.ends_with('\n')) {
+				return "final newline added".to_string();
+			}
+			// Auto picks the ending per file, so report the one written
+			let ending = if added.iter().any(|l| l.ends_with("\r\n")) {
+				LineEnding::Crlf
+			} else {
+				LineEnding::Lf
+			};
+			return format!("line endings set to {:?}", ending);
+		}
+		if pairs().all(|(r, a)| r.trim_start() == a.trim_start()) {
+			return match CONFIG.indent_style {
+				IndentStyle::Tabs => "indentation set to tabs".to_string(),
+				IndentStyle::Spaces => {
+					format!("indentation set to {} spaces", CONFIG.indent_width)
+				}
+			};
+		}
+	}
+
+	let mut sorted_removed: Vec<&str> =
+		removed.iter().map(|l| l.trim()).collect();
+	let mut sorted_added: Vec<&str> = added.iter().map(|l| l.trim()).collect();
+	sorted_removed.sort_unstable();
+	sorted_added.sort_unstable();
+	if removed.len() > 1 && sorted_removed == sorted_added {
+		let imports = ["import ", "use ", "from ", "#include", "require"];
+		return if removed
+			.iter()
+			.all(|l| imports.iter().any(|i| l.trim_start().starts_with(i)))
+		{
+			format!("imports sorted by {}", backend)
+		} else {
+			format!("lines reordered by {}", backend)
+		};
+	}
+
+	let (before, after) = (removed.concat(), added.concat());
+	if strip(&before, "") == strip(&after, "") {
+		let columns =
+			|l: &&str| CONFIG.text_width(l.trim_end_matches(['\r', '\n']));
+		return if removed.iter().map(columns).max() > Some(width.into()) {
+			format!("line exceeded width {}", width)
+		} else if added.len() < removed.len() {
+			format!("lines joined to fit width {}", width)
+		} else {
+			format!("spacing and line breaks set by {}", backend)
+		};
+	}
+
+	let count = |text: &str, c: char| text.matches(c).count();
+	let mut reasons = Vec::new();
+	if count(&before, '\'') != count(&after, '\'') {
+		reasons.push(format!("quote style {:?}", CONFIG.quote_style));
+	}
+	if count(&before, ';') != count(&after, ';') {
+		reasons.push(format!("semicolons {:?}", CONFIG.semicolons));
+	}
+	if count(&before, ',') != count(&after, ',') {
+		reasons.push(format!("trailing commas {:?}", CONFIG.trailing_comma));
+	}
+	if reasons.is_empty() || strip(&before, "'\";,") != strip(&after, "'\";,") {
+		reasons.push(format!("rewritten by {}", backend));
+	}
+	reasons.join(", ")
+}
+
+/// `text` without whitespace or any character in `chars`
+fn strip(text: &str, chars: &str) -> String {
+	text.chars()
+		.filter(|c| !c.is_whitespace() && !chars.contains(*c))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn why(removed: &[&str], added: &[&str]) -> String {
+		reason(removed, added, "biome", 80)
+	}
+
+	#[test]
+	fn test_reason_whitespace() {
+		assert_eq!(why(&[], &["\n"]), "blank line inserted");
+		assert_eq!(why(&["a  \n"], &["a\n"]), "trailing whitespace removed");
+		assert_eq!(why(&["a"], &["a\n"]), "final newline added");
+		assert_eq!(why(&["a\r\n"], &["a\n"]), "line endings set to Lf");
+		assert_eq!(why(&["    a\n"], &["\ta\n"]), "indentation set to tabs");
+	}
+
+	#[test]
+	fn test_reason_layout() {
+		let long = format!("f({});\n", "x, ".repeat(40));
+		let args = format!("{}\n", "x, ".repeat(40));
+		let wrapped = ["f(\n", &args, ");\n"];
+		assert_eq!(why(&[&long], &wrapped), "line exceeded width 80");
+		assert_eq!(
+			why(&["f(\n", "a\n", ");\n"], &["f(a);\n"]),
+			"lines joined to fit width 80"
+		);
+		// 60 code points, 118 columns on screen
+		let cjk = format!("- {}\n", "漢字".repeat(29));
+		let first = format!("- {}\n", "漢字".repeat(15));
+		let rest = format!("  {}\n", "漢字".repeat(14));
+		assert_eq!(
+			why(&[&cjk], &[&first, &rest]),
+			"spacing and line breaks set by biome"
+		);
+		assert_eq!(
+			why(
+				&["import { b } from \"b\";\n", "import { a } from \"a\";\n"],
+				&["import { a } from \"a\";\n", "import { b } from \"b\";\n"]
+			),
+			"imports sorted by biome"
+		);
+	}
+
+	#[test]
+	fn test_reason_punctuation() {
+		assert_eq!(
+			why(&["f('a')\n"], &["f(\"a\");\n"]),
+			"quote style Double, semicolons Always"
+		);
+		assert_eq!(why(&["a\n"], &["b\n"]), "rewritten by biome");
+	}
+
+	#[test]
+	fn test_annotate_labels_hunks() {
+		let out =
+			annotate(Path::new("a.ts"), "let a = 'x'\n", "let a = \"x\";\n");
+		assert!(out.starts_with("--- a.ts\n+++ a.ts\n"));
+		assert!(out.contains("@@ -1 +1 @@ quote style Double, semicolons"));
+		assert!(out.contains(&Color::Green.paint("+let a = \"x\";")));
+	}
+}
@@ -0,0 +1,47 @@
+// tests/common/mod.rs - Corpus helpers shared by the harness-less test targets
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Root of the per-language test corpus
+pub fn corpus_dir() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+/// All corpus inputs whose path contains one of `filters` (all if empty),
+/// skipping the snapshot directories
+pub fn corpus_files(filters: &[&String]) -> Vec<PathBuf> {
+	let mut files = Vec::new();
+	for language in fs::read_dir(corpus_dir()).unwrap().flatten() {
+		for entry in fs::read_dir(language.path()).unwrap().flatten() {
+			let path = entry.path();
+			let matches = filters.is_empty()
+				|| filters
+					.iter()
+					.any(|f| path.to_string_lossy().contains(f.as_str()));
+			if path.is_file() && matches {
+				files.push(path);
+			}
+		}
+	}
+	files.sort();
+	files
+}
+
+/// Format a copy of `input` with the fama binary and return the result
+pub fn format_with_fama(input: &Path) -> String {
+	let temp_dir = tempfile::TempDir::new().unwrap();
+	let path = temp_dir.path().join(input.file_name().unwrap());
+	fs::copy(input, &path).unwrap();
+
+	let status = Command::new(env!("CARGO_BIN_EXE_fama"))
+		.arg("--quiet")
+		.arg(&path)
+		.current_dir(temp_dir.path())
+		.status()
+		.expect("failed to run fama");
+	assert!(status.success(), "fama failed on {}", input.display());
+
+	fs::read_to_string(&path).unwrap()
+}
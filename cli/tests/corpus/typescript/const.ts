@@ -0,0 +1 @@
+const answer:number=42
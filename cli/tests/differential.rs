@@ -0,0 +1,144 @@
+// tests/differential.rs - Compare fama against reference formatters
+//
+// Opt-in: set FAMA_DIFFERENTIAL=1. Every corpus file that a locally
+// installed reference tool (prettier, gofmt, ruff) understands is formatted
+// by both, and divergences are printed as unified diffs. Divergences are
+// reported, not failed on, since the backends only aim for compatibility.
+//
+//   FAMA_DIFFERENTIAL=1 cargo test -p fama --test differential -- --nocapture
+
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A reference formatter invoked as `program args...` with the source on stdin
+struct Reference {
+	program: &'static str,
+	extensions: &'static [&'static str],
+	args: fn(&Path) -> Vec<String>,
+}
+
+/// Reference tools, configured to match the fama style where they can
+const REFERENCES: &[Reference] = &[
+	Reference {
+		program: "prettier",
+		extensions: &[
+			"js", "jsx", "ts", "tsx", "json", "css", "scss", "less", "md",
+			"yaml", "yml", "html", "vue", "graphql",
+		],
+		args: |path| {
+			vec![
+				"--use-tabs".into(),
+				"--print-width=80".into(),
+				"--end-of-line=lf".into(),
+				format!("--stdin-filepath={}", path.display()),
+			]
+		},
+	},
+	Reference {
+		program: "gofmt",
+		extensions: &["go"],
+		args: |_| Vec::new(),
+	},
+	Reference {
+		program: "ruff",
+		extensions: &["py"],
+		args: |path| {
+			vec![
+				"format".into(),
+				"--config=format.indent-style='tab'".into(),
+				"--config=line-length=80".into(),
+				format!("--stdin-filename={}", path.display()),
+				"-".into(),
+			]
+		},
+	},
+];
+
+fn main() {
+	if std::env::var_os("FAMA_DIFFERENTIAL").is_none() {
+		println!("differential tests skipped (set FAMA_DIFFERENTIAL=1)");
+		return;
+	}
+
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let filters: Vec<&String> =
+		args.iter().filter(|arg| !arg.starts_with('-')).collect();
+	let corpus = common::corpus_dir();
+
+	let mut compared = 0;
+	let mut divergent = 0;
+	for reference in REFERENCES {
+		if !is_installed(reference.program) {
+			println!("{}: not installed, skipping", reference.program);
+			continue;
+		}
+
+		for input in common::corpus_files(&filters) {
+			let ext = input.extension().and_then(|e| e.to_str()).unwrap_or("");
+			if !reference.extensions.contains(&ext) {
+				continue;
+			}
+
+			let relative = input.strip_prefix(&corpus).unwrap();
+			let Some(expected) = run_reference(reference, &input) else {
+				println!(
+					"{}: {} rejected input",
+					relative.display(),
+					reference.program
+				);
+				continue;
+			};
+			let actual = common::format_with_fama(&input);
+
+			compared += 1;
+			if actual != expected {
+				divergent += 1;
+				let diff = similar::TextDiff::from_lines(&expected, &actual);
+				println!(
+					"{}",
+					diff.unified_diff().header(
+						&format!(
+							"{} ({})",
+							relative.display(),
+							reference.program
+						),
+						&format!("{} (fama)", relative.display()),
+					)
+				);
+			}
+		}
+	}
+
+	println!("{compared} files compared, {divergent} diverge");
+}
+
+fn is_installed(program: &str) -> bool {
+	Command::new(program)
+		.arg("--version")
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.is_ok()
+}
+
+/// Format `input` with a reference tool, or None if the tool fails
+fn run_reference(reference: &Reference, input: &Path) -> Option<String> {
+	let source = std::fs::read(input).ok()?;
+	let mut child = Command::new(reference.program)
+		.args((reference.args)(input))
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.spawn()
+		.ok()?;
+	child.stdin.take()?.write_all(&source).ok()?;
+
+	let output = child.wait_with_output().ok()?;
+	if !output.status.success() {
+		return None;
+	}
+	String::from_utf8(output.stdout).ok()
+}
@@ -0,0 +1,56 @@
+// tests/snapshots.rs - Golden-file tests over the per-language corpus
+//
+// Every file in tests/corpus/<language>/ is formatted with the fama binary
+// and compared against its checked-in insta snapshot in
+// tests/corpus/<language>/snapshots/.
+//
+//   cargo test -p fama --test snapshots                # compare
+//   cargo test -p fama --test snapshots -- --bless     # regenerate
+//   cargo test -p fama --test snapshots -- --bless ts  # regenerate matches
+
+mod common;
+
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let bless = args.iter().any(|arg| arg == "--bless");
+	let filters: Vec<&String> =
+		args.iter().filter(|arg| !arg.starts_with('-')).collect();
+	if bless {
+		std::env::set_var("INSTA_UPDATE", "always");
+		std::env::set_var("INSTA_FORCE_PASS", "1");
+	}
+
+	let corpus = common::corpus_dir();
+	let inputs = common::corpus_files(&filters);
+
+	let mut failures = Vec::new();
+	for input in &inputs {
+		let name = input.file_stem().unwrap().to_string_lossy().to_string();
+		let snapshots = input.parent().unwrap().join("snapshots");
+		let output = common::format_with_fama(input);
+
+		let result = std::panic::catch_unwind(|| {
+			let mut settings = insta::Settings::clone_current();
+			settings.set_snapshot_path(&snapshots);
+			settings.set_prepend_module_to_snapshot(false);
+			settings.set_input_file(input);
+			settings.bind(|| insta::assert_snapshot!(name, output));
+		});
+		if result.is_err() {
+			failures.push(input.strip_prefix(&corpus).unwrap().to_path_buf());
+		}
+	}
+
+	if bless {
+		println!("blessed {} snapshots", inputs.len());
+	} else if failures.is_empty() {
+		println!("{} snapshots match", inputs.len());
+	} else {
+		eprintln!("{} of {} snapshots differ:", failures.len(), inputs.len());
+		for failure in &failures {
+			eprintln!("  {}", failure.display());
+		}
+		eprintln!("Run with `-- --bless` to accept the new output");
+		std::process::exit(1);
+	}
+}
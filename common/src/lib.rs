@@ -8,7 +8,10 @@
 // - 80 character line width
 // - LF line endings
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use unicode_width::UnicodeWidthChar;
 
 /// Indent style for formatting
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -21,7 +24,11 @@ pub enum IndentStyle {
 /// Line ending style
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum LineEnding {
+	/// Per file, as git would check it out: `.gitattributes` `eol`, then
+	/// `core.autocrlf`, then the file's own first line ending. Backends
+	/// write LF and the CLI converts their output.
 	#[default]
+	Auto,
 	Lf,
 	Crlf,
 }
@@ -64,15 +71,172 @@ pub enum BraceStyle {
 	NewLine,
 }
 
+/// Where `*` and `&` go in declarations (C-family)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerAlignment {
+	/// `int* a`
+	Left,
+	/// `int *a` (default)
+	#[default]
+	Right,
+	/// `int * a`
+	Middle,
+}
+
+/// Which function definitions may stay on a single line (C-family)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShortFunctions {
+	/// Never put a function on a single line
+	None,
+	/// Only empty functions, `void f() {}`
+	Empty,
+	/// Only functions defined inside a class
+	Inline,
+	/// Any function that fits (default)
+	#[default]
+	All,
+}
+
+/// What to do when the clang-format WASM build fails on a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClangFallback {
+	/// Report the error (default)
+	#[default]
+	Never,
+	/// Retry with a host-installed `clang-format` 16 or newer
+	System,
+}
+
+/// Which rules Go files are formatted with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GoStyle {
+	/// Plain `gofmt` output (default)
+	#[default]
+	Gofmt,
+	/// `gofumpt`'s stricter superset of gofmt
+	Gofumpt,
+}
+
+/// How Markdown paragraphs are wrapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarkdownTextWrap {
+	/// Fill paragraphs up to `prose_width`
+	Always,
+	/// Put each paragraph on a single line
+	Never,
+	/// Keep line breaks as written (default)
+	#[default]
+	Maintain,
+}
+
+/// How the width of a line is counted against `line_width`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WidthMeasure {
+	/// One column per Unicode code point (default)
+	#[default]
+	Codepoints,
+	/// Terminal display width: two columns for CJK and other wide
+	/// characters, none for combining marks
+	UnicodeWidth,
+}
+
+/// How fama treats files of a language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LanguageMode {
+	/// Format and write files (default)
+	#[default]
+	Format,
+	/// Report files that need formatting but never rewrite them
+	CheckOnly,
+	/// Skip files entirely
+	Off,
+}
+
+/// Output style for JSONC files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsoncOutput {
+	/// Keep comments and trailing commas (default)
+	#[default]
+	Preserve,
+	/// Strip comments and trailing commas, emitting strict JSON
+	Strict,
+}
+
+/// How sqruff renders templated SQL before formatting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SqlTemplater {
+	/// Plain SQL, where `{{ ... }}` is a parse error (default)
+	#[default]
+	Raw,
+	/// Jinja, with `sql_templater_vars` as its context
+	Jinja,
+	/// dbt models of the project in the working directory, with
+	/// `sql_templater_vars` as project vars
+	Dbt,
+}
+
+/// Named option bundles that a [`FormatConfig`] is built from; see
+/// [`presets`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+	/// go-fmt style (default)
+	#[default]
+	Gofmt,
+	/// Prettier's defaults
+	Prettier,
+	/// Google style guides
+	Google,
+}
+
+impl Preset {
+	/// The preset's options, without any changes layered on top
+	pub const fn config(self) -> FormatConfig {
+		match self {
+			Preset::Gofmt => presets::GOFMT,
+			Preset::Prettier => presets::PRETTIER,
+			Preset::Google => presets::GOOGLE,
+		}
+	}
+}
+
+/// Options for files matching glob patterns, layered over the global
+/// values; when several overrides match, later ones win
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Override {
+	/// Files the override applies to; see [`matches_path_pattern`]
+	pub files: &'static [&'static str],
+	/// Skip matching files entirely
+	pub ignore: bool,
+	/// Line width for matching files (Biome and dprint languages)
+	pub line_width: Option<u16>,
+}
+
+impl Override {
+	/// An override that changes nothing, for use with struct update syntax
+	pub const NONE: Override = Override {
+		files: &[],
+		ignore: false,
+		line_width: None,
+	};
+
+	fn matches(&self, path: &Path) -> bool {
+		matches_path_pattern(path, self.files)
+	}
+}
+
 /// Centralized format configuration
 ///
 /// All formatters should use this config to ensure consistent formatting
 /// across the codebase. Defaults follow go-fmt style:
 /// - Tabs for indentation
 /// - 80 character line width
-/// - LF line endings
+/// - Line endings as git checks each file out
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FormatConfig {
+	/// Preset this config is built from, kept so a value can be traced to
+	/// the preset or to a change on top of it (default: Gofmt)
+	pub preset: Preset,
+
 	// === Core options (all formatters) ===
 	/// Indent style: tabs or spaces (default: Tabs)
 	pub indent_style: IndentStyle,
@@ -80,8 +244,52 @@ pub struct FormatConfig {
 	pub indent_width: u8,
 	/// Maximum line width (default: 80)
 	pub line_width: u16,
-	/// Line ending style (default: Lf)
+	/// How line width is counted where fama measures it itself; backends
+	/// keep their own measure (default: Codepoints)
+	pub width_measure: WidthMeasure,
+	/// Line ending style (default: Auto)
 	pub line_ending: LineEnding,
+	/// End every file with a line ending (default: true)
+	pub insert_final_newline: bool,
+	/// Per-language overrides for `insert_final_newline` (default: none)
+	pub final_newline_overrides: &'static [(FileType, bool)],
+	/// Cap on consecutive blank lines; None keeps each backend's own
+	/// default (default: None)
+	pub max_blank_lines: Option<u8>,
+	/// Per-language modes for gradual rollout, e.g. `CheckOnly` for SQL
+	/// until its output is trusted (default: none, every language formats)
+	pub language_modes: &'static [(FileType, LanguageMode)],
+	/// Per-glob overrides, e.g. ignore `**/vendor/**` or use 120 columns
+	/// for `docs/**.md` (default: none)
+	pub overrides: &'static [Override],
+	/// Run backends that spawn a host-installed CLI (ktfmt); turn off in
+	/// restricted environments that must not execute other programs
+	/// (default: true)
+	pub subprocess_backends: bool,
+	/// Percent of a file's non-whitespace text that `--check` lets
+	/// formatting change before flagging the file for review
+	/// (default: 20)
+	pub review_threshold: u8,
+	/// Turn U+2028/U+2029 outside strings into line breaks and remove
+	/// zero-width spaces and byte order marks past the start of a file,
+	/// reporting each change (default: false)
+	pub normalize_invisible_chars: bool,
+
+	// === JSON options (Biome) ===
+	/// `.json` files that allow comments and trailing commas, detected as
+	/// JSONC; see [`matches_path_pattern`] (default: [`JSONC_FILENAMES`])
+	pub jsonc_filenames: &'static [&'static str],
+	/// Keep JSONC comments or strip them to strict JSON (default: Preserve)
+	pub jsonc_output: JsoncOutput,
+	/// Sort object keys alphabetically in every JSON/JSONC file
+	/// (default: false)
+	pub json_sort_keys: bool,
+	/// Sort object keys only in files matching these patterns, e.g.
+	/// `locales/*.json`; see [`matches_path_pattern`] (default: none)
+	pub json_sort_keys_paths: &'static [&'static str],
+	/// Locale files compared by `--check-locales`; files in the same
+	/// directory must share one key set (default: `locales/*.json`)
+	pub locale_paths: &'static [&'static str],
 
 	// === String options (JS/TS/CSS/Lua) ===
 	/// Quote style for strings (default: Double)
@@ -94,31 +302,267 @@ pub struct FormatConfig {
 	pub semicolons: Semicolons,
 	/// Spaces inside brackets in objects (default: true)
 	pub bracket_spacing: bool,
+	/// Sort JSX props, keeping `key`/`ref` first (default: false)
+	pub sort_jsx_props: bool,
+	/// Remove unused imports before sorting them (default: false)
+	pub remove_unused_imports: bool,
+	/// Keep a leading license banner pinned above imports (default: true)
+	pub preserve_banner_comments: bool,
 
 	// === Brace style (CSS, C-family) ===
 	/// Brace style for blocks (default: SameLine)
 	pub brace_style: BraceStyle,
+
+	// === C-family options (clang-format) ===
+	/// Where `*` and `&` go in declarations (default: Right)
+	pub pointer_alignment: PointerAlignment,
+	/// Which functions may stay on one line (default: All)
+	pub short_functions: ShortFunctions,
+	/// Sort `#include` blocks and Java imports (default: true)
+	pub sort_includes: bool,
+	/// Regroup sorted `#include`s into blocks: the file's main header,
+	/// then project (`"..."`), then system (`<...>`) headers
+	/// (default: false)
+	pub group_includes: bool,
+	/// Retry files the WASM build fails on with a host-installed
+	/// `clang-format`; needs `subprocess_backends` (default: Never)
+	pub clang_fallback: ClangFallback,
+
+	// === Go options ===
+	/// Formatting rules for Go files (default: Gofmt)
+	pub go_style: GoStyle,
+
+	// === Starlark options ===
+	/// Sort adjacent `load()` statements by label and the symbols each one
+	/// loads, as buildifier does (default: true)
+	pub starlark_sort_loads: bool,
+	/// Put rule arguments in buildifier's order: `name` first, `deps` and
+	/// friends last (default: true)
+	pub starlark_sort_arguments: bool,
+
+	// === Markdown options (dprint) ===
+	/// How paragraphs are wrapped (default: Maintain)
+	pub markdown_text_wrap: MarkdownTextWrap,
+	/// Width prose is wrapped at, separate from the code line width; None
+	/// uses the file's line width (default: None)
+	pub prose_width: Option<u16>,
+	/// Re-pad tables so their columns line up; off keeps every table as
+	/// written (default: true)
+	pub markdown_format_tables: bool,
+	/// Rebuild the table of contents between `<!-- toc -->` and
+	/// `<!-- tocstop -->` from the headings after it (default: false)
+	pub markdown_toc: bool,
+	/// Warn about `#anchor` links with no matching heading and relative
+	/// links to missing files (default: false)
+	pub markdown_check_links: bool,
+
+	// === SQL options (sqruff) ===
+	/// Warn about lint findings that formatting can't fix, with their rule
+	/// code and line (default: false)
+	pub sql_lint: bool,
+	/// Templater for Jinja and dbt models; anything but `Raw` needs the
+	/// `sql-templating` build feature (default: Raw)
+	pub sql_templater: SqlTemplater,
+	/// Variables the templater renders with, such as dbt project vars
+	/// (default: none)
+	pub sql_templater_vars: &'static [(&'static str, &'static str)],
 }
 
+/// Well-known `.json` files whose tools accept comments and trailing commas
+pub const JSONC_FILENAMES: &[&str] = &[
+	"tsconfig.json",
+	"tsconfig.*.json",
+	"jsconfig.json",
+	"jsconfig.*.json",
+	".eslintrc.json",
+	"turbo.json",
+	"devcontainer.json",
+	".devcontainer.json",
+	".vscode/*.json",
+	".devcontainer/*.json",
+];
+
 /// Global format configuration constant
 ///
 /// This constant is used by all formatters to ensure consistent formatting.
 /// Using a const allows compile-time optimization and is inherently thread-safe.
-pub const CONFIG: FormatConfig = FormatConfig {
-	// Core - go-fmt style
-	indent_style: IndentStyle::Tabs,
-	indent_width: 4,
-	line_width: 80,
-	line_ending: LineEnding::Lf,
-	// Strings
-	quote_style: QuoteStyle::Double,
-	// JS/TS
-	trailing_comma: TrailingComma::All,
-	semicolons: Semicolons::Always,
-	bracket_spacing: true,
-	// Brace style
-	brace_style: BraceStyle::SameLine,
-};
+/// Fama's own style is the [`presets::GOFMT`] preset; start from another
+/// preset with e.g. `FormatConfig { line_width: 100, ..presets::PRETTIER }`.
+pub const CONFIG: FormatConfig = presets::GOFMT;
+
+/// Named bundles of options to use as the base layer for [`CONFIG`]
+pub mod presets {
+	use super::*;
+
+	/// go-fmt style: tabs, 80 columns, double quotes, semicolons (default)
+	pub const GOFMT: FormatConfig = FormatConfig {
+		preset: Preset::Gofmt,
+		// Core
+		indent_style: IndentStyle::Tabs,
+		indent_width: 4,
+		line_width: 80,
+		width_measure: WidthMeasure::Codepoints,
+		line_ending: LineEnding::Auto,
+		insert_final_newline: true,
+		final_newline_overrides: &[],
+		max_blank_lines: None,
+		language_modes: &[],
+		overrides: &[],
+		subprocess_backends: true,
+		review_threshold: 20,
+		normalize_invisible_chars: false,
+		// JSON
+		jsonc_filenames: JSONC_FILENAMES,
+		jsonc_output: JsoncOutput::Preserve,
+		json_sort_keys: false,
+		json_sort_keys_paths: &[],
+		locale_paths: &["locales/*.json"],
+		// Strings
+		quote_style: QuoteStyle::Double,
+		// JS/TS
+		trailing_comma: TrailingComma::All,
+		semicolons: Semicolons::Always,
+		bracket_spacing: true,
+		sort_jsx_props: false,
+		remove_unused_imports: false,
+		preserve_banner_comments: true,
+		// Brace style
+		brace_style: BraceStyle::SameLine,
+		// C-family
+		pointer_alignment: PointerAlignment::Right,
+		short_functions: ShortFunctions::All,
+		sort_includes: true,
+		group_includes: false,
+		clang_fallback: ClangFallback::Never,
+		go_style: GoStyle::Gofmt,
+		// Starlark
+		starlark_sort_loads: true,
+		starlark_sort_arguments: true,
+		// Markdown
+		markdown_text_wrap: MarkdownTextWrap::Maintain,
+		prose_width: None,
+		markdown_format_tables: true,
+		markdown_toc: false,
+		markdown_check_links: false,
+		// SQL
+		sql_lint: false,
+		sql_templater: SqlTemplater::Raw,
+		sql_templater_vars: &[],
+	};
+
+	/// Prettier's defaults: 2 spaces, 80 columns, double quotes,
+	/// semicolons, trailing commas everywhere
+	pub const PRETTIER: FormatConfig = FormatConfig {
+		preset: Preset::Prettier,
+		indent_style: IndentStyle::Spaces,
+		indent_width: 2,
+		..GOFMT
+	};
+
+	/// Google style guides (gts for TypeScript, clang-format's Google style
+	/// for C-family): 2 spaces, 80 columns, single quotes, no spaces inside
+	/// object braces, `int* a`
+	pub const GOOGLE: FormatConfig = FormatConfig {
+		preset: Preset::Google,
+		indent_style: IndentStyle::Spaces,
+		indent_width: 2,
+		quote_style: QuoteStyle::Single,
+		bracket_spacing: false,
+		pointer_alignment: PointerAlignment::Left,
+		..GOFMT
+	};
+}
+
+impl FormatConfig {
+	/// Final newline policy for a file type, honoring per-language overrides
+	pub fn insert_final_newline_for(&self, file_type: FileType) -> bool {
+		self.final_newline_overrides
+			.iter()
+			.find(|(ty, _)| *ty == file_type)
+			.map_or(self.insert_final_newline, |(_, insert)| *insert)
+	}
+
+	/// `content` ending the way the final newline policy for `file_type`
+	/// asks: a missing line ending added, or every trailing one stripped.
+	/// Backends apply it themselves so they agree when used as libraries.
+	pub fn apply_final_newline(
+		&self,
+		mut content: String,
+		file_type: FileType,
+	) -> String {
+		let trimmed_len = content.trim_end_matches(['\r', '\n']).len();
+		if !self.insert_final_newline_for(file_type) {
+			content.truncate(trimmed_len);
+		} else if trimmed_len > 0 && !content.ends_with('\n') {
+			content.push_str(match self.line_ending {
+				LineEnding::Lf | LineEnding::Auto => "\n",
+				LineEnding::Crlf => "\r\n",
+			});
+		}
+		content
+	}
+
+	/// Mode for a file type, honoring `language_modes`
+	pub fn mode_for(&self, file_type: FileType) -> LanguageMode {
+		self.language_modes
+			.iter()
+			.find(|(ty, _)| *ty == file_type)
+			.map_or(LanguageMode::Format, |(_, mode)| *mode)
+	}
+
+	/// Line width for the file at `path`, honoring `overrides`
+	pub fn line_width_for(&self, path: &str) -> u16 {
+		self.override_for(path, |o| o.line_width)
+			.map_or(self.line_width, |(_, width)| width)
+	}
+
+	/// Width Markdown prose at `path` is wrapped at: `prose_width`, or the
+	/// file's line width
+	pub fn prose_width_for(&self, path: &str) -> u16 {
+		self.prose_width
+			.unwrap_or_else(|| self.line_width_for(path))
+	}
+
+	/// Columns `line` takes up under `width_measure`, a tab counting as
+	/// `indent_width`
+	pub fn text_width(&self, line: &str) -> usize {
+		line.chars()
+			.map(|c| match (c, self.width_measure) {
+				('\t', _) => usize::from(self.indent_width),
+				(_, WidthMeasure::Codepoints) => 1,
+				(_, WidthMeasure::UnicodeWidth) => c.width().unwrap_or(0),
+			})
+			.sum()
+	}
+
+	/// The override that makes discovery skip `path`, if any
+	pub fn ignore_override(&self, path: &Path) -> Option<&'static Override> {
+		self.overrides
+			.iter()
+			.rev()
+			.find(|o| o.ignore && o.matches(path))
+	}
+
+	/// The last override matching `path` that sets a value, with the value
+	pub fn override_for<T>(
+		&self,
+		path: &str,
+		value: impl Fn(&Override) -> Option<T>,
+	) -> Option<(&'static Override, T)> {
+		let path = Path::new(path);
+		self.overrides
+			.iter()
+			.rev()
+			.filter(|o| o.matches(path))
+			.find_map(|o| value(o).map(|v| (o, v)))
+	}
+
+	/// Whether JSON object keys are sorted in the file at `path`
+	pub fn json_sort_keys_for(&self, path: &str) -> bool {
+		self.json_sort_keys
+			|| matches_path_pattern(Path::new(path), self.json_sort_keys_paths)
+	}
+}
 
 impl Default for FormatConfig {
 	fn default() -> Self {
@@ -126,16 +570,22 @@ impl Default for FormatConfig {
 	}
 }
 
-/// Render EditorConfig contents from the shared format configuration.
+/// Render EditorConfig contents from the shared format configuration:
+/// the core options for every file, then sections for the languages whose
+/// backends use a fixed style or an option of their own, the per-language
+/// final newline overrides and the per-glob line widths.
 pub fn editorconfig_contents(config: &FormatConfig) -> String {
 	let indent_style = match config.indent_style {
 		IndentStyle::Tabs => "tab",
 		IndentStyle::Spaces => "space",
 	};
 
+	// EditorConfig has no per-file setting; leaving it out keeps each
+	// file's own line endings
 	let end_of_line = match config.line_ending {
-		LineEnding::Lf => "lf",
-		LineEnding::Crlf => "crlf",
+		LineEnding::Auto => String::new(),
+		LineEnding::Lf => "end_of_line = lf\n".to_string(),
+		LineEnding::Crlf => "end_of_line = crlf\n".to_string(),
 	};
 
 	let quote_type = match config.quote_style {
@@ -143,34 +593,180 @@ pub fn editorconfig_contents(config: &FormatConfig) -> String {
 		QuoteStyle::Double => "double",
 	};
 
-	format!(
-		r#"# EditorConfig - generated by fama (go-fmt style)
+	let mut out = format!(
+		r#"# EditorConfig - generated by fama ({preset} preset)
 # https://editorconfig.org
 
 root = true
 
 [*]
 charset = utf-8
-end_of_line = {end_of_line}
-insert_final_newline = true
+{end_of_line}insert_final_newline = {insert_final_newline}
 trim_trailing_whitespace = true
 indent_style = {indent_style}
 indent_size = {indent_size}
 tab_width = {indent_size}
 max_line_length = {line_width}
-
-# JavaScript/TypeScript
-[*.{{js,jsx,ts,tsx,mjs,cjs}}]
-quote_type = {quote_type}
-
-# Shell scripts
-[*.{{sh,bash,zsh}}]
-indent_style = {indent_style}
-indent_size = {indent_size}
 "#,
+		preset = format!("{:?}", config.preset).to_lowercase(),
 		indent_size = config.indent_width,
-		line_width = config.line_width
-	)
+		line_width = config.line_width,
+		insert_final_newline = config.insert_final_newline,
+	);
+
+	let quoted = [
+		FileType::JavaScript,
+		FileType::TypeScript,
+		FileType::Jsx,
+		FileType::Tsx,
+		FileType::Python,
+		FileType::Lua,
+		FileType::Css,
+		FileType::Scss,
+		FileType::Less,
+		FileType::Php,
+	];
+	push_section(
+		&mut out,
+		"Languages that follow quote_style",
+		&editorconfig_glob(&quoted),
+		&[("quote_type", quote_type.to_string())],
+	);
+
+	let indent = |style: &str, size: u8| {
+		[
+			("indent_style", style.to_string()),
+			("indent_size", size.to_string()),
+		]
+	};
+	// YAML cannot be indented with tabs
+	let fixed = [
+		(
+			"YAML",
+			&[FileType::Yaml][..],
+			indent("space", config.indent_width),
+		),
+		(
+			"Go (gofmt)",
+			&[FileType::Go, FileType::GoMod],
+			indent("tab", config.indent_width),
+		),
+		("HCL (hclwrite)", &[FileType::Hcl], indent("space", 2)),
+		("Ruby (rubyfmt)", &[FileType::Ruby], indent("space", 2)),
+		(
+			"Starlark (buildifier)",
+			&[FileType::Starlark],
+			indent("space", 4),
+		),
+		("Zig (zig fmt)", &[FileType::Zig], indent("space", 4)),
+	];
+	for (name, file_types, properties) in &fixed {
+		push_section(
+			&mut out,
+			name,
+			&editorconfig_glob(file_types),
+			properties,
+		);
+	}
+
+	if let Some(width) = config.prose_width {
+		push_section(
+			&mut out,
+			"Markdown prose_width",
+			&editorconfig_glob(&[FileType::Markdown]),
+			&[("max_line_length", width.to_string())],
+		);
+	}
+
+	for (file_type, insert) in config.final_newline_overrides {
+		push_section(
+			&mut out,
+			&format!("{:?} insert_final_newline", file_type),
+			&editorconfig_glob(&[*file_type]),
+			&[("insert_final_newline", insert.to_string())],
+		);
+	}
+
+	// Later sections win in EditorConfig too, matching override order
+	for o in config.overrides {
+		let Some(width) = o.line_width else {
+			continue;
+		};
+		let globs: Vec<String> = o
+			.files
+			.iter()
+			.map(|pattern| glob_at_any_depth(pattern))
+			.collect();
+		push_section(
+			&mut out,
+			"override",
+			&brace_list(&globs),
+			&[("max_line_length", width.to_string())],
+		);
+	}
+
+	out
+}
+
+/// A [`matches_path_pattern`] pattern as a glob for tools that anchor
+/// patterns with `/` at the project root: fama matches those against the
+/// trailing components of a path, so they get a leading `**/`
+pub fn glob_at_any_depth(pattern: &str) -> String {
+	if pattern.contains('/') && !pattern.starts_with("**/") {
+		format!("**/{}", pattern)
+	} else {
+		pattern.to_string()
+	}
+}
+
+/// Append a `[glob]` section with a comment line above it
+fn push_section(
+	out: &mut String,
+	comment: &str,
+	glob: &str,
+	properties: &[(&str, String)],
+) {
+	out.push_str(&format!("\n# {}\n[{}]\n", comment, glob));
+	for (key, value) in properties {
+		out.push_str(&format!("{} = {}\n", key, value));
+	}
+}
+
+/// EditorConfig glob for the files detected as any of `file_types`
+fn editorconfig_glob(file_types: &[FileType]) -> String {
+	let extensions: Vec<&str> = EXTENSIONS
+		.iter()
+		.filter(|(_, file_type)| file_types.contains(file_type))
+		.map(|(ext, _)| *ext)
+		.collect();
+	let names: Vec<&str> = file_types
+		.iter()
+		.flat_map(|file_type| match file_type {
+			FileType::Dockerfile => &["Dockerfile", "Dockerfile.*"][..],
+			FileType::GoMod => &["go.mod", "go.work"],
+			FileType::Starlark => &["BUILD", "WORKSPACE"],
+			FileType::Ruby => RUBY_FILENAMES,
+			_ => &[],
+		})
+		.copied()
+		.collect();
+	if names.is_empty() && extensions.len() > 1 {
+		return format!("*.{{{}}}", extensions.join(","));
+	}
+	let patterns: Vec<String> = extensions
+		.iter()
+		.map(|ext| format!("*.{}", ext))
+		.chain(names.iter().map(|name| name.to_string()))
+		.collect();
+	brace_list(&patterns)
+}
+
+/// `pattern`, or `{a,b}` for several
+fn brace_list(patterns: &[String]) -> String {
+	match patterns {
+		[pattern] => pattern.clone(),
+		_ => format!("{{{}}}", patterns.join(",")),
+	}
 }
 
 /// File type enum for language detection
@@ -195,10 +791,14 @@ pub enum FileType {
 	Markdown,
 	Rust,
 	Python,
+	/// Bazel's Starlark: `BUILD`, `WORKSPACE` and `.bzl` files
+	Starlark,
 	Lua,
 	Ruby,
 	Shell,
 	Go,
+	/// `go.mod` and `go.work`
+	GoMod,
 	Zig,
 	Hcl,
 	Dockerfile,
@@ -207,87 +807,311 @@ pub enum FileType {
 	Xml,
 	Php,
 	Kotlin,
+	/// Groovy, mostly Gradle build scripts
+	Groovy,
 	// C-family languages (clang-format)
 	C,
 	Cpp,
 	CSharp,
 	ObjectiveC,
+	ObjectiveCpp,
+	Cuda,
+	/// Metal, HLSL and GLSL shaders
+	Shader,
 	Java,
 	Protobuf,
 	Unknown,
 }
 
+/// A formatter backend, as routed to by the CLI
+///
+/// One instance serves every worker thread, so calls run concurrently and
+/// must not share unsynchronized state. A backend whose library isn't
+/// reentrant serializes its own calls.
+pub trait FormatterBackend: Send + Sync {
+	/// Name shown in reports and `fama explain`
+	fn name(&self) -> &'static str;
+
+	/// Format one file
+	fn format(
+		&self,
+		source: &str,
+		path: &str,
+		file_type: FileType,
+	) -> Result<String, String>;
+
+	/// Format several `(path, source)` files of the same type, returning
+	/// one result per file in order.
+	///
+	/// Defaults to one `format` call per file; backends with per-call setup
+	/// cost override it to share that setup across the batch.
+	fn format_batch(
+		&self,
+		files: &[(&str, &str)],
+		file_type: FileType,
+	) -> Vec<Result<String, String>> {
+		files
+			.iter()
+			.map(|(path, source)| self.format(source, path, file_type))
+			.collect()
+	}
+}
+
+/// Every supported extension and the file type it maps to; the single
+/// registry behind [`detect_file_type`] and discovery. Kept sorted and
+/// de-duplicated, which is checked at compile time.
+pub const EXTENSIONS: &[(&str, FileType)] = &[
+	("astro", FileType::Astro),
+	("bash", FileType::Shell),
+	("bazel", FileType::Starlark),
+	("bzl", FileType::Starlark),
+	("c", FileType::C),
+	("cc", FileType::Cpp),
+	("cjs", FileType::JavaScript),
+	("cpp", FileType::Cpp),
+	("cs", FileType::CSharp),
+	("css", FileType::Css),
+	("cu", FileType::Cuda),
+	("cuh", FileType::Cuda),
+	("cxx", FileType::Cpp),
+	("gemspec", FileType::Ruby),
+	("glsl", FileType::Shader),
+	("go", FileType::Go),
+	("gql", FileType::GraphQL),
+	("gradle", FileType::Groovy),
+	("graphql", FileType::GraphQL),
+	("groovy", FileType::Groovy),
+	("h", FileType::C),
+	("hcl", FileType::Hcl),
+	("hh", FileType::Cpp),
+	("hlsl", FileType::Shader),
+	("hpp", FileType::Cpp),
+	("htm", FileType::Html),
+	("html", FileType::Html),
+	("hxx", FileType::Cpp),
+	("java", FileType::Java),
+	("js", FileType::JavaScript),
+	("json", FileType::Json),
+	("jsonc", FileType::Jsonc),
+	("jsx", FileType::Jsx),
+	("kt", FileType::Kotlin),
+	("kts", FileType::Kotlin),
+	("less", FileType::Less),
+	("lua", FileType::Lua),
+	("m", FileType::ObjectiveC),
+	("markdown", FileType::Markdown),
+	("md", FileType::Markdown),
+	("metal", FileType::Shader),
+	("mjs", FileType::JavaScript),
+	("mjsx", FileType::Jsx),
+	("mm", FileType::ObjectiveCpp),
+	("mts", FileType::TypeScript),
+	("php", FileType::Php),
+	("phtml", FileType::Php),
+	("proto", FileType::Protobuf),
+	("py", FileType::Python),
+	("rake", FileType::Ruby),
+	("rb", FileType::Ruby),
+	("rs", FileType::Rust),
+	("ru", FileType::Ruby),
+	("sass", FileType::Sass),
+	("scss", FileType::Scss),
+	("sh", FileType::Shell),
+	("sql", FileType::Sql),
+	("svelte", FileType::Svelte),
+	("tf", FileType::Hcl),
+	("tfvars", FileType::Hcl),
+	("tofu", FileType::Hcl),
+	("toml", FileType::Toml),
+	("ts", FileType::TypeScript),
+	("tsx", FileType::Tsx),
+	("vue", FileType::Vue),
+	("xml", FileType::Xml),
+	("yaml", FileType::Yaml),
+	("yml", FileType::Yaml),
+	("zig", FileType::Zig),
+	("zsh", FileType::Shell),
+];
+
+const _: () = {
+	let mut i = 1;
+	while i < EXTENSIONS.len() {
+		assert!(
+			str_less(EXTENSIONS[i - 1].0, EXTENSIONS[i].0),
+			"EXTENSIONS must be sorted and de-duplicated"
+		);
+		i += 1;
+	}
+};
+
+/// Byte-wise `a < b`, usable in const context
+const fn str_less(a: &str, b: &str) -> bool {
+	let (a, b) = (a.as_bytes(), b.as_bytes());
+	let mut i = 0;
+	while i < a.len() && i < b.len() {
+		if a[i] != b[i] {
+			return a[i] < b[i];
+		}
+		i += 1;
+	}
+	a.len() < b.len()
+}
+
+/// File type for an extension (without the dot), if it is supported
+pub fn file_type_for_extension(ext: &str) -> Option<FileType> {
+	EXTENSIONS
+		.binary_search_by(|(known, _)| known.cmp(&ext))
+		.ok()
+		.map(|i| EXTENSIONS[i].1)
+}
+
+/// Ruby files without an extension
+const RUBY_FILENAMES: &[&str] = &[
+	"Rakefile",
+	"Gemfile",
+	"Guardfile",
+	"Vagrantfile",
+	"Berksfile",
+	"Capfile",
+	"Thorfile",
+	"Fastfile",
+	"Appfile",
+	"Matchfile",
+	"Snapfile",
+	"Deliverfile",
+	"Scanfile",
+	"Gymfile",
+];
+
 /// Detect file type from extension
 pub fn detect_file_type(path: &str) -> FileType {
 	let path = Path::new(path);
-	match path.extension().and_then(|ext| ext.to_str()) {
-		Some("js") | Some("cjs") | Some("mjs") => FileType::JavaScript,
-		Some("ts") | Some("mts") => FileType::TypeScript,
-		Some("jsx") | Some("mjsx") => FileType::Jsx,
-		Some("tsx") => FileType::Tsx,
-		Some("json") => FileType::Json,
-		Some("jsonc") => FileType::Jsonc,
-		Some("css") => FileType::Css,
-		Some("scss") => FileType::Scss,
-		Some("less") => FileType::Less,
-		Some("sass") => FileType::Sass,
-		Some("html") | Some("htm") => FileType::Html,
-		Some("vue") => FileType::Vue,
-		Some("svelte") => FileType::Svelte,
-		Some("astro") => FileType::Astro,
-		Some("yaml") | Some("yml") => FileType::Yaml,
-		Some("toml") => FileType::Toml,
-		Some("md") | Some("markdown") => FileType::Markdown,
-		Some("rs") => FileType::Rust,
-		Some("py") => FileType::Python,
-		Some("lua") => FileType::Lua,
-		Some("rb") | Some("rake") | Some("gemspec") | Some("ru") => {
-			FileType::Ruby
+	let ext = path.extension().and_then(|ext| ext.to_str());
+	if ext == Some("json") && matches_path_pattern(path, CONFIG.jsonc_filenames)
+	{
+		return FileType::Jsonc;
+	}
+	if let Some(file_type) = ext.and_then(file_type_for_extension) {
+		return file_type;
+	}
+
+	// Check for special filenames
+	if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+		// Dockerfile
+		if name == "Dockerfile" || name.starts_with("Dockerfile.") {
+			return FileType::Dockerfile;
 		}
-		Some("sh") | Some("bash") | Some("zsh") => FileType::Shell,
-		Some("go") => FileType::Go,
-		Some("zig") => FileType::Zig,
-		Some("hcl") | Some("tf") | Some("tfvars") => FileType::Hcl,
-		Some("graphql") | Some("gql") => FileType::GraphQL,
-		Some("sql") => FileType::Sql,
-		Some("xml") => FileType::Xml,
-		Some("php") | Some("phtml") => FileType::Php,
-		Some("kt") | Some("kts") => FileType::Kotlin,
-		// C-family languages
-		Some("c") | Some("h") => FileType::C,
-		Some("cpp") | Some("cc") | Some("cxx") | Some("hpp") | Some("hxx")
-		| Some("hh") => FileType::Cpp,
-		Some("cs") => FileType::CSharp,
-		Some("m") | Some("mm") => FileType::ObjectiveC,
-		Some("java") => FileType::Java,
-		Some("proto") => FileType::Protobuf,
-		_ => {
-			// Check for special filenames
-			if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-				// Dockerfile
-				if name == "Dockerfile" || name.starts_with("Dockerfile.") {
-					return FileType::Dockerfile;
-				}
-				// Ruby files without extensions
-				if matches!(
-					name,
-					"Rakefile"
-						| "Gemfile" | "Guardfile"
-						| "Vagrantfile" | "Berksfile"
-						| "Capfile" | "Thorfile"
-						| "Fastfile" | "Appfile"
-						| "Matchfile" | "Snapfile"
-						| "Deliverfile" | "Scanfile"
-						| "Gymfile"
-				) {
-					return FileType::Ruby;
-				}
-			}
-			FileType::Unknown
+		// Go module and workspace files
+		if name == "go.mod" || name == "go.work" {
+			return FileType::GoMod;
+		}
+		// Bazel build files
+		if name == "BUILD" || name == "WORKSPACE" {
+			return FileType::Starlark;
+		}
+		// Babel's config is JSON with comments
+		if name == ".babelrc" {
+			return FileType::Jsonc;
+		}
+		// Ruby files without extensions
+		if RUBY_FILENAMES.contains(&name) {
+			return FileType::Ruby;
+		}
+	}
+	FileType::Unknown
+}
+
+/// Whether `path` matches one of `patterns`.
+///
+/// A pattern with `/` matches trailing path components, `*` matches any
+/// run of characters within one component, and a `**` component matches
+/// any number of components.
+pub fn matches_path_pattern(path: &Path, patterns: &[&str]) -> bool {
+	let components: Vec<&str> = path
+		.components()
+		.filter_map(|c| c.as_os_str().to_str())
+		.collect();
+
+	patterns.iter().any(|pattern| {
+		let parts: Vec<&str> = pattern.split('/').collect();
+		(0..components.len())
+			.any(|start| components_match(&parts, &components[start..]))
+	})
+}
+
+/// Match all of `components` against all of `parts`
+fn components_match(parts: &[&str], components: &[&str]) -> bool {
+	match parts.split_first() {
+		None => components.is_empty(),
+		Some((&"**", rest)) => (0..=components.len())
+			.any(|skip| components_match(rest, &components[skip..])),
+		Some((part, rest)) => {
+			components
+				.split_first()
+				.is_some_and(|(component, components)| {
+					wildcard_match(part, component)
+						&& components_match(rest, components)
+				})
+		}
+	}
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+	match pattern.split_once('*') {
+		None => pattern == text,
+		Some((prefix, rest)) => {
+			let Some(text) = text.strip_prefix(prefix) else {
+				return false;
+			};
+			(0..=text.len())
+				.filter(|&i| text.is_char_boundary(i))
+				.any(|i| wildcard_match(rest, &text[i..]))
 		}
 	}
 }
 
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Forbid network access for the rest of the run (`--offline`, or
+/// CARGO_NET_OFFLINE in build scripts)
+pub fn set_offline() {
+	OFFLINE.store(true, Ordering::SeqCst);
+}
+
+/// Check before any download, such as fetching a prebuilt formatter.
+///
+/// Fails fast in offline mode, naming what would have been fetched, so CI
+/// can seed caches instead of hanging on or silently using the network.
+pub fn require_network(what: &str) -> Result<(), String> {
+	check_network(OFFLINE.load(Ordering::SeqCst), what)
+}
+
+fn check_network(offline: bool, what: &str) -> Result<(), String> {
+	if offline {
+		return Err(format!(
+			"{} would need a network download, but fama is offline; \
+			 seed the cache before running offline",
+			what
+		));
+	}
+	Ok(())
+}
+
+static TEMP_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Put the temp files backends create in `dir` for the rest of the run;
+/// the CLI removes it when the run ends. Only the first call has effect.
+pub fn set_temp_dir(dir: PathBuf) {
+	let _ = TEMP_DIR.set(dir);
+}
+
+/// Directory for temp files: the run's own, or the system temp dir when
+/// none was set
+pub fn temp_dir() -> PathBuf {
+	TEMP_DIR.get().cloned().unwrap_or_else(std::env::temp_dir)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -323,6 +1147,73 @@ mod tests {
 		assert_eq!(detect_file_type("tsconfig.jsonc"), FileType::Jsonc);
 	}
 
+	#[test]
+	fn test_detect_well_known_jsonc() {
+		assert_eq!(detect_file_type("tsconfig.json"), FileType::Jsonc);
+		assert_eq!(
+			detect_file_type("app/tsconfig.build.json"),
+			FileType::Jsonc
+		);
+		assert_eq!(detect_file_type(".eslintrc.json"), FileType::Jsonc);
+		assert_eq!(detect_file_type("turbo.json"), FileType::Jsonc);
+		assert_eq!(detect_file_type(".vscode/settings.json"), FileType::Jsonc);
+		assert_eq!(
+			detect_file_type("repo/.vscode/extensions.json"),
+			FileType::Jsonc
+		);
+		assert_eq!(detect_file_type("vscode/settings.json"), FileType::Json);
+		assert_eq!(detect_file_type("settings.json"), FileType::Json);
+		assert_eq!(detect_file_type("tsconfig.json.bak"), FileType::Unknown);
+	}
+
+	#[test]
+	fn test_mode_for() {
+		let config = FormatConfig {
+			language_modes: &[
+				(FileType::Sql, LanguageMode::CheckOnly),
+				(FileType::Ruby, LanguageMode::Off),
+			],
+			..CONFIG
+		};
+		assert_eq!(config.mode_for(FileType::Sql), LanguageMode::CheckOnly);
+		assert_eq!(config.mode_for(FileType::Ruby), LanguageMode::Off);
+		assert_eq!(config.mode_for(FileType::TypeScript), LanguageMode::Format);
+	}
+
+	#[test]
+	fn test_json_sort_keys_for() {
+		let config = FormatConfig {
+			json_sort_keys_paths: &["locales/*.json"],
+			..CONFIG
+		};
+		assert!(config.json_sort_keys_for("app/locales/en.json"));
+		assert!(!config.json_sort_keys_for("package.json"));
+
+		let config = FormatConfig {
+			json_sort_keys: true,
+			..CONFIG
+		};
+		assert!(config.json_sort_keys_for("package.json"));
+	}
+
+	#[test]
+	fn test_jsonc_filename_patterns_are_configurable() {
+		let patterns = &["locales/*.json"];
+		assert!(matches_path_pattern(
+			Path::new("src/locales/en.json"),
+			patterns
+		));
+		assert!(!matches_path_pattern(Path::new("tsconfig.json"), patterns));
+	}
+
+	#[test]
+	fn test_wildcard_match() {
+		assert!(wildcard_match("tsconfig.*.json", "tsconfig.app.json"));
+		assert!(wildcard_match("*", ""));
+		assert!(!wildcard_match("tsconfig.*.json", "tsconfig.json"));
+		assert!(!wildcard_match("a*c", "abd"));
+	}
+
 	#[test]
 	fn test_detect_css_variants() {
 		assert_eq!(detect_file_type("test.css"), FileType::Css);
@@ -386,6 +1277,13 @@ mod tests {
 		assert_eq!(detect_file_type("test.go"), FileType::Go);
 		assert_eq!(detect_file_type("main.go"), FileType::Go);
 		assert_eq!(detect_file_type("path/to/file.go"), FileType::Go);
+		assert_eq!(detect_file_type("go.mod"), FileType::GoMod);
+		assert_eq!(detect_file_type("path/to/go.work"), FileType::GoMod);
+		assert_eq!(detect_file_type("pkg/BUILD"), FileType::Starlark);
+		assert_eq!(detect_file_type("BUILD.bazel"), FileType::Starlark);
+		assert_eq!(detect_file_type("WORKSPACE"), FileType::Starlark);
+		assert_eq!(detect_file_type("defs.bzl"), FileType::Starlark);
+		assert_eq!(detect_file_type("other.mod"), FileType::Unknown);
 	}
 
 	#[test]
@@ -400,6 +1298,7 @@ mod tests {
 		assert_eq!(detect_file_type("test.hcl"), FileType::Hcl);
 		assert_eq!(detect_file_type("main.tf"), FileType::Hcl);
 		assert_eq!(detect_file_type("variables.tfvars"), FileType::Hcl);
+		assert_eq!(detect_file_type("main.tofu"), FileType::Hcl);
 		assert_eq!(detect_file_type("path/to/config.hcl"), FileType::Hcl);
 	}
 
@@ -440,6 +1339,40 @@ mod tests {
 		assert_eq!(detect_file_type("path/to/file.kt"), FileType::Kotlin);
 	}
 
+	#[test]
+	fn test_detect_groovy() {
+		assert_eq!(detect_file_type("build.gradle"), FileType::Groovy);
+		assert_eq!(detect_file_type("app/settings.gradle"), FileType::Groovy);
+		assert_eq!(detect_file_type("Jenkins.groovy"), FileType::Groovy);
+	}
+
+	#[test]
+	fn test_detect_c_family_variants() {
+		assert_eq!(detect_file_type("view.m"), FileType::ObjectiveC);
+		assert_eq!(detect_file_type("view.mm"), FileType::ObjectiveCpp);
+		assert_eq!(detect_file_type("kernel.cu"), FileType::Cuda);
+		assert_eq!(detect_file_type("kernel.cuh"), FileType::Cuda);
+		assert_eq!(detect_file_type("blur.metal"), FileType::Shader);
+		assert_eq!(detect_file_type("blur.hlsl"), FileType::Shader);
+		assert_eq!(detect_file_type("blur.glsl"), FileType::Shader);
+	}
+
+	#[test]
+	fn test_extensions_registry() {
+		for (ext, file_type) in EXTENSIONS {
+			let path = format!("src/file.{}", ext);
+			assert_eq!(detect_file_type(&path), *file_type, "{}", path);
+		}
+		assert_eq!(file_type_for_extension("sass"), Some(FileType::Sass));
+		assert_eq!(file_type_for_extension("txt"), None);
+	}
+
+	#[test]
+	fn test_detect_babelrc() {
+		assert_eq!(detect_file_type(".babelrc"), FileType::Jsonc);
+		assert_eq!(detect_file_type("app/.babelrc"), FileType::Jsonc);
+	}
+
 	#[test]
 	fn test_detect_unknown() {
 		assert_eq!(detect_file_type("unknown.xyz"), FileType::Unknown);
@@ -453,13 +1386,142 @@ mod tests {
 		assert_eq!(config.indent_style, IndentStyle::Tabs);
 		assert_eq!(config.indent_width, 4);
 		assert_eq!(config.line_width, 80);
-		assert_eq!(config.line_ending, LineEnding::Lf);
+		assert_eq!(config.line_ending, LineEnding::Auto);
+		assert!(config.insert_final_newline);
+		assert!(config.final_newline_overrides.is_empty());
+		assert_eq!(config.max_blank_lines, None);
 		// String options
 		assert_eq!(config.quote_style, QuoteStyle::Double);
 		// JS/TS options
 		assert_eq!(config.trailing_comma, TrailingComma::All);
 		assert_eq!(config.semicolons, Semicolons::Always);
 		assert!(config.bracket_spacing);
+		assert!(!config.sort_jsx_props);
+		assert!(!config.remove_unused_imports);
+		assert!(config.preserve_banner_comments);
+		// C-family options
+		assert_eq!(config.pointer_alignment, PointerAlignment::Right);
+		assert_eq!(config.short_functions, ShortFunctions::All);
+		assert!(config.sort_includes);
+		assert!(!config.group_includes);
+		assert_eq!(config.clang_fallback, ClangFallback::Never);
+		// Go options
+		assert_eq!(config.go_style, GoStyle::Gofmt);
+		// Starlark options
+		assert!(config.starlark_sort_loads);
+		assert!(config.starlark_sort_arguments);
+	}
+
+	#[test]
+	fn test_insert_final_newline_overrides() {
+		let config = FormatConfig {
+			insert_final_newline: true,
+			final_newline_overrides: &[(FileType::Markdown, false)],
+			..FormatConfig::default()
+		};
+		assert!(config.insert_final_newline_for(FileType::Rust));
+		assert!(!config.insert_final_newline_for(FileType::Markdown));
+	}
+
+	#[test]
+	fn test_apply_final_newline() {
+		let config = FormatConfig {
+			final_newline_overrides: &[(FileType::Markdown, false)],
+			..FormatConfig::default()
+		};
+		let apply = |content: &str, file_type| {
+			config.apply_final_newline(content.to_string(), file_type)
+		};
+		assert_eq!(apply("a", FileType::Yaml), "a\n");
+		assert_eq!(apply("a\n", FileType::Yaml), "a\n");
+		assert_eq!(apply("", FileType::Yaml), "");
+		assert_eq!(apply("a\r\n\n", FileType::Markdown), "a");
+		let crlf = FormatConfig {
+			line_ending: LineEnding::Crlf,
+			..config
+		};
+		assert_eq!(
+			crlf.apply_final_newline("a".into(), FileType::Xml),
+			"a\r\n"
+		);
+	}
+
+	#[test]
+	fn test_double_star_patterns() {
+		let matches = |path: &str, pattern: &str| {
+			matches_path_pattern(Path::new(path), &[pattern])
+		};
+		assert!(matches("lib/vendor/a/b.js", "**/vendor/**"));
+		assert!(matches("vendor/b.js", "**/vendor/**"));
+		assert!(!matches("src/vendors/b.js", "**/vendor/**"));
+		assert!(matches("./site/docs/intro.md", "docs/**.md"));
+		assert!(!matches("docs/intro.txt", "docs/**.md"));
+		assert!(matches("a/b/c.min.js", "*.min.js"));
+	}
+
+	#[test]
+	fn test_overrides() {
+		let config = FormatConfig {
+			overrides: &[
+				Override {
+					files: &["docs/*.md"],
+					line_width: Some(120),
+					..Override::NONE
+				},
+				Override {
+					files: &["docs/wide.md"],
+					line_width: Some(200),
+					..Override::NONE
+				},
+				Override {
+					files: &["**/vendor/**"],
+					ignore: true,
+					..Override::NONE
+				},
+			],
+			..CONFIG
+		};
+		assert_eq!(config.line_width_for("src/main.rs"), 80);
+		assert_eq!(config.line_width_for("docs/guide.md"), 120);
+		assert_eq!(config.line_width_for("docs/wide.md"), 200);
+		assert!(config.ignore_override(Path::new("x/vendor/a.js")).is_some());
+		assert!(config.ignore_override(Path::new("docs/guide.md")).is_none());
+		assert_eq!(config.prose_width_for("docs/guide.md"), 120);
+		let config = FormatConfig {
+			prose_width: Some(72),
+			..config
+		};
+		assert_eq!(config.prose_width_for("docs/guide.md"), 72);
+	}
+
+	#[test]
+	fn test_text_width() {
+		assert_eq!(CONFIG.text_width("\tab"), 6);
+		assert_eq!(CONFIG.text_width("名前: 山田太郎"), 8);
+		let config = FormatConfig {
+			width_measure: WidthMeasure::UnicodeWidth,
+			..CONFIG
+		};
+		assert_eq!(config.text_width("名前: 山田太郎"), 14);
+		assert_eq!(config.text_width("| 表 | e\u{301} |"), 10);
+	}
+
+	#[test]
+	fn test_presets() {
+		assert_eq!(CONFIG, presets::GOFMT);
+		assert_eq!(Preset::Prettier.config(), presets::PRETTIER);
+
+		let prettier = presets::PRETTIER;
+		assert_eq!(prettier.indent_style, IndentStyle::Spaces);
+		assert_eq!(prettier.indent_width, 2);
+		assert_eq!(prettier.quote_style, QuoteStyle::Double);
+		assert_eq!(prettier.semicolons, Semicolons::Always);
+
+		let google = presets::GOOGLE;
+		assert_eq!(google.indent_width, 2);
+		assert_eq!(google.quote_style, QuoteStyle::Single);
+		assert!(!google.bracket_spacing);
+		assert_eq!(google.pointer_alignment, PointerAlignment::Left);
 	}
 
 	#[test]
@@ -470,5 +1532,72 @@ mod tests {
 		assert!(content.contains("indent_size = 4"));
 		assert!(content.contains("max_line_length = 80"));
 		assert!(content.contains("quote_type = double"));
+		assert!(content.contains("insert_final_newline = true"));
+	}
+
+	#[test]
+	fn test_editorconfig_follows_overrides() {
+		let config = FormatConfig {
+			final_newline_overrides: &[(FileType::Markdown, false)],
+			overrides: &[Override {
+				files: &["docs/**", "*.min.js"],
+				line_width: Some(120),
+				..Override::NONE
+			}],
+			prose_width: Some(72),
+			..presets::PRETTIER
+		};
+		let content = editorconfig_contents(&config);
+
+		assert!(content.starts_with(
+			"# EditorConfig - generated by fama (prettier preset)"
+		));
+		assert!(content.contains("indent_style = space\nindent_size = 2\n"));
+		assert!(content.contains(
+			"[*.{cjs,css,js,jsx,less,lua,mjs,mjsx,mts,php,phtml,py,scss,ts,\
+			 tsx}]\nquote_type = double\n"
+		));
+		assert!(
+			content.contains("[{*.go,go.mod,go.work}]\nindent_style = tab\n")
+		);
+		assert!(content.contains("[{*.bazel,*.bzl,BUILD,WORKSPACE}]\n"));
+		assert!(content
+			.contains("[*.{markdown,md}]\ninsert_final_newline = false\n"));
+		assert!(content
+			.contains("[{**/docs/**,*.min.js}]\nmax_line_length = 120\n"));
+		assert!(content.contains("max_line_length = 72\n"));
+	}
+
+	#[test]
+	fn test_require_network_offline() {
+		// The flag is process-wide, so other tests must not see it set
+		assert!(check_network(false, "ktfmt.jar").is_ok());
+		let error = check_network(true, "ktfmt.jar").unwrap_err();
+		assert!(error.starts_with("ktfmt.jar would need a network download"));
+	}
+
+	#[test]
+	fn test_default_format_batch() {
+		struct Upper;
+		impl FormatterBackend for Upper {
+			fn name(&self) -> &'static str {
+				"upper"
+			}
+			fn format(
+				&self,
+				source: &str,
+				path: &str,
+				_file_type: FileType,
+			) -> Result<String, String> {
+				match source {
+					"" => Err(format!("{}: empty", path)),
+					_ => Ok(source.to_uppercase()),
+				}
+			}
+		}
+
+		let results = Upper
+			.format_batch(&[("a.txt", "a"), ("b.txt", "")], FileType::Unknown);
+		assert_eq!(results, [Ok("A".to_string()), Err("b.txt: empty".into())]);
 	}
 }
@@ -0,0 +1,170 @@
+//! JSONC detection and conversion to strict JSON
+//!
+//! Removes comments and trailing commas while leaving string contents
+//! untouched, so the result can be re-parsed as plain JSON.
+
+/// Strip `//` and `/* */` comments and trailing commas from JSONC source.
+///
+/// Line comments keep their terminating newline so the remaining layout is
+/// still line-oriented; the caller is expected to reformat the result.
+pub fn strip_comments_and_trailing_commas(source: &str) -> String {
+	let without_comments = strip_comments(source);
+	strip_trailing_commas(&without_comments)
+}
+
+/// Whether `.json` source uses JSONC syntax: a comment or a trailing comma
+/// outside string literals.
+///
+/// One pass over the bytes, much cheaper than a failed strict parse, so
+/// `.json` files go to the right parser the first time. Every character
+/// it looks for is ASCII, so bytes of multi-byte characters never match.
+pub fn needs_jsonc(source: &str) -> bool {
+	let bytes = source.as_bytes();
+	let mut i = 0;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'"' => {
+				i += 1;
+				while i < bytes.len() && bytes[i] != b'"' {
+					i += if bytes[i] == b'\\' { 2 } else { 1 };
+				}
+			}
+			b'/' if matches!(bytes.get(i + 1), Some(b'/' | b'*')) => {
+				return true;
+			}
+			b',' => {
+				let next = bytes[i + 1..]
+					.iter()
+					.find(|byte| !byte.is_ascii_whitespace());
+				if matches!(next, Some(b'}' | b']')) {
+					return true;
+				}
+			}
+			_ => {}
+		}
+		i += 1;
+	}
+	false
+}
+
+/// Remove comments outside of string literals
+fn strip_comments(source: &str) -> String {
+	let mut result = String::with_capacity(source.len());
+	let mut chars = source.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'"' => {
+				result.push(c);
+				push_string_rest(&mut chars, &mut result);
+			}
+			'/' if chars.peek() == Some(&'/') => {
+				while chars.next_if(|&c| c != '\n').is_some() {}
+			}
+			'/' if chars.peek() == Some(&'*') => {
+				chars.next();
+				let mut previous = '\0';
+				for c in chars.by_ref() {
+					if previous == '*' && c == '/' {
+						break;
+					}
+					previous = c;
+				}
+			}
+			_ => result.push(c),
+		}
+	}
+
+	result
+}
+
+/// Remove commas followed only by whitespace and a closing bracket
+fn strip_trailing_commas(source: &str) -> String {
+	let mut result = String::with_capacity(source.len());
+	let mut chars = source.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'"' => {
+				result.push(c);
+				push_string_rest(&mut chars, &mut result);
+			}
+			',' => {
+				let mut whitespace = String::new();
+				while let Some(w) = chars.next_if(|c| c.is_whitespace()) {
+					whitespace.push(w);
+				}
+				if !matches!(chars.peek(), Some('}') | Some(']')) {
+					result.push(c);
+				}
+				result.push_str(&whitespace);
+			}
+			_ => result.push(c),
+		}
+	}
+
+	result
+}
+
+/// Copy the rest of a string literal, including its closing quote
+fn push_string_rest(
+	chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+	result: &mut String,
+) {
+	while let Some(c) = chars.next() {
+		result.push(c);
+		match c {
+			'\\' => result.extend(chars.next()),
+			'"' => return,
+			_ => {}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_needs_jsonc() {
+		assert!(!needs_jsonc(r#"{"a": [1, 2], "b": {}}"#));
+		assert!(needs_jsonc("{\n\t// line\n\t\"a\": 1\n}"));
+		assert!(needs_jsonc(r#"{"a": 1 /* block */}"#));
+		assert!(needs_jsonc("{\"a\": [1, 2,\n]}"));
+		assert!(needs_jsonc("[1, // last\n]"));
+		// Only outside strings, escaped quotes included
+		assert!(!needs_jsonc(r#"{"url": "http://x/*y*/", "s": "a,]"}"#));
+		assert!(!needs_jsonc(r#"{"q": "\",}", "é": "//"}"#));
+		assert!(!needs_jsonc(r#"{"open": "\"#));
+	}
+
+	#[test]
+	fn test_strip_comments() {
+		let source = "{\n\t// line\n\t\"a\": 1 /* block */\n}\n";
+		assert_eq!(
+			strip_comments_and_trailing_commas(source),
+			"{\n\t\n\t\"a\": 1 \n}\n"
+		);
+	}
+
+	#[test]
+	fn test_strip_trailing_commas() {
+		let source = "{\"a\": [1, 2,], \"b\": {},\n}";
+		assert_eq!(
+			strip_comments_and_trailing_commas(source),
+			"{\"a\": [1, 2], \"b\": {}\n}"
+		);
+	}
+
+	#[test]
+	fn test_strings_are_untouched() {
+		let source = r#"{"url": "http://x/*y*/", "s": "a,]", "q": "\",}"}"#;
+		assert_eq!(strip_comments_and_trailing_commas(source), source);
+	}
+
+	#[test]
+	fn test_comment_between_comma_and_bracket() {
+		let source = "[1, // last\n]";
+		assert_eq!(strip_comments_and_trailing_commas(source), "[1 \n]");
+	}
+}
@@ -6,29 +6,34 @@
 
 #![allow(clippy::all)]
 
+mod jsonc;
+pub mod sfc;
+
 // Biome formatter imports
 use biome_formatter::{
 	BracketSpacing, IndentStyle, IndentWidth, LineEnding, LineWidth, QuoteStyle,
 };
 use biome_js_formatter::context::trailing_commas::TrailingCommas;
 use biome_js_formatter::context::{JsFormatOptions, Semicolons};
-use biome_js_syntax::{AnyJsRoot, JsFileSource};
+use biome_js_syntax::{
+	AnyJsRoot, AnyJsxAttribute, JsFileSource, JsxAttributeList,
+};
 
 use biome_graphql_parser::parse_graphql;
 use biome_html_parser::{parse_html, HtmlParseOptions};
 use biome_js_parser::{parse, JsParserOptions};
 use biome_json_parser::parse_json;
-use biome_json_syntax::JsonFileSource;
+use biome_json_syntax::{JsonFileSource, JsonMember, JsonMemberList, JsonRoot};
 
 // Analyzer imports for import sorting
 use biome_analyze::{
 	ActionCategory, AnalysisFilter, AnalyzerOptions, ControlFlow,
-	RuleCategoriesBuilder, SourceActionKind,
+	RuleCategoriesBuilder, RuleFilter, SourceActionKind,
 };
 use biome_js_analyze::JsAnalyzerServices;
 use biome_module_graph::ModuleGraph;
 use biome_project_layout::ProjectLayout;
-use biome_rowan::AstNode;
+use biome_rowan::{AstNode, AstNodeList, BatchMutationExt, SyntaxResult};
 use std::sync::Arc;
 
 use fama_common::{FileType, CONFIG};
@@ -39,9 +44,10 @@ const BIOME_INDENT_STYLE: IndentStyle = match CONFIG.indent_style {
 	fama_common::IndentStyle::Tabs => IndentStyle::Tab,
 };
 const BIOME_INDENT_WIDTH: u8 = CONFIG.indent_width;
-const BIOME_LINE_WIDTH: u16 = CONFIG.line_width;
 const BIOME_LINE_ENDING: LineEnding = match CONFIG.line_ending {
-	fama_common::LineEnding::Lf => LineEnding::Lf,
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
+		LineEnding::Lf
+	}
 	fama_common::LineEnding::Crlf => LineEnding::Crlf,
 };
 const BIOME_QUOTE_STYLE: QuoteStyle = match CONFIG.quote_style {
@@ -57,6 +63,15 @@ const BIOME_SEMICOLONS: Semicolons = match CONFIG.semicolons {
 	fama_common::Semicolons::AsNeeded => Semicolons::AsNeeded,
 };
 const BIOME_BRACKET_SPACING: bool = CONFIG.bracket_spacing;
+const BIOME_SORT_JSX_PROPS: bool = CONFIG.sort_jsx_props;
+const BIOME_REMOVE_UNUSED_IMPORTS: bool = CONFIG.remove_unused_imports;
+const BIOME_PRESERVE_BANNER_COMMENTS: bool = CONFIG.preserve_banner_comments;
+const BIOME_JSONC_STRICT: bool =
+	matches!(CONFIG.jsonc_output, fama_common::JsoncOutput::Strict);
+const BIOME_NEWLINE: &str = match CONFIG.line_ending {
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => "\n",
+	fama_common::LineEnding::Crlf => "\r\n",
+};
 
 /// Sort imports in a JavaScript/TypeScript file using Biome's OrganizeImports analyzer rule.
 ///
@@ -119,17 +134,297 @@ fn sort_imports(
 	result_root
 }
 
+/// Remove unused imports using the fix of Biome's noUnusedImports lint rule.
+///
+/// Each fix is computed against the tree it was analyzed on, so only the
+/// first fix is applied per analyzer run and the analyzer is re-run on the
+/// updated tree until no unused imports remain.
+fn remove_unused_imports(
+	root: &AnyJsRoot,
+	source_type: JsFileSource,
+	file_path: &str,
+) -> AnyJsRoot {
+	let rules = [RuleFilter::Rule("correctness", "noUnusedImports")];
+	let options = AnalyzerOptions::default().with_file_path(file_path);
+
+	let mut result_root = root.clone();
+	loop {
+		let filter = AnalysisFilter {
+			categories: RuleCategoriesBuilder::default().with_lint().build(),
+			enabled_rules: Some(&rules),
+			..AnalysisFilter::default()
+		};
+		let services = JsAnalyzerServices::from((
+			Arc::new(ModuleGraph::default()),
+			Arc::new(ProjectLayout::default()),
+			source_type,
+		));
+
+		let mut fixed_root = None;
+		let _ = biome_js_analyze::analyze(
+			&result_root,
+			filter,
+			&options,
+			&[], // No plugins
+			services,
+			|signal| {
+				for action in signal.actions() {
+					if matches!(action.category, ActionCategory::QuickFix(_)) {
+						fixed_root = AnyJsRoot::cast(action.mutation.commit());
+						return ControlFlow::Break(());
+					}
+				}
+				ControlFlow::Continue(())
+			},
+		);
+
+		// Every fix removes code, so an unchanged tree means we're done
+		match fixed_root {
+			Some(new_root)
+				if new_root.syntax().text() != result_root.syntax().text() =>
+			{
+				result_root = new_root;
+			}
+			_ => return result_root,
+		}
+	}
+}
+
+/// Sort key for a JSX attribute: `key` first, `ref` second, then by name.
+///
+/// Returns `None` for spread attributes, which act as sorting barriers since
+/// props after a spread override the spread values.
+fn jsx_attribute_sort_key(attribute: &AnyJsxAttribute) -> Option<(u8, String)> {
+	let AnyJsxAttribute::JsxAttribute(attribute) = attribute else {
+		return None;
+	};
+	let name = attribute.name().ok()?.syntax().text_trimmed().to_string();
+	let rank = match name.as_str() {
+		"key" => 0,
+		"ref" => 1,
+		_ => 2,
+	};
+	Some((rank, name.to_lowercase()))
+}
+
+/// Return the attributes of a list in sorted order, sorting each run of
+/// plain attributes between spread attributes independently.
+fn sorted_jsx_attributes(list: &JsxAttributeList) -> Vec<AnyJsxAttribute> {
+	let mut sorted = Vec::new();
+	let mut run: Vec<(Option<(u8, String)>, AnyJsxAttribute)> = Vec::new();
+
+	for attribute in list.iter() {
+		match jsx_attribute_sort_key(&attribute) {
+			Some(key) => run.push((Some(key), attribute)),
+			None => {
+				run.sort_by(|a, b| a.0.cmp(&b.0));
+				sorted.extend(run.drain(..).map(|(_, attribute)| attribute));
+				sorted.push(attribute);
+			}
+		}
+	}
+	run.sort_by(|a, b| a.0.cmp(&b.0));
+	sorted.extend(run.into_iter().map(|(_, attribute)| attribute));
+
+	sorted
+}
+
+/// Sort JSX props alphabetically, keeping `key` and `ref` first.
+///
+/// Lists are sorted one at a time (outermost first) so that attributes
+/// containing nested JSX are moved as a whole before their own props are
+/// sorted.
+fn sort_jsx_props(root: &AnyJsRoot) -> AnyJsRoot {
+	let mut result_root = root.clone();
+	let list_count = root
+		.syntax()
+		.descendants()
+		.filter(|node| JsxAttributeList::can_cast(node.kind()))
+		.count();
+
+	for _ in 0..list_count {
+		let unsorted = result_root
+			.syntax()
+			.descendants()
+			.filter_map(JsxAttributeList::cast)
+			.find(|list| {
+				list.iter()
+					.zip(sorted_jsx_attributes(list))
+					.any(|(current, sorted)| current != sorted)
+			});
+		let Some(list) = unsorted else {
+			break;
+		};
+
+		let mut mutation = result_root.clone().begin();
+		for (current, sorted) in list.iter().zip(sorted_jsx_attributes(&list)) {
+			if current != sorted {
+				mutation.replace_node(current, sorted);
+			}
+		}
+
+		match AnyJsRoot::cast(mutation.commit()) {
+			Some(new_root) => result_root = new_root,
+			None => break,
+		}
+	}
+
+	result_root
+}
+
+/// Check whether a comment looks like a license header
+fn is_license_comment(comment: &str) -> bool {
+	let lower = comment.to_lowercase();
+	comment.starts_with("/*!")
+		|| lower.contains("@license")
+		|| lower.contains("@preserve")
+		|| lower.contains("copyright")
+}
+
+/// Split a leading license/header banner off a JS-family source.
+///
+/// The banner is the run of comments at the top of the file (after an
+/// optional shebang) that is either followed by a blank line or contains a
+/// license marker. Comments directly attached to the first statement without
+/// a license marker are not a banner, they belong to that statement.
+///
+/// Returns the banner (including any shebang) and the remaining source.
+fn split_leading_banner(source: &str) -> Option<(&str, &str)> {
+	let mut offset = 0;
+	if source.starts_with("#!") {
+		offset = source.find('\n').map_or(source.len(), |i| i + 1);
+	}
+
+	let mut banner_end = None;
+	let mut licensed = false;
+	loop {
+		let rest = &source[offset..];
+		let trimmed = rest.trim_start();
+		let whitespace = &rest[..rest.len() - trimmed.len()];
+
+		// A blank line after the comments ends the banner
+		if banner_end.is_some() && whitespace.matches('\n').count() >= 2 {
+			break;
+		}
+
+		let comment_len = if trimmed.starts_with("//") {
+			trimmed.find('\n').unwrap_or(trimmed.len())
+		} else if trimmed.starts_with("/*") {
+			trimmed.find("*/")? + 2
+		} else {
+			// Code (or EOF) directly follows the comments
+			if banner_end.is_some() && (licensed || trimmed.is_empty()) {
+				break;
+			}
+			return None;
+		};
+
+		licensed |= is_license_comment(&trimmed[..comment_len]);
+		offset += whitespace.len() + comment_len;
+		banner_end = Some(offset);
+	}
+
+	let end = banner_end?;
+	Some((&source[..end], &source[end..]))
+}
+
+/// Directive that turns off import sorting for the file it appears in
+const ORGANIZE_IMPORTS_DISABLE: &str = "fama-organize-imports-disable";
+
+/// Check whether the leading comments of a JS-family source contain the
+/// `fama-organize-imports-disable` directive.
+fn organize_imports_disabled(source: &str) -> bool {
+	let mut rest = source;
+	if rest.starts_with("#!") {
+		rest = rest.find('\n').map_or("", |i| &rest[i + 1..]);
+	}
+
+	loop {
+		rest = rest.trim_start();
+		let (body, len) = if let Some(line) = rest.strip_prefix("//") {
+			let end = line.find('\n').unwrap_or(line.len());
+			(&line[..end], end + 2)
+		} else if let Some(block) = rest.strip_prefix("/*") {
+			let Some(end) = block.find("*/") else {
+				return false;
+			};
+			(&block[..end], end + 4)
+		} else {
+			return false;
+		};
+
+		if body.trim() == ORGANIZE_IMPORTS_DISABLE {
+			return true;
+		}
+		rest = &rest[len..];
+	}
+}
+
+/// Re-attach a banner to formatted code, normalizing its trailing whitespace
+/// and separating it from the code by exactly one blank line.
+fn join_banner(banner: &str, formatted: &str) -> String {
+	let banner = banner
+		.lines()
+		.map(str::trim_end)
+		.collect::<Vec<_>>()
+		.join(BIOME_NEWLINE);
+
+	if formatted.trim().is_empty() {
+		format!("{banner}{BIOME_NEWLINE}")
+	} else {
+		format!("{banner}{BIOME_NEWLINE}{BIOME_NEWLINE}{formatted}")
+	}
+}
+
+/// Line width for a file, honoring per-glob overrides
+fn line_width(file_path: &str) -> LineWidth {
+	LineWidth::try_from(CONFIG.line_width_for(file_path)).unwrap()
+}
+
 /// Internal helper for formatting JS-family files (JS, TS, JSX, TSX)
 fn format_js_family(
 	source: &str,
 	file_path: &str,
 	source_type: JsFileSource,
 	file_type_name: &str,
+) -> Result<String, String> {
+	let organize_imports = !organize_imports_disabled(source);
+
+	// Pin the license banner so import sorting can never move it
+	if BIOME_PRESERVE_BANNER_COMMENTS {
+		if let Some((banner, body)) = split_leading_banner(source) {
+			let formatted = format_js_source(
+				body,
+				file_path,
+				source_type,
+				file_type_name,
+				organize_imports,
+			)?;
+			return Ok(join_banner(banner, &formatted));
+		}
+	}
+
+	format_js_source(
+		source,
+		file_path,
+		source_type,
+		file_type_name,
+		organize_imports,
+	)
+}
+
+/// Parse, clean up imports, and format a JS-family source
+fn format_js_source(
+	source: &str,
+	file_path: &str,
+	source_type: JsFileSource,
+	file_type_name: &str,
+	organize_imports: bool,
 ) -> Result<String, String> {
 	let options = JsFormatOptions::new(source_type)
 		.with_indent_style(BIOME_INDENT_STYLE)
 		.with_indent_width(IndentWidth::try_from(BIOME_INDENT_WIDTH).unwrap())
-		.with_line_width(LineWidth::try_from(BIOME_LINE_WIDTH).unwrap())
+		.with_line_width(line_width(file_path))
 		.with_line_ending(BIOME_LINE_ENDING)
 		.with_quote_style(BIOME_QUOTE_STYLE)
 		.with_trailing_commas(BIOME_TRAILING_COMMAS)
@@ -142,9 +437,19 @@ fn format_js_family(
 		return Err(format!("Parse errors in {file_type_name} file"));
 	}
 
-	// Sort imports before formatting
-	let root = parsed.tree();
-	let sorted_root = sort_imports(&root, source_type, file_path);
+	// Clean up and sort imports before formatting
+	let mut root = parsed.tree();
+	if BIOME_REMOVE_UNUSED_IMPORTS {
+		root = remove_unused_imports(&root, source_type, file_path);
+	}
+	let mut sorted_root = if organize_imports {
+		sort_imports(&root, source_type, file_path)
+	} else {
+		root
+	};
+	if BIOME_SORT_JSX_PROPS {
+		sorted_root = sort_jsx_props(&sorted_root);
+	}
 	let syntax = sorted_root.syntax();
 
 	let formatted = biome_js_formatter::format_node(options, syntax)
@@ -177,16 +482,38 @@ pub fn format_tsx(source: &str, file_path: &str) -> Result<String, String> {
 }
 
 /// Format JSON source code
-pub fn format_json(source: &str, _file_path: &str) -> Result<String, String> {
-	format_json_internal(source, JsonFileSource::json(), false)
+pub fn format_json(source: &str, file_path: &str) -> Result<String, String> {
+	format_json_internal(
+		source,
+		JsonFileSource::json(),
+		false,
+		CONFIG.json_sort_keys_for(file_path),
+		line_width(file_path),
+	)
 }
 
 /// Format JSONC (JSON with comments) source code
-pub fn format_jsonc(source: &str, _file_path: &str) -> Result<String, String> {
-	format_json_internal(
+///
+/// With `JsoncOutput::Strict`, comments and trailing commas are stripped
+/// from the formatted output, which is then reformatted as plain JSON.
+pub fn format_jsonc(source: &str, file_path: &str) -> Result<String, String> {
+	let formatted = format_json_internal(
 		source,
 		JsonFileSource::json_allow_comments("jsonc"),
 		true,
+		CONFIG.json_sort_keys_for(file_path),
+		line_width(file_path),
+	)?;
+	if !BIOME_JSONC_STRICT {
+		return Ok(formatted);
+	}
+	let stripped = jsonc::strip_comments_and_trailing_commas(&formatted);
+	format_json_internal(
+		&stripped,
+		JsonFileSource::json(),
+		false,
+		false,
+		line_width(file_path),
 	)
 }
 
@@ -195,6 +522,8 @@ fn format_json_internal(
 	source: &str,
 	source_type: JsonFileSource,
 	allow_comments: bool,
+	sort_keys: bool,
+	line_width: LineWidth,
 ) -> Result<String, String> {
 	use biome_json_parser::JsonParserOptions;
 
@@ -204,11 +533,13 @@ fn format_json_internal(
 			.with_indent_width(
 				IndentWidth::try_from(BIOME_INDENT_WIDTH).unwrap(),
 			)
-			.with_line_width(LineWidth::try_from(BIOME_LINE_WIDTH).unwrap())
+			.with_line_width(line_width)
 			.with_line_ending(BIOME_LINE_ENDING);
 
 	let parser_options = if allow_comments {
-		JsonParserOptions::default().with_allow_comments()
+		JsonParserOptions::default()
+			.with_allow_comments()
+			.with_allow_trailing_commas()
 	} else {
 		JsonParserOptions::default()
 	};
@@ -219,9 +550,12 @@ fn format_json_internal(
 		return Err("Parse errors in JSON file".to_string());
 	}
 
-	let syntax = parsed.syntax();
+	let mut root = parsed.tree();
+	if sort_keys {
+		root = sort_json_keys(&root);
+	}
 
-	let formatted = biome_json_formatter::format_node(options, &syntax)
+	let formatted = biome_json_formatter::format_node(options, root.syntax())
 		.map_err(|e| format!("Format error: {e:?}"))?;
 
 	formatted
@@ -230,12 +564,81 @@ fn format_json_internal(
 		.map_err(|e| format!("Print error: {e:?}"))
 }
 
-/// Format HTML source code
-pub fn format_html(source: &str, _file_path: &str) -> Result<String, String> {
+/// Unquoted key text of a JSON object member
+fn json_member_key(member: &JsonMember) -> String {
+	let Ok(name) = member.name() else {
+		return String::new();
+	};
+	let text = name.syntax().text_trimmed().to_string();
+	text.trim_matches('"').to_string()
+}
+
+/// Members of an object sorted by key, or `None` if the list has errors
+fn sorted_json_members(list: &JsonMemberList) -> Option<Vec<JsonMember>> {
+	let mut members = list.iter().collect::<SyntaxResult<Vec<_>>>().ok()?;
+	members.sort_by_cached_key(json_member_key);
+	Some(members)
+}
+
+/// Sort object keys alphabetically at every nesting level.
+///
+/// Like `sort_jsx_props`, objects are sorted one at a time (outermost
+/// first) so nested objects move as a whole before their own keys are
+/// sorted. Comments attached to a member move with it.
+fn sort_json_keys(root: &JsonRoot) -> JsonRoot {
+	let mut result_root = root.clone();
+	let list_count = root
+		.syntax()
+		.descendants()
+		.filter(|node| JsonMemberList::can_cast(node.kind()))
+		.count();
+
+	for _ in 0..list_count {
+		let unsorted = result_root
+			.syntax()
+			.descendants()
+			.filter_map(JsonMemberList::cast)
+			.find_map(|list| {
+				let sorted = sorted_json_members(&list)?;
+				let changed =
+					list.iter().zip(&sorted).any(|(current, sorted)| {
+						current.ok().as_ref() != Some(sorted)
+					});
+				changed.then_some((list, sorted))
+			});
+		let Some((list, sorted)) = unsorted else {
+			break;
+		};
+
+		let mut mutation = result_root.clone().begin();
+		for (current, sorted) in list.iter().flatten().zip(sorted) {
+			if current != sorted {
+				mutation.replace_node(current, sorted);
+			}
+		}
+
+		match JsonRoot::cast(mutation.commit()) {
+			Some(new_root) => result_root = new_root,
+			None => break,
+		}
+	}
+
+	result_root
+}
+
+/// Format HTML source code, including the contents of inline `<script>`s
+pub fn format_html(source: &str, file_path: &str) -> Result<String, String> {
+	let formatted = format_markup(source, file_path)?;
+	Ok(format_inline_scripts(&formatted, file_path))
+}
+
+/// Format HTML-like markup with Biome's HTML formatter, leaving embedded
+/// script and style contents as written
+fn format_markup(source: &str, file_path: &str) -> Result<String, String> {
 	let options = biome_html_formatter::context::HtmlFormatOptions::default()
 		.with_indent_style(BIOME_INDENT_STYLE)
 		.with_indent_width(IndentWidth::try_from(BIOME_INDENT_WIDTH).unwrap())
-		.with_line_width(LineWidth::try_from(BIOME_LINE_WIDTH).unwrap());
+		.with_line_width(line_width(file_path));
 
 	let parsed = parse_html(source, HtmlParseOptions::default());
 
@@ -254,59 +657,268 @@ pub fn format_html(source: &str, _file_path: &str) -> Result<String, String> {
 		.map_err(|e| format!("Print error: {e:?}"))
 }
 
+/// How the contents of an HTML `<script>` element are formatted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InlineScript {
+	/// JavaScript, as a classic script or an ES module
+	Js(JsFileSource),
+	/// Import maps and other JSON data blocks
+	Json,
+	/// Templates and other types the browser does not run
+	Verbatim,
+}
+
+/// Classify an HTML `<script>` by its `type` attribute, as browsers do:
+/// no type or a JavaScript MIME type is a classic script, `module` an ES
+/// module, and `importmap` or a JSON MIME type a JSON data block
+fn inline_script(block: &sfc::Block) -> InlineScript {
+	let script_type = block
+		.attribute("type")
+		.map(|t| t.trim().to_ascii_lowercase());
+	match script_type.as_deref() {
+		None
+		| Some("")
+		| Some("text/javascript")
+		| Some("application/javascript")
+		| Some("text/ecmascript")
+		| Some("application/ecmascript") => {
+			InlineScript::Js(JsFileSource::js_script())
+		}
+		Some("module") => InlineScript::Js(JsFileSource::js_module()),
+		Some("importmap")
+		| Some("speculationrules")
+		| Some("application/json")
+		| Some("application/ld+json") => InlineScript::Json,
+		_ => InlineScript::Verbatim,
+	}
+}
+
+/// Format the contents of every inline `<script>` in formatted HTML and
+/// indent them one level past the opening tag's line.
+///
+/// Scripts with a `src`, an unknown type, or that fail to parse are left
+/// untouched.
+fn format_inline_scripts(source: &str, file_path: &str) -> String {
+	let mut result = String::with_capacity(source.len());
+	let mut last = 0;
+
+	for block in sfc::blocks(source) {
+		let content = &source[block.content.clone()];
+		if block.tag != "script"
+			|| block.attribute("src").is_some()
+			|| content.trim().is_empty()
+		{
+			continue;
+		}
+		let formatted = match inline_script(&block) {
+			InlineScript::Js(source_type) => {
+				format_js_family(content, file_path, source_type, "JavaScript")
+			}
+			InlineScript::Json => format_json_internal(
+				content,
+				JsonFileSource::json(),
+				false,
+				false,
+				line_width(file_path),
+			),
+			InlineScript::Verbatim => continue,
+		};
+		let Ok(formatted) = formatted else {
+			continue;
+		};
+
+		let line_start = source[..block.content.start]
+			.rfind('\n')
+			.map_or(0, |i| i + 1);
+		let line = &source[line_start..block.content.start];
+		let indent = &line[..line.len() - line.trim_start().len()];
+		result.push_str(&source[last..block.content.start]);
+		result.push_str(&indent_script(&formatted, indent));
+		last = block.content.end;
+	}
+
+	result.push_str(&source[last..]);
+	result
+}
+
+/// Script contents on their own lines, one indent level deeper than the
+/// `<script>` tag's line, with the closing tag back at `indent`
+fn indent_script(formatted: &str, indent: &str) -> String {
+	let unit = match BIOME_INDENT_STYLE {
+		IndentStyle::Tab => "\t".to_string(),
+		IndentStyle::Space => " ".repeat(BIOME_INDENT_WIDTH.into()),
+	};
+	let nested = format!("{indent}{unit}");
+	let mut state = ScriptState::Code;
+	let body: Vec<String> = formatted
+		.trim_end()
+		.lines()
+		.map(|line| {
+			// Indenting would change the text of a multi-line template
+			// literal, so its continuation lines are kept as is
+			let continuation = state == ScriptState::Template;
+			state = scan_script_line(line, state);
+			if line.is_empty() || continuation {
+				line.to_string()
+			} else {
+				format!("{nested}{line}")
+			}
+		})
+		.collect();
+	format!(
+		"{BIOME_NEWLINE}{}{BIOME_NEWLINE}{indent}",
+		body.join(BIOME_NEWLINE)
+	)
+}
+
+/// What a script line leaves open for the next one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScriptState {
+	Code,
+	Template,
+	BlockComment,
+}
+
+/// The state after `line`, skipping strings and comments so a backtick in
+/// one never opens a template literal
+fn scan_script_line(line: &str, mut state: ScriptState) -> ScriptState {
+	let mut chars = line.chars();
+	while let Some(c) = chars.next() {
+		match (state, c) {
+			(ScriptState::Template, '\\') => {
+				chars.next();
+			}
+			(ScriptState::Template, '`') => state = ScriptState::Code,
+			(ScriptState::BlockComment, '*')
+				if chars.as_str().starts_with('/') =>
+			{
+				chars.next();
+				state = ScriptState::Code;
+			}
+			(ScriptState::Code, '`') => state = ScriptState::Template,
+			(ScriptState::Code, '/') if chars.as_str().starts_with('/') => {
+				break;
+			}
+			(ScriptState::Code, '/') if chars.as_str().starts_with('*') => {
+				chars.next();
+				state = ScriptState::BlockComment;
+			}
+			// Strings can't span lines, so one left open ends with it
+			(ScriptState::Code, '"' | '\'') => {
+				while let Some(d) = chars.next() {
+					if d == '\\' {
+						chars.next();
+					} else if d == c {
+						break;
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+	state
+}
+
+/// JS source type for an SFC `<script>` block's file type
+fn script_source_type(file_type: FileType) -> Option<JsFileSource> {
+	match file_type {
+		FileType::JavaScript => Some(JsFileSource::js_module()),
+		FileType::TypeScript => Some(JsFileSource::ts()),
+		FileType::Jsx => Some(JsFileSource::jsx()),
+		FileType::Tsx => Some(JsFileSource::tsx()),
+		_ => None,
+	}
+}
+
+/// Run OrganizeImports on every `<script>` block of a Vue/Svelte SFC.
+///
+/// Only import order changes; the rest of each block is kept byte-for-byte.
+/// Blocks in an unknown `lang`, that fail to parse, or that start with the
+/// `fama-organize-imports-disable` directive are left untouched.
+fn sort_sfc_script_imports(source: &str, file_path: &str) -> String {
+	let mut result = String::with_capacity(source.len());
+	let mut last = 0;
+
+	for block in sfc::blocks(source) {
+		if block.tag != "script" {
+			continue;
+		}
+		let Some(source_type) = block.file_type().and_then(script_source_type)
+		else {
+			continue;
+		};
+
+		let content = &source[block.content.clone()];
+		if organize_imports_disabled(content) {
+			continue;
+		}
+		let parsed = parse(content, source_type, JsParserOptions::default());
+		if parsed.has_errors() {
+			continue;
+		}
+
+		let sorted = sort_imports(&parsed.tree(), source_type, file_path);
+		result.push_str(&source[last..block.content.start]);
+		result.push_str(&sorted.syntax().to_string());
+		last = block.content.end;
+	}
+
+	result.push_str(&source[last..]);
+	result
+}
+
 /// Format Vue SFC source code (limited - extracts and formats template/script/style)
 pub fn format_vue(source: &str, file_path: &str) -> Result<String, String> {
 	// Vue SFC has special syntax - for now use HTML formatter with lenient parsing
 	// Full Vue support would require extracting each section and formatting separately
-	match format_html(source, file_path) {
-		Ok(result) => Ok(result),
+	let result = match format_markup(source, file_path) {
+		Ok(result) => result,
 		Err(_) => {
 			// If HTML parser fails, return original content (Vue has features HTML parser can't handle)
-			Ok(source.to_string())
+			source.to_string()
 		}
-	}
+	};
+	Ok(sort_sfc_script_imports(&result, file_path))
 }
 
 /// Format Svelte source code (limited - uses HTML parser)
 pub fn format_svelte(source: &str, file_path: &str) -> Result<String, String> {
 	// Svelte has special syntax - for now use HTML formatter with lenient parsing
 	// Full Svelte support would require a dedicated Svelte parser
-	match format_html(source, file_path) {
-		Ok(result) => Ok(result),
+	let result = match format_markup(source, file_path) {
+		Ok(result) => result,
 		Err(_) => {
 			// If HTML parser fails, return original content (Svelte has features HTML parser can't handle)
-			eprintln!("Warning: {file_path} syntax not fully supported, file may not be properly formatted");
-			Ok(source.to_string())
+			tracing::warn!("{file_path} syntax not fully supported, file may not be properly formatted");
+			source.to_string()
 		}
-	}
+	};
+	Ok(sort_sfc_script_imports(&result, file_path))
 }
 
 /// Format Astro source code (limited - extracts frontmatter and HTML)
 pub fn format_astro(source: &str, file_path: &str) -> Result<String, String> {
 	// Astro has frontmatter (fenced code block) - for now use HTML formatter
 	// Full Astro support would require extracting and formatting frontmatter separately
-	match format_html(source, file_path) {
+	match format_markup(source, file_path) {
 		Ok(result) => Ok(result),
 		Err(_) => {
 			// If HTML parser fails, return original content (Astro has features HTML parser can't handle)
-			eprintln!("Warning: {file_path} syntax not fully supported, file may not be properly formatted");
+			tracing::warn!("{file_path} syntax not fully supported, file may not be properly formatted");
 			Ok(source.to_string())
 		}
 	}
 }
 
 /// Format GraphQL source code
-pub fn format_graphql(
-	source: &str,
-	_file_path: &str,
-) -> Result<String, String> {
+pub fn format_graphql(source: &str, file_path: &str) -> Result<String, String> {
 	let options =
 		biome_graphql_formatter::context::GraphqlFormatOptions::default()
 			.with_indent_style(BIOME_INDENT_STYLE)
 			.with_indent_width(
 				IndentWidth::try_from(BIOME_INDENT_WIDTH).unwrap(),
 			)
-			.with_line_width(LineWidth::try_from(BIOME_LINE_WIDTH).unwrap())
+			.with_line_width(line_width(file_path))
 			.with_line_ending(BIOME_LINE_ENDING);
 
 	let parsed = parse_graphql(source);
@@ -337,13 +949,11 @@ pub fn format_file(
 		FileType::TypeScript => format_typescript(source, file_path),
 		FileType::Jsx => format_jsx(source, file_path),
 		FileType::Tsx => format_tsx(source, file_path),
-		FileType::Json => {
-			// Try standard JSON first, if that fails try JSON with comments
-			match format_json(source, file_path) {
-				Ok(result) => Ok(result),
-				Err(_) => format_jsonc(source, file_path),
-			}
+		// `.json` files with comments or trailing commas are JSONC
+		FileType::Json if jsonc::needs_jsonc(source) => {
+			format_jsonc(source, file_path)
 		}
+		FileType::Json => format_json(source, file_path),
 		FileType::Jsonc => format_jsonc(source, file_path),
 		FileType::Html => format_html(source, file_path),
 		FileType::Vue => format_vue(source, file_path),
@@ -382,6 +992,65 @@ mod tests {
 		assert!(result.contains("<html>") || result.contains("<body>"));
 	}
 
+	#[test]
+	fn test_inline_script_types() {
+		let kind = |tag: &str| {
+			let blocks = sfc::blocks(tag);
+			inline_script(&blocks[0])
+		};
+		assert_eq!(
+			kind("<script>a()</script>"),
+			InlineScript::Js(JsFileSource::js_script())
+		);
+		assert_eq!(
+			kind("<script type=\"text/javascript\">a()</script>"),
+			InlineScript::Js(JsFileSource::js_script())
+		);
+		assert_eq!(
+			kind("<script type=\"module\">a()</script>"),
+			InlineScript::Js(JsFileSource::js_module())
+		);
+		assert_eq!(
+			kind("<script type=\"importmap\">{}</script>"),
+			InlineScript::Json
+		);
+		assert_eq!(
+			kind("<script type=\"application/ld+json\">{}</script>"),
+			InlineScript::Json
+		);
+		assert_eq!(
+			kind("<script type=\"text/x-template\"><div /></script>"),
+			InlineScript::Verbatim
+		);
+	}
+
+	#[test]
+	fn test_format_html_inline_scripts() {
+		let source = concat!(
+			"<div>\n",
+			"<script type=\"module\">import {a} from \"./a.js\"; a( 1 )</script>\n",
+			"<script type=\"importmap\">{\"imports\":{\"a\":\"./a.js\"}}</script>\n",
+			"<script type=\"text/x-template\"><p>{{ a }}</p></script>\n",
+			"</div>\n",
+		);
+		let result = format_inline_scripts(source, "index.html");
+		assert!(result
+			.contains("\timport { a } from \"./a.js\";\n\ta(1);\n</script>"));
+		assert!(result.contains("{ \"imports\": { \"a\": \"./a.js\" } }"));
+		assert!(result.contains(
+			"<script type=\"text/x-template\"><p>{{ a }}</p></script>"
+		));
+	}
+
+	#[test]
+	fn test_indent_script_skips_template_continuations() {
+		let script = "const q = \"`\"; // `\nconst t = `a\n  b`;\nf();\n";
+		assert_eq!(
+			indent_script(script, ""),
+			"\n\tconst q = \"`\"; // `\n\tconst t = `a\n  b`;\n\tf();\n"
+		);
+	}
+
 	#[test]
 	fn test_format_file_with_javascript() {
 		let source = "const   x   =   1;";
@@ -417,7 +1086,7 @@ mod tests {
 
 	#[test]
 	fn test_format_json_with_comments_fallback() {
-		// JSON file with comments should fallback to JSONC mode
+		// JSON file with comments should be formatted as JSONC
 		let source = r#"{
   // This is a comment
   "name": "test",
@@ -428,6 +1097,45 @@ mod tests {
 		assert!(result.contains("// This is a comment"));
 	}
 
+	#[test]
+	fn test_format_json_with_trailing_commas() {
+		let source = "{\"a\": [1, 2,],}";
+		let result = format_file(source, "test.json", FileType::Json).unwrap();
+		assert!(result.contains("\"a\""));
+		assert!(format_file("{\"a\": }", "test.json", FileType::Json).is_err());
+	}
+
+	#[test]
+	fn test_sort_json_keys() {
+		let source = r#"{"b": 1, "a": {"d": [{"z": 1, "y": 2}], "c": 3}}"#;
+		let parsed = parse_json(source, Default::default());
+		let sorted = sort_json_keys(&parsed.tree()).syntax().to_string();
+		let position = |key: &str| sorted.find(key).unwrap();
+		assert!(position("\"a\"") < position("\"b\""));
+		assert!(position("\"c\"") < position("\"d\""));
+		assert!(position("\"y\"") < position("\"z\""));
+	}
+
+	#[test]
+	fn test_sort_json_keys_keeps_comments_with_members() {
+		let source = "{\n\t// about b\n\t\"b\": 1,\n\t\"a\": 2\n}";
+		let options = biome_json_parser::JsonParserOptions::default()
+			.with_allow_comments();
+		let parsed = parse_json(source, options);
+		let sorted = sort_json_keys(&parsed.tree()).syntax().to_string();
+		let comment = sorted.find("// about b").unwrap();
+		assert!(sorted.find("\"a\"").unwrap() < comment);
+		assert!(comment < sorted.find("\"b\"").unwrap());
+	}
+
+	#[test]
+	fn test_format_jsonc_allows_trailing_commas() {
+		let source = "{\n\t// editor settings\n\t\"a\": [1, 2,],\n}\n";
+		let result = format_jsonc(source, "tsconfig.json").unwrap();
+		assert!(result.contains("// editor settings"));
+		assert!(result.contains("\"a\""));
+	}
+
 	#[test]
 	fn test_sort_imports_javascript() {
 		// Imports in wrong order: relative paths should come after packages
@@ -492,6 +1200,111 @@ import path from "node:path";
 		);
 	}
 
+	#[test]
+	fn test_banner_stays_above_sorted_imports() {
+		let source = r#"/*! MyLib v1.0 | MIT License */
+import z from "./local";
+import a from "package-a";
+"#;
+		let result = format_javascript(source, "test.js").unwrap();
+		assert!(
+			result.starts_with("/*! MyLib v1.0 | MIT License */\n\nimport a"),
+			"Banner should stay at the top. Got: {}",
+			result
+		);
+	}
+
+	#[test]
+	fn test_line_comment_banner_with_blank_line() {
+		let source = "// Copyright (c) Acme   \n// All rights reserved\n\n\nimport b from \"b\";\nimport a from \"a\";\n";
+		let result = format_javascript(source, "test.js").unwrap();
+		assert!(
+			result.starts_with(
+				"// Copyright (c) Acme\n// All rights reserved\n\nimport a"
+			),
+			"Got: {}",
+			result
+		);
+	}
+
+	#[test]
+	fn test_split_leading_banner() {
+		let (banner, body) =
+			split_leading_banner("#!/usr/bin/env node\n/* header */\n\nrun();")
+				.unwrap();
+		assert_eq!(banner, "#!/usr/bin/env node\n/* header */");
+		assert_eq!(body, "\n\nrun();");
+
+		// A plain comment attached to the first statement is not a banner
+		assert!(split_leading_banner("// the answer\nconst x = 42;").is_none());
+		assert!(split_leading_banner("const x = 1; // trailing").is_none());
+	}
+
+	/// `source` with unused imports removed, formatted as it would be
+	fn remove_unused(source: &str, source_type: JsFileSource) -> String {
+		let parsed = parse(source, source_type, JsParserOptions::default());
+		let root =
+			remove_unused_imports(&parsed.tree(), source_type, "test.ts");
+		let options = JsFormatOptions::new(source_type);
+		biome_js_formatter::format_node(options, root.syntax())
+			.unwrap()
+			.print()
+			.unwrap()
+			.as_code()
+			.to_string()
+	}
+
+	#[test]
+	fn test_remove_unused_imports() {
+		let source = r#"import { used, unused } from "a";
+import other from "b";
+console.log(used);
+"#;
+		assert_eq!(
+			remove_unused(source, JsFileSource::js_module()),
+			"import { used } from \"a\";\nconsole.log(used);\n"
+		);
+	}
+
+	#[test]
+	fn test_remove_unused_imports_keeps_side_effects_and_types() {
+		let source = r#"import "./polyfill";
+import type { Props } from "./types";
+const p: Props = {};
+"#;
+		assert_eq!(remove_unused(source, JsFileSource::ts()), source);
+	}
+
+	fn sort_props(source: &str) -> String {
+		let parsed =
+			parse(source, JsFileSource::jsx(), JsParserOptions::default());
+		sort_jsx_props(&parsed.tree()).syntax().to_string()
+	}
+
+	#[test]
+	fn test_sort_jsx_props_alphabetical() {
+		let result = sort_props("<Foo zeta={1} alpha=\"a\" Beta />;");
+		assert_eq!(result, "<Foo alpha=\"a\" Beta zeta={1} />;");
+	}
+
+	#[test]
+	fn test_sort_jsx_props_key_ref_first() {
+		let result = sort_props("<Foo b={1} ref={r} a={2} key=\"k\" />;");
+		assert_eq!(result, "<Foo key=\"k\" ref={r} a={2} b={1} />;");
+	}
+
+	#[test]
+	fn test_sort_jsx_props_spread_is_barrier() {
+		let result = sort_props("<Foo c={1} {...rest} b={2} a={3} />;");
+		assert_eq!(result, "<Foo c={1} {...rest} a={3} b={2} />;");
+	}
+
+	#[test]
+	fn test_sort_jsx_props_nested() {
+		let result = sort_props("<Foo z={<Bar y x />} a />;");
+		assert_eq!(result, "<Foo a z={<Bar x y />} />;");
+	}
+
 	#[test]
 	fn test_sort_imports_with_side_effects() {
 		// Side-effect imports should not be reordered with regular imports
@@ -508,4 +1321,84 @@ import a from "package-a";
 			result
 		);
 	}
+	#[test]
+	fn test_sort_vue_script_imports() {
+		let source = r#"<template>
+	<div />
+</template>
+
+<script setup lang="ts">
+import z from "./local";
+import a from "package-a";
+</script>
+"#;
+		let result = format_vue(source, "App.vue").unwrap();
+		let a_pos = result.find("package-a").unwrap();
+		let local_pos = result.find("./local").unwrap();
+		assert!(
+			a_pos < local_pos,
+			"Script imports should be sorted. Got: {}",
+			result
+		);
+		assert!(result.contains("<template>"));
+	}
+
+	#[test]
+	fn test_sort_svelte_script_imports() {
+		let source = r#"<script>
+import z from "./local";
+import a from "package-a";
+</script>
+
+<h1>Hello</h1>
+"#;
+		let result = format_svelte(source, "App.svelte").unwrap();
+		let a_pos = result.find("package-a").unwrap();
+		let local_pos = result.find("./local").unwrap();
+		assert!(
+			a_pos < local_pos,
+			"Script imports should be sorted. Got: {}",
+			result
+		);
+	}
+
+	#[test]
+	fn test_sfc_script_unknown_lang_untouched() {
+		let source = r#"<script lang="coffee">
+import z from "./local"
+import a from "package-a"
+</script>
+"#;
+		assert_eq!(sort_sfc_script_imports(source, "App.vue"), source);
+	}
+
+	#[test]
+	fn test_organize_imports_disable_directive() {
+		let source = r#"// fama-organize-imports-disable
+import z from "./local";
+import a from "package-a";
+"#;
+		let result = format_javascript(source, "test.js").unwrap();
+		let a_pos = result.find("package-a").unwrap();
+		let local_pos = result.find("./local").unwrap();
+		assert!(
+			local_pos < a_pos,
+			"Import order should be kept. Got: {}",
+			result
+		);
+	}
+
+	#[test]
+	fn test_organize_imports_disabled() {
+		assert!(organize_imports_disabled(
+			"// fama-organize-imports-disable\nimport a from \"a\";"
+		));
+		assert!(organize_imports_disabled(
+			"#!/usr/bin/env node\n/*! MIT */\n/* fama-organize-imports-disable */"
+		));
+		assert!(!organize_imports_disabled(
+			"import a from \"a\";\n// fama-organize-imports-disable"
+		));
+		assert!(!organize_imports_disabled("// fama-organize-imports"));
+	}
 }
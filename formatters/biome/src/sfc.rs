@@ -0,0 +1,229 @@
+//! Single-file component (Vue/Svelte) block extraction
+//!
+//! Locates top-level `<script>` and `<style>` blocks so their contents can be
+//! processed by the matching language backend and spliced back in place.
+
+use fama_common::FileType;
+use std::ops::Range;
+
+/// A `<script>` or `<style>` block in an SFC source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block<'a> {
+	/// Lowercased tag name (`script` or `style`)
+	pub tag: String,
+	/// Raw attribute text of the opening tag
+	pub attributes: &'a str,
+	/// Byte range of the block content between the open and close tags
+	pub content: Range<usize>,
+}
+
+impl<'a> Block<'a> {
+	/// Get the value of an attribute on the opening tag
+	pub fn attribute(&self, name: &str) -> Option<&'a str> {
+		attribute_value(self.attributes, name)
+	}
+
+	/// The `lang` attribute, if present
+	pub fn lang(&self) -> Option<&'a str> {
+		self.attribute("lang")
+	}
+
+	/// File type of the block content, from its tag and `lang`; `None` for
+	/// languages fama does not format, such as Stylus or CoffeeScript
+	pub fn file_type(&self) -> Option<FileType> {
+		let lang = self.lang().map(str::to_ascii_lowercase);
+		match (self.tag.as_str(), lang.as_deref()) {
+			("script", None | Some("js" | "javascript")) => {
+				Some(FileType::JavaScript)
+			}
+			("script", Some("ts" | "typescript")) => Some(FileType::TypeScript),
+			("script", Some("jsx")) => Some(FileType::Jsx),
+			("script", Some("tsx")) => Some(FileType::Tsx),
+			("style", None | Some("css" | "postcss")) => Some(FileType::Css),
+			("style", Some("scss")) => Some(FileType::Scss),
+			("style", Some("less")) => Some(FileType::Less),
+			("style", Some("sass")) => Some(FileType::Sass),
+			_ => None,
+		}
+	}
+}
+
+/// Find all `<script>` and `<style>` blocks in source order.
+///
+/// HTML comments are skipped. An unterminated block ends the scan, leaving
+/// the rest of the file untouched.
+pub fn blocks(source: &str) -> Vec<Block<'_>> {
+	let lower = source.to_ascii_lowercase();
+	let mut blocks = Vec::new();
+	let mut offset = 0;
+
+	while let Some(start) = lower[offset..].find('<').map(|i| offset + i) {
+		if lower[start..].starts_with("<!--") {
+			match lower[start..].find("-->") {
+				Some(end) => offset = start + end + 3,
+				None => break,
+			}
+			continue;
+		}
+
+		let Some(tag) = ["script", "style"].into_iter().find(|tag| {
+			lower[start + 1..].starts_with(tag)
+				&& lower[start + 1 + tag.len()..]
+					.starts_with(|c: char| c == '>' || c.is_whitespace())
+		}) else {
+			offset = start + 1;
+			continue;
+		};
+
+		let attributes_start = start + 1 + tag.len();
+		let Some(open_end) = find_tag_end(source, attributes_start) else {
+			break;
+		};
+		let close_tag = format!("</{tag}");
+		let Some(close_start) = lower[open_end + 1..]
+			.find(&close_tag)
+			.map(|i| open_end + 1 + i)
+		else {
+			break;
+		};
+
+		blocks.push(Block {
+			tag: tag.to_string(),
+			attributes: source[attributes_start..open_end].trim(),
+			content: open_end + 1..close_start,
+		});
+		offset = close_start + close_tag.len();
+	}
+
+	blocks
+}
+
+/// Find the `>` closing an opening tag, skipping quoted attribute values
+fn find_tag_end(source: &str, from: usize) -> Option<usize> {
+	let mut quote = None;
+	for (i, c) in source[from..].char_indices() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some(_), _) => {}
+			(None, '"' | '\'') => quote = Some(c),
+			(None, '>') => return Some(from + i),
+			(None, _) => {}
+		}
+	}
+	None
+}
+
+/// Parse an attribute value (quoted or unquoted) from raw attribute text
+fn attribute_value<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+	let mut rest = attributes;
+	while !rest.is_empty() {
+		rest = rest.trim_start();
+		let name_end = rest
+			.find(|c: char| c == '=' || c.is_whitespace())
+			.unwrap_or(rest.len());
+		let attribute_name = &rest[..name_end];
+		rest = rest[name_end..].trim_start();
+
+		let value = if let Some(after_eq) = rest.strip_prefix('=') {
+			let after_eq = after_eq.trim_start();
+			let (value, remaining) = match after_eq.chars().next() {
+				Some(q @ ('"' | '\'')) => {
+					let end = after_eq[1..].find(q)? + 1;
+					(&after_eq[1..end], &after_eq[end + 1..])
+				}
+				_ => {
+					let end = after_eq
+						.find(char::is_whitespace)
+						.unwrap_or(after_eq.len());
+					(&after_eq[..end], &after_eq[end..])
+				}
+			};
+			rest = remaining;
+			Some(value)
+		} else {
+			None
+		};
+
+		if attribute_name.eq_ignore_ascii_case(name) {
+			return Some(value.unwrap_or(""));
+		}
+		if attribute_name.is_empty() && value.is_none() {
+			break;
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_blocks_vue() {
+		let source = r#"<template><div /></template>
+<script setup lang="ts">
+const x = 1;
+</script>
+<style scoped>
+a { color: red; }
+</style>
+"#;
+		let found = blocks(source);
+		assert_eq!(found.len(), 2);
+		assert_eq!(found[0].tag, "script");
+		assert_eq!(found[0].lang(), Some("ts"));
+		assert_eq!(&source[found[0].content.clone()], "\nconst x = 1;\n");
+		assert_eq!(found[1].tag, "style");
+		assert_eq!(found[1].lang(), None);
+		assert_eq!(found[1].attribute("scoped"), Some(""));
+	}
+
+	#[test]
+	fn test_blocks_skip_comments_and_lookalikes() {
+		let source = "<!-- <script>bad</script> --><scripts></scripts>\
+			<SCRIPT>ok</SCRIPT>";
+		let found = blocks(source);
+		assert_eq!(found.len(), 1);
+		assert_eq!(&source[found[0].content.clone()], "ok");
+	}
+
+	#[test]
+	fn test_blocks_quoted_gt_in_attribute() {
+		let source = r#"<script data-x="a>b" lang='tsx'>y</script>"#;
+		let found = blocks(source);
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].lang(), Some("tsx"));
+		assert_eq!(&source[found[0].content.clone()], "y");
+	}
+
+	#[test]
+	fn test_block_file_type() {
+		let source = "<script setup lang=\"TSX\"></script>\
+			<style lang=\"postcss\"></style><style lang=\"scss\"></style>\
+			<style lang=\"stylus\"></style><script></script>";
+		let types: Vec<_> =
+			blocks(source).iter().map(Block::file_type).collect();
+		assert_eq!(
+			types,
+			[
+				Some(FileType::Tsx),
+				Some(FileType::Css),
+				Some(FileType::Scss),
+				None,
+				Some(FileType::JavaScript),
+			]
+		);
+	}
+
+	#[test]
+	fn test_blocks_unterminated() {
+		assert!(blocks("<script>const x = 1;").is_empty());
+	}
+
+	#[test]
+	fn test_attribute_value_unquoted() {
+		assert_eq!(attribute_value("setup lang=ts", "lang"), Some("ts"));
+		assert_eq!(attribute_value("setup lang=ts", "setup"), Some(""));
+		assert_eq!(attribute_value("setup", "lang"), None);
+	}
+}
@@ -1,35 +1,105 @@
-//! Clang-format WASM-based formatter for C/C++/Objective-C/Java/Protobuf/C#
+//! Clang-format WASM-based formatter for C/C++/Objective-C/Java/Protobuf/C#,
+//! plus CUDA and Metal/HLSL/GLSL shaders
 //!
 //! This formatter uses a standalone WASM module compiled from clang-format
 //! and runs it via wasmi with WASI support.
 
+use std::cell::RefCell;
 use std::sync::OnceLock;
 
-use fama_common::{FileType, IndentStyle, CONFIG};
+use fama_common::{
+	FileType, IndentStyle, PointerAlignment, ShortFunctions, CONFIG,
+};
 use wasmi::{Engine, Instance, Linker, Memory, Module, Store, TypedFunc};
 use wasmi_wasi::{WasiCtx, WasiCtxBuilder};
 
 /// Embedded clang-format WASM binary
 const CLANG_FORMAT_WASM: &[u8] = include_bytes!("../wasm/clang-format.wasm");
 
-/// Generate clang-format style configuration based on fama's FormatConfig
+/// clang-format `Language:` sections in the generated style. clang-format
+/// picks a file's language from its extension, so `.mm` uses ObjC and
+/// `.cu`, `.cuh`, `.metal`, `.hlsl` and `.glsl` use Cpp; a header is
+/// ObjC when its content looks like it.
+const LANGUAGES: &[&str] = &["Cpp", "ObjC", "Java", "CSharp", "Proto", "Json"];
+
+const POINTER_ALIGNMENT: &str = match CONFIG.pointer_alignment {
+	PointerAlignment::Left => "Left",
+	PointerAlignment::Right => "Right",
+	PointerAlignment::Middle => "Middle",
+};
+const SHORT_FUNCTIONS: &str = match CONFIG.short_functions {
+	ShortFunctions::None => "None",
+	ShortFunctions::Empty => "Empty",
+	ShortFunctions::Inline => "Inline",
+	ShortFunctions::All => "All",
+};
+
+/// `#include` blocks for `group_includes`. clang-format gives the main
+/// header (`foo.h` for `foo.cpp`) priority 0 on its own, so it comes
+/// first; project headers follow, then system headers.
+const INCLUDE_GROUPS: &str = "IncludeBlocks: Regroup, \
+	IncludeCategories: [\
+	{Regex: '^\"', Priority: 1}, \
+	{Regex: '^<', Priority: 2}, \
+	{Regex: '.*', Priority: 3}]";
+
+/// Style keys for one language beyond the ones shared by every language
+fn language_options(language: &str) -> String {
+	let pointer = format!("PointerAlignment: {}", POINTER_ALIGNMENT);
+	let functions =
+		format!("AllowShortFunctionsOnASingleLine: {}", SHORT_FUNCTIONS);
+	let sort = format!("SortIncludes: {}", CONFIG.sort_includes);
+	let options = match language {
+		"Cpp" | "ObjC" if CONFIG.group_includes => {
+			vec![pointer, functions, sort, INCLUDE_GROUPS.to_string()]
+		}
+		"Cpp" | "ObjC" => vec![pointer, functions, sort],
+		"Java" => vec![functions, sort],
+		"CSharp" => vec![pointer, functions],
+		_ => vec![],
+	};
+	options
+		.iter()
+		.map(|option| format!(", {}", option))
+		.collect()
+}
+
+/// Generate clang-format style configuration based on fama's FormatConfig,
+/// one YAML document per language in [`LANGUAGES`]. The whole style is set
+/// once per instance; clang-format applies the section matching each file.
 fn generate_style_config() -> String {
 	let use_tab = matches!(CONFIG.indent_style, IndentStyle::Tabs);
 	let indent_width = CONFIG.indent_width;
 	let column_limit = CONFIG.line_width;
 
 	// YAML-style inline config for clang-format
-	format!(
-		"{{BasedOnStyle: LLVM, \
-		UseTab: {}, \
-		IndentWidth: {}, \
-		TabWidth: {}, \
-		ColumnLimit: {}}}",
-		if use_tab { "Always" } else { "Never" },
-		indent_width,
-		indent_width,
-		column_limit,
-	)
+	LANGUAGES
+		.iter()
+		.map(|language| {
+			format!(
+				"{{Language: {}, \
+				BasedOnStyle: LLVM, \
+				UseTab: {}, \
+				IndentWidth: {}, \
+				TabWidth: {}, \
+				ColumnLimit: {}{}}}",
+				language,
+				if use_tab { "Always" } else { "Never" },
+				indent_width,
+				indent_width,
+				column_limit,
+				language_options(language),
+			)
+		})
+		.collect::<Vec<_>>()
+		.join("\n---\n")
+}
+
+static STYLE: OnceLock<String> = OnceLock::new();
+
+/// The generated style, built once per process since `CONFIG` is constant
+pub fn style_config() -> &'static str {
+	STYLE.get_or_init(generate_style_config)
 }
 
 /// Store context for WASI + our custom imports
@@ -45,6 +115,14 @@ struct CachedInstance {
 
 static CACHED_MODULE: OnceLock<CachedInstance> = OnceLock::new();
 
+thread_local! {
+	/// Initialized instance reused by every file formatted on this thread.
+	/// Dropped after a failure, so a trap cannot leave later files with a
+	/// broken instance.
+	static INSTANCE: RefCell<Option<(Store<StoreCtx>, Instance, Memory)>> =
+		const { RefCell::new(None) };
+}
+
 fn get_cached_module() -> &'static CachedInstance {
 	CACHED_MODULE.get_or_init(|| {
 		let engine = Engine::default();
@@ -54,8 +132,10 @@ fn get_cached_module() -> &'static CachedInstance {
 	})
 }
 
-/// Create a new store and instance for formatting
-fn create_instance() -> Result<(Store<StoreCtx>, Instance, Memory), String> {
+/// Create a new store and instance formatting with `style`
+fn create_instance(
+	style: &str,
+) -> Result<(Store<StoreCtx>, Instance, Memory), String> {
 	let cached = get_cached_module();
 
 	// Create WASI context
@@ -94,17 +174,17 @@ fn create_instance() -> Result<(Store<StoreCtx>, Instance, Memory), String> {
 	init.call(&mut store, ())
 		.map_err(|e| format!("Failed to call wasm_init: {}", e))?;
 
-	// Set formatting style based on fama config
-	let style = generate_style_config();
+	// Set formatting style. The module only stores it here; a style
+	// clang-format can't parse makes each wasm_format call fail instead.
 	let style_ptr =
-		write_string_to_memory(&mut store, &memory, &instance, &style)?;
+		write_string_to_memory(&mut store, &memory, &instance, style)?;
 	let style_len = style.len() as i32;
 
 	let set_style: TypedFunc<(i32, i32), i32> = instance
 		.get_typed_func(&store, "wasm_set_style")
 		.map_err(|e| format!("Failed to get wasm_set_style: {}", e))?;
 
-	set_style
+	let status = set_style
 		.call(&mut store, (style_ptr, style_len))
 		.map_err(|e| format!("Failed to set style: {}", e))?;
 
@@ -116,6 +196,9 @@ fn create_instance() -> Result<(Store<StoreCtx>, Instance, Memory), String> {
 	free.call(&mut store, style_ptr)
 		.map_err(|e| format!("Failed to free style: {}", e))?;
 
+	if status != 0 {
+		return Err(format!("Failed to set style: status {}", status));
+	}
 	Ok((store, instance, memory))
 }
 
@@ -254,6 +337,9 @@ fn read_string_from_memory(
 
 /// Format code using clang-format WASM
 ///
+/// Reuses this thread's initialized instance, so only the first file on
+/// each thread pays for instantiation and style setup.
+///
 /// # Arguments
 /// * `content` - The source code to format
 /// * `path` - The file path (used to determine language)
@@ -267,69 +353,102 @@ pub fn format_file(
 	path: &str,
 	_file_type: FileType,
 ) -> Result<String, String> {
-	let (mut store, instance, memory) = create_instance()?;
+	INSTANCE.with(|current| {
+		let mut current = current.borrow_mut();
+		let (store, instance, memory) = match current.as_mut() {
+			Some(instance) => instance,
+			None => current.insert(create_instance(style_config())?),
+		};
+		let result =
+			format_with_instance(store, instance, memory, content, path);
+		if result.is_err() {
+			*current = None;
+		}
+		result
+	})
+}
+
+/// Format several `(path, content)` files, reusing one WASM instance
+///
+/// Instantiation and style setup run once per thread instead of once per
+/// file, so this is `format_file` in a loop.
+pub fn format_batch(files: &[(&str, &str)]) -> Vec<Result<String, String>> {
+	files
+		.iter()
+		.map(|(path, content)| format_file(content, path, FileType::Unknown))
+		.collect()
+}
 
+/// Format one file on an already initialized instance
+fn format_with_instance(
+	store: &mut Store<StoreCtx>,
+	instance: &Instance,
+	memory: &Memory,
+	content: &str,
+	path: &str,
+) -> Result<String, String> {
 	// Write input strings to WASM memory
-	let code_ptr =
-		write_string_to_memory(&mut store, &memory, &instance, content)?;
+	let code_ptr = write_string_to_memory(store, memory, instance, content)?;
 	let code_len = content.len() as i32;
 
-	let filename_ptr =
-		write_string_to_memory(&mut store, &memory, &instance, path)?;
+	let filename_ptr = write_string_to_memory(store, memory, instance, path)?;
 	let filename_len = path.len() as i32;
 
 	// Get the format function
 	let format: TypedFunc<(i32, i32, i32, i32), i32> = instance
-		.get_typed_func(&store, "wasm_format")
+		.get_typed_func(&*store, "wasm_format")
 		.map_err(|e| format!("Failed to get wasm_format: {}", e))?;
 
 	// Call format
 	let status = format
-		.call(&mut store, (code_ptr, code_len, filename_ptr, filename_len))
+		.call(
+			&mut *store,
+			(code_ptr, code_len, filename_ptr, filename_len),
+		)
 		.map_err(|e| format!("Failed to call wasm_format: {}", e))?;
 
 	// Free input memory
 	let free: TypedFunc<i32, ()> = instance
-		.get_typed_func(&store, "free")
+		.get_typed_func(&*store, "free")
 		.map_err(|e| format!("Failed to get free: {}", e))?;
 
-	free.call(&mut store, code_ptr)
+	free.call(&mut *store, code_ptr)
 		.map_err(|e| format!("Failed to free code: {}", e))?;
-	free.call(&mut store, filename_ptr)
+	free.call(&mut *store, filename_ptr)
 		.map_err(|e| format!("Failed to free filename: {}", e))?;
 
 	match status {
 		0 => {
 			// Success - get the result
 			let get_ptr: TypedFunc<(), i32> = instance
-				.get_typed_func(&store, "wasm_get_result_ptr")
+				.get_typed_func(&*store, "wasm_get_result_ptr")
 				.map_err(|e| {
 					format!("Failed to get wasm_get_result_ptr: {}", e)
 				})?;
 			let get_len: TypedFunc<(), i32> = instance
-				.get_typed_func(&store, "wasm_get_result_len")
+				.get_typed_func(&*store, "wasm_get_result_len")
 				.map_err(|e| {
 					format!("Failed to get wasm_get_result_len: {}", e)
 				})?;
 			let free_result: TypedFunc<(), ()> = instance
-				.get_typed_func(&store, "wasm_free_result")
+				.get_typed_func(&*store, "wasm_free_result")
 				.map_err(|e| {
 					format!("Failed to get wasm_free_result: {}", e)
 				})?;
 
 			let result_ptr = get_ptr
-				.call(&mut store, ())
+				.call(&mut *store, ())
 				.map_err(|e| format!("Failed to get result ptr: {}", e))?;
 			let result_len = get_len
-				.call(&mut store, ())
+				.call(&mut *store, ())
 				.map_err(|e| format!("Failed to get result len: {}", e))?;
 
 			let result = read_string_from_memory(
-				&store, &memory, result_ptr, result_len,
+				&*store, memory, result_ptr, result_len,
 			)?;
 
 			free_result
-				.call(&mut store, ())
+				.call(&mut *store, ())
 				.map_err(|e| format!("Failed to free result: {}", e))?;
 
 			Ok(result)
@@ -337,33 +456,33 @@ pub fn format_file(
 		1 => {
 			// Error - get error message
 			let get_ptr: TypedFunc<(), i32> = instance
-				.get_typed_func(&store, "wasm_get_result_ptr")
+				.get_typed_func(&*store, "wasm_get_result_ptr")
 				.map_err(|e| {
 					format!("Failed to get wasm_get_result_ptr: {}", e)
 				})?;
 			let get_len: TypedFunc<(), i32> = instance
-				.get_typed_func(&store, "wasm_get_result_len")
+				.get_typed_func(&*store, "wasm_get_result_len")
 				.map_err(|e| {
 					format!("Failed to get wasm_get_result_len: {}", e)
 				})?;
 			let free_result: TypedFunc<(), ()> = instance
-				.get_typed_func(&store, "wasm_free_result")
+				.get_typed_func(&*store, "wasm_free_result")
 				.map_err(|e| {
 					format!("Failed to get wasm_free_result: {}", e)
 				})?;
 
 			let err_ptr = get_ptr
-				.call(&mut store, ())
+				.call(&mut *store, ())
 				.map_err(|e| format!("Failed to get error ptr: {}", e))?;
 			let err_len = get_len
-				.call(&mut store, ())
+				.call(&mut *store, ())
 				.map_err(|e| format!("Failed to get error len: {}", e))?;
 
 			let error_msg =
-				read_string_from_memory(&store, &memory, err_ptr, err_len)?;
+				read_string_from_memory(&*store, memory, err_ptr, err_len)?;
 
 			free_result
-				.call(&mut store, ())
+				.call(&mut *store, ())
 				.map_err(|e| format!("Failed to free error result: {}", e))?;
 
 			Err(error_msg)
@@ -411,6 +530,19 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_invalid_style_fails_formatting() {
+		let (mut store, instance, memory) =
+			create_instance("{IndentWidth: wide}").unwrap();
+		let result = format_with_instance(
+			&mut store, &instance, &memory, "int x;\n", "test.c",
+		);
+		assert_eq!(
+			result,
+			Err("Error parsing -style: Invalid argument".to_string())
+		);
+	}
+
 	#[test]
 	fn test_style_config_generation() {
 		let style = generate_style_config();
@@ -418,5 +550,69 @@ mod tests {
 		assert!(style.contains("UseTab: Always"), "Style: {}", style);
 		assert!(style.contains("IndentWidth: 4"), "Style: {}", style);
 		assert!(style.contains("ColumnLimit: 80"), "Style: {}", style);
+		assert!(style.starts_with("{Language: Cpp, "), "Style: {}", style);
+		assert_eq!(style.matches("\n---\n").count(), LANGUAGES.len() - 1);
+	}
+
+	#[test]
+	fn test_style_config_language_options() {
+		let style = generate_style_config();
+		let section = |language: &str| {
+			style
+				.split("\n---\n")
+				.find(|doc| doc.contains(&format!("Language: {},", language)))
+				.unwrap()
+				.to_string()
+		};
+		assert!(section("Cpp").contains("PointerAlignment: Right"));
+		assert!(section("ObjC").contains("SortIncludes: true"));
+		assert!(
+			section("Java").contains("AllowShortFunctionsOnASingleLine: All")
+		);
+		assert!(!section("Java").contains("PointerAlignment"));
+		assert!(section("Json").ends_with("ColumnLimit: 80}"));
+		assert_eq!(
+			section("Cpp").contains(INCLUDE_GROUPS),
+			CONFIG.group_includes
+		);
+		assert!(!section("Java").contains("IncludeBlocks"));
+	}
+
+	#[test]
+	fn test_format_objective_cpp_and_cuda() {
+		let input = "@interface Foo\n@end\nclass Bar{public:void baz(){}};";
+		let result = format_file(input, "test.mm", FileType::ObjectiveCpp);
+		assert!(result.is_ok(), "Format failed: {:?}", result);
+		let input = "__global__ void k(float*x){x[threadIdx.x]=0;}";
+		let result = format_file(input, "kernel.cu", FileType::Cuda);
+		assert!(result.unwrap().contains("x[threadIdx.x] = 0;"));
+	}
+
+	#[test]
+	fn test_style_config_is_cached() {
+		assert!(std::ptr::eq(style_config(), style_config()));
+		assert_eq!(style_config(), generate_style_config());
+	}
+
+	#[test]
+	fn test_reused_instance_gives_same_output() {
+		let input = "int main(){int x=1;return x;}";
+		let first = format_file(input, "a.c", FileType::C);
+		assert!(first.is_ok(), "Format failed: {:?}", first);
+		assert!(format_file("int y;", "b.c", FileType::C).is_ok());
+		assert_eq!(format_file(input, "a.c", FileType::C), first);
+	}
+
+	#[test]
+	fn test_format_batch_matches_single_file() {
+		let files = [
+			("a.c", "int main(){return 0;}"),
+			("b.cpp", "class Foo{public:void bar(){}};"),
+		];
+		let results = format_batch(&files);
+		assert_eq!(results.len(), 2);
+		for ((path, input), result) in files.iter().zip(results) {
+			assert_eq!(result, format_file(input, path, FileType::Unknown));
+		}
 	}
 }
@@ -24,7 +24,7 @@ pub fn format_dockerfile(
 	let fmt_config = FormatConfig::default();
 
 	let new_line_kind = match fmt_config.line_ending {
-		LineEnding::Lf => NewLineKind::LineFeed,
+		LineEnding::Lf | LineEnding::Auto => NewLineKind::LineFeed,
 		LineEnding::Crlf => NewLineKind::CarriageReturnLineFeed,
 	};
 
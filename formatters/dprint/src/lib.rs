@@ -5,14 +5,19 @@
 
 #![allow(clippy::all)]
 
+pub mod links;
+pub mod tables;
+pub mod toc;
+
 use dprint_core::configuration::NewLineKind;
-use fama_common::{FileType, CONFIG};
+use fama_common::{FileType, MarkdownTextWrap, CONFIG};
 
 // Module-level constants - pre-converted config values
-const DPRINT_LINE_WIDTH: u16 = CONFIG.line_width;
 const DPRINT_INDENT_WIDTH: u8 = CONFIG.indent_width;
 const DPRINT_NEW_LINE_KIND: NewLineKind = match CONFIG.line_ending {
-	fama_common::LineEnding::Lf => NewLineKind::LineFeed,
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
+		NewLineKind::LineFeed
+	}
 	fama_common::LineEnding::Crlf => NewLineKind::CarriageReturnLineFeed,
 };
 const DPRINT_USE_TABS: bool =
@@ -20,7 +25,9 @@ const DPRINT_USE_TABS: bool =
 
 // Malva constants
 const MALVA_LINE_BREAK: malva::config::LineBreak = match CONFIG.line_ending {
-	fama_common::LineEnding::Lf => malva::config::LineBreak::Lf,
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
+		malva::config::LineBreak::Lf
+	}
 	fama_common::LineEnding::Crlf => malva::config::LineBreak::Crlf,
 };
 const MALVA_QUOTES: malva::config::Quotes = match CONFIG.quote_style {
@@ -32,21 +39,28 @@ const MALVA_TRAILING_COMMA: bool = matches!(CONFIG.trailing_comma, fama_common::
 // YAML constants
 const YAML_LINE_BREAK: pretty_yaml::config::LineBreak = match CONFIG.line_ending
 {
-	fama_common::LineEnding::Lf => pretty_yaml::config::LineBreak::Lf,
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
+		pretty_yaml::config::LineBreak::Lf
+	}
 	fama_common::LineEnding::Crlf => pretty_yaml::config::LineBreak::Crlf,
 };
 
 /// Format Markdown source code with specified options
 pub fn format_markdown(
 	source: &str,
-	_file_path: &str,
+	file_path: &str,
 ) -> Result<String, String> {
 	use dprint_plugin_markdown::configuration::*;
 
 	let config = Configuration {
-		line_width: DPRINT_LINE_WIDTH as u32,
+		// Only prose is wrapped; code blocks are left as written
+		line_width: CONFIG.prose_width_for(file_path) as u32,
 		new_line_kind: DPRINT_NEW_LINE_KIND,
-		text_wrap: TextWrap::Maintain,
+		text_wrap: match CONFIG.markdown_text_wrap {
+			MarkdownTextWrap::Always => TextWrap::Always,
+			MarkdownTextWrap::Never => TextWrap::Never,
+			MarkdownTextWrap::Maintain => TextWrap::Maintain,
+		},
 		emphasis_kind: EmphasisKind::Underscores,
 		strong_kind: StrongKind::Asterisks,
 		unordered_list_kind: UnorderedListKind::Dashes,
@@ -63,27 +77,37 @@ pub fn format_markdown(
 		 _line_width: u32|
 		 -> Result<Option<String>, anyhow::Error> { Ok(None) };
 
-	match dprint_plugin_markdown::format_text(
+	let formatted = match dprint_plugin_markdown::format_text(
 		source,
 		&config,
 		format_code_block,
 	) {
-		Ok(Some(result)) => Ok(result),
-		Ok(None) => {
-			// No changes needed, return original content
-			Ok(source.to_string())
-		}
-		Err(e) => Err(format!("Markdown formatting error: {}", e)),
-	}
+		Ok(Some(result)) => result,
+		// No changes needed, return original content
+		Ok(None) => source.to_string(),
+		Err(e) => return Err(format!("Markdown formatting error: {}", e)),
+	};
+
+	let formatted = if CONFIG.markdown_format_tables {
+		formatted
+	} else {
+		tables::restore(source, &formatted)
+	};
+	let formatted = if CONFIG.markdown_toc {
+		toc::refresh(&formatted)
+	} else {
+		formatted
+	};
+	Ok(CONFIG.apply_final_newline(formatted, FileType::Markdown))
 }
 
 /// Format YAML source code with specified options
-pub fn format_yaml(source: &str, _file_path: &str) -> Result<String, String> {
+pub fn format_yaml(source: &str, file_path: &str) -> Result<String, String> {
 	use pretty_yaml::config::{FormatOptions, LanguageOptions, LayoutOptions};
 
 	let config = FormatOptions {
 		layout: LayoutOptions {
-			print_width: DPRINT_LINE_WIDTH as usize,
+			print_width: CONFIG.line_width_for(file_path) as usize,
 			indent_width: DPRINT_INDENT_WIDTH as usize,
 			line_break: YAML_LINE_BREAK,
 		},
@@ -91,16 +115,42 @@ pub fn format_yaml(source: &str, _file_path: &str) -> Result<String, String> {
 	};
 
 	pretty_yaml::format_text(source, &config)
+		.map(|formatted| CONFIG.apply_final_newline(formatted, FileType::Yaml))
 		.map_err(|e| format!("YAML formatting error: {}", e))
 }
 
-/// Create Malva options from format config
-fn malva_options() -> malva::config::FormatOptions {
+/// Check that `formatted` loads to the same YAML documents as `source`.
+///
+/// Compares the loaded data model (keys, values, and their order), so
+/// comments and layout are free to change but data is not.
+pub fn validate_yaml(source: &str, formatted: &str) -> Result<(), String> {
+	use yaml_rust2::YamlLoader;
+
+	let before = YamlLoader::load_from_str(source)
+		.map_err(|e| format!("YAML parse error in input: {}", e))?;
+	let after = YamlLoader::load_from_str(formatted)
+		.map_err(|e| format!("YAML parse error in output: {}", e))?;
+
+	if before.len() != after.len() {
+		return Err(format!(
+			"document count changed from {} to {}",
+			before.len(),
+			after.len()
+		));
+	}
+	match before.iter().zip(&after).position(|(a, b)| a != b) {
+		Some(i) => Err(format!("document {} changed", i + 1)),
+		None => Ok(()),
+	}
+}
+
+/// Create Malva options from format config for the file at `file_path`
+fn malva_options(file_path: &str) -> malva::config::FormatOptions {
 	use malva::config::{LanguageOptions, LayoutOptions};
 
 	malva::config::FormatOptions {
 		layout: LayoutOptions {
-			print_width: DPRINT_LINE_WIDTH as usize,
+			print_width: CONFIG.line_width_for(file_path) as usize,
 			use_tabs: DPRINT_USE_TABS,
 			indent_width: DPRINT_INDENT_WIDTH as usize,
 			line_break: MALVA_LINE_BREAK,
@@ -114,30 +164,30 @@ fn malva_options() -> malva::config::FormatOptions {
 }
 
 /// Format CSS source code using Malva formatter
-pub fn format_css(source: &str, _file_path: &str) -> Result<String, String> {
+pub fn format_css(source: &str, file_path: &str) -> Result<String, String> {
 	use malva::{format_text, Syntax};
-	format_text(source, Syntax::Css, &malva_options())
+	format_text(source, Syntax::Css, &malva_options(file_path))
 		.map_err(|e| format!("CSS formatting error: {}", e))
 }
 
 /// Format SCSS source code using Malva formatter
-pub fn format_scss(source: &str, _file_path: &str) -> Result<String, String> {
+pub fn format_scss(source: &str, file_path: &str) -> Result<String, String> {
 	use malva::{format_text, Syntax};
-	format_text(source, Syntax::Scss, &malva_options())
+	format_text(source, Syntax::Scss, &malva_options(file_path))
 		.map_err(|e| format!("SCSS formatting error: {}", e))
 }
 
 /// Format LESS source code using Malva formatter
-pub fn format_less(source: &str, _file_path: &str) -> Result<String, String> {
+pub fn format_less(source: &str, file_path: &str) -> Result<String, String> {
 	use malva::{format_text, Syntax};
-	format_text(source, Syntax::Less, &malva_options())
+	format_text(source, Syntax::Less, &malva_options(file_path))
 		.map_err(|e| format!("LESS formatting error: {}", e))
 }
 
 /// Format SASS source code using Malva formatter
-pub fn format_sass(source: &str, _file_path: &str) -> Result<String, String> {
+pub fn format_sass(source: &str, file_path: &str) -> Result<String, String> {
 	use malva::{format_text, Syntax};
-	format_text(source, Syntax::Sass, &malva_options())
+	format_text(source, Syntax::Sass, &malva_options(file_path))
 		.map_err(|e| format!("SASS formatting error: {}", e))
 }
 
@@ -179,6 +229,54 @@ mod tests {
 		assert!(result.contains("name") || result.contains("age"));
 	}
 
+	#[test]
+	fn test_format_yaml_preserves_comments_anchors_and_order() {
+		// pretty_yaml works on the CST, unlike a serde round-trip
+		let source = "# header
+zeta: 1 # trailing
+base: &base
+  a: 1
+alpha:
+  <<: *base
+";
+		let result = format_yaml(source, "test.yaml").unwrap();
+		assert!(result.contains("# header"));
+		assert!(result.contains("# trailing"));
+		assert!(result.contains("&base"));
+		assert!(result.contains("*base"));
+		let zeta = result.find("zeta").unwrap();
+		let alpha = result.find("alpha").unwrap();
+		assert!(zeta < alpha, "Key order should be kept. Got: {}", result);
+	}
+
+	#[test]
+	fn test_markdown_counts_display_width() {
+		// Columns are padded to the widest cell as displayed, two columns
+		// per CJK character, whatever `width_measure` says
+		let source = "| 名前 | x |\n|-|-|\n| 山田太郎 | abcd |\n";
+		assert_eq!(
+			format_markdown(source, "test.md").unwrap(),
+			concat!(
+				"| 名前     | x    |\n",
+				"| -------- | ---- |\n",
+				"| 山田太郎 | abcd |\n",
+			)
+		);
+	}
+
+	#[test]
+	fn test_yaml_counts_utf8_bytes() {
+		// pretty_yaml measures lines in UTF-8 bytes, three per CJK
+		// character: seven items take 80 bytes and fit, eight take 91 and
+		// are broken up, though they are 44 code points and 67 columns wide
+		let flow =
+			|n: usize| format!("k: [{}]\n", vec!["日本語"; n].join(", "));
+		assert_eq!(CONFIG.line_width_for("test.yaml"), 80);
+		assert_eq!(format_yaml(&flow(7), "test.yaml").unwrap(), flow(7));
+		let broken = format_yaml(&flow(8), "test.yaml").unwrap();
+		assert_eq!(broken.lines().count(), 10, "Got: {}", broken);
+	}
+
 	#[test]
 	fn test_format_css() {
 		let source = "body{margin:0;padding:0;}";
@@ -222,4 +320,12 @@ mod tests {
 		let result = format_file(source, "test.js", FileType::JavaScript);
 		assert!(result.is_err());
 	}
+
+	#[test]
+	fn test_validate_yaml() {
+		let source = "a:   1\nb: [x, y] # note\n";
+		assert!(validate_yaml(source, "a: 1\nb:\n  - x\n  - y\n").is_ok());
+		assert!(validate_yaml(source, "a: 1\nb: [x]\n").is_err());
+		assert!(validate_yaml(source, "a: 1\n---\nb: [x, y]\n").is_err());
+	}
 }
@@ -0,0 +1,224 @@
+// links.rs - Broken anchor and relative link checks for Markdown
+//
+// `[text](#anchor)` must match a heading or an HTML `id`/`name` in the
+// same document, and `[text](other.md#anchor)` a file next to it (and a
+// heading in it). External URLs are not fetched. Results are warnings for
+// the caller to report; the document itself is never changed.
+
+use std::path::Path;
+
+use crate::toc;
+
+/// A link whose target does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+	/// 1-based line of the link
+	pub line: usize,
+	/// Link target as written
+	pub target: String,
+	pub reason: &'static str,
+}
+
+/// Links in `text`, a Markdown file at `file_path`, that point nowhere
+pub fn check(text: &str, file_path: &str) -> Vec<BrokenLink> {
+	let dir = Path::new(file_path).parent().unwrap_or(Path::new(""));
+	let own_anchors = anchors(text);
+	let mut broken = Vec::new();
+
+	for (number, line) in prose_lines(text) {
+		for target in targets(line) {
+			if let Some(reason) = broken_reason(&target, dir, &own_anchors) {
+				broken.push(BrokenLink {
+					line: number + 1,
+					target,
+					reason,
+				});
+			}
+		}
+	}
+	broken
+}
+
+/// Why `target`, linked from a file in `dir` whose anchors are
+/// `own_anchors`, points nowhere, or None if it resolves
+fn broken_reason(
+	target: &str,
+	dir: &Path,
+	own_anchors: &[String],
+) -> Option<&'static str> {
+	let (path, fragment) = match target.split_once('#') {
+		Some((path, fragment)) => (path, Some(decode(fragment).to_lowercase())),
+		None => (target, None),
+	};
+	if path.is_empty() {
+		return fragment
+			.filter(|f| !own_anchors.contains(f))
+			.map(|_| "no heading or id with this anchor");
+	}
+
+	let linked = dir.join(decode(path));
+	if !linked.exists() {
+		return Some("file not found");
+	}
+	let is_markdown = linked
+		.extension()
+		.is_some_and(|ext| ext == "md" || ext == "markdown");
+	let fragment = fragment.filter(|_| is_markdown)?;
+	let linked_text = std::fs::read_to_string(&linked).ok()?;
+	(!anchors(&linked_text).contains(&fragment))
+		.then_some("no heading or id with this anchor in the linked file")
+}
+
+/// Heading anchors and HTML `id`/`name` values of a document, lowercased
+fn anchors(text: &str) -> Vec<String> {
+	let mut anchors = toc::anchors(&toc::headings(text));
+	for attribute in ["id=\"", "name=\""] {
+		for (i, _) in text.match_indices(attribute) {
+			let value = &text[i + attribute.len()..];
+			if let Some(end) = value.find('"') {
+				anchors.push(value[..end].to_lowercase());
+			}
+		}
+	}
+	anchors
+}
+
+/// Lines outside fenced and indented code blocks, numbered from 0
+fn prose_lines(text: &str) -> Vec<(usize, &str)> {
+	let mut lines = Vec::new();
+	let mut open_fence: Option<(char, usize)> = None;
+	for (number, line) in text.lines().enumerate() {
+		let trimmed = line.trim();
+		if let Some(open) = open_fence {
+			if toc::closes_fence(trimmed, open) {
+				open_fence = None;
+			}
+		} else if let Some(fence) = toc::fence(trimmed) {
+			open_fence = Some(fence);
+		} else if line.len() - line.trim_start().len() <= 3 {
+			lines.push((number, line));
+		}
+	}
+	lines
+}
+
+/// Local targets of the inline links, images and link reference
+/// definitions on a line, skipping code spans and URLs with a scheme
+fn targets(line: &str) -> Vec<String> {
+	let line = without_code_spans(line);
+	let mut found: Vec<&str> = Vec::new();
+
+	let trimmed = line.trim_start();
+	if trimmed.starts_with('[') {
+		if let Some((_, target)) = trimmed.split_once("]: ") {
+			found.push(target.trim());
+		}
+	}
+	for (i, _) in line.match_indices("](") {
+		let rest = &line[i + 2..];
+		let end = rest.find(')').unwrap_or(rest.len());
+		found.push(&rest[..end]);
+	}
+
+	found
+		.into_iter()
+		.filter_map(|target| {
+			// `<with spaces.md>` or `target "title"`
+			let target = match target.strip_prefix('<') {
+				Some(rest) => rest.split('>').next()?,
+				None => target.split_whitespace().next()?,
+			};
+			let external = target.contains("://")
+				|| target.starts_with(['/', '{'])
+				|| target.starts_with("mailto:")
+				|| target.starts_with("tel:");
+			(!target.is_empty() && !external).then(|| target.to_string())
+		})
+		.collect()
+}
+
+/// `line` with the contents of every code span blanked out
+fn without_code_spans(line: &str) -> String {
+	let mut out = String::with_capacity(line.len());
+	let mut rest = line;
+	while let Some(start) = rest.find('`') {
+		let ticks = rest[start..].chars().take_while(|&c| c == '`').count();
+		let fence = &rest[start..start + ticks];
+		out.push_str(&rest[..start]);
+		match rest[start + ticks..].find(fence) {
+			Some(end) => rest = &rest[start + ticks + end + ticks..],
+			None => {
+				out.push_str(&rest[start..]);
+				rest = "";
+			}
+		}
+	}
+	out.push_str(rest);
+	out
+}
+
+/// Percent-decode `%XX` escapes, such as `%20` in file names
+fn decode(text: &str) -> String {
+	let mut bytes = Vec::with_capacity(text.len());
+	let mut input = text.bytes();
+	while let Some(b) = input.next() {
+		let escaped = (b == b'%')
+			.then(|| input.clone().take(2).collect::<Vec<u8>>())
+			.and_then(|hex| std::str::from_utf8(&hex).ok().map(str::to_owned))
+			.and_then(|hex| u8::from_str_radix(&hex, 16).ok());
+		match escaped {
+			Some(decoded) => {
+				bytes.push(decoded);
+				input.nth(1);
+			}
+			None => bytes.push(b),
+		}
+	}
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fs;
+
+	#[test]
+	fn test_targets() {
+		let line = "See [a](#a), ![b](img.png \"t\"), [c](https://x.dev) \
+		            and `[d](#d)` or [e](<my file.md>)";
+		assert_eq!(targets(line), ["#a", "img.png", "my file.md"]);
+		assert_eq!(
+			targets("[ref]: docs/guide.md#setup"),
+			["docs/guide.md#setup"]
+		);
+	}
+
+	#[test]
+	fn test_check() {
+		let dir = tempfile::tempdir().unwrap();
+		fs::write(dir.path().join("guide.md"), "# Setup\n").unwrap();
+		let text = concat!(
+			"# Usage\n\n",
+			"[ok](#usage) [ok](#Usage) [missing](#install)\n",
+			"<a id=\"custom\"></a> [ok](#custom)\n",
+			"[ok](guide.md#setup) [bad](guide.md#nope) [gone](gone.md)\n",
+			"```md\n[skipped](#nowhere)\n```\n",
+		);
+		let path = dir.path().join("README.md");
+		let broken = check(text, path.to_str().unwrap());
+		let found: Vec<(usize, &str)> = broken
+			.iter()
+			.map(|link| (link.line, link.target.as_str()))
+			.collect();
+		assert_eq!(
+			found,
+			[(3, "#install"), (5, "guide.md#nope"), (5, "gone.md")]
+		);
+	}
+
+	#[test]
+	fn test_decode() {
+		assert_eq!(decode("my%20file.md"), "my file.md");
+		assert_eq!(decode("100%"), "100%");
+	}
+}
@@ -0,0 +1,275 @@
+// tables.rs - Keep Markdown tables as written
+//
+// dprint re-pads every table so its columns line up. With
+// `markdown_format_tables` off, each table in the formatted output is
+// swapped back for the table as it was written; the rest of the document
+// is formatted as usual. Tables are found the way GFM finds them: a
+// header row and a delimiter row with the same number of cells, outside
+// code blocks. Each line is looked at a bounded number of times, so the
+// scan stays linear however the input is crafted.
+
+use std::ops::Range;
+
+use crate::toc;
+
+/// `formatted` with its tables replaced by the tables of `original`, or
+/// unchanged if the two don't have the same number of tables
+pub fn restore(original: &str, formatted: &str) -> String {
+	let old: Vec<&str> = original.split_inclusive('\n').collect();
+	let new: Vec<&str> = formatted.split_inclusive('\n').collect();
+	let (old_tables, new_tables) = (tables(&old), tables(&new));
+	if old_tables.len() != new_tables.len() {
+		return formatted.to_string();
+	}
+
+	let newline = if formatted.contains("\r\n") {
+		"\r\n"
+	} else {
+		"\n"
+	};
+	let mut out = String::with_capacity(formatted.len());
+	let mut copied = 0;
+	for (old_range, new_range) in old_tables.into_iter().zip(new_tables) {
+		new[copied..new_range.start]
+			.iter()
+			.for_each(|line| out.push_str(line));
+		for line in &old[old_range] {
+			out.push_str(line.trim_end_matches(['\r', '\n']));
+			out.push_str(newline);
+		}
+		// A table on the last line keeps the output's missing final newline
+		if new_range.end == new.len() && !formatted.ends_with('\n') {
+			out.truncate(out.len() - newline.len());
+		}
+		copied = new_range.end;
+	}
+	new[copied..].iter().for_each(|line| out.push_str(line));
+	out
+}
+
+/// Line ranges of the tables in `lines`: a row with `|`, a delimiter row
+/// like `| --- | :-: |` with as many cells, and the rows after it up to a
+/// blank line
+fn tables(lines: &[&str]) -> Vec<Range<usize>> {
+	let mut tables = Vec::new();
+	let mut open_fence = None;
+	let mut i = 0;
+	while i < lines.len() {
+		let trimmed = lines[i].trim();
+		if let Some(open) = open_fence {
+			if toc::closes_fence(trimmed, open) {
+				open_fence = None;
+			}
+		} else if let Some(fence) = toc::fence(trimmed) {
+			open_fence = Some(fence);
+		} else if trimmed.contains('|')
+			&& trimmed.len() <= MAX_ROW_LEN
+			&& lines.get(i + 1).is_some_and(|next| {
+				is_delimiter_row(next)
+					&& cells(trimmed).len() == cells(next.trim()).len()
+			}) {
+			let end = (i + 2..lines.len())
+				.find(|&j| {
+					let row = lines[j].trim();
+					row.is_empty() || !row.contains('|')
+				})
+				.unwrap_or(lines.len());
+			tables.push(i..end);
+			i = end;
+			continue;
+		}
+		i += 1;
+	}
+	tables
+}
+
+fn is_delimiter_row(line: &str) -> bool {
+	let line = line.trim();
+	line.contains(['|', ':'])
+		&& cells(line).iter().all(|cell| {
+			let cell = cell.trim();
+			let dashes = cell.strip_prefix(':').unwrap_or(cell);
+			let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+			!dashes.is_empty() && dashes.chars().all(|c| c == '-')
+		})
+}
+
+/// Header rows longer than this aren't taken for tables
+const MAX_ROW_LEN: usize = 16 * 1024;
+
+/// Cells of a trimmed table row, split at the pipes that aren't escaped
+/// or in a code span; the pipes at either end only bound the row
+fn cells(row: &str) -> Vec<&str> {
+	let bytes = row.as_bytes();
+	let mut cells = Vec::new();
+	// Backtick runs known to have no closing run, so a row of unclosed
+	// runs isn't searched again for each of them
+	let mut unclosed = Vec::new();
+	let (mut start, mut i) = (0, 0);
+	while i < bytes.len() {
+		match bytes[i] {
+			b'\\' => i += 2,
+			b'`' => {
+				let n = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+				let close = if unclosed.contains(&n) {
+					None
+				} else {
+					closing_run(&bytes[i + n..], n)
+				};
+				match close {
+					Some(end) => i += n + end,
+					None => {
+						unclosed.push(n);
+						i += n;
+					}
+				}
+			}
+			b'|' => {
+				cells.push(&row[start..i]);
+				i += 1;
+				start = i;
+			}
+			_ => i += 1,
+		}
+	}
+	cells.push(&row[start..]);
+	if row.starts_with('|') {
+		cells.remove(0);
+	}
+	if cells.len() > 1 && cells.last() == Some(&"") {
+		cells.pop();
+	}
+	cells
+}
+
+/// Offset just past the first run of exactly `n` backticks in `bytes`
+fn closing_run(bytes: &[u8], n: usize) -> Option<usize> {
+	let mut i = 0;
+	while i < bytes.len() {
+		let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+		if run == n {
+			return Some(i + n);
+		}
+		i += run.max(1);
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_restore_tables() {
+		let original = concat!(
+			"Intro  text\n\n",
+			"|a|`b | c`|\n|-|:-:|\n|$x^2$|<br>|\n\n",
+			"```\n|not|a|\n|-|-|\n```\n",
+			"| x |\n| - |",
+		);
+		let formatted = concat!(
+			"Intro text\n\n",
+			"| a     | `b | c` |\n| ----- | :------: |\n| $x^2$ | <br>     |\n\n",
+			"```\n|not|a|\n|-|-|\n```\n",
+			"| x   |\n| --- |",
+		);
+		assert_eq!(
+			restore(original, formatted),
+			concat!(
+				"Intro text\n\n",
+				"|a|`b | c`|\n|-|:-:|\n|$x^2$|<br>|\n\n",
+				"```\n|not|a|\n|-|-|\n```\n",
+				"| x |\n| - |",
+			)
+		);
+	}
+
+	#[test]
+	fn test_restore_needs_matching_tables() {
+		let formatted = "| a   |\n| --- |\n";
+		assert_eq!(restore("a\n", formatted), formatted);
+		assert!(!is_delimiter_row("---"));
+		assert!(is_delimiter_row("| :-- | --: |"));
+		assert!(!is_delimiter_row("| -:- |"));
+		assert!(!is_delimiter_row("|  |"));
+	}
+
+	#[test]
+	fn test_tables_follow_gfm() {
+		let found = |text: &str| {
+			let lines: Vec<&str> = text.split_inclusive('\n').collect();
+			tables(&lines)
+		};
+		assert_eq!(found("|a|b|\n|-|-|\n|1|2|\n\ntext\n"), vec![0..3]);
+		assert!(found("a \\| b\n|-|-|\n").is_empty());
+		assert!(found("a | b\n:-\n").is_empty());
+		assert!(found("~~~~\n|a|\n|-|\n~~~\n|b|\n~~~~\n").is_empty());
+		assert_eq!(found("```\n```\n|a|\n|-|\n"), vec![2..4]);
+		assert_eq!(found("|`a|b` \\| c|\n|-|\n"), vec![0..2]);
+		assert_eq!(found("|`a|b`` c|\n|-|-|\n"), vec![0..2]);
+	}
+
+	#[test]
+	fn test_cells_of_crafted_rows() {
+		let row = "|`".repeat(MAX_ROW_LEN);
+		let lines = [row.as_str(), &"|-".repeat(MAX_ROW_LEN)];
+		assert!(tables(&lines).is_empty());
+		let row: String = (1..150).map(|n| "`".repeat(n) + "|").collect();
+		assert_eq!(cells(&row).len(), 149);
+		let lines = [row.as_str(), "|-|\n"].repeat(10);
+		assert!(tables(&lines).is_empty());
+	}
+
+	/// Every document of up to five lines drawn from fragments that look
+	/// like tables, fences or neither
+	fn documents() -> Vec<String> {
+		const FRAGMENTS: [&str; 9] = [
+			"text\n",
+			"\n",
+			"|a|b|\n",
+			"|-|:-:|\n",
+			"a|b\n",
+			":-\n",
+			"```\n",
+			"~~~~\n",
+			"| x |\n",
+		];
+		let mut documents = vec![String::new()];
+		let mut last = documents.clone();
+		for _ in 0..5 {
+			last = last
+				.iter()
+				.flat_map(|doc| FRAGMENTS.iter().map(move |f| doc.clone() + f))
+				.collect();
+			documents.extend(last.iter().cloned());
+		}
+		documents
+	}
+
+	#[test]
+	fn test_restore_only_touches_tables() {
+		for original in documents() {
+			assert_eq!(restore(&original, &original), original);
+
+			// Re-pad the tables as dprint would, leaving everything else
+			let lines: Vec<&str> = original.split_inclusive('\n').collect();
+			let ranges = tables(&lines);
+			let formatted: String = lines
+				.iter()
+				.enumerate()
+				.map(|(i, line)| {
+					if ranges.iter().any(|range| range.contains(&i)) {
+						format!("{}\n", line.trim().replace('|', " | ").trim())
+					} else {
+						line.to_string()
+					}
+				})
+				.collect();
+			assert_eq!(
+				restore(&original, &formatted),
+				original,
+				"{original:?}"
+			);
+		}
+	}
+}
@@ -0,0 +1,302 @@
+// toc.rs - Markdown table of contents between `<!-- toc -->` markers
+//
+// With `markdown_toc` on, the list between `<!-- toc -->` and
+// `<!-- tocstop -->` is rebuilt on every run from the headings that follow
+// it, each linked by its GitHub anchor. Headings above the markers, such
+// as the document title, are left out. Files without both markers are
+// left as they are.
+
+use std::collections::HashMap;
+
+const START: &str = "<!-- toc -->";
+const STOP: &str = "<!-- tocstop -->";
+
+/// An ATX (`## Usage`) or setext (`Usage` over `---`) heading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+	/// 1 to 6
+	pub level: usize,
+	/// Heading text, inline markup included
+	pub text: String,
+	/// 0-based line the heading text is on
+	pub line: usize,
+}
+
+/// Headings outside front matter and code blocks, in document order
+pub fn headings(text: &str) -> Vec<Heading> {
+	let lines: Vec<&str> = text.lines().collect();
+	let mut headings = Vec::new();
+
+	// YAML front matter would otherwise end in a setext underline
+	let mut start = 0;
+	if lines.first() == Some(&"---") {
+		if let Some(end) = lines[1..].iter().position(|line| *line == "---") {
+			start = end + 2;
+		}
+	}
+
+	let mut open_fence: Option<(char, usize)> = None;
+	let mut paragraph: Option<(usize, &str)> = None;
+	for (number, line) in lines.iter().enumerate().skip(start) {
+		let trimmed = line.trim();
+		if let Some(open) = open_fence {
+			if closes_fence(trimmed, open) {
+				open_fence = None;
+			}
+			continue;
+		}
+		// Four spaces of indentation make an indented code block
+		if line.len() - line.trim_start().len() > 3 {
+			paragraph = None;
+			continue;
+		}
+		if let Some(f) = fence(trimmed) {
+			open_fence = Some(f);
+			paragraph = None;
+			continue;
+		}
+		if let Some((level, text)) = atx(trimmed) {
+			headings.push(Heading {
+				level,
+				text,
+				line: number,
+			});
+			paragraph = None;
+			continue;
+		}
+		let underline = match trimmed.chars().next() {
+			Some(c @ ('=' | '-')) if trimmed.chars().all(|d| d == c) => {
+				Some(if c == '=' { 1 } else { 2 })
+			}
+			_ => None,
+		};
+		match (underline, paragraph) {
+			(Some(level), Some((line, text))) => {
+				headings.push(Heading {
+					level,
+					text: text.to_string(),
+					line,
+				});
+				paragraph = None;
+			}
+			_ if trimmed.is_empty() || !starts_paragraph(trimmed) => {
+				paragraph = None;
+			}
+			_ => paragraph = Some((number, trimmed)),
+		}
+	}
+	headings
+}
+
+/// Fence character and length of a line opening or closing a code block
+pub(crate) fn fence(line: &str) -> Option<(char, usize)> {
+	let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+	let n = line.chars().take_while(|&d| d == c).count();
+	(n >= 3).then_some((c, n))
+}
+
+/// Whether `line` closes the code block `open` fence started
+pub(crate) fn closes_fence(line: &str, (c, n): (char, usize)) -> bool {
+	fence(line).is_some_and(|(d, m)| d == c && m >= n)
+		&& line.chars().all(|d| d == c)
+}
+
+/// Level and text of an ATX heading line, without a closing `#` run
+fn atx(line: &str) -> Option<(usize, String)> {
+	let level = line.chars().take_while(|&c| c == '#').count();
+	let rest = line.get(level..)?;
+	if !(1..=6).contains(&level)
+		|| !(rest.is_empty() || rest.starts_with([' ', '\t']))
+	{
+		return None;
+	}
+	let text = rest.trim();
+	let open = text.trim_end_matches('#');
+	let text = if open.is_empty() || open.ends_with([' ', '\t']) {
+		open.trim_end()
+	} else {
+		text
+	};
+	Some((level, text.to_string()))
+}
+
+/// Whether a line can be the text of a setext heading, rather than a list
+/// item, quote, table row or HTML
+fn starts_paragraph(line: &str) -> bool {
+	!(line.starts_with(['>', '|', '<'])
+		|| line.starts_with("- ")
+		|| line.starts_with("* ")
+		|| line.starts_with("+ "))
+}
+
+/// Heading text as rendered: link targets, HTML tags and code and
+/// emphasis markers dropped
+pub fn plain(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'`' | '*' | '~' | '[' => {}
+			'!' if chars.peek() == Some(&'[') => {}
+			']' if chars.peek() == Some(&'(') => {
+				chars.find(|&c| c == ')');
+			}
+			']' => {}
+			'<' if chars
+				.peek()
+				.is_some_and(|c| c.is_alphabetic() || *c == '/') =>
+			{
+				chars.find(|&c| c == '>');
+			}
+			_ => out.push(c),
+		}
+	}
+	out.trim().to_string()
+}
+
+/// GitHub's anchor for each heading: lowercased rendered text, spaces as
+/// `-`, punctuation dropped, and `-1`, `-2`, ... appended to repeats
+pub fn anchors(headings: &[Heading]) -> Vec<String> {
+	let mut seen: HashMap<String, usize> = HashMap::new();
+	headings
+		.iter()
+		.map(|heading| {
+			let slug: String = plain(&heading.text)
+				.to_lowercase()
+				.chars()
+				.filter_map(|c| match c {
+					' ' => Some('-'),
+					'-' | '_' => Some(c),
+					_ if c.is_alphanumeric() => Some(c),
+					_ => None,
+				})
+				.collect();
+			let repeats = seen.entry(slug.clone()).or_insert(0);
+			let anchor = match *repeats {
+				0 => slug,
+				n => format!("{}-{}", slug, n),
+			};
+			*repeats += 1;
+			anchor
+		})
+		.collect()
+}
+
+/// Rebuild the table of contents between the markers, if there are any
+pub fn refresh(text: &str) -> String {
+	let lines: Vec<&str> = text.split_inclusive('\n').collect();
+	let Some(start) = lines.iter().position(|line| line.trim() == START) else {
+		return text.to_string();
+	};
+	let Some(stop) = lines[start..]
+		.iter()
+		.position(|line| line.trim() == STOP)
+		.map(|i| start + i)
+	else {
+		return text.to_string();
+	};
+
+	let headings = headings(text);
+	let anchors = anchors(&headings);
+	let entries: Vec<(&Heading, &String)> = headings
+		.iter()
+		.zip(&anchors)
+		.filter(|(heading, _)| heading.line > stop)
+		.collect();
+	let top = entries.iter().map(|(heading, _)| heading.level).min();
+
+	let ending =
+		&lines[stop][lines[stop].trim_end_matches(['\r', '\n']).len()..];
+	let newline = if lines[start].ends_with("\r\n") {
+		"\r\n"
+	} else {
+		"\n"
+	};
+	let mut toc = format!("{START}{newline}{newline}");
+	for (heading, anchor) in &entries {
+		let depth = heading.level - top.unwrap_or(heading.level);
+		toc.push_str(&format!(
+			"{}- [{}](#{}){}",
+			"  ".repeat(depth),
+			plain(&heading.text),
+			anchor,
+			newline
+		));
+	}
+	if !entries.is_empty() {
+		toc.push_str(newline);
+	}
+	toc.push_str(STOP);
+	toc.push_str(ending);
+
+	let before: usize = lines[..start].iter().map(|line| line.len()).sum();
+	let after: usize = lines[..=stop].iter().map(|line| line.len()).sum();
+	format!("{}{}{}", &text[..before], toc, &text[after..])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_headings() {
+		let text = concat!(
+			"---\ntitle: x\n---\n",
+			"# Title\n",
+			"```sh\n# not a heading\n```\n",
+			"Setext\n======\n",
+			"- item\n---\n",
+			"## Closed ##\n",
+			"#hashtag\n",
+		);
+		let headings = headings(text);
+		let found: Vec<(usize, &str, usize)> = headings
+			.iter()
+			.map(|h| (h.level, h.text.as_str(), h.line))
+			.collect();
+		assert_eq!(
+			found,
+			[(1, "Title", 3), (1, "Setext", 7), (2, "Closed", 11)]
+		);
+	}
+
+	#[test]
+	fn test_anchors() {
+		let heading = |text: &str| Heading {
+			level: 2,
+			text: text.to_string(),
+			line: 0,
+		};
+		let headings = [
+			heading("Run `fama explain`"),
+			heading("See [the docs](https://x.dev)!"),
+			heading("Usage"),
+			heading("Usage"),
+		];
+		assert_eq!(
+			anchors(&headings),
+			["run-fama-explain", "see-the-docs", "usage", "usage-1"]
+		);
+	}
+
+	#[test]
+	fn test_refresh() {
+		let text = concat!(
+			"# Title\n\n",
+			"<!-- toc -->\n- [Old](#old)\n<!-- tocstop -->\n\n",
+			"## Install\n\n### From `cargo`\n\n## Usage\n",
+		);
+		let expected = concat!(
+			"# Title\n\n",
+			"<!-- toc -->\n\n",
+			"- [Install](#install)\n",
+			"  - [From cargo](#from-cargo)\n",
+			"- [Usage](#usage)\n\n",
+			"<!-- tocstop -->\n\n",
+			"## Install\n\n### From `cargo`\n\n## Usage\n",
+		);
+		assert_eq!(refresh(text), expected);
+		assert_eq!(refresh(expected), expected);
+		assert_eq!(refresh("# A\n<!-- toc -->\n"), "# A\n<!-- toc -->\n");
+	}
+}
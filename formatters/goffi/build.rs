@@ -3,6 +3,9 @@ use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
 
+#[path = "../prebuilt.rs"]
+mod prebuilt;
+
 fn main() {
 	let go_dir =
 		PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("go");
@@ -36,8 +39,9 @@ fn main() {
 	let lib_name = format!("libgoffi-{}.a", target);
 	let lib_src = go_dir.join(&lib_name);
 
-	// Always rebuild when target changes or library doesn't exist
-	if !lib_src.exists() {
+	// Always rebuild when target changes or library doesn't exist, unless
+	// a prebuilt library was fetched (opt-in, see prebuilt.rs)
+	if !lib_src.exists() && !prebuilt::fetch("go", &lib_name, &lib_src) {
 		println!(
 			"cargo:warning=Building Go static library for target {} (GOOS={}, GOARCH={})...",
 			target, goos, goarch
@@ -6,9 +6,11 @@ import "C"
 import (
 	"bytes"
 	"go/format"
+	"path/filepath"
 	"unsafe"
 
 	"github.com/hashicorp/hcl/v2/hclwrite"
+	"golang.org/x/mod/modfile"
 	"mvdan.cc/sh/v3/syntax"
 )
 
@@ -129,6 +131,29 @@ func FormatGoBatch(sources **C.char, lengths *C.size_t, count C.size_t) **C.char
 	return (**C.char)(cResults)
 }
 
+//export FormatGoMod
+func FormatGoMod(source *C.char, sourceLen C.size_t, path *C.char) *C.char {
+	goSource := C.GoBytes(unsafe.Pointer(source), C.int(sourceLen))
+	goPath := C.GoString(path)
+
+	var fileSyntax *modfile.FileSyntax
+	if filepath.Base(goPath) == "go.work" {
+		file, err := modfile.ParseWork(goPath, goSource, nil)
+		if err != nil {
+			return C.CString(string(goSource))
+		}
+		fileSyntax = file.Syntax
+	} else {
+		file, err := modfile.Parse(goPath, goSource, nil)
+		if err != nil {
+			return C.CString(string(goSource))
+		}
+		fileSyntax = file.Syntax
+	}
+
+	return C.CString(string(modfile.Format(fileSyntax)))
+}
+
 //export FormatHcl
 func FormatHcl(source *C.char, sourceLen C.size_t) *C.char {
 	goSource := C.GoBytes(unsafe.Pointer(source), C.int(sourceLen))
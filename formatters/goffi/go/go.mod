@@ -4,6 +4,7 @@ go 1.23
 
 require (
 	github.com/hashicorp/hcl/v2 v2.23.0
+	golang.org/x/mod v0.18.0
 	mvdan.cc/sh/v3 v3.10.0
 )
 
@@ -14,7 +15,6 @@ require (
 	github.com/google/go-cmp v0.6.0 // indirect
 	github.com/mitchellh/go-wordwrap v0.0.0-20150314170334-ad45545899c7 // indirect
 	github.com/zclconf/go-cty v1.13.0 // indirect
-	golang.org/x/mod v0.18.0 // indirect
 	golang.org/x/sync v0.8.0 // indirect
 	golang.org/x/text v0.11.0 // indirect
 	golang.org/x/tools v0.22.0 // indirect
@@ -100,6 +100,7 @@ extern void FreeString(char *str);
 extern void FreeStringArray(char **arr, size_t count);
 extern char *FormatGo(char *source, size_t sourceLen);
 extern char **FormatGoBatch(char **sources, size_t *lengths, size_t count);
+extern char *FormatGoMod(char *source, size_t sourceLen, char *path);
 extern char *FormatHcl(char *source, size_t sourceLen);
 extern char **FormatHclBatch(char **sources, size_t *lengths, size_t count);
 
@@ -2,8 +2,11 @@
 
 use fama_common::{FileType, FormatConfig, IndentStyle};
 use libc::{c_char, c_uint, size_t};
-use std::ffi::{CStr, CString};
-use std::slice;
+use std::ffi::CString;
+
+mod owned;
+
+use owned::{OwnedCStr, OwnedCStrArray};
 
 extern "C" {
 	fn FormatShell(
@@ -23,6 +26,11 @@ extern "C" {
 		lengths: *const size_t,
 		count: size_t,
 	) -> *mut *mut c_char;
+	fn FormatGoMod(
+		source: *const c_char,
+		source_len: size_t,
+		path: *const c_char,
+	) -> *mut c_char;
 	fn FormatHcl(source: *const c_char, source_len: size_t) -> *mut c_char;
 	fn FormatHclBatch(
 		sources: *const *const c_char,
@@ -41,27 +49,23 @@ fn get_indent() -> c_uint {
 	}
 }
 
-pub fn format_shell(source: &str, _file_path: &str) -> Result<String, String> {
-	let c_source =
-		CString::new(source).map_err(|e| format!("Invalid source: {}", e))?;
-	let c_result = unsafe {
-		FormatShell(c_source.as_ptr(), source.len() as size_t, get_indent())
-	};
-
-	if c_result.is_null() {
-		return Err("Formatter returned null".to_string());
-	}
-
-	let result = unsafe { CStr::from_ptr(c_result) }
-		.to_str()
-		.map(|s| s.to_string())
-		.map_err(|e| format!("Invalid UTF-8: {}", e));
-
-	unsafe { FreeString(c_result) };
-	result
+/// Read the result of a single-source call
+fn read_result(c_result: *mut c_char) -> Result<String, String> {
+	unsafe { OwnedCStr::from_raw(c_result, FreeString) }
+		.ok_or_else(|| "Formatter returned null".to_string())?
+		.read()
 }
 
-pub fn format_shell_batch(sources: &[&str]) -> Vec<Result<String, String>> {
+/// Call a batch entry point with the sources, their lengths and their
+/// count, and read its results
+fn format_batch(
+	sources: &[&str],
+	call: impl FnOnce(
+		*const *const c_char,
+		*const size_t,
+		size_t,
+	) -> *mut *mut c_char,
+) -> Vec<Result<String, String>> {
 	if sources.is_empty() {
 		return Vec::new();
 	}
@@ -82,40 +86,32 @@ pub fn format_shell_batch(sources: &[&str]) -> Vec<Result<String, String>> {
 	let lengths: Vec<size_t> =
 		sources.iter().map(|s| s.len() as size_t).collect();
 
-	let c_results = unsafe {
-		FormatShellBatch(
-			c_ptrs.as_ptr(),
-			lengths.as_ptr(),
-			sources.len() as size_t,
-			get_indent(),
-		)
-	};
-
-	if c_results.is_null() {
-		return sources
+	let c_results =
+		call(c_ptrs.as_ptr(), lengths.as_ptr(), sources.len() as size_t);
+	match unsafe {
+		OwnedCStrArray::from_raw(c_results, sources.len(), FreeStringArray)
+	} {
+		Some(results) => results.read(),
+		None => sources
 			.iter()
 			.map(|_| Err("Formatter returned null".to_string()))
-			.collect();
+			.collect(),
 	}
+}
 
-	let results_slice =
-		unsafe { slice::from_raw_parts(c_results, sources.len()) };
-	let results: Vec<Result<String, String>> = results_slice
-		.iter()
-		.map(|&c_str| {
-			if c_str.is_null() {
-				Err("Null result".to_string())
-			} else {
-				unsafe { CStr::from_ptr(c_str) }
-					.to_str()
-					.map(|s| s.to_string())
-					.map_err(|e| format!("Invalid UTF-8: {}", e))
-			}
-		})
-		.collect();
+pub fn format_shell(source: &str, _file_path: &str) -> Result<String, String> {
+	let c_source =
+		CString::new(source).map_err(|e| format!("Invalid source: {}", e))?;
+	let c_result = unsafe {
+		FormatShell(c_source.as_ptr(), source.len() as size_t, get_indent())
+	};
+	read_result(c_result)
+}
 
-	unsafe { FreeStringArray(c_results, sources.len() as size_t) };
-	results
+pub fn format_shell_batch(sources: &[&str]) -> Vec<Result<String, String>> {
+	format_batch(sources, |ptrs, lengths, count| unsafe {
+		FormatShellBatch(ptrs, lengths, count, get_indent())
+	})
 }
 
 pub fn format_go(source: &str, _file_path: &str) -> Result<String, String> {
@@ -123,74 +119,25 @@ pub fn format_go(source: &str, _file_path: &str) -> Result<String, String> {
 		CString::new(source).map_err(|e| format!("Invalid source: {}", e))?;
 	let c_result =
 		unsafe { FormatGo(c_source.as_ptr(), source.len() as size_t) };
-
-	if c_result.is_null() {
-		return Err("Formatter returned null".to_string());
-	}
-
-	let result = unsafe { CStr::from_ptr(c_result) }
-		.to_str()
-		.map(|s| s.to_string())
-		.map_err(|e| format!("Invalid UTF-8: {}", e));
-
-	unsafe { FreeString(c_result) };
-	result
+	read_result(c_result)
 }
 
 pub fn format_go_batch(sources: &[&str]) -> Vec<Result<String, String>> {
-	if sources.is_empty() {
-		return Vec::new();
-	}
-
-	let c_sources: Vec<CString> =
-		match sources.iter().map(|s| CString::new(*s)).collect() {
-			Ok(v) => v,
-			Err(_) => {
-				return sources
-					.iter()
-					.map(|_| Err("Invalid source".to_string()))
-					.collect()
-			}
-		};
-
-	let c_ptrs: Vec<*const c_char> =
-		c_sources.iter().map(|s| s.as_ptr()).collect();
-	let lengths: Vec<size_t> =
-		sources.iter().map(|s| s.len() as size_t).collect();
+	format_batch(sources, |ptrs, lengths, count| unsafe {
+		FormatGoBatch(ptrs, lengths, count)
+	})
+}
 
-	let c_results = unsafe {
-		FormatGoBatch(
-			c_ptrs.as_ptr(),
-			lengths.as_ptr(),
-			sources.len() as size_t,
-		)
+/// Format a `go.mod` or `go.work` file, told apart by `file_path`
+pub fn format_go_mod(source: &str, file_path: &str) -> Result<String, String> {
+	let c_source =
+		CString::new(source).map_err(|e| format!("Invalid source: {}", e))?;
+	let c_path =
+		CString::new(file_path).map_err(|e| format!("Invalid path: {}", e))?;
+	let c_result = unsafe {
+		FormatGoMod(c_source.as_ptr(), source.len() as size_t, c_path.as_ptr())
 	};
-
-	if c_results.is_null() {
-		return sources
-			.iter()
-			.map(|_| Err("Formatter returned null".to_string()))
-			.collect();
-	}
-
-	let results_slice =
-		unsafe { slice::from_raw_parts(c_results, sources.len()) };
-	let results: Vec<Result<String, String>> = results_slice
-		.iter()
-		.map(|&c_str| {
-			if c_str.is_null() {
-				Err("Null result".to_string())
-			} else {
-				unsafe { CStr::from_ptr(c_str) }
-					.to_str()
-					.map(|s| s.to_string())
-					.map_err(|e| format!("Invalid UTF-8: {}", e))
-			}
-		})
-		.collect();
-
-	unsafe { FreeStringArray(c_results, sources.len() as size_t) };
-	results
+	read_result(c_result)
 }
 
 pub fn format_hcl(source: &str, _file_path: &str) -> Result<String, String> {
@@ -198,74 +145,13 @@ pub fn format_hcl(source: &str, _file_path: &str) -> Result<String, String> {
 		CString::new(source).map_err(|e| format!("Invalid source: {}", e))?;
 	let c_result =
 		unsafe { FormatHcl(c_source.as_ptr(), source.len() as size_t) };
-
-	if c_result.is_null() {
-		return Err("Formatter returned null".to_string());
-	}
-
-	let result = unsafe { CStr::from_ptr(c_result) }
-		.to_str()
-		.map(|s| s.to_string())
-		.map_err(|e| format!("Invalid UTF-8: {}", e));
-
-	unsafe { FreeString(c_result) };
-	result
+	read_result(c_result)
 }
 
 pub fn format_hcl_batch(sources: &[&str]) -> Vec<Result<String, String>> {
-	if sources.is_empty() {
-		return Vec::new();
-	}
-
-	let c_sources: Vec<CString> =
-		match sources.iter().map(|s| CString::new(*s)).collect() {
-			Ok(v) => v,
-			Err(_) => {
-				return sources
-					.iter()
-					.map(|_| Err("Invalid source".to_string()))
-					.collect()
-			}
-		};
-
-	let c_ptrs: Vec<*const c_char> =
-		c_sources.iter().map(|s| s.as_ptr()).collect();
-	let lengths: Vec<size_t> =
-		sources.iter().map(|s| s.len() as size_t).collect();
-
-	let c_results = unsafe {
-		FormatHclBatch(
-			c_ptrs.as_ptr(),
-			lengths.as_ptr(),
-			sources.len() as size_t,
-		)
-	};
-
-	if c_results.is_null() {
-		return sources
-			.iter()
-			.map(|_| Err("Formatter returned null".to_string()))
-			.collect();
-	}
-
-	let results_slice =
-		unsafe { slice::from_raw_parts(c_results, sources.len()) };
-	let results: Vec<Result<String, String>> = results_slice
-		.iter()
-		.map(|&c_str| {
-			if c_str.is_null() {
-				Err("Null result".to_string())
-			} else {
-				unsafe { CStr::from_ptr(c_str) }
-					.to_str()
-					.map(|s| s.to_string())
-					.map_err(|e| format!("Invalid UTF-8: {}", e))
-			}
-		})
-		.collect();
-
-	unsafe { FreeStringArray(c_results, sources.len() as size_t) };
-	results
+	format_batch(sources, |ptrs, lengths, count| unsafe {
+		FormatHclBatch(ptrs, lengths, count)
+	})
 }
 
 pub fn format_file(
@@ -276,6 +162,7 @@ pub fn format_file(
 	match file_type {
 		FileType::Shell => format_shell(source, file_path),
 		FileType::Go => format_go(source, file_path),
+		FileType::GoMod => format_go_mod(source, file_path),
 		FileType::Hcl => format_hcl(source, file_path),
 		_ => Err(format!("File type {:?} not supported", file_type)),
 	}
@@ -284,6 +171,8 @@ pub fn format_file(
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use std::fs;
+	use std::path::Path;
 
 	#[test]
 	fn test_format_shell() {
@@ -329,6 +218,23 @@ mod tests {
 		assert!(results.iter().all(|r| r.is_ok()));
 	}
 
+	#[test]
+	fn test_format_go_mod() {
+		let source = "module example.com/m\n\ngo 1.22\n\nrequire (\n\
+			example.com/a v1.0.0\n  example.com/b v1.2.0 // indirect\n)\n";
+		let formatted = format_go_mod(source, "go.mod").unwrap();
+		assert!(formatted.contains("\texample.com/a v1.0.0\n"));
+		assert!(formatted.contains("\texample.com/b v1.2.0 // indirect\n"));
+		assert_eq!(format_go_mod(&formatted, "go.mod").unwrap(), formatted);
+	}
+
+	#[test]
+	fn test_format_go_work() {
+		let source = "go 1.22\n\nuse (\n./a\n    ./b\n)\n";
+		let formatted = format_go_mod(source, "go.work").unwrap();
+		assert!(formatted.contains("use (\n\t./a\n\t./b\n)\n"));
+	}
+
 	#[test]
 	fn test_format_hcl() {
 		let source = r#"resource "aws_instance"   "example" {
@@ -342,6 +248,31 @@ ami           = "ami-12345"
 		assert!(formatted.contains("instance_type = "));
 	}
 
+	/// Each `testdata/terraform/<name>.tf` must format to the expected
+	/// `terraform fmt` output in `<name>.golden`, which
+	/// `make terraform-goldens` regenerates
+	#[test]
+	fn test_format_hcl_matches_terraform_fmt() {
+		let dir =
+			Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/terraform");
+		let mut checked = 0;
+		for entry in fs::read_dir(&dir).unwrap() {
+			let input = entry.unwrap().path();
+			if input.extension().and_then(|e| e.to_str()) != Some("tf") {
+				continue;
+			}
+			let source = fs::read_to_string(&input).unwrap();
+			let golden =
+				fs::read_to_string(input.with_extension("golden")).unwrap();
+			let formatted =
+				format_hcl(&source, input.to_str().unwrap()).unwrap();
+			assert_eq!(formatted, golden, "{}", input.display());
+			assert_eq!(format_hcl(&golden, "golden.tf").unwrap(), golden);
+			checked += 1;
+		}
+		assert!(checked > 0);
+	}
+
 	#[test]
 	fn test_format_hcl_batch() {
 		let sources = vec![r#"foo   =    "bar""#, r#"baz={x=1}"#];
@@ -0,0 +1,161 @@
+// owned.rs - Strings the Go library hands back, freed on drop
+//
+// Every result of FormatShell, FormatGo and friends is allocated by Go and
+// has to go back through FreeString or FreeStringArray, including when it
+// turns out to be invalid UTF-8. Holding the pointer in a type whose Drop
+// frees it keeps early returns from leaking it. The free function is a
+// field so tests can hand in Rust-allocated strings and run under Miri.
+
+use libc::{c_char, size_t};
+use std::ffi::CStr;
+use std::ptr::NonNull;
+use std::slice;
+
+/// Frees one string returned by the library
+pub(crate) type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// Frees an array of strings returned by the library, and the strings
+pub(crate) type FreeArrayFn = unsafe extern "C" fn(*mut *mut c_char, size_t);
+
+/// A NUL-terminated string owned by the library
+pub(crate) struct OwnedCStr {
+	ptr: NonNull<c_char>,
+	free: FreeFn,
+}
+
+impl OwnedCStr {
+	/// Take ownership of `ptr`, or `None` if it is null
+	///
+	/// # Safety
+	///
+	/// A non-null `ptr` must point to a NUL-terminated string that stays
+	/// valid until it is passed to `free`, and nothing else may free it.
+	pub(crate) unsafe fn from_raw(
+		ptr: *mut c_char,
+		free: FreeFn,
+	) -> Option<Self> {
+		NonNull::new(ptr).map(|ptr| OwnedCStr { ptr, free })
+	}
+
+	/// Copy the string out
+	pub(crate) fn read(&self) -> Result<String, String> {
+		unsafe { CStr::from_ptr(self.ptr.as_ptr()) }
+			.to_str()
+			.map(|s| s.to_string())
+			.map_err(|e| format!("Invalid UTF-8: {}", e))
+	}
+}
+
+impl Drop for OwnedCStr {
+	fn drop(&mut self) {
+		unsafe { (self.free)(self.ptr.as_ptr()) }
+	}
+}
+
+/// An array of `len` strings owned by the library; entries may be null
+pub(crate) struct OwnedCStrArray {
+	ptr: NonNull<*mut c_char>,
+	len: usize,
+	free: FreeArrayFn,
+}
+
+impl OwnedCStrArray {
+	/// Take ownership of the `len` strings at `ptr`, or `None` if it is null
+	///
+	/// # Safety
+	///
+	/// A non-null `ptr` must point to `len` pointers that are each null or
+	/// a NUL-terminated string, all valid until passed to `free`, and
+	/// nothing else may free them.
+	pub(crate) unsafe fn from_raw(
+		ptr: *mut *mut c_char,
+		len: usize,
+		free: FreeArrayFn,
+	) -> Option<Self> {
+		NonNull::new(ptr).map(|ptr| OwnedCStrArray { ptr, len, free })
+	}
+
+	/// Copy every string out, in order
+	pub(crate) fn read(&self) -> Vec<Result<String, String>> {
+		let entries =
+			unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) };
+		entries
+			.iter()
+			.map(|&entry| {
+				if entry.is_null() {
+					return Err("Null result".to_string());
+				}
+				unsafe { CStr::from_ptr(entry) }
+					.to_str()
+					.map(|s| s.to_string())
+					.map_err(|e| format!("Invalid UTF-8: {}", e))
+			})
+			.collect()
+	}
+}
+
+impl Drop for OwnedCStrArray {
+	fn drop(&mut self) {
+		unsafe { (self.free)(self.ptr.as_ptr(), self.len as size_t) }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::ffi::CString;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	// Shared by tests running in parallel, so only its growth is checked
+	static FREED: AtomicUsize = AtomicUsize::new(0);
+
+	unsafe extern "C" fn free(ptr: *mut c_char) {
+		drop(CString::from_raw(ptr));
+		FREED.fetch_add(1, Ordering::SeqCst);
+	}
+
+	unsafe extern "C" fn free_array(ptr: *mut *mut c_char, len: size_t) {
+		let entries = Vec::from_raw_parts(ptr, len, len);
+		for entry in entries {
+			if !entry.is_null() {
+				free(entry);
+			}
+		}
+	}
+
+	fn raw(s: &[u8]) -> *mut c_char {
+		CString::new(s).unwrap().into_raw()
+	}
+
+	#[test]
+	fn test_owned_cstr() {
+		assert!(unsafe { OwnedCStr::from_raw(std::ptr::null_mut(), free) }
+			.is_none());
+
+		let before = FREED.load(Ordering::SeqCst);
+		let owned = unsafe { OwnedCStr::from_raw(raw(b"ok"), free) }.unwrap();
+		assert_eq!(owned.read(), Ok("ok".to_string()));
+		drop(owned);
+		// Invalid UTF-8 is still freed
+		let owned = unsafe { OwnedCStr::from_raw(raw(b"\xff"), free) }.unwrap();
+		assert!(owned.read().unwrap_err().starts_with("Invalid UTF-8"));
+		drop(owned);
+		assert!(FREED.load(Ordering::SeqCst) >= before + 2);
+	}
+
+	#[test]
+	fn test_owned_cstr_array() {
+		let mut entries = vec![raw(b"a"), std::ptr::null_mut(), raw(b"\xfe")];
+		entries.shrink_to_fit();
+		let len = entries.len();
+		let ptr = entries.as_mut_ptr();
+		std::mem::forget(entries);
+
+		let owned =
+			unsafe { OwnedCStrArray::from_raw(ptr, len, free_array) }.unwrap();
+		let results = owned.read();
+		assert_eq!(results[0], Ok("a".to_string()));
+		assert_eq!(results[1], Err("Null result".to_string()));
+		assert!(results[2].is_err());
+	}
+}
@@ -0,0 +1,16 @@
+locals {
+  name = "${var.prefix}-web"
+  count = var.enabled ? 1 : 0
+    total = var.base+var.extra*2
+}
+
+module "vpc" {
+source = "./vpc"
+
+  dynamic "ingress" {
+  for_each = var.ports
+  content {
+  from_port = ingress.value
+  }
+  }
+}
@@ -0,0 +1,12 @@
+resource "aws_iam_policy" "p" {
+name="p"
+
+policy = <<EOT
+{
+    "Version":   "2012-10-17",
+
+
+    "Statement": []
+}
+EOT
+}
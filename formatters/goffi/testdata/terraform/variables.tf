@@ -0,0 +1,11 @@
+# Network settings
+variable "cidr" {
+type = string
+default="10.0.0.0/16" # main range
+  description   =   "VPC range"
+}
+
+variable "zones" {
+  type = list(string)
+  default = ["a","b",   "c"]
+}
@@ -0,0 +1,190 @@
+// fama-groovy - Groovy (Gradle build script) formatter using tree-sitter
+//
+// There is no maintained Groovy formatter to embed, so this one only
+// fixes layout: each line is re-indented by bracket nesting and trailing
+// whitespace is removed. The tree-sitter-groovy parse tree tells real
+// brackets apart from ones inside strings and comments, and marks lines
+// inside multi-line strings and comments, which are left verbatim.
+
+use fama_common::{IndentStyle, CONFIG};
+use tree_sitter::{Node, Parser};
+
+/// Format Groovy source code
+pub fn format_groovy(source: &str, _file_path: &str) -> Result<String, String> {
+	let mut parser = Parser::new();
+	parser
+		.set_language(&tree_sitter_groovy::LANGUAGE.into())
+		.map_err(|e| format!("Failed to load Groovy grammar: {}", e))?;
+	let tree = parser
+		.parse(source, None)
+		.ok_or_else(|| "Groovy parser returned no tree".to_string())?;
+	let root = tree.root_node();
+	if root.has_error() {
+		let position = first_error(root).unwrap_or(root).start_position();
+		return Err(format!(
+			"{}:{}: Groovy syntax error",
+			position.row + 1,
+			position.column + 1
+		));
+	}
+
+	let line_count = source.lines().count();
+	let mut layout = Layout {
+		tokens: vec![Vec::new(); line_count],
+		verbatim: vec![false; line_count],
+	};
+	layout.collect(root, source);
+	Ok(layout.render(source))
+}
+
+/// Brackets per line and lines to keep as written
+struct Layout {
+	tokens: Vec<Vec<Bracket>>,
+	verbatim: Vec<bool>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bracket {
+	Open,
+	Close,
+	Other,
+}
+
+impl Layout {
+	/// Record the leaf tokens under `node`, treating strings and comments
+	/// as single opaque tokens
+	fn collect(&mut self, node: Node, source: &str) {
+		let kind = node.kind();
+		let opaque = kind.contains("string") || kind.contains("comment");
+		if opaque || node.child_count() == 0 {
+			let (start, end) = (node.start_position(), node.end_position());
+			let bracket = match &source[node.byte_range()] {
+				"{" | "(" | "[" if !opaque => Bracket::Open,
+				"}" | ")" | "]" if !opaque => Bracket::Close,
+				_ => Bracket::Other,
+			};
+			if let Some(tokens) = self.tokens.get_mut(start.row) {
+				tokens.push(bracket);
+			}
+			for row in start.row + 1..=end.row {
+				if let Some(verbatim) = self.verbatim.get_mut(row) {
+					*verbatim = true;
+				}
+			}
+			return;
+		}
+		let mut cursor = node.walk();
+		for child in node.children(&mut cursor) {
+			self.collect(child, source);
+		}
+	}
+
+	/// Re-indent every line, one level per line that leaves brackets open
+	fn render(&self, source: &str) -> String {
+		let unit = match CONFIG.indent_style {
+			IndentStyle::Tabs => "\t".to_string(),
+			IndentStyle::Spaces => " ".repeat(usize::from(CONFIG.indent_width)),
+		};
+		// Brackets still open, grouped by the line that opened them
+		let mut groups: Vec<usize> = Vec::new();
+		let mut out = String::with_capacity(source.len());
+
+		for (row, line) in source.lines().enumerate() {
+			let tokens = &self.tokens[row];
+			let leading = tokens
+				.iter()
+				.take_while(|&&token| token == Bracket::Close)
+				.count();
+			for _ in 0..leading {
+				close(&mut groups);
+			}
+
+			let text = line.trim();
+			if self.verbatim[row] {
+				out.push_str(line);
+			} else if !text.is_empty() {
+				out.push_str(&unit.repeat(groups.len()));
+				out.push_str(text);
+			}
+			out.push('\n');
+
+			let mut opened = 0;
+			for token in &tokens[leading..] {
+				match token {
+					Bracket::Open => opened += 1,
+					Bracket::Close if opened > 0 => opened -= 1,
+					Bracket::Close => close(&mut groups),
+					Bracket::Other => {}
+				}
+			}
+			if opened > 0 {
+				groups.push(opened);
+			}
+		}
+
+		out
+	}
+}
+
+/// Close one bracket of the innermost open group
+fn close(groups: &mut Vec<usize>) {
+	if let Some(open) = groups.last_mut() {
+		*open -= 1;
+		if *open == 0 {
+			groups.pop();
+		}
+	}
+}
+
+/// The first error or missing node under `node`, in source order
+fn first_error(node: Node) -> Option<Node> {
+	if node.is_error() || node.is_missing() {
+		return Some(node);
+	}
+	let mut cursor = node.walk();
+	let children: Vec<Node> = node.children(&mut cursor).collect();
+	children.into_iter().find_map(first_error)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format_groovy_indentation() {
+		let source = "plugins {\nid 'java'\n    }\n\ndependencies {\n  \
+			implementation('a:b:1.0') {\n exclude group: 'c'\n}\n}\n";
+		assert_eq!(
+			format_groovy(source, "build.gradle").unwrap(),
+			"plugins {\n\tid 'java'\n}\n\ndependencies {\n\
+			 \timplementation('a:b:1.0') {\n\t\texclude group: 'c'\n\
+			 \t}\n}\n"
+		);
+	}
+
+	#[test]
+	fn test_format_groovy_keeps_strings() {
+		let source = "task hello {\ndoLast {\nprintln '''{\n  x\n'''\n}\n}\n";
+		assert_eq!(
+			format_groovy(source, "build.gradle").unwrap(),
+			"task hello {\n\tdoLast {\n\t\tprintln '''{\n  x\n'''\n\t}\n}\n"
+		);
+	}
+
+	#[test]
+	fn test_format_groovy_ignores_brackets_in_strings_and_comments() {
+		let source = "android {\n// }\nname = \"${v}-{\"\n/* {\n } */\n\
+			def m = [a: 1]\n}\n";
+		assert_eq!(
+			format_groovy(source, "build.gradle").unwrap(),
+			"android {\n\t// }\n\tname = \"${v}-{\"\n\t/* {\n } */\n\
+			 \tdef m = [a: 1]\n}\n"
+		);
+	}
+
+	#[test]
+	fn test_format_groovy_syntax_error() {
+		let error = format_groovy("plugins {\n", "build.gradle").unwrap_err();
+		assert!(error.contains("Groovy syntax error"), "{}", error);
+	}
+}
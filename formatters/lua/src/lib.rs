@@ -15,7 +15,9 @@ const STYLUA_INDENT_TYPE: IndentType = match CONFIG.indent_style {
 };
 const STYLUA_INDENT_WIDTH: usize = CONFIG.indent_width as usize;
 const STYLUA_LINE_ENDINGS: LineEndings = match CONFIG.line_ending {
-	fama_common::LineEnding::Lf => LineEndings::Unix,
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
+		LineEndings::Unix
+	}
 	fama_common::LineEnding::Crlf => LineEndings::Windows,
 };
 const STYLUA_COLUMN_WIDTH: usize = CONFIG.line_width as usize;
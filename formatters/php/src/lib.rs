@@ -32,7 +32,7 @@ pub fn format_php(source: &str, file_path: &str) -> Result<String, String> {
 		tab_width: CONFIG.indent_width as usize,
 		use_tabs: matches!(CONFIG.indent_style, fama_common::IndentStyle::Tabs),
 		end_of_line: match CONFIG.line_ending {
-			fama_common::LineEnding::Lf => {
+			fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
 				mago_formatter::settings::EndOfLine::Lf
 			}
 			fama_common::LineEnding::Crlf => {
@@ -0,0 +1,119 @@
+// prebuilt.rs - Opt-in download of prebuilt FFI static libraries
+//
+// Shared by the goffi and zigffi build scripts via `#[path]`. When
+// FAMA_PREBUILT_URL is set and the local toolchain (Go or Zig) is missing,
+// the static library is downloaded from `<url>/<package>-<version>/<lib>`
+// instead of being compiled, and checked against the SHA-256 pinned in
+// prebuilt.sha256. Build scripts can't see cargo's `--offline`, so
+// CARGO_NET_OFFLINE=true stands in for it.
+
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const URL_VAR: &str = "FAMA_PREBUILT_URL";
+const OFFLINE_VAR: &str = "CARGO_NET_OFFLINE";
+
+/// Checksums are pinned in the repo rather than fetched, so a compromised
+/// server can't serve a library along with its matching checksum
+const PINNED: &str = include_str!("prebuilt.sha256");
+
+/// Download `lib_name` to `dest` when opted in and `toolchain` is missing.
+///
+/// Returns false when the library should be built locally instead,
+/// including when no checksum is pinned for it. A failed download or
+/// checksum mismatch panics rather than falling back to an unverified
+/// file.
+pub fn fetch(toolchain: &str, lib_name: &str, dest: &Path) -> bool {
+	println!("cargo:rerun-if-env-changed={}", URL_VAR);
+	let Ok(base) = env::var(URL_VAR) else {
+		return false;
+	};
+	if toolchain_available(toolchain) {
+		return false;
+	}
+
+	// The version pins the FFI sources and the target the ABI, so a
+	// library is only ever fetched for the exact build it was made for
+	let key = format!(
+		"{}-{}/{}",
+		env::var("CARGO_PKG_NAME").unwrap(),
+		env::var("CARGO_PKG_VERSION").unwrap(),
+		lib_name
+	);
+	let Some(expected) = pinned_checksum(PINNED, &key) else {
+		println!(
+			"cargo:warning=No pinned checksum for prebuilt {}, building it",
+			key
+		);
+		return false;
+	};
+
+	let url = format!("{}/{}", base.trim_end_matches('/'), key);
+	println!("cargo:rerun-if-env-changed={}", OFFLINE_VAR);
+	if env::var(OFFLINE_VAR).is_ok_and(|v| v == "true" || v == "1") {
+		fama_common::set_offline();
+	}
+	if let Err(e) = fama_common::require_network(&url) {
+		panic!("{}", e);
+	}
+	println!(
+		"cargo:warning={} not found, downloading prebuilt {}",
+		toolchain, url
+	);
+
+	let bytes = download(&url);
+	let actual = sha256_hex(&bytes);
+	if actual != expected {
+		panic!(
+			"Checksum mismatch for {}: expected {}, got {}",
+			url, expected, actual
+		);
+	}
+
+	fs::write(dest, bytes).expect("Failed to write prebuilt library");
+	true
+}
+
+/// The checksum pinned for `key` in a `sha256sum`-style list, skipping
+/// `#` comments
+fn pinned_checksum(pinned: &str, key: &str) -> Option<String> {
+	pinned
+		.lines()
+		.filter(|line| !line.starts_with('#'))
+		.find_map(|line| {
+			let (checksum, path) = line.split_once(char::is_whitespace)?;
+			(path.trim() == key).then(|| checksum.to_ascii_lowercase())
+		})
+}
+
+fn toolchain_available(toolchain: &str) -> bool {
+	Command::new(toolchain)
+		.arg("version")
+		.output()
+		.is_ok_and(|o| o.status.success())
+}
+
+fn download(url: &str) -> Vec<u8> {
+	let output = Command::new("curl")
+		.args(["--fail", "--silent", "--show-error", "--location", url])
+		.output()
+		.unwrap_or_else(|e| panic!("Failed to run curl for {}: {}", url, e));
+	if !output.status.success() {
+		panic!(
+			"Failed to download {}: {}",
+			url,
+			String::from_utf8_lossy(&output.stderr)
+		);
+	}
+	output.stdout
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+	Sha256::digest(bytes)
+		.iter()
+		.map(|b| format!("{:02x}", b))
+		.collect()
+}
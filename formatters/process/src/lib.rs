@@ -1,11 +1,35 @@
 //! Process-based formatter support for host-installed CLIs.
+//!
+//! Every CLI runs through [`sandboxed_command`]: no shell, an allowlisted
+//! environment, an explicit working directory and, on Unix, a CPU time
+//! limit.
 
-use fama_common::{editorconfig_contents, FormatConfig};
+use fama_common::{editorconfig_contents, FormatConfig, CONFIG};
 use std::ffi::OsStr;
 use std::fs;
 use std::io::{ErrorKind, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+
+/// Environment variables passed through to formatter CLIs; everything else
+/// is dropped so secrets and tool overrides in the caller's environment
+/// don't leak into, or change the behavior of, the formatter
+const ENV_ALLOWLIST: &[&str] = &[
+	"PATH",
+	"HOME",
+	"TMPDIR",
+	"TEMP",
+	"TMP",
+	"LANG",
+	"LC_ALL",
+	"JAVA_HOME",
+	"SYSTEMROOT",
+];
+
+/// CPU seconds a formatter CLI may use before the kernel stops it
+#[cfg(unix)]
+const CPU_LIMIT_SECS: u64 = 60;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessMode {
@@ -24,6 +48,7 @@ pub struct ProcessFormatter {
 
 impl ProcessFormatter {
 	fn format(self, source: &str, file_path: &str) -> Result<String, String> {
+		subprocesses_allowed(self.name, self.command)?;
 		match self.mode {
 			ProcessMode::StdinStdout => self.format_via_stdin(source),
 			ProcessMode::TempFile => {
@@ -33,31 +58,9 @@ impl ProcessFormatter {
 	}
 
 	fn format_via_stdin(self, source: &str) -> Result<String, String> {
-		let mut command = Command::new(self.command);
-		command
-			.args(self.args)
-			.stdin(Stdio::piped())
-			.stdout(Stdio::piped())
-			.stderr(Stdio::piped());
-
-		let mut child = command
-			.spawn()
-			.map_err(|error| command_error(self.name, self.command, error))?;
-
-		child
-			.stdin
-			.as_mut()
-			.ok_or_else(|| format!("{} stdin was not available", self.name))?
-			.write_all(source.as_bytes())
-			.map_err(|error| {
-				format!("Failed to write to {} stdin: {}", self.name, error)
-			})?;
-
-		let output = child.wait_with_output().map_err(|error| {
-			format!("Failed to wait for {}: {}", self.name, error)
-		})?;
-
-		success_output(self.name, output)
+		let mut command = sandboxed_command(self.command);
+		command.args(self.args);
+		pipe_through(self.name, self.command, command, source)
 	}
 
 	fn format_via_temp_file(
@@ -65,9 +68,13 @@ impl ProcessFormatter {
 		source: &str,
 		original_path: &str,
 	) -> Result<String, String> {
-		let temp_dir = tempfile::tempdir().map_err(|error| {
-			format!("Failed to create temp dir for {}: {}", self.name, error)
-		})?;
+		let temp_dir =
+			tempfile::tempdir_in(fama_common::temp_dir()).map_err(|error| {
+				format!(
+					"Failed to create temp dir for {}: {}",
+					self.name, error
+				)
+			})?;
 		let temp_file = temp_file_path(temp_dir.path(), original_path);
 
 		if self.write_editorconfig {
@@ -102,8 +109,8 @@ impl ProcessFormatter {
 
 	fn command_with_file(self, file_path: &Path) -> Command {
 		let file_path = file_path.to_string_lossy();
-		let mut command = Command::new(self.command);
-		command.args(
+		let mut command = sandboxed_command(self.command);
+		command.stdin(Stdio::null()).args(
 			self.args
 				.iter()
 				.map(|arg| arg.replace("{file}", &file_path)),
@@ -134,6 +141,158 @@ pub fn format_kotlin(source: &str, file_path: &str) -> Result<String, String> {
 	)
 }
 
+/// Format Go with a host-installed `gofumpt`, for `go_style = Gofumpt`
+pub fn format_gofumpt(source: &str, file_path: &str) -> Result<String, String> {
+	format_with_process(
+		source,
+		file_path,
+		ProcessFormatter {
+			name: "gofumpt",
+			command: "gofumpt",
+			args: &[],
+			mode: ProcessMode::StdinStdout,
+			write_editorconfig: false,
+		},
+	)
+}
+
+/// Oldest system clang-format used as a fallback; fama's generated style
+/// has a `Language: Json` section, which older releases reject
+const CLANG_FORMAT_MIN_MAJOR: u32 = 16;
+
+/// Format with a host-installed `clang-format` using fama's `style`, for
+/// when the WASM build can't handle a file. The installed version is
+/// checked once per process.
+pub fn format_clang(
+	source: &str,
+	file_path: &str,
+	style: &str,
+) -> Result<String, String> {
+	subprocesses_allowed("clang-format", "clang-format")?;
+	static MAJOR: OnceLock<Result<u32, String>> = OnceLock::new();
+	let major = MAJOR.get_or_init(clang_format_major).clone()?;
+	if major < CLANG_FORMAT_MIN_MAJOR {
+		return Err(format!(
+			"system clang-format {} is older than {}, the oldest that \
+			 accepts fama's style",
+			major, CLANG_FORMAT_MIN_MAJOR
+		));
+	}
+
+	let mut command = sandboxed_command("clang-format");
+	command
+		.arg(format!("--style={}", style))
+		.arg(format!("--assume-filename={}", file_path));
+	pipe_through("clang-format", "clang-format", command, source)
+}
+
+/// Major version of the `clang-format` on PATH
+fn clang_format_major() -> Result<u32, String> {
+	let output = sandboxed_command("clang-format")
+		.arg("--version")
+		.current_dir(fama_common::temp_dir())
+		.stdin(Stdio::null())
+		.output()
+		.map_err(|error| {
+			command_error("clang-format", "clang-format", error)
+		})?;
+	let version = success_output("clang-format", output)?;
+	parse_major_version(&version).ok_or_else(|| {
+		format!("Unrecognized clang-format version: {}", version.trim())
+	})
+}
+
+/// Major version from `--version` output such as
+/// `Ubuntu clang-format version 18.1.3 (1ubuntu1)`
+fn parse_major_version(version: &str) -> Option<u32> {
+	version
+		.split("version ")
+		.nth(1)?
+		.split(|c: char| !c.is_ascii_digit())
+		.next()?
+		.parse()
+		.ok()
+}
+
+/// Fail unless the config allows running other programs
+fn subprocesses_allowed(name: &str, command: &str) -> Result<(), String> {
+	if CONFIG.subprocess_backends {
+		return Ok(());
+	}
+	Err(format!(
+		"{} runs `{}` as a subprocess, which is disabled by \
+		 subprocess_backends in the config",
+		name, command
+	))
+}
+
+/// Run `command` in the temp dir with `source` on stdin, returning stdout
+fn pipe_through(
+	name: &str,
+	program: &str,
+	mut command: Command,
+	source: &str,
+) -> Result<String, String> {
+	command
+		.current_dir(fama_common::temp_dir())
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped());
+
+	let mut child = command
+		.spawn()
+		.map_err(|error| command_error(name, program, error))?;
+
+	child
+		.stdin
+		.as_mut()
+		.ok_or_else(|| format!("{} stdin was not available", name))?
+		.write_all(source.as_bytes())
+		.map_err(|error| {
+			format!("Failed to write to {} stdin: {}", name, error)
+		})?;
+
+	let output = child
+		.wait_with_output()
+		.map_err(|error| format!("Failed to wait for {}: {}", name, error))?;
+
+	success_output(name, output)
+}
+
+/// A command for `program` with only allowlisted environment variables and,
+/// on Unix, a CPU time limit. The program is executed directly, never
+/// through a shell, and callers must set the working directory.
+fn sandboxed_command(program: &str) -> Command {
+	let mut command = Command::new(program);
+	command.env_clear();
+	for name in ENV_ALLOWLIST {
+		if let Some(value) = std::env::var_os(name) {
+			command.env(name, value);
+		}
+	}
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		// SAFETY: setrlimit is async-signal-safe and touches no memory
+		// shared with the parent
+		unsafe {
+			command.pre_exec(|| {
+				let limit = libc::rlimit {
+					rlim_cur: CPU_LIMIT_SECS as libc::rlim_t,
+					rlim_max: CPU_LIMIT_SECS as libc::rlim_t,
+				};
+				if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+					return Err(std::io::Error::last_os_error());
+				}
+				Ok(())
+			});
+		}
+	}
+
+	command
+}
+
 fn temp_file_path(base_dir: &Path, original_path: &str) -> PathBuf {
 	let file_name = Path::new(original_path)
 		.file_name()
@@ -264,6 +423,19 @@ mod tests {
 		assert_eq!(result, "formatted");
 	}
 
+	#[test]
+	fn test_format_go_gofumpt() {
+		let source = "package main\n\nfunc main() {\n\n\tprintln()\n}\n";
+		match format_gofumpt(source, "main.go") {
+			Ok(formatted) => assert_eq!(
+				formatted,
+				"package main\n\nfunc main() {\n\tprintln()\n}\n"
+			),
+			// Hosts without gofumpt get the usual missing-CLI error
+			Err(error) => assert!(error.contains("CLI was not found in PATH")),
+		}
+	}
+
 	#[test]
 	fn test_format_with_process_command_not_found() {
 		let result = format_with_process(
@@ -282,9 +454,36 @@ mod tests {
 		assert!(error.contains("CLI was not found in PATH"));
 	}
 
+	#[cfg(unix)]
+	#[test]
+	fn test_sandboxed_command_environment_and_limits() {
+		std::env::set_var("FAMA_TEST_SECRET", "leaked");
+		let output = sandboxed_command("sh")
+			.args(["-c", "echo \"$FAMA_TEST_SECRET|$PATH\"; ulimit -t"])
+			.current_dir(fama_common::temp_dir())
+			.output()
+			.unwrap();
+		let stdout = String::from_utf8(output.stdout).unwrap();
+		let (env, cpu_limit) = stdout.split_once('\n').unwrap();
+		assert_eq!(env, format!("|{}", std::env::var("PATH").unwrap()));
+		assert_eq!(cpu_limit.trim(), CPU_LIMIT_SECS.to_string());
+	}
+
 	#[test]
 	fn test_temp_file_path_preserves_filename() {
 		let path = temp_file_path(Path::new("/tmp"), "nested/build.gradle.kts");
 		assert_eq!(path.file_name(), Some(OsStr::new("build.gradle.kts")));
 	}
+
+	#[test]
+	fn test_parse_major_version() {
+		for (version, major) in [
+			("clang-format version 17.0.6\n", Some(17)),
+			("Ubuntu clang-format version 18.1.3 (1ubuntu1)\n", Some(18)),
+			("Homebrew clang-format version 19.1.7", Some(19)),
+			("clang-format 17", None),
+		] {
+			assert_eq!(parse_major_version(version), major, "{version}");
+		}
+	}
 }
@@ -9,6 +9,9 @@ use ruff_python_formatter::{
 	format_module_source, PyFormatOptions, QuoteStyle as RuffQuoteStyle,
 };
 
+mod starlark;
+pub use starlark::format_starlark;
+
 // Module-level constants - pre-converted config values
 const RUFF_INDENT_STYLE: RuffIndentStyle = match CONFIG.indent_style {
 	fama_common::IndentStyle::Tabs => RuffIndentStyle::Tab,
@@ -17,7 +20,9 @@ const RUFF_INDENT_STYLE: RuffIndentStyle = match CONFIG.indent_style {
 const RUFF_INDENT_WIDTH: u8 = CONFIG.indent_width;
 const RUFF_LINE_WIDTH: u16 = CONFIG.line_width;
 const RUFF_LINE_ENDING: RuffLineEnding = match CONFIG.line_ending {
-	fama_common::LineEnding::Lf => RuffLineEnding::LineFeed,
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => {
+		RuffLineEnding::LineFeed
+	}
 	fama_common::LineEnding::Crlf => RuffLineEnding::CarriageReturnLineFeed,
 };
 const RUFF_QUOTE_STYLE: RuffQuoteStyle = match CONFIG.quote_style {
@@ -75,4 +80,27 @@ mod tests {
 		assert!(result.contains("class Foo:"));
 		assert!(result.contains("self.x = x"));
 	}
+
+	#[test]
+	fn test_format_python_fmt_skip() {
+		let source = "x=1+2  # fmt: skip\ny=3+4\n";
+		let result = format_python(source, "test.py").unwrap();
+		assert_eq!(result, "x=1+2  # fmt: skip\ny = 3 + 4\n");
+	}
+
+	#[test]
+	fn test_format_python_fmt_off_on() {
+		let source = concat!(
+			"# fmt: off\n",
+			"matrix = [\n",
+			"    1, 0,\n",
+			"    0, 1,\n",
+			"]\n",
+			"# fmt: on\n",
+			"y=3+4\n",
+		);
+		let result = format_python(source, "test.py").unwrap();
+		assert!(result.starts_with("# fmt: off\nmatrix = [\n    1, 0,\n"));
+		assert!(result.ends_with("# fmt: on\ny = 3 + 4\n"));
+	}
 }
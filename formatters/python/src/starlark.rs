@@ -0,0 +1,392 @@
+// starlark.rs - Bazel Starlark (BUILD, .bzl) formatting on top of ruff
+//
+// Starlark is a Python dialect, so ruff lays it out; the rest of
+// buildifier's style is applied around it. Before formatting, `load()`
+// statements are sorted and rule arguments put in buildifier's order,
+// with a trailing comma so ruff expands each rule one argument per line.
+// After formatting, keyword arguments get buildifier's `name = value`
+// spacing.
+
+use fama_common::CONFIG;
+use ruff_formatter::printer::LineEnding as RuffLineEnding;
+use ruff_formatter::{IndentStyle, IndentWidth, LineWidth};
+use ruff_python_ast::{Expr, Keyword, Stmt};
+use ruff_python_formatter::{
+	format_module_source, PyFormatOptions, QuoteStyle,
+};
+use ruff_python_parser::{parse_module, TokenKind};
+use ruff_text_size::{Ranged, TextRange};
+use std::cmp::Ordering;
+
+/// Rule arguments buildifier moves ahead of (negative) or behind
+/// (positive) all others, which keep their order at 0
+const ARGUMENT_PRIORITY: &[(&str, i32)] = &[
+	("name", -99),
+	("gwt_name", -98),
+	("package_name", -97),
+	("visible_node_name", -96),
+	("size", -95),
+	("timeout", -94),
+	("testonly", -93),
+	("src", -92),
+	("srcdir", -91),
+	("srcs", -90),
+	("out", -89),
+	("outs", -88),
+	("hdrs", -87),
+	("has_services", -86),
+	("include", -85),
+	("of", -84),
+	("baseline", -83),
+	("destdir", 1),
+	("exports", 2),
+	("runtime_deps", 3),
+	("deps", 4),
+	("implementation", 5),
+	("implements", 6),
+	("alwayslink", 7),
+];
+
+/// Format Starlark source code in buildifier's style
+pub fn format_starlark(
+	source: &str,
+	_file_path: &str,
+) -> Result<String, String> {
+	// Reordering could move code into or out of a `# fmt: off` region
+	let rewritten =
+		if source.contains("fmt: off") || source.contains("fmt: skip") {
+			source.to_string()
+		} else {
+			rewrite(source)?
+		};
+	// buildifier's layout is fixed: 4 spaces and double quotes
+	let options = PyFormatOptions::default()
+		.with_indent_style(IndentStyle::Space)
+		.with_indent_width(IndentWidth::try_from(4u8).unwrap())
+		.with_line_width(LineWidth::try_from(CONFIG.line_width).unwrap())
+		.with_line_ending(RuffLineEnding::LineFeed)
+		.with_quote_style(QuoteStyle::Double);
+	let formatted = format_module_source(&rewritten, options)
+		.map_err(|e| format!("Starlark formatting error: {}", e))?
+		.into_code();
+	space_keywords(&formatted)
+}
+
+/// Sort loads and order rule arguments in the top-level statements
+fn rewrite(source: &str) -> Result<String, String> {
+	let parsed = parse_module(source)
+		.map_err(|e| format!("Starlark parse error: {}", e))?;
+	let mut edits: Vec<(TextRange, String)> = Vec::new();
+	let mut loads: Vec<(TextRange, String, String)> = Vec::new();
+
+	for stmt in &parsed.syntax().body {
+		let Some((callee, args, keywords)) = top_level_call(stmt) else {
+			flush_loads(source, &mut loads, &mut edits);
+			continue;
+		};
+		let text = &source[stmt.range()];
+		if text.contains('#') {
+			// Rebuilding the call would drop its comments
+			flush_loads(source, &mut loads, &mut edits);
+			continue;
+		}
+		if callee == "load" {
+			let label = match args.first() {
+				Some(Expr::StringLiteral(label)) => label.value.to_str(),
+				_ => "",
+			};
+			let call = match CONFIG.starlark_sort_loads {
+				true => sorted_load(source, args, keywords),
+				false => text.to_string(),
+			};
+			loads.push((stmt.range(), label.to_string(), call));
+			continue;
+		}
+		flush_loads(source, &mut loads, &mut edits);
+		if keywords
+			.iter()
+			.any(|k| k.arg.as_ref().is_some_and(|a| a.as_str() == "name"))
+		{
+			edits.push((stmt.range(), rule(source, callee, args, keywords)));
+		}
+	}
+	flush_loads(source, &mut loads, &mut edits);
+
+	let mut rewritten = source.to_string();
+	for (range, replacement) in edits.iter().rev() {
+		rewritten.replace_range(
+			usize::from(range.start())..usize::from(range.end()),
+			replacement,
+		);
+	}
+	Ok(rewritten)
+}
+
+/// Callee name, positional and keyword arguments of a statement that is
+/// a bare call to a plain name, like `cc_library(...)`
+fn top_level_call(stmt: &Stmt) -> Option<(&str, &[Expr], &[Keyword])> {
+	let Stmt::Expr(stmt) = stmt else { return None };
+	let Expr::Call(call) = stmt.value.as_ref() else {
+		return None;
+	};
+	let Expr::Name(callee) = call.func.as_ref() else {
+		return None;
+	};
+	Some((
+		callee.id.as_str(),
+		&call.arguments.args[..],
+		&call.arguments.keywords[..],
+	))
+}
+
+/// `load()` with its label first and the loaded symbols sorted by the
+/// name they are bound to; aliased symbols stay after plain ones, since
+/// ruff parses Starlark as Python
+fn sorted_load(source: &str, args: &[Expr], keywords: &[Keyword]) -> String {
+	let mut symbols: Vec<(&str, &str)> = args
+		.iter()
+		.skip(1)
+		.map(|arg| match arg {
+			Expr::StringLiteral(symbol) => {
+				(symbol.value.to_str(), &source[arg.range()])
+			}
+			_ => ("", &source[arg.range()]),
+		})
+		.collect();
+	symbols.sort_by_key(|&(bound, _)| bound);
+	let mut aliases: Vec<(&str, &str)> = keywords
+		.iter()
+		.map(|keyword| {
+			let bound = keyword.arg.as_ref().map_or("", |a| a.as_str());
+			(bound, &source[keyword.range()])
+		})
+		.collect();
+	aliases.sort_by_key(|&(bound, _)| bound);
+
+	let label = args.first().map_or("", |label| &source[label.range()]);
+	let parts: Vec<&str> = std::iter::once(label)
+		.chain(symbols.iter().chain(&aliases).map(|&(_, text)| text))
+		.collect();
+	format!("load({})", parts.join(", "))
+}
+
+/// Replace each run of adjacent `load()` statements with the same
+/// statements sorted by label, when `starlark_sort_loads` is on
+fn flush_loads(
+	source: &str,
+	loads: &mut Vec<(TextRange, String, String)>,
+	edits: &mut Vec<(TextRange, String)>,
+) {
+	let (Some(first), Some(last)) = (loads.first(), loads.last()) else {
+		return;
+	};
+	let range = TextRange::new(first.0.start(), last.0.end());
+	let adjacent = loads.windows(2).all(|pair| {
+		source[TextRange::new(pair[0].0.end(), pair[1].0.start())]
+			.trim()
+			.is_empty()
+	});
+	if CONFIG.starlark_sort_loads && adjacent {
+		loads.sort_by(|a, b| compare_load_labels(&a.1, &b.1));
+		let calls: Vec<&str> =
+			loads.iter().map(|(_, _, call)| call.as_str()).collect();
+		edits.push((range, calls.join("\n")));
+	} else {
+		edits.extend(
+			loads.iter().map(|(range, _, call)| (*range, call.clone())),
+		);
+	}
+	loads.clear();
+}
+
+/// buildifier's order for `load()` labels: ones naming a repository
+/// (`@repo//`) first, then by package with the current package (`:x.bzl`)
+/// first, then by file name
+fn compare_load_labels(a: &str, b: &str) -> Ordering {
+	let names_repo = |label: &str| {
+		label
+			.strip_prefix('@')
+			.and_then(|rest| rest.split_once("//"))
+			.is_some_and(|(repo, _)| {
+				!repo.is_empty()
+					&& repo.chars().all(|c| {
+						c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+					})
+			})
+	};
+	fn split(label: &str) -> (&str, &str) {
+		label.split_once(':').unwrap_or(("", label))
+	}
+	let ((package_a, file_a), (package_b, file_b)) = (split(a), split(b));
+	names_repo(b)
+		.cmp(&names_repo(a))
+		.then_with(|| package_a.is_empty().cmp(&package_b.is_empty()).reverse())
+		.then_with(|| package_a.cmp(package_b))
+		.then_with(|| file_a.cmp(file_b))
+}
+
+/// A rule call with its arguments in buildifier's order, when
+/// `starlark_sort_arguments` is on, and a trailing comma so the rule is
+/// laid out one argument per line
+fn rule(
+	source: &str,
+	callee: &str,
+	args: &[Expr],
+	keywords: &[Keyword],
+) -> String {
+	let mut keywords: Vec<&Keyword> = keywords.iter().collect();
+	// `**kwargs` pins the order, since later keys may override it
+	if CONFIG.starlark_sort_arguments
+		&& keywords.iter().all(|k| k.arg.is_some())
+	{
+		keywords.sort_by_key(|keyword| {
+			let name = keyword.arg.as_ref().map_or("", |a| a.as_str());
+			ARGUMENT_PRIORITY
+				.iter()
+				.find(|(known, _)| *known == name)
+				.map_or(0, |&(_, priority)| priority)
+		});
+	}
+	let parts: Vec<&str> = args
+		.iter()
+		.map(|arg| &source[arg.range()])
+		.chain(keywords.iter().map(|keyword| &source[keyword.range()]))
+		.collect();
+	format!("{}({},)", callee, parts.join(", "))
+}
+
+/// Put spaces around each keyword argument and parameter default `=`, as
+/// in `name = "a"`. Only an `=` directly inside parentheses is one of
+/// those; assignments are spaced by ruff already, and `=` in brackets,
+/// braces or an f-string's `{x=}` is left alone.
+fn space_keywords(formatted: &str) -> Result<String, String> {
+	let parsed = parse_module(formatted)
+		.map_err(|e| format!("Starlark formatting error: {}", e))?;
+	let mut out = String::with_capacity(formatted.len());
+	let mut copied = 0;
+	let mut brackets: Vec<TokenKind> = Vec::new();
+	for token in parsed.tokens().iter() {
+		match token.kind() {
+			kind @ (TokenKind::Lpar | TokenKind::Lsqb | TokenKind::Lbrace) => {
+				brackets.push(kind);
+				continue;
+			}
+			TokenKind::Rpar | TokenKind::Rsqb | TokenKind::Rbrace => {
+				brackets.pop();
+				continue;
+			}
+			TokenKind::Equal if brackets.last() == Some(&TokenKind::Lpar) => {}
+			_ => continue,
+		}
+		let (start, end) =
+			(usize::from(token.start()), usize::from(token.end()));
+		out.push_str(&formatted[copied..start]);
+		if !out.ends_with([' ', '\n']) {
+			out.push(' ');
+		}
+		out.push('=');
+		if !formatted[end..].starts_with([' ', '\n']) {
+			out.push(' ');
+		}
+		copied = end;
+	}
+	out.push_str(&formatted[copied..]);
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format_starlark_rule() {
+		let source = "cc_library(deps=[\":b\"], srcs=['a.cc'], name='a')\n";
+		assert_eq!(
+			format_starlark(source, "BUILD").unwrap(),
+			concat!(
+				"cc_library(\n",
+				"    name = \"a\",\n",
+				"    srcs = [\"a.cc\"],\n",
+				"    deps = [\":b\"],\n",
+				")\n",
+			)
+		);
+	}
+
+	#[test]
+	fn test_format_starlark_loads() {
+		let source = concat!(
+			"load(\"@rules_cc//cc:defs.bzl\", \"cc_test\", \"cc_binary\")\n",
+			"load(\"//tools:a.bzl\", \"c\", \"a\", b = \"x\")\n",
+			"load(\"//:defs.bzl\", \"defs\")\n",
+		);
+		assert_eq!(
+			format_starlark(source, "BUILD").unwrap(),
+			concat!(
+				"load(\"@rules_cc//cc:defs.bzl\", \"cc_binary\", \"cc_test\")\n",
+				"load(\"//:defs.bzl\", \"defs\")\n",
+				"load(\"//tools:a.bzl\", \"a\", \"c\", b = \"x\")\n",
+			)
+		);
+	}
+
+	#[test]
+	fn test_compare_load_labels() {
+		let mut labels = vec![
+			"//tools:b.bzl",
+			"//tools:a.bzl",
+			":local.bzl",
+			"//:defs.bzl",
+			"@z//:z.bzl",
+			"@a.b-c//x:y.bzl",
+		];
+		labels.sort_by(|a, b| compare_load_labels(a, b));
+		assert_eq!(
+			labels,
+			[
+				"@a.b-c//x:y.bzl",
+				"@z//:z.bzl",
+				":local.bzl",
+				"//:defs.bzl",
+				"//tools:a.bzl",
+				"//tools:b.bzl",
+			]
+		);
+	}
+
+	#[test]
+	fn test_space_keywords_only() {
+		let source = concat!(
+			"x = a==b\n",
+			"y = f(a<=b, c!=d, e=1)\n",
+			"s = \"k=v\"\n",
+			"d = {\"k\": [g(k=1)]}\n",
+			"t = f\"{x=}\"\n",
+		);
+		assert_eq!(
+			format_starlark(source, "defs.bzl").unwrap(),
+			concat!(
+				"x = a == b\n",
+				"y = f(a <= b, c != d, e = 1)\n",
+				"s = \"k=v\"\n",
+				"d = {\"k\": [g(k = 1)]}\n",
+				"t = f\"{x=}\"\n",
+			)
+		);
+	}
+
+	#[test]
+	fn test_format_starlark_function() {
+		let source = concat!(
+			"def my_macro(name, visibility=None):\n",
+			"  native.filegroup(name=name)\n",
+		);
+		assert_eq!(
+			format_starlark(source, "defs.bzl").unwrap(),
+			concat!(
+				"def my_macro(name, visibility = None):\n",
+				"    native.filegroup(name = name)\n",
+			)
+		);
+	}
+}
@@ -18,7 +18,7 @@ const RUSTFMT_HARD_TABS: &str =
 const RUSTFMT_TAB_SPACES: &str = "4";
 const RUSTFMT_MAX_WIDTH: &str = "80";
 const RUSTFMT_NEWLINE_STYLE: &str = match CONFIG.line_ending {
-	fama_common::LineEnding::Lf => "Unix",
+	fama_common::LineEnding::Lf | fama_common::LineEnding::Auto => "Unix",
 	fama_common::LineEnding::Crlf => "Windows",
 };
 
@@ -1,39 +1,134 @@
 // sqruff-formatter - SQL code formatter using sqruff
 //
-// Provides SQL code formatting using the sqruff crate.
+// Provides SQL code formatting using the sqruff crate, and the lint
+// findings its fixes can't resolve. Jinja and dbt models go through
+// sqruff's templaters, which run sqlfluff's through an embedded Python
+// and so are only built with the `templating` feature.
 
 use ahash::AHashMap;
-use fama_common::{IndentStyle, CONFIG};
+use fama_common::{IndentStyle, SqlTemplater, CONFIG};
 use sqruff_lib::core::config::{FluffConfig, Value};
 use sqruff_lib::core::linter::core::Linter;
+use sqruff_lib::core::linter::linted_file::LintedFile;
+use sqruff_lib_core::parser::segments::Tables;
 
 /// Format SQL source code using sqruff
 ///
 /// # Arguments
 /// * `source` - The SQL source code to format
-/// * `_file_path` - Path to the file (unused, for future context)
+/// * `file_path` - Path to the file, which the dbt templater compiles
 ///
 /// # Returns
 /// * `Ok(String)` - Formatted SQL code
 /// * `Err(String)` - Error message if formatting fails
-pub fn format_sql(source: &str, _file_path: &str) -> Result<String, String> {
-	let config = create_config();
-	let linter = Linter::new(config, None, None, false);
-	let linted_file = linter.lint_string(source, None, true);
-	Ok(linted_file.fix_string())
+pub fn format_sql(source: &str, file_path: &str) -> Result<String, String> {
+	check_templater()?;
+	Ok(lint(source, file_path, CONFIG.sql_templater, true)?.fix_string())
+}
+
+/// Lint `source`, applying fixes with `fix`. Unlike `Linter::lint_string`,
+/// a template that fails to render is an error rather than a panic.
+fn lint(
+	source: &str,
+	file_path: &str,
+	templater: SqlTemplater,
+	fix: bool,
+) -> Result<LintedFile, String> {
+	let linter = Linter::new(create_config(templater), None, None, false);
+	let tables = Tables::default();
+	let parsed = linter
+		.parse_string(&tables, source, Some(file_path.to_string()))
+		.map_err(|e| e.value)?;
+	Ok(linter.lint_parsed(&tables, parsed, fix))
+}
+
+/// Fail clearly when `sql_templater` asks for a templater this build
+/// doesn't have
+fn check_templater() -> Result<(), String> {
+	if CONFIG.sql_templater == SqlTemplater::Raw || cfg!(feature = "templating")
+	{
+		Ok(())
+	} else {
+		Err(format!(
+			"sql_templater = {:?} needs fama built with the \
+			 `sql-templating` feature",
+			CONFIG.sql_templater
+		))
+	}
+}
+
+/// sqruff's name for `templater`
+fn templater_name(templater: SqlTemplater) -> &'static str {
+	match templater {
+		SqlTemplater::Raw => "raw",
+		SqlTemplater::Jinja => "jinja",
+		SqlTemplater::Dbt => "dbt",
+	}
+}
+
+/// A lint finding sqruff can't fix. These come from rules in sqruff's
+/// default rule set that have no fix, such as ambiguous `DISTINCT` with
+/// `GROUP BY` (AM01), mixed positional and named `GROUP BY`/`ORDER BY`
+/// references (AM06), self-aliased columns (AL09) and `= NULL` (CV05),
+/// plus parse errors. Fixable findings such as `UNION` without `DISTINCT`
+/// (AM02) aren't reported, since formatting applies their fixes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+	/// 1-based line of the finding
+	pub line: usize,
+	/// Rule code such as `AM01`, or `PRS` when the SQL doesn't parse
+	pub code: &'static str,
+	pub description: String,
+}
+
+/// Findings in `source` that formatting leaves behind, in source order
+pub fn lint_sql(source: &str, file_path: &str) -> Vec<LintFinding> {
+	if check_templater().is_err() {
+		return Vec::new();
+	}
+	// Formatting already reports templates that don't render
+	let Ok(linted_file) = lint(source, file_path, CONFIG.sql_templater, false)
+	else {
+		return Vec::new();
+	};
+	// sqruff sorts violations by position
+	linted_file
+		.into_violations()
+		.into_iter()
+		.filter(|violation| !violation.fixable)
+		.map(|violation| LintFinding {
+			line: violation.line_no,
+			code: violation.rule.as_ref().map_or("PRS", |rule| rule.code),
+			description: violation.description,
+		})
+		.collect()
 }
 
 /// Create sqruff FluffConfig from fama FormatConfig
-fn create_config() -> FluffConfig {
+fn create_config(templater: SqlTemplater) -> FluffConfig {
 	let mut configs = AHashMap::new();
 
-	// Core section
+	// Core section - `[sqruff]` in a config file, keyed `core` here
 	let mut core = AHashMap::new();
 	core.insert(
 		"max_line_length".to_string(),
 		Value::Int(CONFIG.line_width as i32),
 	);
-	configs.insert("sqruff".to_string(), Value::Map(core));
+	let templater = templater_name(templater);
+	core.insert("templater".to_string(), Value::String(templater.into()));
+	configs.insert("core".to_string(), Value::Map(core));
+
+	// Templater section - `[sqruff:templater:<name>:context]`
+	let context: AHashMap<String, Value> = CONFIG
+		.sql_templater_vars
+		.iter()
+		.map(|(name, value)| (name.to_string(), Value::String((*value).into())))
+		.collect();
+	let mut templater_section = AHashMap::new();
+	templater_section.insert("context".to_string(), Value::Map(context));
+	let mut templaters = AHashMap::new();
+	templaters.insert(templater.to_string(), Value::Map(templater_section));
+	configs.insert("templater".to_string(), Value::Map(templaters));
 
 	// Indentation section
 	let mut indentation = AHashMap::new();
@@ -82,4 +177,53 @@ mod tests {
 		assert!(output.contains("SELECT"));
 		assert!(output.contains("FROM"));
 	}
+
+	#[test]
+	fn test_lint_reports_unfixable_findings() {
+		// No fix can make an unparsable statement parse
+		let findings = lint_sql("SELECT a FROM b;\n\nSELECT (\n", "");
+		assert!(
+			findings.iter().any(|f| f.code == "PRS" && f.line == 3),
+			"{findings:?}"
+		);
+		assert!(lint_sql("SELECT a FROM b\n", "").is_empty());
+	}
+
+	#[test]
+	fn test_core_section_applies() {
+		let config = create_config(SqlTemplater::Jinja);
+		assert_eq!(
+			config.get("max_line_length", "core").as_int(),
+			Some(CONFIG.line_width as i32)
+		);
+		assert_eq!(config.get("templater", "core").as_string(), Some("jinja"));
+	}
+
+	/// Needs the `sqruff` Python package, whose templaters sqruff-lib
+	/// imports, and jinja2: `pip install sqruff`
+	#[cfg(feature = "templating")]
+	#[test]
+	fn test_format_jinja_ref() {
+		let source = "select id from {{ ref('users') }} where id=1\n";
+		let formatted = lint(source, "model.sql", SqlTemplater::Jinja, true)
+			.unwrap()
+			.fix_string();
+		assert_eq!(
+			formatted,
+			"SELECT id FROM {{ ref('users') }} WHERE id = 1\n"
+		);
+	}
+
+	#[test]
+	fn test_lint_reports_unfixable_rules_only() {
+		let codes = |source| -> Vec<&str> {
+			lint_sql(source, "").iter().map(|f| f.code).collect()
+		};
+		assert_eq!(codes("SELECT DISTINCT a FROM b GROUP BY a\n"), ["AM01"]);
+		assert_eq!(codes("SELECT a, b FROM t GROUP BY 1, b\n"), ["AM06"]);
+		assert_eq!(codes("SELECT a AS a FROM t\n"), ["AL09"]);
+		assert_eq!(codes("SELECT a FROM t WHERE a = NULL\n"), ["CV05"]);
+		// AM02 and LT11 are fixable, so formatting takes care of them
+		assert!(codes("SELECT a FROM b UNION SELECT a FROM c\n").is_empty());
+	}
 }
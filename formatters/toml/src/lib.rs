@@ -1,11 +1,15 @@
 // toml-fmt - TOML formatting library using Taplo
 
-use fama_common::CONFIG;
+use fama_common::{FileType, CONFIG};
 
 // Module-level constants - pre-converted config values
 const TAPLO_COLUMN_WIDTH: usize = CONFIG.line_width as usize;
 const TAPLO_CRLF: bool =
 	matches!(CONFIG.line_ending, fama_common::LineEnding::Crlf);
+const TAPLO_ALLOWED_BLANK_LINES: usize = match CONFIG.max_blank_lines {
+	Some(max) => max as usize,
+	None => 1,
+};
 const TAPLO_INDENT_STRING: &str = match CONFIG.indent_style {
 	fama_common::IndentStyle::Tabs => "\t",
 	fama_common::IndentStyle::Spaces => " ", // Placeholder, actual string is generated at runtime
@@ -30,7 +34,7 @@ pub fn format_toml(source: &str, _file_path: &str) -> Result<String, String> {
 		column_width: TAPLO_COLUMN_WIDTH,
 		indent_string: TAPLO_INDENT_STRING.to_owned(),
 		crlf: TAPLO_CRLF,
-		trailing_newline: true,
+		trailing_newline: CONFIG.insert_final_newline_for(FileType::Toml),
 		align_entries: false,
 		align_comments: true,
 		array_trailing_comma: true,
@@ -42,11 +46,12 @@ pub fn format_toml(source: &str, _file_path: &str) -> Result<String, String> {
 		indent_entries: false,
 		reorder_keys: false,
 		reorder_arrays: false,
-		allowed_blank_lines: 1,
+		allowed_blank_lines: TAPLO_ALLOWED_BLANK_LINES,
 		..Default::default()
 	};
 
-	Ok(format_syntax(parsed.into_syntax(), options))
+	let formatted = format_syntax(parsed.into_syntax(), options);
+	Ok(CONFIG.apply_final_newline(formatted, FileType::Toml))
 }
 
 #[cfg(test)]
@@ -1,6 +1,6 @@
 // xml-fmt - XML formatting library using quick-xml
 
-use fama_common::{IndentStyle, CONFIG};
+use fama_common::{FileType, IndentStyle, CONFIG};
 use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::reader::Reader;
 use quick_xml::writer::Writer;
@@ -90,14 +90,101 @@ pub fn format_xml(source: &str, _file_path: &str) -> Result<String, String> {
 	}
 
 	let result = writer.into_inner().into_inner();
-	let mut formatted = String::from_utf8(result).map_err(|e| e.to_string())?;
+	let formatted = String::from_utf8(result).map_err(|e| e.to_string())?;
+	Ok(CONFIG.apply_final_newline(formatted, FileType::Xml))
+}
+
+/// Check that `formatted` describes the same document as `source`.
+///
+/// Compares element names, attributes (order-insensitive), trimmed text,
+/// comments, CDATA, and processing instructions; `<a/>` and `<a></a>` are
+/// considered equal. Returns the first difference as an error.
+pub fn validate_xml(source: &str, formatted: &str) -> Result<(), String> {
+	let before = semantic_events(source)?;
+	let after = semantic_events(formatted)?;
+
+	for (i, (a, b)) in before.iter().zip(&after).enumerate() {
+		if a != b {
+			return Err(format!("node {i} changed from {a} to {b}"));
+		}
+	}
+	if before.len() != after.len() {
+		return Err(format!(
+			"node count changed from {} to {}",
+			before.len(),
+			after.len()
+		));
+	}
+	Ok(())
+}
 
-	// Ensure trailing newline
-	if !formatted.ends_with('\n') {
-		formatted.push('\n');
+/// Flatten a document into comparable, whitespace-insensitive events
+fn semantic_events(source: &str) -> Result<Vec<String>, String> {
+	let mut reader = Reader::from_str(source);
+	reader.config_mut().trim_text(true);
+
+	let mut events = Vec::new();
+	loop {
+		let event = reader
+			.read_event()
+			.map_err(|e| format!("XML parse error: {:?}", e))?;
+		match event {
+			Event::Start(e) => events.push(start_event(&e)?),
+			Event::Empty(e) => {
+				events.push(start_event(&e)?);
+				events.push(format!(
+					"</{}>",
+					String::from_utf8_lossy(e.name().as_ref())
+				));
+			}
+			Event::End(e) => events.push(format!(
+				"</{}>",
+				String::from_utf8_lossy(e.name().as_ref())
+			)),
+			Event::Text(e) => {
+				let text = e.unescape().map_err(|e| e.to_string())?;
+				if !text.trim().is_empty() {
+					events.push(format!("text {:?}", text.trim()));
+				}
+			}
+			Event::CData(e) => events.push(raw_event("cdata", &e)),
+			Event::Comment(e) => events.push(raw_event("comment", &e)),
+			Event::Decl(e) => events.push(raw_event("decl", &e)),
+			Event::PI(e) => events.push(raw_event("pi", &e)),
+			Event::DocType(e) => events.push(raw_event("doctype", &e)),
+			Event::Eof => break,
+		}
 	}
+	Ok(events)
+}
 
-	Ok(formatted)
+/// Describe a node compared by its raw content
+fn raw_event(kind: &str, content: &[u8]) -> String {
+	format!("{kind} {:?}", String::from_utf8_lossy(content))
+}
+
+/// Describe a start tag with its attributes sorted by name
+fn start_event(e: &BytesStart) -> Result<String, String> {
+	let mut attributes = e
+		.attributes()
+		.map(|attribute| {
+			let attribute = attribute.map_err(|e| e.to_string())?;
+			let value =
+				attribute.unescape_value().map_err(|e| e.to_string())?;
+			Ok(format!(
+				"{}={:?}",
+				String::from_utf8_lossy(attribute.key.as_ref()),
+				value
+			))
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+	attributes.sort();
+
+	Ok(format!(
+		"<{} {}>",
+		String::from_utf8_lossy(e.name().as_ref()),
+		attributes.join(" ")
+	))
 }
 
 #[cfg(test)]
@@ -136,4 +223,25 @@ mod tests {
 		// but the output won't be valid XML either
 		assert!(result.is_ok());
 	}
+
+	#[test]
+	fn test_validate_xml_ignores_layout() {
+		let source = r#"<root><a x="1" y="2"></a><b>text</b></root>"#;
+		let formatted =
+			"<root>\n\t<a y=\"2\" x=\"1\"/>\n\t<b>text</b>\n</root>\n";
+		assert!(validate_xml(source, formatted).is_ok());
+	}
+
+	#[test]
+	fn test_validate_xml_detects_dropped_attribute() {
+		let source = r#"<root><item name="a"/></root>"#;
+		let err = validate_xml(source, "<root><item/></root>").unwrap_err();
+		assert!(err.contains("name"), "Got: {}", err);
+	}
+
+	#[test]
+	fn test_validate_xml_detects_text_change() {
+		let result = validate_xml("<a>one</a>", "<a>two</a>");
+		assert!(result.is_err());
+	}
 }
@@ -1,8 +1,35 @@
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+#[path = "../prebuilt.rs"]
+mod prebuilt;
+
+/// Functions src/lib.rs imports. A library missing any of them was built
+/// from an older root.zig and must be rebuilt, since linking it would at
+/// best fail and at worst call zig_fmt with the old FormatResult layout.
+const SYMBOLS: [&str; 5] = [
+	"zig_fmt",
+	"zig_fmt_batch",
+	"zig_fmt_free",
+	"zig_fmt_batch_free",
+	"zig_fmt_version",
+];
+
+/// Whether the static library at `path` exports every function in
+/// `SYMBOLS`
+fn is_current(path: &Path) -> bool {
+	let Ok(bytes) = fs::read(path) else {
+		return false;
+	};
+	SYMBOLS.iter().all(|symbol| {
+		bytes
+			.windows(symbol.len())
+			.any(|window| window == symbol.as_bytes())
+	})
+}
+
 fn main() {
 	let zig_dir =
 		PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("zig");
@@ -32,8 +59,19 @@ fn main() {
 	let lib_name = format!("libzigfmt-{}.a", target);
 	let lib_src = zig_dir.join(&lib_name);
 
-	// Always rebuild when target changes or library doesn't exist
-	if !lib_src.exists() {
+	let stale = lib_src.exists() && !is_current(&lib_src);
+	if stale {
+		println!(
+			"cargo:warning={} predates the current zig_fmt ABI, rebuilding it",
+			lib_name
+		);
+	}
+
+	// Always rebuild when target changes or library doesn't exist, unless
+	// a prebuilt library was fetched (opt-in, see prebuilt.rs)
+	if (stale || !lib_src.exists())
+		&& !prebuilt::fetch("zig", &lib_name, &lib_src)
+	{
 		println!(
 			"cargo:warning=Building Zig static library for target {} (zig target: {})...",
 			target, zig_target
@@ -4,16 +4,29 @@ use fama_common::FileType;
 use libc::{c_char, size_t};
 use std::ffi::{CStr, CString};
 
+mod owned;
+
+use owned::{OwnedResult, OwnedResultArray};
+
 #[repr(C)]
 struct FormatResult {
 	data: *mut c_char,
 	len: size_t,
 	error_msg: *const c_char,
+	error_line: size_t,
+	error_column: size_t,
+	error_owned: bool,
 }
 
 extern "C" {
 	fn zig_fmt(source: *const c_char, source_len: size_t) -> FormatResult;
+	fn zig_fmt_batch(
+		sources: *const *const c_char,
+		lengths: *const size_t,
+		count: size_t,
+	) -> *mut FormatResult;
 	fn zig_fmt_free(result: *mut FormatResult);
+	fn zig_fmt_batch_free(results: *mut FormatResult, count: size_t);
 	fn zig_fmt_version() -> *const c_char;
 }
 
@@ -30,32 +43,56 @@ pub fn version() -> &'static str {
 }
 
 /// Format Zig source code
+///
+/// Parse errors are reported as `line:column: message`, pointing at the
+/// first error in the file.
 pub fn format_zig(source: &str, _file_path: &str) -> Result<String, String> {
 	let c_source =
 		CString::new(source).map_err(|e| format!("Invalid source: {}", e))?;
 
-	let mut result =
-		unsafe { zig_fmt(c_source.as_ptr(), source.len() as size_t) };
+	let result = unsafe { zig_fmt(c_source.as_ptr(), source.len() as size_t) };
+	unsafe { OwnedResult::new(result, zig_fmt_free) }.read()
+}
 
-	if result.data.is_null() {
-		let error = if result.error_msg.is_null() {
-			"Unknown error".to_string()
-		} else {
-			unsafe { CStr::from_ptr(result.error_msg) }
-				.to_str()
-				.unwrap_or("Unknown error")
-				.to_string()
-		};
-		return Err(error);
+/// Format several Zig sources in one FFI call
+pub fn format_zig_batch(sources: &[&str]) -> Vec<Result<String, String>> {
+	if sources.is_empty() {
+		return Vec::new();
 	}
 
-	let formatted = unsafe { CStr::from_ptr(result.data) }
-		.to_str()
-		.map(|s| s.to_string())
-		.map_err(|e| format!("Invalid UTF-8: {}", e));
+	let c_sources: Vec<CString> =
+		match sources.iter().map(|s| CString::new(*s)).collect() {
+			Ok(v) => v,
+			Err(_) => {
+				return sources
+					.iter()
+					.map(|_| Err("Invalid source".to_string()))
+					.collect()
+			}
+		};
 
-	unsafe { zig_fmt_free(&mut result) };
-	formatted
+	let c_ptrs: Vec<*const c_char> =
+		c_sources.iter().map(|s| s.as_ptr()).collect();
+	let lengths: Vec<size_t> =
+		sources.iter().map(|s| s.len() as size_t).collect();
+
+	let c_results = unsafe {
+		zig_fmt_batch(
+			c_ptrs.as_ptr(),
+			lengths.as_ptr(),
+			sources.len() as size_t,
+		)
+	};
+
+	match unsafe {
+		OwnedResultArray::from_raw(c_results, sources.len(), zig_fmt_batch_free)
+	} {
+		Some(results) => results.read(),
+		None => sources
+			.iter()
+			.map(|_| Err("Out of memory allocating results".to_string()))
+			.collect(),
+	}
 }
 
 /// Format a file based on its type
@@ -112,4 +149,21 @@ mod tests {
 		let result = format_zig(source, "test.zig");
 		assert!(result.is_err());
 	}
+
+	#[test]
+	fn test_format_zig_error_location() {
+		let source = "const a = 1;\nconst x = ;\n";
+		let error = format_zig(source, "test.zig").unwrap_err();
+		assert!(error.starts_with("2:11: "), "{}", error);
+		assert!(error.contains("expected expression"), "{}", error);
+	}
+
+	#[test]
+	fn test_format_zig_batch() {
+		let results = format_zig_batch(&["const x=1;", "const x = ;"]);
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].as_deref(), Ok("const x = 1;\n"));
+		assert!(results[1].is_err());
+		assert!(format_zig_batch(&[]).is_empty());
+	}
 }
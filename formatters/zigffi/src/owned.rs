@@ -0,0 +1,189 @@
+// owned.rs - Results the Zig library hands back, freed on drop
+//
+// The output and error message of a FormatResult are allocated by Zig and
+// have to go back through zig_fmt_free or zig_fmt_batch_free once they
+// are copied out. Holding the result in a type whose Drop frees it keeps
+// early returns from leaking it. The free function is a field so tests
+// can hand in Rust-allocated results and run under Miri.
+
+use libc::size_t;
+use std::ffi::CStr;
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::FormatResult;
+
+/// Frees the buffers of one result returned by `zig_fmt`
+pub(crate) type FreeFn = unsafe extern "C" fn(*mut FormatResult);
+
+/// Frees an array of results returned by `zig_fmt_batch`
+pub(crate) type FreeArrayFn = unsafe extern "C" fn(*mut FormatResult, size_t);
+
+/// A result whose buffers are owned by the library
+pub(crate) struct OwnedResult {
+	result: FormatResult,
+	free: FreeFn,
+}
+
+impl OwnedResult {
+	/// Take ownership of the buffers of `result`
+	///
+	/// # Safety
+	///
+	/// The non-null pointers in `result` must be NUL-terminated strings
+	/// that stay valid until `result` is passed to `free`, and nothing else
+	/// may free them.
+	pub(crate) unsafe fn new(result: FormatResult, free: FreeFn) -> Self {
+		OwnedResult { result, free }
+	}
+
+	/// Copy the output or error out
+	pub(crate) fn read(&self) -> Result<String, String> {
+		read(&self.result)
+	}
+}
+
+impl Drop for OwnedResult {
+	fn drop(&mut self) {
+		unsafe { (self.free)(&mut self.result) }
+	}
+}
+
+/// An array of `len` results owned by the library
+pub(crate) struct OwnedResultArray {
+	ptr: NonNull<FormatResult>,
+	len: usize,
+	free: FreeArrayFn,
+}
+
+impl OwnedResultArray {
+	/// Take ownership of the `len` results at `ptr`, or `None` if it is null
+	///
+	/// # Safety
+	///
+	/// A non-null `ptr` must point to `len` results that meet the
+	/// requirements of [`OwnedResult::new`] until passed to `free`.
+	pub(crate) unsafe fn from_raw(
+		ptr: *mut FormatResult,
+		len: usize,
+		free: FreeArrayFn,
+	) -> Option<Self> {
+		NonNull::new(ptr).map(|ptr| OwnedResultArray { ptr, len, free })
+	}
+
+	/// Copy every output or error out, in order
+	pub(crate) fn read(&self) -> Vec<Result<String, String>> {
+		unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+			.iter()
+			.map(read)
+			.collect()
+	}
+}
+
+impl Drop for OwnedResultArray {
+	fn drop(&mut self) {
+		unsafe { (self.free)(self.ptr.as_ptr(), self.len as size_t) }
+	}
+}
+
+/// Parse errors are reported as `line:column: message`
+fn read(result: &FormatResult) -> Result<String, String> {
+	if result.data.is_null() {
+		let error = if result.error_msg.is_null() {
+			"Unknown error"
+		} else {
+			unsafe { CStr::from_ptr(result.error_msg) }
+				.to_str()
+				.unwrap_or("Unknown error")
+		};
+		return Err(match result.error_line {
+			0 => error.to_string(),
+			line => format!("{}:{}: {}", line, result.error_column, error),
+		});
+	}
+
+	unsafe { CStr::from_ptr(result.data) }
+		.to_str()
+		.map(|s| s.to_string())
+		.map_err(|e| format!("Invalid UTF-8: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::ffi::CString;
+	use std::ptr;
+
+	unsafe extern "C" fn free(result: *mut FormatResult) {
+		let result = &mut *result;
+		if !result.data.is_null() {
+			drop(CString::from_raw(result.data));
+			result.data = ptr::null_mut();
+		}
+		if result.error_owned {
+			drop(CString::from_raw(result.error_msg as *mut _));
+			result.error_msg = ptr::null();
+		}
+	}
+
+	unsafe extern "C" fn free_array(results: *mut FormatResult, len: size_t) {
+		let mut results = Vec::from_raw_parts(results, len, len);
+		for result in &mut results {
+			free(result);
+		}
+	}
+
+	fn output(data: &[u8]) -> FormatResult {
+		let data = CString::new(data).unwrap();
+		FormatResult {
+			len: data.as_bytes().len(),
+			data: data.into_raw(),
+			error_msg: ptr::null(),
+			error_line: 0,
+			error_column: 0,
+			error_owned: false,
+		}
+	}
+
+	fn error(message: &str, line: usize) -> FormatResult {
+		FormatResult {
+			data: ptr::null_mut(),
+			len: 0,
+			error_msg: CString::new(message).unwrap().into_raw(),
+			error_line: line,
+			error_column: 3,
+			error_owned: true,
+		}
+	}
+
+	#[test]
+	fn test_owned_result() {
+		let owned =
+			unsafe { OwnedResult::new(output(b"const x = 1;\n"), free) };
+		assert_eq!(owned.read(), Ok("const x = 1;\n".to_string()));
+		let owned = unsafe { OwnedResult::new(output(b"\xff"), free) };
+		assert!(owned.read().unwrap_err().starts_with("Invalid UTF-8"));
+		let owned = unsafe { OwnedResult::new(error("bad", 2), free) };
+		assert_eq!(owned.read(), Err("2:3: bad".to_string()));
+	}
+
+	#[test]
+	fn test_owned_result_array() {
+		let mut results = vec![output(b"a"), error("Out of memory", 0)];
+		results.shrink_to_fit();
+		let len = results.len();
+		let ptr = results.as_mut_ptr();
+		std::mem::forget(results);
+
+		let owned = unsafe { OwnedResultArray::from_raw(ptr, len, free_array) }
+			.unwrap();
+		assert_eq!(
+			owned.read(),
+			[Ok("a".to_string()), Err("Out of memory".to_string())]
+		);
+		assert!(unsafe {
+			OwnedResultArray::from_raw(ptr::null_mut(), 0, free_array)
+		}
+		.is_none());
+	}
+}
@@ -11,39 +11,51 @@ pub const FormatResult = extern struct {
     len: usize,
     /// Error message if data is null, otherwise null
     error_msg: ?[*:0]const u8,
+    /// 1-based line of the first parse error, or 0 for other errors
+    error_line: usize,
+    /// 1-based column of the first parse error, or 0 for other errors
+    error_column: usize,
+    /// Whether error_msg was allocated and is freed by zig_fmt_free
+    error_owned: bool,
 };
 
 /// Global allocator for C FFI
 var gpa = std.heap.GeneralPurposeAllocator(.{}){};
 
-/// Format Zig source code
-/// Returns a FormatResult struct with either the formatted code or an error message
-/// The caller must call zig_fmt_free() on the result when done
-export fn zig_fmt(source: [*:0]const u8, source_len: usize) FormatResult {
-    const allocator = gpa.allocator();
-
-    // Create a slice from the source
-    const src_slice: [:0]const u8 = source[0..source_len :0];
+/// A failed FormatResult with a static error message
+fn failure(error_msg: [*:0]const u8) FormatResult {
+    return .{
+        .data = null,
+        .len = 0,
+        .error_msg = error_msg,
+        .error_line = 0,
+        .error_column = 0,
+        .error_owned = false,
+    };
+}
 
+/// Format one source buffer
+fn format(allocator: Allocator, src_slice: [:0]const u8) FormatResult {
     // Parse the source
     var tree = Ast.parse(allocator, src_slice, .zig) catch |err| {
-        return .{
-            .data = null,
-            .len = 0,
-            .error_msg = switch (err) {
-                error.OutOfMemory => "Out of memory during parsing",
-            },
-        };
+        return failure(switch (err) {
+            error.OutOfMemory => "Out of memory during parsing",
+        });
     };
     defer tree.deinit(allocator);
 
-    // Check for parse errors
+    // Report the first parse error with its location
     if (tree.errors.len > 0) {
-        return .{
-            .data = null,
-            .len = 0,
-            .error_msg = "Parse error in source code",
-        };
+        const parse_error = tree.errors[0];
+        const location = tree.tokenLocation(0, parse_error.token);
+        var result = failure("Parse error in source code");
+        result.error_line = location.line + 1;
+        result.error_column = location.column + 1 + tree.errorOffset(parse_error);
+        if (renderParseError(allocator, tree, parse_error)) |message| {
+            result.error_msg = message;
+            result.error_owned = true;
+        }
+        return result;
     }
 
     // Render the formatted output using Writer.Allocating
@@ -51,14 +63,10 @@ export fn zig_fmt(source: [*:0]const u8, source_len: usize) FormatResult {
     defer aw.deinit();
 
     Ast.Render.renderTree(allocator, &aw.writer, tree, .{}) catch |err| {
-        return .{
-            .data = null,
-            .len = 0,
-            .error_msg = switch (err) {
-                error.OutOfMemory => "Out of memory during rendering",
-                error.WriteFailed => "Write failed during rendering",
-            },
-        };
+        return failure(switch (err) {
+            error.OutOfMemory => "Out of memory during rendering",
+            error.WriteFailed => "Write failed during rendering",
+        });
     };
 
     // Get the output
@@ -66,11 +74,7 @@ export fn zig_fmt(source: [*:0]const u8, source_len: usize) FormatResult {
 
     // Allocate result with null terminator
     const result = allocator.allocSentinel(u8, output_list.items.len, 0) catch {
-        return .{
-            .data = null,
-            .len = 0,
-            .error_msg = "Out of memory allocating result",
-        };
+        return failure("Out of memory allocating result");
     };
     @memcpy(result, output_list.items);
 
@@ -81,18 +85,69 @@ export fn zig_fmt(source: [*:0]const u8, source_len: usize) FormatResult {
         .data = result.ptr,
         .len = result.len,
         .error_msg = null,
+        .error_line = 0,
+        .error_column = 0,
+        .error_owned = false,
     };
 }
 
+/// Render a parse error message, e.g. "expected expression, found ';'"
+fn renderParseError(allocator: Allocator, tree: Ast, parse_error: Ast.Error) ?[*:0]u8 {
+    var aw: Writer.Allocating = .init(allocator);
+    defer aw.deinit();
+
+    tree.renderError(parse_error, &aw.writer) catch return null;
+    const message = allocator.dupeZ(u8, aw.written()) catch return null;
+    return message.ptr;
+}
+
+/// Format Zig source code
+/// Returns a FormatResult struct with either the formatted code or an error message
+/// The caller must call zig_fmt_free() on the result when done
+export fn zig_fmt(source: [*:0]const u8, source_len: usize) FormatResult {
+    return format(gpa.allocator(), source[0..source_len :0]);
+}
+
+/// Format several sources in one call
+/// Returns an array of `count` results, or null if it can't be allocated
+/// The caller must call zig_fmt_batch_free() on the array when done
+export fn zig_fmt_batch(
+    sources: [*]const [*:0]const u8,
+    lengths: [*]const usize,
+    count: usize,
+) ?[*]FormatResult {
+    const allocator = gpa.allocator();
+    const results = allocator.alloc(FormatResult, count) catch return null;
+    for (results, 0..) |*result, i| {
+        result.* = format(allocator, sources[i][0..lengths[i] :0]);
+    }
+    return results.ptr;
+}
+
 /// Free the memory allocated by zig_fmt
 export fn zig_fmt_free(result: *FormatResult) void {
+    const allocator = gpa.allocator();
     if (result.data) |data| {
-        const allocator = gpa.allocator();
         const slice: [:0]u8 = data[0..result.len :0];
         allocator.free(slice);
         result.data = null;
         result.len = 0;
     }
+    if (result.error_owned) {
+        if (result.error_msg) |msg| {
+            allocator.free(std.mem.span(msg));
+        }
+        result.error_msg = null;
+        result.error_owned = false;
+    }
+}
+
+/// Free the array and every result returned by zig_fmt_batch
+export fn zig_fmt_batch_free(results: [*]FormatResult, count: usize) void {
+    for (results[0..count]) |*result| {
+        zig_fmt_free(result);
+    }
+    gpa.allocator().free(results[0..count]);
 }
 
 /// Get the version of the formatter
@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	fama_fuzz::check(data, "fuzz.hcl", goffi::format_hcl);
+});
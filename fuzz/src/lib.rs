@@ -0,0 +1,35 @@
+// fama-fuzz - Shared checks for the cargo-fuzz targets
+//
+// Every target feeds arbitrary UTF-8 into one backend wrapper and asserts:
+// - the backend never panics (errors are fine, they mean "not parseable")
+// - the output is valid UTF-8, which matters for the FFI/WASM backends that
+//   build strings from foreign buffers
+// - formatting is idempotent: format(format(x)) == format(x)
+//
+// Run with `cargo +nightly fuzz run <target>` from this directory.
+
+/// Backend entry point, matching the formatter crates' signature
+pub type FormatFn = fn(&str, &str) -> Result<String, String>;
+
+/// Run the safety and idempotency checks for one fuzz input
+pub fn check(data: &[u8], file_path: &str, format: FormatFn) {
+	let Ok(source) = std::str::from_utf8(data) else {
+		return;
+	};
+	let Ok(formatted) = format(source, file_path) else {
+		return;
+	};
+
+	assert!(
+		std::str::from_utf8(formatted.as_bytes()).is_ok(),
+		"{file_path}: output is not valid UTF-8"
+	);
+
+	let reformatted = format(&formatted, file_path).unwrap_or_else(|e| {
+		panic!("{file_path}: formatted output no longer parses: {e}")
+	});
+	assert_eq!(
+		formatted, reformatted,
+		"{file_path}: formatting is not idempotent"
+	);
+}